@@ -4,6 +4,45 @@
 @group(2) @binding(1) var mino_textures: texture_2d_array<f32>;
 @group(2) @binding(2) var mino_textures_sampler: sampler;
 @group(2) @binding(3) var<storage, read> data: array<u32>;
+@group(2) @binding(4) var<uniform> tint: vec4f;
+@group(2) @binding(5) var<storage, read> palette: array<vec4f>;
+@group(2) @binding(6) var<uniform> patterns: u32;
+@group(2) @binding(7) var<uniform> well: u32;
+@group(2) @binding(8) var<uniform> well_background: u32;
+@group(2) @binding(9) var<uniform> well_color: vec4f;
+
+// Whether the given point within a cell falls on the marks of the pattern for the given kind of
+// cell. Each of the standard pieces has its own pattern, and the generic slots reuse them in turn.
+fn on_pattern(cell_type: u32, p: vec2f) -> bool {
+    // empty and garbage cells are left plain
+    if cell_type == 0u || cell_type == 8u {
+        return false;
+    }
+    var pattern = cell_type;
+    if cell_type > 8u {
+        pattern = (cell_type - 9u) % 7u + 1u;
+    }
+
+    switch pattern {
+        case 1u: { return fract(p.y * 4.0) < 0.3; }
+        case 2u: { return length(fract(p * 3.0) - 0.5) < 0.2; }
+        case 3u: { return fract((p.x + p.y) * 3.0) < 0.3; }
+        case 4u: { return fract((p.x - p.y) * 3.0) < 0.3; }
+        case 5u: { return fract(p.x * 4.0) < 0.3; }
+        case 6u: { return abs(p.x - 0.5) < 0.1 || abs(p.y - 0.5) < 0.1; }
+        default: { return (u32(floor(p.x * 4.0)) + u32(floor(p.y * 4.0))) % 2u == 0u; }
+    }
+}
+
+// How strongly the well background shows at the given point, from 0 to 1. The uv runs from the top
+// of the matrix to its floor.
+fn well_shade(uv: vec2f, cell: vec2u) -> f32 {
+    switch well_background {
+        case 1u: { return uv.y * uv.y; }
+        case 2u: { return select(0.0, 1.0, (cell.x + cell.y) % 2u == 0u); }
+        default: { return 0.0; }
+    }
+}
 
 @fragment
 fn fragment(in: VertexOutput) -> @location(0) vec4f {
@@ -16,7 +55,21 @@ fn fragment(in: VertexOutput) -> @location(0) vec4f {
     let cell_inner_position = cell_position - floor(cell_position);
 
     let nothing = vec4f(0f);
-    let sampled = textureSample(mino_textures, mino_textures_sampler, cell_inner_position, cell_type);
+    // the neutral copy of each texture comes after all of the original textures
+    let original = textureSample(mino_textures, mino_textures_sampler, cell_inner_position, cell_type);
+    let neutral = textureSample(mino_textures, mino_textures_sampler, cell_inner_position, cell_type + arrayLength(&palette));
+    let color = palette[cell_type];
+    let recolored = vec4f(neutral.rgb * color.rgb, neutral.a);
+    var sampled = select(original, recolored, color.a > 0.0) * tint;
+
+    if patterns != 0u && on_pattern(cell_type, cell_inner_position) {
+        sampled = vec4f(sampled.rgb * 0.45, sampled.a);
+    }
+
+    if well != 0u && cell_type == 0u {
+        let shade = well_shade(in.uv, integral_position) * well_color.a;
+        sampled = vec4f(sampled.rgb + well_color.rgb * shade, max(sampled.a, shade));
+    }
 
     return select(nothing, sampled, in.uv.x < 1.0);
 }
\ No newline at end of file
@@ -0,0 +1,68 @@
+//! Plays a game through the headless API which builds up a combo and a back to back chain, then
+//! checks that the chains found in its record agree with those tracked while it was played. Exits
+//! with a panic if any check fails.
+
+use stack_practice::api::{load_default_tables, Game, Move};
+use stack_practice::board::MinoKind;
+use stack_practice::replay::code::RunSettings;
+use stack_practice::stats::lock_outcomes;
+
+/// Pieces to play after the scripted ones, to check the chains of a less orderly game as well
+const PIECES: u32 = 80;
+
+/// Drops an upright I piece into the given column
+fn drop_upright(game: &mut Game, column: i32) {
+    let placement = game
+        .legal_placements()
+        .into_iter()
+        .filter(|&mino| {
+            game.shape_table()[mino]
+                .iter()
+                .all(|cell| cell.x + mino.position.x == column)
+        })
+        .min_by_key(|mino| mino.position.y)
+        .expect("an upright I piece should fit in every column");
+    game.submit(Move::Place(placement))
+        .expect("legal placements should always be accepted");
+}
+
+fn main() {
+    let (shapes, kicks) = load_default_tables().expect("the default tables should load");
+    let settings = RunSettings {
+        preset_queue: vec![MinoKind::I; 20],
+        ..Default::default()
+    };
+    let mut game = Game::new(settings, 0, shapes, kicks);
+
+    // two stacks of upright I pieces eight tall, leaving the last column for two fours in a row
+    for column in (0..9).chain(0..9) {
+        drop_upright(&mut game, column);
+    }
+    drop_upright(&mut game, 9);
+    drop_upright(&mut game, 9);
+    assert_eq!(game.stats().lines, 8);
+    assert_eq!(game.stats().max_combo, 2);
+    assert_eq!(game.stats().max_b2b, 2);
+
+    // the lowest placement each time, so that lines are cleared every so often
+    while !game.is_over() && game.stats().pieces < PIECES {
+        let placement = game
+            .legal_placements()
+            .into_iter()
+            .min_by_key(|mino| (mino.position.y, mino.position.x))
+            .expect("a game which is not over has placements");
+        game.submit(Move::Place(placement))
+            .expect("legal placements should always be accepted");
+    }
+
+    let (record, resimulated) = game.record().expect("the game should play back");
+    let outcomes = lock_outcomes(&record, game.shape_table());
+    let max_combo = outcomes.iter().map(|o| o.combo).max().unwrap_or(0);
+    let max_b2b = outcomes.iter().map(|o| o.b2b).max().unwrap_or(0);
+    for stats in [game.stats(), &resimulated] {
+        assert_eq!(stats.max_combo, max_combo);
+        assert_eq!(stats.max_b2b, max_b2b);
+    }
+    assert_eq!(game.stats().chain.combo, outcomes.last().unwrap().combo);
+    assert_eq!(game.stats().chain.b2b, outcomes.last().unwrap().b2b);
+}
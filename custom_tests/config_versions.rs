@@ -0,0 +1,164 @@
+//! Loads sample files from the fixtures, written in each version of a made up format as well as a
+//! newer one, checking that each is brought up to the current format with the fields it leaves
+//! out filled in, and that a file from a newer version loads with a warning. Then does the same
+//! for the files that the game keeps between sessions, as written by older versions of the game.
+//! Exits with a panic if any check fails.
+
+use bevy::math::vec2;
+use bevy::prelude::KeyCode;
+use serde::{Deserialize, Serialize};
+use stack_practice::board::GameMode;
+use stack_practice::config::{self, ConfigError, Versioned};
+use stack_practice::controller::keybinds::{Action, Binding};
+use stack_practice::controller::profiles::{Handling, Profiles};
+use stack_practice::screens::settings_profiles::SettingsProfiles;
+use stack_practice::screens::GlobalSettings;
+use stack_practice::window::WindowOptions;
+
+/// The current format, in which `title` has been renamed to `name`
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Sample {
+    name: String,
+    count: u32,
+}
+
+/// The format of versions 0 and 1, the first of which had no count
+#[derive(Deserialize)]
+struct OldSample {
+    title: String,
+    #[serde(default)]
+    count: u32,
+}
+
+impl From<OldSample> for Sample {
+    fn from(old: OldSample) -> Self {
+        Self {
+            name: old.title,
+            count: old.count,
+        }
+    }
+}
+
+impl Versioned for Sample {
+    const VERSION: u32 = 2;
+
+    fn migrate(version: u32, text: &str) -> Result<Self, ConfigError> {
+        match version {
+            0 => Ok(ron::from_str::<OldSample>(text)?.into()),
+            1 => Ok(config::read_data::<OldSample>(text)?.into()),
+            _ => Err(ConfigError::UnknownVersion(version)),
+        }
+    }
+}
+
+/// Loads a fixture, which should load without a warning unless it is from a newer version
+fn load<T: Versioned>(name: &str) -> (T, Option<String>) {
+    let path = format!(
+        "{}/custom_tests/fixtures/{name}",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    config::load::<T>(&path).unwrap_or_else(|e| panic!("{name} should load: {e}"))
+}
+
+fn sample(name: &str, count: u32) -> Sample {
+    Sample {
+        name: name.to_string(),
+        count,
+    }
+}
+
+fn check_profiles(profiles: &Profiles, name: &str) {
+    assert_eq!(profiles.profiles.len(), 1, "{name}");
+    assert_eq!(profiles.active, 0, "{name}");
+    let profile = &profiles.profiles[0];
+    assert_eq!(
+        profile.keybinds.bindings[&Action::HardDrop],
+        Binding::Physical(KeyCode::Space),
+        "{name}"
+    );
+    assert_eq!(profile.handling.initial_delay, "110", "{name}");
+    // left out of the file, so taken from the defaults
+    assert_eq!(
+        profile.handling.input_buffer,
+        Handling::default().input_buffer,
+        "{name}"
+    );
+    assert!(
+        !profile.keybinds.bindings.contains_key(&Action::PauseReplay),
+        "{name} should only hold the bindings in the file"
+    );
+}
+
+fn main() {
+    for (file, expected) in [
+        // from before versions were written, so it holds the data alone
+        ("sample_v0.ron", sample("first", 0)),
+        ("sample_v1.ron", sample("second", 3)),
+        ("sample_v2.ron", sample("third", 4)),
+    ] {
+        let (loaded, warning) = load::<Sample>(file);
+        assert_eq!(warning, None, "{file}");
+        assert_eq!(loaded, expected, "{file}");
+    }
+
+    // a newer version keeps what the current format knows of, and leaves out the rest
+    let (loaded, warning) = load::<Sample>("sample_v3.ron");
+    assert!(warning.is_some(), "a file from a newer version should warn");
+    assert_eq!(loaded, sample("fourth", 5));
+
+    // profiles from before versions were written hold the profiles alone
+    for name in ["config_v0_profiles.ron", "config_v1_profiles.ron"] {
+        let (profiles, warning) = load::<Profiles>(name);
+        assert_eq!(warning, None, "{name}");
+        check_profiles(&profiles, name);
+    }
+    let (profiles, _) = load::<Profiles>("config_v1_profiles.ron");
+    let handling = &profiles.profiles[0].handling;
+    assert_eq!(
+        (
+            handling.soft_drop_power.as_str(),
+            handling.repeat_delay.as_str()
+        ),
+        ("20", "20")
+    );
+
+    let (settings_profiles, warning) = load::<SettingsProfiles>("config_v1_settings_profiles.ron");
+    assert_eq!(warning, None);
+    assert_eq!(settings_profiles.selected, Some(0));
+    assert_eq!(settings_profiles.profiles.len(), 1);
+    let profile = &settings_profiles.profiles[0];
+    assert_eq!(profile.name, "sprint");
+    let settings = &profile.settings;
+    assert_eq!(settings.gravity_power, "2");
+    assert_eq!(settings.lock_delay, "0.4");
+    assert!(!settings.particles);
+    assert_eq!(settings.mode, GameMode::Sprint);
+    // left out of the file, so taken from the defaults
+    let defaults = GlobalSettings::default();
+    assert_eq!(settings.move_reset_limit, defaults.move_reset_limit);
+    assert_eq!(settings.replay_frame_rate, defaults.replay_frame_rate);
+
+    let (window, warning) = load::<WindowOptions>("config_v1_window.ron");
+    assert_eq!(warning, None);
+    assert_eq!(
+        window,
+        WindowOptions {
+            board_offset: vec2(10.0, -5.0),
+            always_on_top: true,
+            ..Default::default()
+        }
+    );
+    // a newer version keeps what the current format knows of, and leaves out the rest
+    let (window, warning) = load::<WindowOptions>("config_v2_window.ron");
+    assert!(warning.is_some(), "a file from a newer version should warn");
+    assert_eq!(
+        window,
+        WindowOptions {
+            board_offset: vec2(0.0, 20.0),
+            fixed_size: true,
+            ..Default::default()
+        }
+    );
+
+    println!("Every saved file loaded through the migrations");
+}
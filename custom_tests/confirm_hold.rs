@@ -0,0 +1,37 @@
+//! Holds a confirmation for various lengths of time, checking that it confirms exactly once when
+//! held long enough and starts over when let go. Exits with a panic if any check fails.
+
+use stack_practice::confirm::{ConfirmHold, HOLD_TO_CONFIRM};
+
+/// Length of a frame at 60 frames per second
+const FRAME: f32 = 1.0 / 60.0;
+
+/// Holds for the given number of seconds, returning how many times the hold confirmed
+fn hold_for(hold: &mut ConfirmHold, seconds: f32) -> usize {
+    let frames = (seconds / FRAME).round() as usize;
+    (0..frames).filter(|_| hold.update(true, FRAME)).count()
+}
+
+fn main() {
+    let mut hold = ConfirmHold::default();
+
+    // a tap does nothing
+    assert_eq!(hold_for(&mut hold, FRAME), 0);
+    assert!(hold.progress() > 0.0);
+    assert!(!hold.update(false, FRAME));
+    assert_eq!(hold.progress(), 0.0);
+
+    // letting go just short of the time starts the hold over
+    assert_eq!(hold_for(&mut hold, HOLD_TO_CONFIRM - 4.0 * FRAME), 0);
+    hold.update(false, FRAME);
+    assert_eq!(hold_for(&mut hold, HOLD_TO_CONFIRM - 4.0 * FRAME), 0);
+    hold.update(false, FRAME);
+
+    // holding on past the time confirms only once
+    assert_eq!(hold_for(&mut hold, HOLD_TO_CONFIRM * 3.0), 1);
+    assert_eq!(hold.progress(), 0.0);
+    hold.update(false, FRAME);
+    assert_eq!(hold_for(&mut hold, HOLD_TO_CONFIRM + 2.0 * FRAME), 1);
+
+    println!("Holds confirmed once each, and only when held long enough");
+}
@@ -0,0 +1,97 @@
+//! Plays a short scripted game through the headless API, with the first pieces preset so that the
+//! game is the same every time, and checks the exact sequence of events that it sends. Exits with a
+//! panic if the events differ.
+
+use bevy::math::{ivec2, IVec2};
+use bevy::prelude::Entity;
+use stack_practice::api::{load_default_tables, Action, Game, Move};
+use stack_practice::board::events::{GameEvent, GameStarted, HoldUsed, LinesCleared, PieceLocked};
+use stack_practice::board::{Mino, MinoKind};
+use stack_practice::replay::code::RunSettings;
+
+/// The cells of the bottom row from the given column, for as many columns as given
+fn bottom_row(from: i32, columns: i32) -> Vec<IVec2> {
+    (from..from + columns).map(|x| ivec2(x, 0)).collect()
+}
+
+/// The cells that the given piece fills, in the same order as the cells of the scripted pieces
+fn filled(game: &Game, mino: Mino) -> Vec<IVec2> {
+    let mut cells = game.shape_table()[mino]
+        .iter()
+        .map(|&cell| cell + mino.position)
+        .collect::<Vec<_>>();
+    cells.sort_by_key(|c| (c.y, c.x));
+    cells
+}
+
+/// Places the active piece so that it fills exactly the given cells, returning the event that its
+/// lock should send
+fn place(game: &mut Game, cells: Vec<IVec2>, clear: usize) -> GameEvent {
+    let placement = game
+        .legal_placements()
+        .into_iter()
+        .find(|&mino| filled(game, mino) == cells)
+        .expect("the scripted placement should be legal");
+    game.submit(Move::Place(placement))
+        .expect("legal placements should always be accepted");
+    GameEvent::PieceLocked(PieceLocked {
+        board: Entity::PLACEHOLDER,
+        kind: placement.kind,
+        mino: placement,
+        cells,
+        clear,
+        spin: false,
+        replayed: false,
+    })
+}
+
+fn main() {
+    use MinoKind::*;
+
+    let (shapes, kicks) = load_default_tables().expect("the default tables should load");
+    let settings = RunSettings {
+        preset_queue: vec![I, I, O, T],
+        ..Default::default()
+    };
+    let mut game = Game::new(settings, 0, shapes, kicks);
+
+    // two flat I pieces and an O fill the bottom row, then the T goes into hold
+    let o_cells = vec![ivec2(8, 0), ivec2(9, 0), ivec2(8, 1), ivec2(9, 1)];
+    let [first, second, third] = [
+        place(&mut game, bottom_row(0, 4), 0),
+        place(&mut game, bottom_row(4, 4), 0),
+        place(&mut game, o_cells, 1),
+    ];
+    game.submit(Move::Actions(vec![Action::Hold]))
+        .expect("the hold is free for the first piece after a lock");
+
+    let expected = vec![
+        GameEvent::GameStarted(GameStarted {
+            board: Entity::PLACEHOLDER,
+        }),
+        first,
+        second,
+        third,
+        GameEvent::LinesCleared(LinesCleared {
+            board: Entity::PLACEHOLDER,
+            rows: vec![0],
+            replayed: false,
+        }),
+        GameEvent::HoldUsed(HoldUsed {
+            board: Entity::PLACEHOLDER,
+            kind: T,
+            replayed: false,
+        }),
+    ];
+    assert_eq!(
+        game.take_events(),
+        expected,
+        "the game should send exactly the scripted events"
+    );
+    assert!(
+        game.take_events().is_empty(),
+        "taking the events should leave none behind"
+    );
+
+    println!("All {} events matched", expected.len());
+}
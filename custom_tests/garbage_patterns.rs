@@ -0,0 +1,105 @@
+//! Generates garbage from fixed seeds, checking how often the gap moves between rows at different
+//! messiness, and that each preset pattern lays out its rows as described. Exits with a panic if
+//! any check fails.
+
+use std::collections::HashSet;
+
+use stack_practice::board::garbage::{cheese, rng, GarbagePattern};
+use stack_practice::board::MinoKind;
+
+const WIDTH: usize = 10;
+const ROWS: usize = 1000;
+const SEEDS: [u64; 3] = [0, 7, 0xdead_beef];
+
+fn gaps(row: &[MinoKind]) -> Vec<usize> {
+    row.iter()
+        .enumerate()
+        .filter(|(_, &kind)| kind == MinoKind::E)
+        .map(|(x, _)| x)
+        .collect()
+}
+
+/// Times that the gap changed column from one row to the next
+fn moves(rows: &[Vec<MinoKind>]) -> usize {
+    rows.windows(2)
+        .filter(|w| gaps(&w[0]) != gaps(&w[1]))
+        .count()
+}
+
+fn main() {
+    for seed in SEEDS {
+        for (pattern, gap_width) in [(GarbagePattern::Clean, 1), (GarbagePattern::FourWide, 4)] {
+            let generate = |messiness| cheese(&mut rng(seed), &pattern, messiness, ROWS, WIDTH);
+
+            // each row has a single gap of the pattern's width
+            for row in generate(0.5) {
+                let gaps = gaps(&row);
+                assert_eq!(gaps.len(), gap_width, "{pattern} row {row:?}");
+                assert_eq!(
+                    gaps[gap_width - 1] - gaps[0],
+                    gap_width - 1,
+                    "{pattern} row {row:?}"
+                );
+            }
+
+            assert_eq!(moves(&generate(0.0)), 0, "{pattern} at no messiness");
+            assert_eq!(
+                moves(&generate(1.0)),
+                ROWS - 1,
+                "{pattern} at full messiness"
+            );
+            let half = moves(&generate(0.5)) as f32 / (ROWS - 1) as f32;
+            assert!(
+                (0.4..=0.6).contains(&half),
+                "{pattern} at half messiness moved its gap on {half} of the rows"
+            );
+
+            // the gap lands in every column it can over enough rows
+            let columns = generate(1.0)
+                .iter()
+                .map(|row| gaps(row)[0])
+                .collect::<HashSet<_>>();
+            assert_eq!(
+                columns.len(),
+                WIDTH - gap_width + 1,
+                "{pattern} gap columns"
+            );
+
+            assert_eq!(generate(0.5), generate(0.5), "{pattern} from the same seed");
+        }
+
+        // alternating cells, which shift over by one on each row of a checkerboard and line up
+        // into columns in a comb, whatever the messiness
+        for messiness in [0.0, 1.0] {
+            let checkerboard = cheese(
+                &mut rng(seed),
+                &GarbagePattern::Checkerboard,
+                messiness,
+                ROWS,
+                WIDTH,
+            );
+            let comb = cheese(
+                &mut rng(seed),
+                &GarbagePattern::Comb,
+                messiness,
+                ROWS,
+                WIDTH,
+            );
+            for rows in [&checkerboard, &comb] {
+                for row in rows.iter() {
+                    assert!(row.windows(2).all(|w| w[0] != w[1]), "row {row:?}");
+                }
+            }
+            assert!(checkerboard.windows(2).all(|w| w[0][0] != w[1][0]));
+            assert!(comb.windows(2).all(|w| w[0] == w[1]));
+        }
+    }
+
+    // rows written out by hand repeat from the bottom up, ending on the last row
+    let custom = GarbagePattern::Custom(vec!["GG.GGGGGGG".into(), "GGGGGGG.GG".into()]);
+    let rows = cheese(&mut rng(0), &custom, 1.0, 5, WIDTH);
+    let columns = rows.iter().map(|row| gaps(row)).collect::<Vec<_>>();
+    assert_eq!(columns, [vec![7], vec![2], vec![7], vec![2], vec![7]]);
+
+    println!("Garbage was laid out as expected for every pattern and seed");
+}
@@ -0,0 +1,109 @@
+//! Works out the locks of a record written out by hand, in which some pieces are placed with hold
+//! free to use and others are placed right after a hold, and checks whether each lock is found to
+//! have had hold available. Exits with a panic if any check fails.
+
+use bevy::math::ivec2;
+use stack_practice::board::queue::PieceQueue;
+use stack_practice::board::{Hold, Mino, MinoKind, RotationState};
+use stack_practice::replay::record::{
+    CompleteRecord, FrameRate, RecordData, RecordItem, RecordSegment,
+};
+use stack_practice::stats::compute_stats;
+
+fn spawned(kind: MinoKind) -> RecordData {
+    RecordData::ActiveChange(Some(Mino {
+        kind,
+        position: ivec2(4, 20),
+        rotation: RotationState::Up,
+    }))
+}
+
+/// The next piece spawning, with the queue moving along as it comes out
+fn next_piece(kind: MinoKind) -> [RecordData; 2] {
+    [
+        RecordData::QueueChange(PieceQueue::default()),
+        spawned(kind),
+    ]
+}
+
+fn main() {
+    use MinoKind::*;
+
+    let frames: Vec<(u64, Vec<RecordData>)> = vec![
+        // before the first piece spawns, then the first piece
+        (
+            0,
+            vec![
+                RecordData::ActiveChange(None),
+                RecordData::Hold(Hold::Empty),
+                RecordData::QueueChange(PieceQueue::default()),
+            ],
+        ),
+        (0, next_piece(T).to_vec()),
+        // placed with the hold empty
+        (30, next_piece(I).to_vec()),
+        // the I is held, and the piece from the queue comes out in its place
+        (
+            40,
+            [RecordData::Hold(Hold::Inactive(I))]
+                .into_iter()
+                .chain(next_piece(O))
+                .collect(),
+        ),
+        // placed right after the hold, which frees it up again
+        (
+            70,
+            next_piece(S)
+                .into_iter()
+                .chain([RecordData::Hold(Hold::Ready(I))])
+                .collect(),
+        ),
+        // placed with the I ready to be swapped in
+        (100, next_piece(Z).to_vec()),
+        // the I is swapped in, which uses up the hold until it is placed
+        (110, vec![RecordData::Hold(Hold::Inactive(Z)), spawned(I)]),
+        (
+            140,
+            next_piece(L)
+                .into_iter()
+                .chain([RecordData::Hold(Hold::Ready(Z))])
+                .collect(),
+        ),
+        // the last piece is placed and the next one cannot spawn
+        (170, vec![RecordData::ActiveChange(None)]),
+    ];
+
+    let rate = FrameRate::default();
+    let mut segment = RecordSegment::default();
+    for (time, data) in frames {
+        segment.extend(data.into_iter().map(|data| RecordItem {
+            time,
+            micros: rate.frame_to_micros(time),
+            tick: None,
+            data,
+        }));
+    }
+    let mut record = CompleteRecord::default();
+    record.add_segment(segment);
+
+    let stats = compute_stats(&record);
+    let locks = stats
+        .locks
+        .iter()
+        .map(|lock| (lock.frame, lock.mino.kind, lock.hold_available))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        locks,
+        [
+            (30, T, true),
+            (70, O, false),
+            (100, S, true),
+            (140, I, false),
+            (170, L, true),
+        ]
+    );
+    assert_eq!(stats.holds, 2);
+    assert_eq!(stats.holds_ignored(), 3);
+
+    println!("Hold was found to be available on the locks which had it");
+}
@@ -0,0 +1,74 @@
+//! Presses rotate on the same frame as a hard drop, once the game has started, checking that the
+//! rotation is kept for the piece which spawns after the drop rather than being lost with the piece
+//! that was dropped. Exits once the next piece has been checked; a panic along the way is a failure.
+
+use bevy::app::AppExit;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use stack_practice::board::{Active, RotationState};
+use stack_practice::replay::ghost::Ghost;
+use stack_practice::schedule::FrameSet;
+use stack_practice::state::{assets_loaded, MainState};
+use stack_practice::StackPracticePlugins;
+
+/// Frame of play on which both keys are pressed, so that the first piece has spawned by then
+const PRESS_FRAME: u32 = 10;
+/// Frames after the press that the next piece is checked on. The piece spawns with the drop, and
+/// the buffered rotation is applied on the frame after.
+const CHECK_DELAY: u32 = 2;
+
+const HARD_DROP_KEY: KeyCode = KeyCode::Space;
+const ROTATE_KEY: KeyCode = KeyCode::Slash;
+
+fn press_together(
+    state: Res<State<MainState>>,
+    mut next: ResMut<NextState<MainState>>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    boards: Query<&Active, Without<Ghost>>,
+    mut playing: Local<u32>,
+    mut exit: EventWriter<AppExit>,
+) {
+    match state.get() {
+        MainState::Ready => next.set(MainState::Playing),
+        MainState::Playing => {
+            *playing += 1;
+            if *playing == PRESS_FRAME {
+                keys.press(HARD_DROP_KEY);
+                keys.press(ROTATE_KEY);
+            } else {
+                keys.release(HARD_DROP_KEY);
+                keys.release(ROTATE_KEY);
+            }
+
+            if *playing == PRESS_FRAME + CHECK_DELAY {
+                let active = boards
+                    .single()
+                    .0
+                    .expect("the next piece should have spawned");
+                assert_eq!(
+                    active.rotation,
+                    RotationState::Right,
+                    "the rotation pressed with the hard drop should turn the next piece"
+                );
+                println!("The rotation pressed with the hard drop was applied to the next piece");
+                exit.send(AppExit);
+            }
+        }
+        MainState::PostGame => panic!("the game should not end after a single drop"),
+        MainState::LoadingFailed => panic!("the assets should load"),
+        MainState::Loading => (),
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, StackPracticePlugins))
+        .add_systems(
+            PreUpdate,
+            press_together
+                .after(InputSystem)
+                .before(FrameSet::Input)
+                .run_if(assets_loaded),
+        )
+        .run();
+}
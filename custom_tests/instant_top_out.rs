@@ -0,0 +1,65 @@
+//! Starts a game on a board which is filled to the top, so that the first piece cannot spawn and
+//! the game ends before a single piece is placed, then stays on the results for a while. Exits once
+//! enough frames have passed; a panic along the way is a failure.
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use stack_practice::board::{Matrix, MinoKind};
+use stack_practice::replay::ghost::Ghost;
+use stack_practice::state::{assets_loaded, MainState};
+use stack_practice::StackPracticePlugins;
+
+/// Frames to stay in the results for before exiting
+const POST_GAME_FRAMES: u32 = 120;
+/// Frames that the game may take to end once it has started
+const PLAYING_FRAMES: u32 = 10;
+
+fn top_out_at_start(
+    state: Res<State<MainState>>,
+    mut next: ResMut<NextState<MainState>>,
+    mut boards: Query<&mut Matrix, Without<Ghost>>,
+    mut playing: Local<u32>,
+    mut post_game: Local<u32>,
+    mut exit: EventWriter<AppExit>,
+) {
+    match state.get() {
+        MainState::Ready => {
+            // the board is spawned on entering this state, so it may not be there yet
+            let mut filled = false;
+            for mut matrix in boards.iter_mut() {
+                for row in matrix.rows_mut() {
+                    row.fill(MinoKind::G);
+                }
+                filled = true;
+            }
+            if filled {
+                next.set(MainState::Playing);
+            }
+        }
+        MainState::Playing => {
+            *playing += 1;
+            assert!(
+                *playing <= PLAYING_FRAMES,
+                "the game should end as soon as it starts"
+            );
+        }
+        MainState::PostGame => {
+            *post_game += 1;
+            if *post_game > POST_GAME_FRAMES {
+                println!(
+                    "Topped out on the first piece and stayed in the results without panicking"
+                );
+                exit.send(AppExit);
+            }
+        }
+        MainState::LoadingFailed => panic!("the assets should load"),
+        MainState::Loading => (),
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, StackPracticePlugins))
+        .add_systems(Update, top_out_at_start.run_if(assets_loaded))
+        .run();
+}
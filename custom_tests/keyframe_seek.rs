@@ -0,0 +1,159 @@
+//! Plays a game by hard dropping every piece where it spawns until the stack tops out, with time
+//! running fast enough for the record to take several keyframes. The replay is then sought far
+//! back and forth, so that the board is restored from keyframes, and the board after each seek is
+//! compared with the state found by playing every item of the record from the start. Exits once
+//! every seek has been checked; a panic along the way is a failure.
+
+use std::time::Duration;
+
+use bevy::app::AppExit;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use stack_practice::board::queue::PieceQueue;
+use stack_practice::board::{Active, Hold, Matrix};
+use stack_practice::replay::ghost::Ghost;
+use stack_practice::replay::record::{CompleteRecord, RecordData};
+use stack_practice::replay::replay::ReplayInfo;
+use stack_practice::schedule::FrameSet;
+use stack_practice::state::{assets_loaded, MainState};
+use stack_practice::StackPracticePlugins;
+
+/// Time that passes on each frame, so that the record spans several keyframes in a short test
+const FRAME: Duration = Duration::from_millis(100);
+/// Frames between each hard drop, so that every piece has spawned before it is dropped
+const DROP_INTERVAL: u32 = 10;
+/// Frames that the game may take to top out before the test gives up on it
+const PLAYING_FRAMES: u32 = 60 * 60;
+
+const HARD_DROP_KEY: KeyCode = KeyCode::Space;
+
+/// The matrix, followed by the active piece, hold and queue written out, as they cannot be compared
+type BoardState = (Matrix, String, String, String);
+
+/// The state of the board once every item of the record up to the given frame has been played
+fn applied_state(record: &CompleteRecord, frame: u64) -> BoardState {
+    let (mut matrix, mut active, mut hold, mut queue): BoardState = Default::default();
+    for item in record.get(0..record.len()).iter() {
+        if item.time > frame {
+            break;
+        }
+        match &item.data {
+            RecordData::ActiveChange(piece) => active = format!("{piece:?}"),
+            RecordData::QueueChange(new) => queue = format!("{new:?}"),
+            RecordData::Hold(new) => hold = format!("{new:?}"),
+            RecordData::MatrixChange(update) => {
+                *matrix.get_mut(update.loc).unwrap() = update.new;
+            }
+            RecordData::Keyframe(_) => (),
+        }
+    }
+    (matrix, active, hold, queue)
+}
+
+/// The frame that the replay has to reach to have played the given item
+fn frame_of_item(record: &CompleteRecord, ix: usize) -> u64 {
+    record.get(ix..ix + 1).iter().next().unwrap().time
+}
+
+fn drop_until_top_out(
+    state: Res<State<MainState>>,
+    mut next: ResMut<NextState<MainState>>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut playing: Local<u32>,
+) {
+    keys.release(HARD_DROP_KEY);
+    match state.get() {
+        MainState::Ready => next.set(MainState::Playing),
+        MainState::Playing => {
+            *playing += 1;
+            assert!(*playing <= PLAYING_FRAMES, "the stack should top out");
+            if *playing % DROP_INTERVAL == 0 {
+                keys.press(HARD_DROP_KEY);
+            }
+        }
+        MainState::LoadingFailed => panic!("the assets should load"),
+        MainState::Loading | MainState::PostGame => (),
+    }
+}
+
+/// Seeks to each destination in turn, checking the board on the frame after each seek, once the
+/// replay has caught up with it
+fn seek_and_check(
+    mut info: ResMut<ReplayInfo>,
+    record: Res<CompleteRecord>,
+    time: Res<Time>,
+    boards: Query<(&Matrix, &Active, &Hold, &PieceQueue), Without<Ghost>>,
+    mut destinations: Local<Option<Vec<u64>>>,
+    mut sought: Local<Option<u64>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let destinations = destinations.get_or_insert_with(|| {
+        let keyframes = record
+            .get(0..record.len())
+            .iter()
+            .filter(|item| matches!(item.data, RecordData::Keyframe(_)))
+            .count();
+        assert!(
+            keyframes > 2,
+            "the record should have keyframes to seek from"
+        );
+
+        // back to the very start, far forward, back to between two keyframes, forward again, and
+        // then to the end
+        let len = record.len();
+        let mut destinations = vec![
+            0,
+            frame_of_item(&record, len * 3 / 4),
+            frame_of_item(&record, len / 4),
+            frame_of_item(&record, len / 2),
+            record.last_frame(),
+        ];
+        destinations.reverse();
+        destinations
+    });
+
+    if let Some(frame) = sought.take() {
+        let (matrix, active, hold, queue) = boards.single();
+        assert_eq!(info.frame, frame);
+        assert_eq!(
+            (
+                matrix.clone(),
+                format!("{:?}", active.0),
+                format!("{hold:?}"),
+                format!("{queue:?}")
+            ),
+            applied_state(&record, frame),
+            "seeking to frame {frame} should leave the board as playing every item up to it"
+        );
+    }
+
+    match destinations.pop() {
+        Some(frame) => {
+            info.seek(frame, &record, &time);
+            *sought = Some(frame);
+        }
+        None => {
+            println!("Seeking between keyframes matched playing the whole record each time");
+            exit.send(AppExit);
+        }
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, StackPracticePlugins))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME))
+        .add_systems(
+            PreUpdate,
+            drop_until_top_out
+                .after(InputSystem)
+                .before(FrameSet::Input)
+                .run_if(assets_loaded),
+        )
+        .add_systems(
+            PreUpdate,
+            seek_and_check.run_if(in_state(MainState::PostGame)),
+        )
+        .run();
+}
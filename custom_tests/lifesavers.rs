@@ -0,0 +1,65 @@
+//! Stacks pieces as high as they go through the headless API, checking that a game with lifesavers
+//! clears away the top of the stack and goes on where a game without them ends, and that the record
+//! and run code of the game play back the same lifesavers. Exits with a panic if any check fails.
+
+use bevy::math::ivec2;
+use stack_practice::api::{load_default_tables, Game, Move};
+use stack_practice::board::{Matrix, MinoKind};
+use stack_practice::replay::code::{RunCode, RunSettings};
+
+const PIECES: u32 = 80;
+const LIFESAVER_ROWS: usize = 6;
+
+/// Places each piece as high as it can go, until the given number of pieces are placed or the game
+/// ends
+fn stack_up(game: &mut Game) {
+    while !game.is_over() && game.stats().pieces < PIECES {
+        let placement = game
+            .legal_placements()
+            .into_iter()
+            .max_by_key(|mino| (mino.position.y, -(mino.position.x - 4).abs()))
+            .expect("a piece in play always has somewhere to go");
+        game.submit(Move::Place(placement))
+            .expect("legal placements should always be accepted");
+    }
+}
+
+fn main() {
+    let mut matrix = Matrix::default();
+    for y in 0..3 {
+        *matrix.get_mut(ivec2(0, y)).unwrap() = MinoKind::G;
+    }
+    assert_eq!(matrix.clear_top_rows(2), 2);
+    assert_eq!(matrix.get(ivec2(0, 0)), Some(MinoKind::G));
+    assert_eq!(matrix.get(ivec2(0, 1)), Some(MinoKind::E));
+    assert_eq!(matrix.clear_top_rows(5), 1);
+    assert_eq!(matrix.clear_top_rows(5), 0);
+
+    let (shapes, kicks) = load_default_tables().expect("the default tables should load");
+
+    let mut ending = Game::new(RunSettings::default(), 3, shapes.clone(), kicks.clone());
+    stack_up(&mut ending);
+    assert!(
+        ending.is_over(),
+        "stacking up without lifesavers should top out"
+    );
+
+    let settings = RunSettings {
+        lifesaver_rows: LIFESAVER_ROWS,
+        ..Default::default()
+    };
+    let mut saved = Game::new(settings, 3, shapes, kicks);
+    stack_up(&mut saved);
+    assert!(!saved.is_over(), "lifesavers should keep the game going");
+    let lifesavers = saved.stats().lifesavers;
+    assert!(lifesavers > 0);
+
+    let (_, resimulated) = saved.record().expect("the game should play back");
+    assert_eq!(resimulated.lifesavers, lifesavers);
+
+    let code = saved.run_code();
+    let decoded = RunCode::decode(&code.encode().expect("run code")).expect("should decode");
+    assert_eq!(decoded.settings.lifesaver_rows, LIFESAVER_ROWS);
+
+    println!("Used {lifesavers} lifesavers in {PIECES} pieces");
+}
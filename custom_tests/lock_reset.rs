@@ -0,0 +1,112 @@
+//! Plays a game with strong gravity, shifting each piece back and forth on the floor under each of
+//! the lock delay behaviours in turn. Wiggling the piece for far longer than the lock delay should
+//! lock it under move reset, once it runs out of resets, and under step reset, but keep it alive
+//! under infinite. Exits once every behaviour has been checked; a panic along the way is a failure.
+
+use std::time::Duration;
+
+use bevy::app::AppExit;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use stack_practice::board::events::PieceLocked;
+use stack_practice::board::LockReset;
+use stack_practice::schedule::FrameSet;
+use stack_practice::screens::GlobalSettings;
+use stack_practice::state::{assets_loaded, MainState};
+use stack_practice::StackPracticePlugins;
+
+/// Length of a frame at 60 frames per second
+const FRAME: Duration = Duration::from_micros(16_667);
+/// Frames spent on each behaviour, long enough for a piece to run out of move resets and then wait
+/// out the lock delay
+const STAGE_FRAMES: u32 = 120;
+/// Frames at the start of each behaviour in which locks are not counted, since the piece left over
+/// from the behaviour before is dropped then
+const GRACE_FRAMES: u32 = 5;
+/// Frames between each shift, well within the lock delay
+const WIGGLE_INTERVAL: u32 = 3;
+
+/// Each behaviour, along with whether a piece wiggled on the floor should lock under it
+const STAGES: [(LockReset, bool); 3] = [
+    (LockReset::MoveReset, true),
+    (LockReset::StepReset, true),
+    (LockReset::Infinite, false),
+];
+
+const HARD_DROP_KEY: KeyCode = KeyCode::Space;
+const SHIFT_KEYS: [KeyCode; 2] = [KeyCode::KeyA, KeyCode::KeyD];
+
+#[allow(clippy::too_many_arguments)]
+fn wiggle(
+    state: Res<State<MainState>>,
+    mut next: ResMut<NextState<MainState>>,
+    mut settings: ResMut<GlobalSettings>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut locks: EventReader<PieceLocked>,
+    mut playing: Local<u32>,
+    mut locked: Local<u32>,
+    mut exit: EventWriter<AppExit>,
+) {
+    keys.release_all();
+    match state.get() {
+        MainState::Ready => {
+            // pieces reach the floor on the frame after they spawn
+            settings.gravity_power = "1000".into();
+            next.set(MainState::Playing);
+        }
+        MainState::Playing => {
+            let (stage, frame) = (*playing / STAGE_FRAMES, *playing % STAGE_FRAMES);
+            *playing += 1;
+            let (reset, should_lock) = STAGES[stage as usize];
+
+            let count = locks.read().count() as u32;
+            if frame == 0 {
+                settings.lock_reset = reset;
+                *locked = 0;
+            } else if frame >= GRACE_FRAMES {
+                *locked += count;
+            }
+
+            if frame + 1 < STAGE_FRAMES {
+                if frame % WIGGLE_INTERVAL == 0 {
+                    keys.press(SHIFT_KEYS[(frame / WIGGLE_INTERVAL % 2) as usize]);
+                }
+                return;
+            }
+
+            assert_eq!(
+                *locked > 0,
+                should_lock,
+                "whether a piece wiggled on the floor under {reset} locks"
+            );
+            println!(
+                "A piece wiggled on the floor under {reset} locked {} times",
+                *locked
+            );
+            if stage as usize + 1 == STAGES.len() {
+                exit.send(AppExit);
+            } else {
+                // every behaviour starts on a fresh piece
+                keys.press(HARD_DROP_KEY);
+            }
+        }
+        MainState::PostGame => panic!("the stack should not top out"),
+        MainState::LoadingFailed => panic!("the assets should load"),
+        MainState::Loading => (),
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, StackPracticePlugins))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME))
+        .add_systems(
+            PreUpdate,
+            wiggle
+                .after(InputSystem)
+                .before(FrameSet::Input)
+                .run_if(assets_loaded),
+        )
+        .run();
+}
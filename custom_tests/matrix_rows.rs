@@ -0,0 +1,64 @@
+//! Collapses rows out of a matrix, one at a time and several at once, both next to each other and
+//! apart, checking that the matrix comes out as the rows kept in order with empty rows on top.
+//! Exits with a panic if any check fails.
+
+use stack_practice::board::{Matrix, MinoKind};
+
+/// A matrix in which each row can be told apart from the others, with a gap in each so that no row
+/// is full
+fn labelled() -> Matrix {
+    use MinoKind::*;
+    let kinds = [I, O, T, S, Z, J, L, G];
+    let mut matrix = Matrix::default();
+    let width = matrix.width();
+    for (y, row) in matrix.rows_mut().enumerate() {
+        row.fill(kinds[y % kinds.len()]);
+        row[(y * 3) % width] = E;
+    }
+    matrix
+}
+
+/// The rows of the matrix after the given rows are taken out, worked out row by row
+fn expected(matrix: &Matrix, cleared: &[usize]) -> Vec<Vec<MinoKind>> {
+    let mut rows = matrix
+        .rows()
+        .enumerate()
+        .filter(|(y, _)| !cleared.contains(y))
+        .map(|(_, row)| row.to_vec())
+        .collect::<Vec<_>>();
+    rows.resize(matrix.height(), vec![MinoKind::E; matrix.width()]);
+    rows
+}
+
+/// Takes out the given rows from the bottom up, as clearing lines does, where each row taken out
+/// moves the rows above it down by one
+fn collapse(matrix: &mut Matrix, cleared: &[usize]) {
+    for (taken, &y) in cleared.iter().enumerate() {
+        matrix.collapse_row(y - taken);
+    }
+}
+
+fn main() {
+    let matrix = labelled();
+    let top = matrix.height() - 1;
+    let cases: [&[usize]; 6] = [
+        &[0],
+        &[5],
+        &[0, 1, 2, 3],
+        &[1, 3],
+        &[0, 2, 5, 9],
+        &[top - 1, top],
+    ];
+    for cleared in cases {
+        let mut collapsed = matrix.clone();
+        collapse(&mut collapsed, cleared);
+        let rows = collapsed.rows().map(<[_]>::to_vec).collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            expected(&matrix, cleared),
+            "clearing rows {cleared:?}"
+        );
+    }
+
+    println!("Rows collapsed as expected for every set of cleared rows");
+}
@@ -0,0 +1,71 @@
+//! Plays a few bags of an openers drill through the headless API, checking that the board starts
+//! over after every bag and that the record plays back the same way. Also checks the least presses
+//! found for a few placements. Exits with a panic if any check fails.
+
+use bevy::math::IVec2;
+use stack_practice::api::{load_default_tables, Game, Move};
+use stack_practice::board::finesse::minimum_presses;
+use stack_practice::board::update::default_mino;
+use stack_practice::board::{GameMode, Hold, Mino, MinoKind, RotationState};
+use stack_practice::replay::code::RunSettings;
+
+const BAGS: usize = 3;
+const BAG_SIZE: usize = 7;
+
+fn main() {
+    let (shapes, kicks) = load_default_tables().expect("the default tables should load");
+
+    let spawned = default_mino(MinoKind::T, &shapes);
+    assert_eq!(minimum_presses(spawned, &shapes, &kicks), Some(0));
+    let tapped = Mino {
+        position: spawned.position - IVec2::X,
+        ..spawned
+    };
+    assert_eq!(minimum_presses(tapped, &shapes, &kicks), Some(1));
+    let flipped = Mino {
+        rotation: RotationState::Down,
+        ..spawned
+    };
+    assert_eq!(minimum_presses(flipped, &shapes, &kicks), Some(1));
+    // an O piece looks the same in every rotation, so turning it is never needed
+    let o = Mino {
+        rotation: RotationState::Right,
+        ..default_mino(MinoKind::O, &shapes)
+    };
+    assert_eq!(minimum_presses(o, &shapes, &kicks), Some(0));
+
+    let settings = RunSettings {
+        mode: GameMode::Openers,
+        ..Default::default()
+    };
+    let mut game = Game::new(settings, 7, shapes, kicks);
+    for bag in 0..BAGS {
+        for piece in 0..BAG_SIZE {
+            let placement = game
+                .legal_placements()
+                .into_iter()
+                .min_by_key(|mino| (mino.position.y, mino.position.x))
+                .expect("a drill never runs out of room within a bag");
+            game.submit(Move::Place(placement))
+                .expect("legal placements should always be accepted");
+
+            let observation = game.observe();
+            let empty = observation
+                .matrix
+                .cells()
+                .iter()
+                .all(|&kind| kind == MinoKind::E);
+            assert_eq!(
+                empty,
+                piece + 1 == BAG_SIZE,
+                "the matrix should be empty exactly at the end of a bag (bag {bag}, piece {piece})"
+            );
+        }
+        assert!(matches!(game.observe().hold, Hold::Empty));
+    }
+    assert!(!game.is_over());
+
+    let (record, resimulated) = game.record().expect("the drill should play back");
+    assert_eq!(resimulated.pieces as usize, BAGS * BAG_SIZE);
+    assert!(!record.segments.is_empty());
+}
@@ -0,0 +1,46 @@
+//! Connects to the overlay server as an overlay would, checking that events arrive as JSON tagged
+//! with their type, and that stopping the server closes the connection cleanly rather than cutting
+//! it off. Exits with a panic if any check fails.
+
+use bevy::prelude::*;
+use serde_json::Value;
+use stack_practice::board::events::{GameEndReason, GameEnded, LinesCleared};
+use stack_practice::overlay::{OverlayMessage, OverlayServer};
+use tungstenite::Message;
+
+fn main() {
+    let server = OverlayServer::start(0).expect("the server should start on a free port");
+    let (mut client, _) = tungstenite::connect(format!("ws://localhost:{}", server.port()))
+        .expect("the overlay should connect");
+
+    let board = Entity::from_raw(3);
+    assert!(server.send(&OverlayMessage::LinesCleared(LinesCleared {
+        board,
+        rows: vec![0, 1],
+        replayed: false,
+    })));
+    assert!(server.send(&OverlayMessage::GameEnded(GameEnded {
+        board,
+        reason: GameEndReason::TopOut,
+    })));
+
+    let mut read_json = || match client.read().expect("a message should arrive") {
+        Message::Text(text) => serde_json::from_str::<Value>(&text).expect("messages are JSON"),
+        other => panic!("expected a text message, got {other:?}"),
+    };
+    let cleared = read_json();
+    assert_eq!(cleared["type"], "lines_cleared");
+    assert_eq!(cleared["rows"], serde_json::json!([0, 1]));
+    assert_eq!(cleared["replayed"], false);
+    let ended = read_json();
+    assert_eq!(ended["type"], "game_ended");
+    assert_eq!(ended["reason"], "top_out");
+
+    drop(server);
+    match client.read() {
+        Ok(Message::Close(_)) => (),
+        other => panic!("expected the server to close the connection, got {other:?}"),
+    }
+
+    println!("Overlay messages were received and the connection was closed");
+}
@@ -0,0 +1,62 @@
+//! Works out the pace of a game played through the headless API, where every piece takes the same
+//! time, and checks the rates against the known timing. Exits with a panic if any check fails.
+
+use stack_practice::api::{load_default_tables, Game, Move};
+use stack_practice::replay::code::PLACEMENT_FRAMES;
+use stack_practice::stats::{lock_outcomes, pace, PACE_WINDOW};
+
+/// Pieces to play, enough for the window to fill several times over
+const PIECES: u32 = 60;
+
+fn main() {
+    let (shapes, kicks) = load_default_tables().expect("the default tables should load");
+    let mut game = Game::new(Default::default(), 0, shapes, kicks);
+
+    // the lowest placement each time, so that lines are cleared every so often
+    let mut lines = Vec::new();
+    while !game.is_over() && game.stats().pieces < PIECES {
+        let placement = game
+            .legal_placements()
+            .into_iter()
+            .min_by_key(|mino| (mino.position.y, mino.position.x))
+            .expect("a game which is not over has placements");
+        let locked = game
+            .submit(Move::Place(placement))
+            .expect("legal placements should always be accepted")
+            .expect("placing a piece locks it");
+        lines.push(locked.lines as u32);
+    }
+
+    let (record, _) = game.record().expect("the game should play back");
+    let outcomes = lock_outcomes(&record, game.shape_table());
+    assert_eq!(
+        outcomes.iter().map(|o| o.lines).collect::<Vec<_>>(),
+        lines,
+        "the clears found in the record should match the clears of the game"
+    );
+
+    let pace = pace(&record, game.shape_table());
+    assert_eq!(pace.points.len(), lines.len());
+    let expected_pps = 60.0 / PLACEMENT_FRAMES as f32;
+    for (ix, point) in pace.points.iter().enumerate() {
+        assert_eq!(point.frame, (ix as u64 + 1) * PLACEMENT_FRAMES);
+        assert!(
+            (point.pps - expected_pps).abs() < 1e-4,
+            "piece {} was placed at {} PPS rather than {expected_pps}",
+            ix + 1,
+            point.pps
+        );
+    }
+
+    let first_clear = lines.iter().position(|&l| l > 0).unwrap_or(lines.len());
+    assert!(pace.points[..first_clear].iter().all(|p| p.apm == 0.0));
+    if first_clear < lines.len() {
+        assert!(pace.points[first_clear].apm > 0.0 || lines[first_clear] == 1);
+    }
+
+    println!(
+        "{} pieces at {expected_pps} PPS over a {PACE_WINDOW}s window, {} lines cleared",
+        lines.len(),
+        lines.iter().sum::<u32>()
+    );
+}
@@ -0,0 +1,83 @@
+//! Moves a piece about on the floor and checks what its motion makes of it: rotations on the floor
+//! use up the move resets, falling to a new row gives them all back, and only a piece whose last
+//! move was a rotation can spin. Exits with a panic if any check fails.
+
+use bevy::math::ivec2;
+use stack_practice::board::motion::{PieceAction, PieceMotion};
+use stack_practice::board::{LockReset, Mino, MinoKind, RotationState};
+
+const LIMIT: u32 = 2;
+
+fn main() {
+    let spawn = Mino {
+        kind: MinoKind::T,
+        position: ivec2(4, 22),
+        rotation: RotationState::Up,
+    };
+    let mut motion = PieceMotion::spawned(spawn);
+    assert_eq!(motion.lowest(), 22);
+    assert_eq!(motion.last_action(), None);
+    assert!(!motion.spun());
+
+    // the piece falls to the floor, and reaching each new row resets the lock delay
+    motion.fell();
+    assert!(motion.lock_reset(1, false, LockReset::MoveReset, LIMIT));
+    assert_eq!(motion.lowest(), 1);
+    assert!(!motion.spun());
+    assert!(!motion.lock_reset(1, false, LockReset::MoveReset, LIMIT));
+
+    // rotating on the floor uses up the resets, then stops resetting
+    for used in 1..=LIMIT {
+        motion.rotated(ivec2(0, 0));
+        assert!(motion.lock_reset(1, true, LockReset::MoveReset, LIMIT));
+        assert_eq!(motion.resets(), used);
+    }
+    motion.rotated(ivec2(-1, 0));
+    assert!(!motion.lock_reset(1, true, LockReset::MoveReset, LIMIT));
+    assert_eq!(motion.resets(), LIMIT);
+    assert!(motion.spun());
+    assert_eq!(motion.last_kick(), Some(ivec2(-1, 0)));
+
+    // a kick down into a new row gives every reset back
+    motion.rotated(ivec2(0, -1));
+    assert!(motion.lock_reset(0, true, LockReset::MoveReset, LIMIT));
+    assert_eq!(motion.resets(), 0);
+    assert_eq!(motion.lowest(), 0);
+
+    // a shift after the rotation means the lock is no spin, though the kick is remembered
+    motion.shifted();
+    assert!(!motion.spun());
+    assert_eq!(motion.last_kick(), Some(ivec2(0, -1)));
+
+    // the other behaviours ignore the limit
+    let mut motion = PieceMotion::spawned(spawn);
+    assert!(motion.lock_reset(22, true, LockReset::Infinite, 0));
+    assert!(!motion.lock_reset(22, true, LockReset::StepReset, LIMIT));
+    assert!(motion.lock_reset(21, false, LockReset::StepReset, LIMIT));
+
+    // moves worked out from positions alone, as in the replay
+    let turned = Mino {
+        rotation: RotationState::Right,
+        position: ivec2(5, 22),
+        ..spawn
+    };
+    assert_eq!(
+        PieceAction::between(spawn, turned),
+        Some(PieceAction::Rotate)
+    );
+    let dropped = Mino {
+        position: ivec2(4, 20),
+        ..spawn
+    };
+    assert_eq!(
+        PieceAction::between(spawn, dropped),
+        Some(PieceAction::Fall)
+    );
+    assert_eq!(PieceAction::between(spawn, spawn), None);
+    let mut motion = PieceMotion::spawned(spawn);
+    motion.moved(spawn, turned);
+    assert!(motion.spun());
+    assert_eq!(motion.last_kick(), Some(ivec2(1, 0)));
+
+    println!("The motion of the piece was followed through rotations, falls and shifts");
+}
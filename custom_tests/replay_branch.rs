@@ -0,0 +1,126 @@
+//! Plays a game by hard dropping every piece where it spawns until the stack tops out, then rewinds
+//! the replay halfway through the game and pauses it on a piece. Pressing shift there should branch the
+//! replay into a new game in which the piece shifts over by exactly one column, and is not hard
+//! dropped along the way. Exits once the piece has been checked; a panic along the way is a
+//! failure.
+
+use bevy::app::AppExit;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use stack_practice::board::{Active, Matrix, Mino};
+use stack_practice::replay::ghost::Ghost;
+use stack_practice::replay::replay::ReplayInfo;
+use stack_practice::schedule::FrameSet;
+use stack_practice::state::{assets_loaded, MainState};
+use stack_practice::StackPracticePlugins;
+
+/// Frames between each hard drop, so that every piece has spawned before it is dropped
+const DROP_INTERVAL: u32 = 10;
+/// Frames that the game may take to top out before the test gives up on it
+const PLAYING_FRAMES: u32 = 60 * 60;
+/// Frames to wait after pausing before shifting, so that the replay has settled on its frame
+const SETTLE_FRAMES: u32 = 2;
+/// Frames to keep playing after the branch, in which the piece should not shift again
+const FRAMES_AFTER: u32 = 10;
+
+const HARD_DROP_KEY: KeyCode = KeyCode::Space;
+const REVERSE_KEY: KeyCode = KeyCode::KeyR;
+const PAUSE_KEY: KeyCode = KeyCode::KeyP;
+const SHIFT_LEFT_KEY: KeyCode = KeyCode::KeyA;
+
+#[derive(Default)]
+enum Step {
+    #[default]
+    Playing,
+    /// Rewinding to the given frame of the record
+    Rewinding(u64),
+    Paused(u32),
+    Branched(Mino, Matrix, u32),
+}
+
+#[allow(clippy::too_many_arguments)]
+fn drop_rewind_and_shift(
+    state: Res<State<MainState>>,
+    mut next: ResMut<NextState<MainState>>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    info: Option<Res<ReplayInfo>>,
+    boards: Query<(&Active, &Matrix), Without<Ghost>>,
+    mut step: Local<Step>,
+    mut playing: Local<u32>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for key in [HARD_DROP_KEY, REVERSE_KEY, PAUSE_KEY, SHIFT_LEFT_KEY] {
+        keys.release(key);
+    }
+
+    match (state.get(), &mut *step) {
+        (MainState::Ready, Step::Playing) => next.set(MainState::Playing),
+        (MainState::Playing, Step::Playing) => {
+            *playing += 1;
+            assert!(*playing <= PLAYING_FRAMES, "the stack should top out");
+            if *playing % DROP_INTERVAL == 0 {
+                keys.press(HARD_DROP_KEY);
+            }
+        }
+        (MainState::PostGame, Step::Playing) => {
+            let info = info.expect("the replay should have begun");
+            keys.press(REVERSE_KEY);
+            *step = Step::Rewinding(info.frame / 2);
+        }
+        (MainState::PostGame, Step::Rewinding(target)) => {
+            let info = info.expect("the replay should have begun");
+            if info.frame <= *target {
+                keys.press(PAUSE_KEY);
+                *step = Step::Paused(0);
+            }
+        }
+        (MainState::PostGame, Step::Paused(frames)) => {
+            *frames += 1;
+            if *frames == SETTLE_FRAMES {
+                let (active, matrix) = boards.single();
+                let piece = active.0.expect("the replay should be paused on a piece");
+                keys.press(SHIFT_LEFT_KEY);
+                *step = Step::Branched(piece, matrix.clone(), 0);
+            }
+        }
+        (MainState::PostGame, Step::Branched(..)) => (),
+        (MainState::Playing, Step::Branched(piece, matrix_before, frames)) => {
+            *frames += 1;
+            assert_eq!(
+                boards.single().1,
+                &*matrix_before,
+                "nothing should have locked"
+            );
+            if *frames < FRAMES_AFTER {
+                return;
+            }
+
+            let active = boards.single().0;
+            let active = active.expect("the piece should still be in play");
+            assert_eq!(active.kind, piece.kind);
+            assert_eq!(
+                active.position.x,
+                piece.position.x - 1,
+                "the piece should have shifted exactly once"
+            );
+            println!("Shifting branched the replay and moved the piece once, without dropping it");
+            exit.send(AppExit);
+        }
+        (MainState::LoadingFailed, _) => panic!("the assets should load"),
+        (MainState::Loading, _) => (),
+        (state, _) => panic!("the game should not be in {state:?} at this point"),
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, StackPracticePlugins))
+        .add_systems(
+            PreUpdate,
+            drop_rewind_and_shift
+                .after(InputSystem)
+                .before(FrameSet::Input)
+                .run_if(assets_loaded),
+        )
+        .run();
+}
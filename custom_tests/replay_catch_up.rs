@@ -0,0 +1,136 @@
+//! Plays a replay along with a mocked clock, once frame by frame and once with a ten second gap
+//! between two updates, as after a long hitch or the app being suspended. The replay should catch
+//! up over the gap by jumping rather than playing every item at once, and end up with the same
+//! board either way. Exits with a panic if any check fails.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use stack_practice::api::{load_default_tables, Game, Move};
+use stack_practice::assets::tables::shape_table::{DefaultShapeTable, ShapeTable};
+use stack_practice::board::events::{HoldUsed, LinesCleared, PieceLocked};
+use stack_practice::board::queue::PieceQueue;
+use stack_practice::board::{Active, Board, Hold, Matrix};
+use stack_practice::replay::record::CompleteRecord;
+use stack_practice::replay::replay::{advance_frame, replay, ReplayInfo};
+use stack_practice::screens::GlobalSettings;
+use stack_practice::toasts::Toasts;
+
+/// Length of a frame at 60 frames per second
+const FRAME: Duration = Duration::from_micros(16_667);
+/// Pieces placed in the game, enough to last well past the gap
+const PIECES: usize = 50;
+/// Seconds played frame by frame before the gap
+const BEFORE_GAP: u32 = 2;
+const GAP: Duration = Duration::from_secs(10);
+
+/// A record of a game which places each piece as low as it goes, so that the stack stays low
+fn make_record() -> CompleteRecord {
+    let (shapes, kicks) = load_default_tables().expect("the default tables should load");
+    let mut game = Game::new(Default::default(), 7, shapes, kicks);
+    for _ in 0..PIECES {
+        if game.is_over() {
+            break;
+        }
+        let placement = game
+            .legal_placements()
+            .into_iter()
+            .min_by_key(|mino| (mino.position.y, mino.position.x))
+            .unwrap();
+        game.submit(Move::Place(placement)).unwrap();
+    }
+    game.record().expect("the game should make a record").0
+}
+
+/// An app which replays the record onto a board from its first frame, with time only passing when
+/// the test advances it
+fn replay_app(record: &CompleteRecord) -> App {
+    let (shapes, _) = load_default_tables().expect("the default tables should load");
+    let mut assets = Assets::<ShapeTable>::default();
+    let table = assets.add(shapes);
+
+    let mut app = App::new();
+    app.insert_resource(Time::<()>::default())
+        .insert_resource(record.clone())
+        .insert_resource(assets)
+        .insert_resource(DefaultShapeTable::new(table))
+        .init_resource::<ReplayInfo>()
+        .init_resource::<GlobalSettings>()
+        .init_resource::<Toasts>()
+        .add_event::<PieceLocked>()
+        .add_event::<LinesCleared>()
+        .add_event::<HoldUsed>()
+        .add_systems(Update, (advance_frame, replay).chain());
+    app.world.spawn(Board::at(Vec3::ZERO));
+
+    let time = app.world.resource::<Time>().clone();
+    app.world
+        .resource_mut::<ReplayInfo>()
+        .play(false, record, &time);
+    app.update();
+    app
+}
+
+fn step(app: &mut App, by: Duration) {
+    app.world.resource_mut::<Time>().advance_by(by);
+    app.update();
+}
+
+/// The parts of the board which the replay brings up to date, written out to be compared
+fn board_state(app: &mut App) -> (Matrix, String) {
+    let (matrix, active, hold, queue) = app
+        .world
+        .query::<(&Matrix, &Active, &Hold, &PieceQueue)>()
+        .single(&app.world);
+    (matrix.clone(), format!("{:?} {hold:?} {queue:?}", active.0))
+}
+
+fn main() {
+    let record = make_record();
+    let frames_before = BEFORE_GAP * 60;
+    let frames_total = frames_before + GAP.as_secs() as u32 * 60;
+    assert!(
+        record.last_frame() > frames_total as u64,
+        "the record should go on past the gap"
+    );
+
+    let mut walked = replay_app(&record);
+    for _ in 0..frames_total {
+        step(&mut walked, FRAME);
+    }
+
+    let mut jumped = replay_app(&record);
+    for _ in 0..frames_before {
+        step(&mut jumped, FRAME);
+    }
+    jumped.world.resource_mut::<Events<PieceLocked>>().clear();
+    step(&mut jumped, GAP);
+    assert!(
+        jumped.world.resource::<Events<PieceLocked>>().is_empty(),
+        "catching up should not play out each lock on the way"
+    );
+
+    assert_eq!(
+        walked.world.resource::<ReplayInfo>().frame,
+        jumped.world.resource::<ReplayInfo>().frame
+    );
+    let (matrix, rest) = board_state(&mut walked);
+    assert_ne!(
+        matrix,
+        Matrix::default(),
+        "pieces should have locked by now"
+    );
+    assert_eq!(board_state(&mut jumped), (matrix, rest));
+
+    // the replay plays on as usual after catching up
+    for _ in 0..60 {
+        step(&mut walked, FRAME);
+        step(&mut jumped, FRAME);
+    }
+    assert_eq!(board_state(&mut walked), board_state(&mut jumped));
+
+    println!(
+        "The replay caught up over a {}s gap to the same board",
+        GAP.as_secs()
+    );
+}
@@ -0,0 +1,107 @@
+//! Cuts a chapter out of a small record and checks that it starts from the board as it stood on
+//! its first frame, that its items are moved to begin there, and that it plays back like any other
+//! record. Exits with a panic if any check fails.
+
+use bevy::math::ivec2;
+use stack_practice::api::load_default_tables;
+use stack_practice::board::queue::PieceQueue;
+use stack_practice::board::{Hold, Matrix, MatrixUpdate, Mino, MinoKind, RotationState};
+use stack_practice::replay::bookmarks::{Bookmark, Bookmarks};
+use stack_practice::replay::file::ReplayFile;
+use stack_practice::replay::record::{CompleteRecord, RecordData, RecordItem, RecordSegment};
+use stack_practice::replay::verify::verify_record;
+
+fn piece(kind: MinoKind) -> RecordData {
+    RecordData::ActiveChange(Some(Mino {
+        kind,
+        position: ivec2(4, 20),
+        rotation: RotationState::Up,
+    }))
+}
+
+fn fill(x: i32, y: i32, kind: MinoKind) -> RecordData {
+    RecordData::MatrixChange(MatrixUpdate {
+        loc: ivec2(x, y),
+        old: MinoKind::E,
+        new: kind,
+    })
+}
+
+fn main() {
+    let (shapes, _) = load_default_tables().expect("the default tables should load");
+    let frames = [
+        (0, RecordData::ActiveChange(None)),
+        (0, RecordData::Hold(Hold::Empty)),
+        (0, RecordData::QueueChange(PieceQueue::default())),
+        (10, piece(MinoKind::T)),
+        (20, fill(0, 0, MinoKind::T)),
+        (20, fill(1, 0, MinoKind::T)),
+        (20, piece(MinoKind::I)),
+        (30, fill(0, 1, MinoKind::I)),
+        (30, piece(MinoKind::O)),
+        (40, RecordData::Hold(Hold::Inactive(MinoKind::O))),
+        (40, piece(MinoKind::L)),
+        (50, piece(MinoKind::J)),
+    ];
+    let mut segment = RecordSegment::default();
+    segment.extend(frames.into_iter().map(|(time, data)| RecordItem {
+        time,
+        micros: time * 1_000_000 / 60,
+        tick: Some(time),
+        data,
+    }));
+    let mut record = CompleteRecord::default();
+    record.add_segment(segment);
+    let len = record.len();
+
+    let mut bookmarks = Bookmarks::default();
+    bookmarks.add(Bookmark::new(5, "before".into(), &record));
+    bookmarks.add(Bookmark::new(40, "held".into(), &record));
+
+    let file = ReplayFile::from_chapter(&record, &bookmarks, 25..=45);
+    assert_eq!(record.len(), len, "the record itself is left alone");
+
+    // the board on frame 25, laid out on the first frame of the chapter
+    let (initial, rest): (Vec<_>, Vec<_>) = file.items.iter().partition(|item| item.time == 0);
+    assert!(matches!(
+        initial[0].data,
+        RecordData::ActiveChange(Some(Mino {
+            kind: MinoKind::I,
+            ..
+        }))
+    ));
+    let filled = initial
+        .iter()
+        .filter(|item| matches!(item.data, RecordData::MatrixChange(_)))
+        .count();
+    assert_eq!(filled, 2);
+    assert!(initial
+        .iter()
+        .any(|item| matches!(item.data, RecordData::Keyframe(_))));
+
+    // the items after frame 25, up to frame 45, moved to begin on the first frame of the chapter
+    assert_eq!(
+        rest.iter().map(|item| item.time).collect::<Vec<_>>(),
+        [5, 5, 15, 15]
+    );
+    assert!(rest.iter().all(|item| item.tick.is_none()));
+    assert_eq!(file.bookmarks.len(), 1);
+    assert_eq!(file.bookmarks[0].frame, 15);
+
+    verify_record(&file.items, &shapes).expect("the chapter should play back");
+    let mut matrix = Matrix::default();
+    for item in &file.items {
+        if let RecordData::MatrixChange(update) = &item.data {
+            *matrix.get_mut(update.loc).unwrap() = update.new;
+        }
+    }
+    assert_eq!(matrix.get(ivec2(0, 0)), Some(MinoKind::T));
+    assert_eq!(matrix.get(ivec2(1, 0)), Some(MinoKind::T));
+    assert_eq!(matrix.get(ivec2(0, 1)), Some(MinoKind::I));
+
+    let text = file.to_text().expect("the chapter should be written");
+    let (_, reread) = ReplayFile::parse(&text).expect("the chapter should be read back");
+    assert_eq!(reread.items.len(), file.items.len());
+
+    println!("The chapter began from the board on its first frame and played back");
+}
@@ -0,0 +1,82 @@
+//! Reads a replay file in each supported version of the format from the fixtures, checking that
+//! missing fields are filled in and that the replay survives being written and read back in the
+//! current version, and that its items can be rescaled to another frame rate. Also checks that
+//! files from newer versions are refused. Exits with a panic if any check fails.
+
+use stack_practice::board::GameMode;
+use stack_practice::replay::file::{ReplayFile, ReplayFileError, FORMAT_VERSION, OLDEST_VERSION};
+use stack_practice::replay::record::{FrameRate, RecordData};
+
+const FIXTURES: [(u32, &str); 3] = [
+    (1, include_str!("fixtures/replay_v1.ron")),
+    (2, include_str!("fixtures/replay_v2.ron")),
+    (3, include_str!("fixtures/replay_v3.ron")),
+];
+
+/// Checks the parts of the fixtures which every version has. The second item is half a second in,
+/// whatever rate the fixture was taken at.
+fn check_record(file: &ReplayFile) {
+    assert_eq!(file.items.len(), 3);
+    assert_eq!(file.items_at(FrameRate::default())[1].time, 30);
+    assert_eq!(file.metadata.frame_rate.seconds(file.items[1].time), 0.5);
+    assert!(matches!(
+        file.items[0].data,
+        RecordData::ActiveChange(Some(_))
+    ));
+    assert_eq!(file.bookmarks.len(), 1);
+    assert_eq!(file.bookmarks[0].label, "held");
+}
+
+fn main() {
+    let versions = FIXTURES.map(|(version, _)| version);
+    assert!((OLDEST_VERSION..=FORMAT_VERSION).all(|v| versions.contains(&v)));
+
+    for (version, text) in FIXTURES {
+        let (read_version, file) = ReplayFile::parse(text).expect("the fixture should be read");
+        assert_eq!(read_version, version);
+        check_record(&file);
+        match version {
+            1 => {
+                assert!(file.settings.is_none());
+                assert_eq!(file.metadata, Default::default());
+            }
+            _ => {
+                let snapshot = file.settings.as_ref().expect("the fixture has settings");
+                assert_eq!(snapshot.settings.mode, GameMode::Openers);
+                assert_eq!(snapshot.seed, 42);
+                assert_eq!(file.metadata.saved_at, 1700000000);
+            }
+        }
+        // files from before version 3 were taken at the old fixed rate, while the version 3
+        // fixture keeps the rate it names
+        let rate = if version < 3 { 60 } else { 120 };
+        assert_eq!(*file.metadata.frame_rate, rate);
+        let rescaled = file.items_at(FrameRate::new(240));
+        assert_eq!(rescaled[1].time, 120);
+        assert_eq!(rescaled[1].micros, file.items[1].micros);
+
+        let written = file.to_text().expect("the replay should be written");
+        let (written_version, reread) = ReplayFile::parse(&written).expect("should read back");
+        assert_eq!(written_version, FORMAT_VERSION);
+        check_record(&reread);
+        assert_eq!(reread.metadata, file.metadata);
+        assert_eq!(reread.to_text().expect("written again"), written);
+    }
+
+    let (_, current) = FIXTURES[FIXTURES.len() - 1];
+    let body = current
+        .split_once('\n')
+        .expect("the fixture has a header")
+        .1;
+    let future = format!("stack-practice-replay {}\n{body}", FORMAT_VERSION + 1);
+    assert!(matches!(
+        ReplayFile::parse(&future),
+        Err(ReplayFileError::NewerVersion(v)) if v == FORMAT_VERSION + 1
+    ));
+    assert!(matches!(
+        ReplayFile::parse("stack-practice-replay two\n()"),
+        Err(ReplayFileError::BadHeader)
+    ));
+
+    println!("Replays of every supported version were read and written back");
+}
@@ -0,0 +1,76 @@
+//! Respawns the board every frame, in between the systems which spawn the display of each board and
+//! the systems which update it, so that the update systems keep seeing boards whose children are
+//! missing. Then despawns the display children of a fresh board one at a time, so that each update
+//! system also sees a board missing only the child it draws to. Exits once every child has been
+//! despawned; a panic along the way is a failure.
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use stack_practice::board::{Board, Matrix};
+use stack_practice::display::DisplayEntitySet;
+use stack_practice::state::assets_loaded;
+use stack_practice::StackPracticePlugins;
+
+/// Frames to respawn the board for before despawning its children
+const FRAMES: u32 = 300;
+
+fn respawn(commands: &mut Commands, boards: &Query<Entity, With<Matrix>>) {
+    for e in boards.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+    commands.spawn(Board::at(Vec3::ZERO));
+}
+
+fn respawn_every_frame(
+    mut commands: Commands,
+    boards: Query<Entity, With<Matrix>>,
+    board_children: Query<&Children>,
+    mut frames: Local<u32>,
+    mut exit: EventWriter<AppExit>,
+) {
+    *frames += 1;
+    if *frames <= FRAMES {
+        respawn(&mut commands, &boards);
+        return;
+    }
+
+    // a board respawned on one frame has all of its children on the next, when one of them is
+    // taken away before the update systems first see the board
+    let step = *frames - FRAMES - 1;
+    if step % 2 == 0 {
+        respawn(&mut commands, &boards);
+        return;
+    }
+    let child = (step / 2) as usize;
+    let Some(children) = boards
+        .get_single()
+        .ok()
+        .and_then(|e| board_children.get(e).ok())
+    else {
+        return;
+    };
+    match children.get(child) {
+        Some(&e) => commands.entity(e).despawn_recursive(),
+        None => {
+            println!(
+                "Respawned the board for {FRAMES} frames and went without each of its {} \
+                 children without panicking",
+                children.len()
+            );
+            exit.send(AppExit);
+        }
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, StackPracticePlugins))
+        .add_systems(
+            PostUpdate,
+            respawn_every_frame
+                .after(DisplayEntitySet::ApplyBuffers)
+                .before(DisplayEntitySet::Update)
+                .run_if(assets_loaded),
+        )
+        .run();
+}
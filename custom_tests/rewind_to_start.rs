@@ -0,0 +1,120 @@
+//! Plays a game by hard dropping every piece where it spawns until the stack tops out, then plays
+//! the replay of the game backward until it stops at the very start. There, the board should be as
+//! it was before the first piece spawned: no active piece, the hold and queue that the game began
+//! with, and an empty matrix. Exits once the board has been checked; a panic along the way is a
+//! failure.
+
+use bevy::app::AppExit;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use stack_practice::board::queue::PieceQueue;
+use stack_practice::board::{Active, Hold, Matrix};
+use stack_practice::replay::ghost::Ghost;
+use stack_practice::replay::record::{CompleteRecord, RecordData};
+use stack_practice::replay::replay::ReplayInfo;
+use stack_practice::schedule::FrameSet;
+use stack_practice::state::{assets_loaded, MainState};
+use stack_practice::StackPracticePlugins;
+
+/// Frames between each hard drop, so that every piece has spawned before it is dropped
+const DROP_INTERVAL: u32 = 10;
+/// Frames that the game may take to top out before the test gives up on it
+const PLAYING_FRAMES: u32 = 60 * 60;
+/// Frames that the replay should stay on its first frame before the board is checked, so that the
+/// replay has caught up with the frame it stopped on
+const SETTLE_FRAMES: u32 = 2;
+
+const HARD_DROP_KEY: KeyCode = KeyCode::Space;
+const REVERSE_KEY: KeyCode = KeyCode::KeyR;
+
+/// Holds the key down if asked to and lets go of it otherwise, so that each press is a fresh one
+fn press_on(keys: &mut ButtonInput<KeyCode>, key: KeyCode, press: bool) {
+    if press {
+        keys.press(key);
+    } else {
+        keys.release(key);
+    }
+}
+
+fn drop_then_rewind(
+    state: Res<State<MainState>>,
+    mut next: ResMut<NextState<MainState>>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut playing: Local<u32>,
+    mut replaying: Local<u32>,
+) {
+    match state.get() {
+        MainState::Ready => next.set(MainState::Playing),
+        MainState::Playing => {
+            *playing += 1;
+            assert!(*playing <= PLAYING_FRAMES, "the stack should top out");
+            press_on(&mut keys, HARD_DROP_KEY, *playing % DROP_INTERVAL == 0);
+        }
+        MainState::PostGame => {
+            keys.release(HARD_DROP_KEY);
+            *replaying += 1;
+            press_on(&mut keys, REVERSE_KEY, *replaying == 1);
+        }
+        MainState::LoadingFailed => panic!("the assets should load"),
+        MainState::Loading => (),
+    }
+}
+
+fn check_start(
+    info: Option<Res<ReplayInfo>>,
+    record: Res<CompleteRecord>,
+    boards: Query<(&Matrix, &Active, &Hold, &PieceQueue), Without<Ghost>>,
+    mut at_start: Local<u32>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if info.map_or(true, |info| info.frame != 0) {
+        return;
+    }
+    *at_start += 1;
+    if *at_start < SETTLE_FRAMES {
+        return;
+    }
+
+    let items = record.get(0..record.len());
+    let first_hold = items
+        .iter()
+        .find_map(|item| match &item.data {
+            RecordData::Hold(hold) => Some(format!("{hold:?}")),
+            _ => None,
+        })
+        .expect("the record should begin with the hold");
+    let first_queue = items
+        .iter()
+        .find_map(|item| match &item.data {
+            RecordData::QueueChange(queue) => Some(format!("{queue:?}")),
+            _ => None,
+        })
+        .expect("the record should begin with the queue");
+
+    let (matrix, active, hold, queue) = boards.single();
+    assert_eq!(
+        *matrix,
+        Matrix::default(),
+        "the matrix should be empty at the start"
+    );
+    assert!(active.0.is_none(), "no piece should have spawned yet");
+    assert_eq!(format!("{hold:?}"), first_hold);
+    assert_eq!(format!("{queue:?}"), first_queue);
+
+    println!("Rewinding to the start left the board as it was before the game began");
+    exit.send(AppExit);
+}
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, StackPracticePlugins))
+        .add_systems(
+            PreUpdate,
+            drop_then_rewind
+                .after(InputSystem)
+                .before(FrameSet::Input)
+                .run_if(assets_loaded),
+        )
+        .add_systems(PreUpdate, check_start.run_if(in_state(MainState::PostGame)))
+        .run();
+}
@@ -0,0 +1,122 @@
+//! Draws screenshots of known boards under each palette, then reads them back and checks that the
+//! boards come out the same, with the cells drawn in colors between two pieces flagged as garbage.
+//! Exits with a panic if any check fails.
+
+use bevy::math::{ivec2, uvec2, IVec2, URect};
+use image::{Rgba, RgbaImage};
+use stack_practice::assets::palette::{Palette, PalettePreset};
+use stack_practice::board::{Matrix, MinoKind};
+use stack_practice::screenshot_import::{classify, read_board};
+use strum::IntoEnumIterator;
+
+/// Size of each cell of the screenshots, in pixels
+const CELL: u32 = 24;
+/// Rows of the board shown in the screenshots
+const ROWS: u32 = 20;
+/// Space around the board in the screenshots, in pixels
+const MARGIN: u32 = 37;
+const BACKGROUND: Rgba<u8> = Rgba([18, 18, 24, 255]);
+const GRID: Rgba<u8> = Rgba([45, 45, 50, 255]);
+
+fn rgb(palette: &Palette, kind: MinoKind) -> [u8; 3] {
+    let [r, g, b, _] = palette.color(kind).as_rgba_u8();
+    [r, g, b]
+}
+
+/// A board with a bit of every piece on it and a few rows of garbage at the bottom
+fn known_board() -> Matrix {
+    use MinoKind::*;
+    let rows: [&[MinoKind]; 6] = [
+        &[G, G, G, G, E, G, G, G, G, G],
+        &[G, G, E, G, G, G, G, G, G, G],
+        &[T, T, T, O, O, L, J, J, S, E],
+        &[E, T, E, O, O, L, J, S, S, Z],
+        &[E, E, E, I, I, L, L, S, Z, Z],
+        &[E, E, E, E, E, E, E, E, Z, E],
+    ];
+    let mut matrix = Matrix::default();
+    for (y, row) in rows.iter().enumerate() {
+        matrix.row_mut(y).copy_from_slice(row);
+    }
+    matrix
+}
+
+/// Draws the board as a screenshot would show it, with lines between the cells. The cells given
+/// are drawn in a color halfway between two pieces, which should not be read as either.
+fn draw(matrix: &Matrix, palette: &Palette, muddled: &[IVec2]) -> RgbaImage {
+    let width = matrix.width() as u32;
+    let mut image = RgbaImage::from_pixel(
+        width * CELL + 2 * MARGIN,
+        ROWS * CELL + 2 * MARGIN,
+        BACKGROUND,
+    );
+    let halfway = {
+        let [a, b] = [MinoKind::T, MinoKind::Z].map(|kind| rgb(palette, kind));
+        [0, 1, 2].map(|i| ((a[i] as u16 + b[i] as u16) / 2) as u8)
+    };
+    for y in 0..ROWS {
+        for x in 0..width {
+            let cell = ivec2(x as i32, y as i32);
+            let [r, g, b] = if muddled.contains(&cell) {
+                halfway
+            } else {
+                match matrix.get(cell) {
+                    Some(MinoKind::E) | None => [BACKGROUND[0], BACKGROUND[1], BACKGROUND[2]],
+                    Some(kind) => rgb(palette, kind),
+                }
+            };
+            let left = MARGIN + x * CELL;
+            let top = MARGIN + (ROWS - 1 - y) * CELL;
+            for py in top..top + CELL {
+                for px in left..left + CELL {
+                    let edge = px == left || py == top;
+                    let color = if edge { GRID } else { Rgba([r, g, b, 255]) };
+                    image.put_pixel(px, py, color);
+                }
+            }
+        }
+    }
+    image
+}
+
+fn main() {
+    let region = URect::from_corners(
+        uvec2(MARGIN, MARGIN),
+        uvec2(MARGIN + 10 * CELL, MARGIN + ROWS * CELL),
+    );
+    // the monochrome palette tells the pieces apart by pattern alone, which no color can show
+    let presets = PalettePreset::iter().filter(|&p| p != PalettePreset::Monochrome);
+    for preset in presets {
+        let palette = Palette::new(preset);
+        for kind in MinoKind::STANDARD.into_iter().chain([MinoKind::G]) {
+            let [r, g, b] = rgb(&palette, kind);
+            let color = [r, g, b].map(|c| c as f32);
+            assert_eq!(classify(color, &palette), Some(kind), "{preset}: {kind:?}");
+        }
+
+        let matrix = known_board();
+        let read = read_board(&draw(&matrix, &palette, &[]), region, &palette);
+        assert_eq!(read.matrix, matrix, "{preset}: the board should read back");
+        assert!(
+            read.flagged.is_empty(),
+            "{preset}: no cell should be flagged"
+        );
+
+        let muddled = [ivec2(0, 2), ivec2(9, 3), ivec2(4, 7)];
+        let read = read_board(&draw(&matrix, &palette, &muddled), region, &palette);
+        let mut expected = matrix.clone();
+        for &cell in &muddled {
+            *expected.get_mut(cell).unwrap() = MinoKind::G;
+        }
+        assert_eq!(
+            read.matrix, expected,
+            "{preset}: muddled cells should be garbage"
+        );
+        let mut flagged = read.flagged.clone();
+        flagged.sort_by_key(|c| (c.y, c.x));
+        assert_eq!(
+            flagged, muddled,
+            "{preset}: muddled cells should be flagged"
+        );
+    }
+}
@@ -0,0 +1,81 @@
+//! Plays a game which holds every few pieces and hard drops the rest where they spawn until the
+//! stack tops out, then works out the session stats again from the record of the game. The pieces
+//! of each kind and the holds counted while playing should match those found in the record. Exits
+//! once the stats have been compared; a panic along the way is a failure.
+
+use bevy::app::AppExit;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use stack_practice::replay::record::CompleteRecord;
+use stack_practice::schedule::FrameSet;
+use stack_practice::state::{assets_loaded, MainState};
+use stack_practice::stats::{compute_stats, Stats};
+use stack_practice::StackPracticePlugins;
+
+/// Frames between each press, so that every piece has spawned before the next press
+const PRESS_INTERVAL: u32 = 10;
+/// Every this many presses is a hold rather than a hard drop
+const HOLD_EVERY: u32 = 3;
+/// Frames that the game may take to top out before the test gives up on it
+const PLAYING_FRAMES: u32 = 60 * 60;
+
+const HARD_DROP_KEY: KeyCode = KeyCode::Space;
+const HOLD_KEY: KeyCode = KeyCode::ShiftLeft;
+
+fn hold_and_drop(
+    state: Res<State<MainState>>,
+    mut next: ResMut<NextState<MainState>>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut playing: Local<u32>,
+) {
+    keys.release(HARD_DROP_KEY);
+    keys.release(HOLD_KEY);
+    match state.get() {
+        MainState::Ready => next.set(MainState::Playing),
+        MainState::Playing => {
+            *playing += 1;
+            assert!(*playing <= PLAYING_FRAMES, "the stack should top out");
+            if *playing % PRESS_INTERVAL == 0 {
+                if (*playing / PRESS_INTERVAL) % HOLD_EVERY == 0 {
+                    keys.press(HOLD_KEY);
+                } else {
+                    keys.press(HARD_DROP_KEY);
+                }
+            }
+        }
+        MainState::LoadingFailed => panic!("the assets should load"),
+        MainState::Loading | MainState::PostGame => (),
+    }
+}
+
+fn compare_stats(record: Res<CompleteRecord>, stats: Res<Stats>, mut exit: EventWriter<AppExit>) {
+    let computed = compute_stats(&record);
+    let live = &stats.session;
+    assert!(live.holds > 0, "pieces should have been held");
+    assert!(live.pieces() > 0, "pieces should have locked");
+    assert_eq!(live.pieces(), stats.pieces);
+
+    assert_eq!(live.placed, computed.placed, "pieces placed of each kind");
+    assert_eq!(live.holds, computed.holds, "holds used");
+
+    println!(
+        "The record agreed with the {} pieces and {} holds counted while playing",
+        live.pieces(),
+        live.holds
+    );
+    exit.send(AppExit);
+}
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, StackPracticePlugins))
+        .add_systems(
+            PreUpdate,
+            hold_and_drop
+                .after(InputSystem)
+                .before(FrameSet::Input)
+                .run_if(assets_loaded),
+        )
+        .add_systems(OnEnter(MainState::PostGame), compare_stats)
+        .run();
+}
@@ -1,27 +1,58 @@
-use bevy::math::ivec2;
+//! Renders every shape of a shape table, with one column for each piece and one row for each
+//! rotation. The table is `default.shape-table` unless another path (relative to the assets folder)
+//! is passed as an argument, e.g. `pentomino.shape-table`.
+
 use bevy::prelude::*;
 use bevy::{
-    math::{uvec2, vec2, Vec2},
+    math::{uvec2, vec2},
     sprite::{ColorMaterial, MaterialMesh2dBundle},
 };
 use itertools::{iproduct, Itertools};
 use stack_practice::assets::matrix_material::MatrixMaterialSpawner;
-use stack_practice::assets::tables::QueryShapeTable;
-use stack_practice::state::MainState;
+use stack_practice::assets::tables::shape_table::ShapeTable;
+use stack_practice::state::assets_loaded;
 use stack_practice::{assets::StackingAssetsPlugin, board::CELL_SIZE, state::StatePlugin};
 
-fn spawn_grid(
+#[derive(Resource)]
+struct TestedTable(Handle<ShapeTable>);
+
+fn load_table(mut commands: Commands, server: Res<AssetServer>) {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "default.shape-table".into());
+    commands.insert_resource(TestedTable(server.load(path)));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render_all_pieces(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    mut camera: Query<&mut OrthographicProjection>,
+    tested: Res<TestedTable>,
+    tables: Res<Assets<ShapeTable>>,
+    mut finished: Local<bool>,
+    mut spawner: MatrixMaterialSpawner,
 ) {
-    let base = vec2(-3.5, -1.5);
-    let size = Vec2::splat(CELL_SIZE as f32 * 4.0);
+    let Some(shapes) = tables.get(&tested.0) else {
+        return;
+    };
+    if *finished {
+        return;
+    }
+
+    let kinds = shapes.kinds();
+    let bounds = shapes.bounds(|_| true);
+    let columns = kinds.len() as u32;
+    // leave a cell of space around each piece
+    let size = (bounds.size() + IVec2::ONE).as_vec2() * CELL_SIZE as f32;
+    let base = -vec2(columns as f32 - 1.0, 3.0) / 2.0;
+
+    camera.single_mut().scale = (columns as f32 * size.x / 1280.0).max(4.0 * size.y / 720.0) * 1.1;
 
     let white = materials.add(ColorMaterial::from(Color::WHITE.with_a(0.2)));
     let black = materials.add(ColorMaterial::from(Color::BLACK.with_a(0.2)));
-
-    for (x, y) in iproduct!(0..7, 0..4) {
+    for (x, y) in iproduct!(0..columns, 0..4) {
         let p = (uvec2(x, y).as_vec2() + base) * size;
         let parity = (x + y) % 2 == 0;
         commands.spawn(MaterialMesh2dBundle {
@@ -31,45 +62,33 @@ fn spawn_grid(
             ..default()
         });
     }
-}
-
-fn render_all_pieces(
-    mut camera: Query<&mut OrthographicProjection>,
-    shapes: QueryShapeTable,
-    mut finished: Local<bool>,
-    mut spawner: MatrixMaterialSpawner,
-) {
-    if !*finished {
-        camera.single_mut().scale = 2.0;
 
-        shapes
-            .iter()
-            .sorted_by_key(|(p, _)| p.rotation)
-            .map(|(p, shape)| (p.kind, shape))
-            .into_group_map()
-            .into_iter()
-            .enumerate()
-            .flat_map(|(ix, (k, a))| {
-                let scale = (CELL_SIZE * 4) as f32;
-                let x = (ix as f32 - 3.5) * scale;
-                let ys = (0..4).map(move |p| (p as f32 - 1.5) * scale);
-                let cs = ys.map(move |y| vec2(x, y));
-                a.into_iter().zip(cs).zip(std::iter::repeat(k))
-            })
-            .for_each(|((shape, pos), kind)| {
-                let mut data = vec![0; 16];
-                for &s in shape {
-                    let loc = s + ivec2(1, 2);
-                    let ix = loc.y * 4 + loc.x;
-                    data[ix as usize] = kind as u32;
-                }
-                spawner
-                    .spawn_centered_with_data(ivec2(4, 4), data)
-                    .insert(Transform::from_translation(pos.extend(0.0)));
-            });
+    shapes
+        .iter()
+        .sorted_by_key(|(p, _)| p.rotation)
+        .map(|(p, shape)| (p.kind, shape))
+        .into_group_map()
+        .into_iter()
+        .flat_map(|(kind, rotations)| {
+            let x = kinds.iter().position(|&k| k == kind).unwrap_or_default();
+            rotations
+                .into_iter()
+                .enumerate()
+                .map(move |(y, shape)| (kind, shape, uvec2(x as u32, y as u32)))
+        })
+        .for_each(|(kind, shape, cell)| {
+            let mut data = vec![0; (bounds.size().x * bounds.size().y) as usize];
+            for &s in shape {
+                let loc = s - bounds.min;
+                data[(loc.y * bounds.size().x + loc.x) as usize] = kind as u32;
+            }
+            let pos = (cell.as_vec2() + base) * size;
+            spawner
+                .spawn_with_data(bounds, data)
+                .insert(Transform::from_translation(pos.extend(0.0)));
+        });
 
-        *finished = true;
-    }
+    *finished = true;
 }
 
 fn camera(mut commands: Commands) {
@@ -79,10 +98,7 @@ fn camera(mut commands: Commands) {
 fn main() {
     App::new()
         .add_plugins((DefaultPlugins, StackingAssetsPlugin, StatePlugin))
-        .add_systems(Startup, (camera, spawn_grid))
-        .add_systems(
-            Update,
-            render_all_pieces.run_if(not(in_state(MainState::Loading))),
-        )
+        .add_systems(Startup, (camera, load_table))
+        .add_systems(Update, render_all_pieces.run_if(assets_loaded))
         .run();
 }
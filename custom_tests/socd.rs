@@ -0,0 +1,88 @@
+//! Feeds sequences of shift key presses through the controller under each policy for holding both
+//! shift keys, checking how far the piece is told to shift on each frame. Exits with a panic if any
+//! check fails.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use stack_practice::controller::keybinds::KeyLayout;
+use stack_practice::controller::profiles::Profiles;
+use stack_practice::controller::{process_input, reset_controller, Controller, SocdPolicy};
+use stack_practice::screens::GlobalSettings;
+
+/// Time between frames, twice the initial and repeat delays so that held keys repeat within a frame
+const FRAME: Duration = Duration::from_millis(100);
+const LEFT: KeyCode = KeyCode::KeyA;
+const RIGHT: KeyCode = KeyCode::KeyD;
+
+#[derive(Resource, Default)]
+struct Shifts(Vec<i32>);
+
+fn record_shift(controller: Res<Controller>, mut shifts: ResMut<Shifts>) {
+    shifts.0.push(controller.shift);
+}
+
+/// The shift on each frame, given which of the left and right shift keys are held on each frame
+fn shifts(policy: SocdPolicy, frames: &[(bool, bool)]) -> Vec<i32> {
+    let mut profiles = Profiles::default();
+    let handling = &mut profiles.active_mut().handling;
+    handling.initial_delay = "50".into();
+    handling.repeat_delay = "50".into();
+    handling.socd = policy;
+
+    let mut world = World::new();
+    world.insert_resource(profiles);
+    world.init_resource::<GlobalSettings>();
+    world.init_resource::<KeyLayout>();
+    world.init_resource::<ButtonInput<KeyCode>>();
+    world.init_resource::<Time>();
+    world.init_resource::<Controller>();
+    world.init_resource::<Shifts>();
+
+    let mut schedule = Schedule::default();
+    schedule.add_systems((process_input, record_shift, reset_controller).chain());
+
+    for &(left, right) in frames {
+        world.resource_mut::<Time>().advance_by(FRAME);
+        let mut keys = world.resource_mut::<ButtonInput<KeyCode>>();
+        keys.clear();
+        for (key, held) in [(LEFT, left), (RIGHT, right)] {
+            if held {
+                keys.press(key);
+            } else {
+                keys.release(key);
+            }
+        }
+        schedule.run(&mut world);
+    }
+    world.remove_resource::<Shifts>().unwrap().0
+}
+
+fn main() {
+    use SocdPolicy::*;
+
+    let left_then_right = [(true, false), (true, true), (true, true), (false, true)];
+    let right_then_left = [(false, true), (true, true), (true, true), (true, false)];
+    let together = [(true, true), (true, true)];
+
+    let expected = [
+        (LastInput, [-1, 1, 2, 3], [1, -1, -2, -3], [-1, -2]),
+        (FirstInput, [-1, -2, -3, 3], [1, 2, 3, -3], [-1, -2]),
+        (Neutral, [-1, 0, 0, 3], [1, 0, 0, -3], [0, 0]),
+        (AbsoluteLeft, [-1, -2, -3, 3], [1, -1, -2, -3], [-1, -2]),
+        (AbsoluteRight, [-1, 1, 2, 3], [1, 2, 3, -3], [1, 2]),
+    ];
+    for (policy, first, second, third) in expected {
+        assert_eq!(
+            shifts(policy, &left_then_right),
+            first,
+            "{policy} (left first)"
+        );
+        assert_eq!(
+            shifts(policy, &right_then_left),
+            second,
+            "{policy} (right first)"
+        );
+        assert_eq!(shifts(policy, &together), third, "{policy} (together)");
+    }
+}
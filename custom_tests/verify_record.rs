@@ -0,0 +1,101 @@
+//! Plays a game on the fixed timestep by hard dropping every piece where it spawns until the stack
+//! tops out, then checks the record of the game with `verify_record`. The record should pass as
+//! it is, and fail once one of its changes to the matrix is made to expect the wrong cell. Exits
+//! once the record has been checked; a panic along the way is a failure.
+
+use bevy::app::AppExit;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use stack_practice::assets::tables::QueryShapeTable;
+use stack_practice::board::MinoKind;
+use stack_practice::replay::record::{CompleteRecord, RecordData, RecordItem};
+use stack_practice::replay::verify::{verify_record, VerifyError};
+use stack_practice::schedule::FrameSet;
+use stack_practice::screens::GlobalSettings;
+use stack_practice::state::{assets_loaded, MainState};
+use stack_practice::StackPracticePlugins;
+
+/// Frames between each hard drop, so that every piece has spawned before it is dropped
+const DROP_INTERVAL: u32 = 10;
+/// Frames that the game may take to top out before the test gives up on it
+const PLAYING_FRAMES: u32 = 60 * 60;
+
+const HARD_DROP_KEY: KeyCode = KeyCode::Space;
+
+fn drop_until_top_out(
+    state: Res<State<MainState>>,
+    mut next: ResMut<NextState<MainState>>,
+    mut settings: ResMut<GlobalSettings>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut playing: Local<u32>,
+) {
+    keys.release(HARD_DROP_KEY);
+    match state.get() {
+        MainState::Ready => {
+            settings.fixed_timestep = true;
+            next.set(MainState::Playing);
+        }
+        MainState::Playing => {
+            *playing += 1;
+            assert!(*playing <= PLAYING_FRAMES, "the stack should top out");
+            if *playing % DROP_INTERVAL == 0 {
+                keys.press(HARD_DROP_KEY);
+            }
+        }
+        MainState::LoadingFailed => panic!("the assets should load"),
+        MainState::Loading | MainState::PostGame => (),
+    }
+}
+
+fn check_record(
+    record: Res<CompleteRecord>,
+    shapes: QueryShapeTable,
+    mut exit: EventWriter<AppExit>,
+) {
+    let mut items = record
+        .get(0..record.len())
+        .iter()
+        .cloned()
+        .collect::<Vec<RecordItem>>();
+    assert!(
+        items.iter().all(|item| item.tick.is_some()),
+        "the game should have been recorded on the fixed timestep"
+    );
+    assert_eq!(verify_record(&items, &shapes), Ok(()));
+
+    let ix = items
+        .iter()
+        .position(|item| matches!(item.data, RecordData::MatrixChange(_)))
+        .expect("pieces should have locked");
+    let RecordData::MatrixChange(update) = &mut items[ix].data else {
+        unreachable!()
+    };
+    update.old = if update.old == MinoKind::E {
+        MinoKind::G
+    } else {
+        MinoKind::E
+    };
+    assert_eq!(
+        verify_record(&items, &shapes),
+        Err(VerifyError::MatrixMismatch(ix))
+    );
+
+    println!(
+        "The record of a game on the fixed timestep verified, and failed once item {ix} was changed"
+    );
+    exit.send(AppExit);
+}
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, StackPracticePlugins))
+        .add_systems(
+            PreUpdate,
+            drop_until_top_out
+                .after(InputSystem)
+                .before(FrameSet::Input)
+                .run_if(assets_loaded),
+        )
+        .add_systems(OnEnter(MainState::PostGame), check_record)
+        .run();
+}
@@ -0,0 +1,45 @@
+//! Draws a watermark into the corner of an image, and checks that it survives being written out as a
+//! PNG, while the rest of the image is left alone. Exits with a panic if any check fails.
+
+use image::{ImageOutputFormat, Rgba, RgbaImage};
+use stack_practice::export::{draw_watermark, ExportSettings, WatermarkCorner};
+use std::io::Cursor;
+
+const BACKGROUND: Rgba<u8> = Rgba([20, 20, 30, 255]);
+
+fn main() {
+    let settings = ExportSettings {
+        watermark: true,
+        watermark_name: "player".into(),
+        ..Default::default()
+    };
+    // 2024-03-01, some time in the afternoon
+    let text = settings.watermark_text(1_709_308_800).unwrap();
+    assert_eq!(text, "player 2024-03-01");
+    assert_eq!(
+        ExportSettings::default().watermark_text(1_709_308_800),
+        None
+    );
+
+    let mut image = RgbaImage::from_pixel(640, 480, BACKGROUND);
+    draw_watermark(&mut image, &text, WatermarkCorner::BottomRight);
+
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+        .unwrap();
+    let decoded = image::load_from_memory(&png).unwrap().to_rgba8();
+    assert_eq!(decoded, image);
+
+    // pixels changed in the quarter of the image with the given top left corner
+    let marked = |x0: u32, y0: u32| {
+        (x0..x0 + 320)
+            .flat_map(|x| (y0..y0 + 240).map(move |y| (x, y)))
+            .filter(|&(x, y)| *decoded.get_pixel(x, y) != BACKGROUND)
+            .count()
+    };
+    assert!(marked(320, 240) > 0, "the watermark is missing");
+    assert_eq!(marked(0, 0), 0, "the watermark is in the wrong corner");
+    assert_eq!(marked(320, 0), 0, "the watermark is in the wrong corner");
+    assert_eq!(marked(0, 240), 0, "the watermark is in the wrong corner");
+}
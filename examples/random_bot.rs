@@ -0,0 +1,41 @@
+//! Plays a game through the headless API by locking each piece at a random legal placement, then
+//! saves it as a replay and prints its run code, so that the game can be watched from the replay
+//! browser or by pasting the code into the game. Run from the root of the repository, so that the
+//! tables in the assets folder are found. The number of pieces to play can be passed as an argument.
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use stack_practice::api::{load_default_tables, Game, Move};
+use stack_practice::replay::file::{ReplayFile, RunTimestamp};
+
+fn main() {
+    let pieces: u32 = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(100);
+    let (shapes, kicks) = load_default_tables().expect("the default tables should load");
+
+    let mut rng = rand::thread_rng();
+    let mut game = Game::new(Default::default(), rng.gen(), shapes, kicks);
+    while !game.is_over() && game.stats().pieces < pieces {
+        let placements = game.legal_placements();
+        let Some(&placement) = placements.choose(&mut rng) else {
+            break;
+        };
+        game.submit(Move::Place(placement))
+            .expect("legal placements should always be accepted");
+    }
+
+    let stats = game.stats();
+    println!(
+        "Placed {} pieces, clearing {} lines",
+        stats.pieces, stats.lines
+    );
+    println!("Run code: {}", game.run_code().encode().expect("run code"));
+
+    let (record, _) = game.record().expect("the game should play back");
+    match ReplayFile::from_record(&record).save(RunTimestamp::now()) {
+        Ok(path) => println!("Saved replay to {}", path.display()),
+        Err(e) => eprintln!("Could not save replay: {e}"),
+    }
+}
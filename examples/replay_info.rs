@@ -0,0 +1,57 @@
+//! Prints the version of the format, the metadata and a summary of each replay file passed as an
+//! argument, without launching the game. Useful for looking into replays sent in with reports.
+
+use std::path::Path;
+
+use stack_practice::replay::file::{ReplayFile, ReplayFileError};
+
+fn main() {
+    let paths: Vec<_> = std::env::args().skip(1).collect();
+    if paths.is_empty() {
+        eprintln!("Usage: replay_info <replay file>...");
+        std::process::exit(2);
+    }
+
+    let mut failed = false;
+    for path in paths {
+        println!("{path}");
+        let read = std::fs::read_to_string(Path::new(&path))
+            .map_err(ReplayFileError::Io)
+            .and_then(|text| ReplayFile::parse(&text));
+        let (version, file) = match read {
+            Ok(read) => read,
+            Err(e) => {
+                println!("  {e}");
+                failed = true;
+                continue;
+            }
+        };
+
+        println!("  format version: {version}");
+        if file.metadata.game_version.is_empty() {
+            println!("  saved by: unknown");
+        } else {
+            println!("  saved by: version {}", file.metadata.game_version);
+            println!("  saved at: {} (unix time)", file.metadata.saved_at);
+        }
+        let length = file.items.last().map_or(0, |item| item.time);
+        println!(
+            "  items: {}, lasting {:.2}s at {} frames per second",
+            file.items.len(),
+            file.metadata.frame_rate.seconds(length),
+            *file.metadata.frame_rate
+        );
+        println!("  bookmarks: {}", file.bookmarks.len());
+        match &file.settings {
+            Some(snapshot) => println!(
+                "  mode: {:?}, seed: {}",
+                snapshot.settings.mode, snapshot.seed
+            ),
+            None => println!("  settings: unknown"),
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
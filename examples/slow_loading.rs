@@ -0,0 +1,79 @@
+//! Runs the game with every read from the assets folder held back for a while, as if loading from
+//! a slow disk, so that the loading screen can be seen. The delay for each file, in milliseconds,
+//! can be passed as an argument.
+
+use std::path::Path;
+use std::time::Duration;
+
+use bevy::asset::io::{
+    AssetReader, AssetReaderError, AssetSource, AssetSourceId, PathStream, Reader,
+};
+use bevy::prelude::*;
+use bevy::utils::BoxedFuture;
+use stack_practice::StackPracticePlugins;
+
+struct SlowReader {
+    inner: Box<dyn AssetReader>,
+    delay: Duration,
+}
+
+impl AssetReader for SlowReader {
+    fn read<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<Box<Reader<'a>>, AssetReaderError>> {
+        // blocks one of the threads loading assets, which is as good as a slow disk
+        std::thread::sleep(self.delay);
+        self.inner.read(path)
+    }
+
+    fn read_meta<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<Box<Reader<'a>>, AssetReaderError>> {
+        self.inner.read_meta(path)
+    }
+
+    fn read_directory<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<Box<PathStream>, AssetReaderError>> {
+        self.inner.read_directory(path)
+    }
+
+    fn is_directory<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<bool, AssetReaderError>> {
+        self.inner.is_directory(path)
+    }
+}
+
+fn main() {
+    let delay = Duration::from_millis(
+        std::env::args()
+            .nth(1)
+            .and_then(|arg| arg.parse().ok())
+            .unwrap_or(1000),
+    );
+
+    App::new()
+        // replaces the assets folder, so this has to come before the asset plugin
+        .register_asset_source(
+            AssetSourceId::Default,
+            AssetSource::build().with_reader(move || {
+                Box::new(SlowReader {
+                    inner: AssetSource::get_default_reader("assets".to_string())(),
+                    delay,
+                })
+            }),
+        )
+        .add_plugins((
+            DefaultPlugins.set(AssetPlugin {
+                watch_for_changes_override: Some(false),
+                ..default()
+            }),
+            StackPracticePlugins,
+        ))
+        .run();
+}
@@ -1,17 +1,245 @@
+use bevy::math::{uvec2, vec2};
 use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+use bevy::utils::HashMap;
+use bevy::window::PrimaryWindow;
+use rand::Rng;
+
+use crate::assets::palette::Palette;
+use crate::board::{Bounds, LineClearEvent, MinoKind, CELL_SIZE};
+use crate::replay::ghost::Ghost;
+use crate::screens::GlobalSettings;
+use crate::window::PresentationMode;
+
+use self::tween::{animate, Tween};
+
+pub mod tween;
 
 pub const DEFAULT_CAMERA_ZOOM: f32 = 1.3;
 pub const REPLAY_CAMERA_ZOOM: f32 = 1.5;
 
+/// How many particles are spawned for each cleared cell
+const PARTICLES_PER_CELL: usize = 3;
+/// No more particles are spawned while this many are alive
+const MAX_PARTICLES: usize = 600;
+/// Seconds until a particle disappears
+const PARTICLE_LIFETIME: f32 = 0.5;
+const PARTICLE_SIZE: f32 = 6.0;
+/// Downward acceleration of particles, in pixels per second squared
+const PARTICLE_GRAVITY: f32 = 1200.0;
+
+/// The scale that the camera is zoomed to. The camera is tweened to each new zoom.
 #[derive(Resource, Deref, DerefMut)]
 pub struct CameraZoom(f32);
 
-fn adjust_camera_zoom(zoom: Res<CameraZoom>, mut cameras: Query<&mut OrthographicProjection>) {
-    let camera = cameras.single();
-    let distance = camera.scale - **zoom;
-    if distance.abs() > f32::EPSILON {
-        let mut camera = cameras.single_mut();
-        camera.scale -= distance / 10.0;
+/// Tweens the camera to its zoom. In presentation mode, the camera is zoomed in further until the
+/// board fills most of the window, though never out from a closer zoom (such as when following the
+/// active piece in the replay).
+#[allow(clippy::too_many_arguments)]
+fn adjust_camera_zoom(
+    zoom: Res<CameraZoom>,
+    mut cameras: Query<&mut OrthographicProjection>,
+    settings: Res<GlobalSettings>,
+    presentation: Res<PresentationMode>,
+    boards: Query<&Bounds, Without<Ghost>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    time: Res<Time>,
+    mut tween: Local<Option<Tween<f32>>>,
+) {
+    let scale = cameras.single().scale;
+    let timing = settings.camera_timing();
+    let target = boards
+        .get_single()
+        .ok()
+        .zip(windows.get_single().ok())
+        .and_then(|(bounds, window)| presentation.zoom(bounds, window))
+        .map_or(**zoom, |fit| fit.min(**zoom));
+    if let Some(scale) = animate(&mut tween, scale, target, timing, time.delta_seconds()) {
+        cameras.single_mut().scale = scale;
+    }
+}
+
+/// The part of the window, in physical pixels, which the camera should draw to. The camera's
+/// viewport is tweened toward it, so that the board glides into place when a panel beside it opens
+/// or closes rather than jumping.
+#[derive(Resource, Default)]
+pub struct CameraFocus(pub Option<Rect>);
+
+fn adjust_camera_focus(
+    focus: Res<CameraFocus>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cameras: Query<(Entity, &mut Camera)>,
+    settings: Res<GlobalSettings>,
+    time: Res<Time>,
+    mut tweens: Local<HashMap<Entity, Tween<Rect>>>,
+) {
+    let (Some(target), Ok(window)) = (focus.0, windows.get_single()) else {
+        return;
+    };
+    let window_size = uvec2(window.physical_width(), window.physical_height());
+    if window_size.cmpeq(UVec2::ZERO).any() {
+        // minimized
+        return;
+    }
+    // the viewport is in whole pixels, so the target is as well, or it would never be reached
+    let target = Rect::from_corners(target.min.round(), target.max.round());
+
+    for (e, mut camera) in cameras.iter_mut() {
+        let current = camera.viewport.as_ref().map_or(target, |viewport| {
+            Rect::from_corners(
+                viewport.physical_position.as_vec2(),
+                (viewport.physical_position + viewport.physical_size).as_vec2(),
+            )
+        });
+        let mut tween = tweens.remove(&e);
+        let next = animate(
+            &mut tween,
+            current,
+            target,
+            settings.camera_timing(),
+            time.delta_seconds(),
+        );
+        tweens.extend(tween.map(|tween| (e, tween)));
+        let Some(Rect { min, max }) = next else {
+            continue;
+        };
+
+        let position = min.round().as_uvec2().min(window_size - UVec2::ONE);
+        let size = (max - min)
+            .round()
+            .as_uvec2()
+            .clamp(UVec2::ONE, window_size - position);
+        let unchanged = camera.viewport.as_ref().is_some_and(|viewport| {
+            viewport.physical_position == position && viewport.physical_size == size
+        });
+        if unchanged {
+            continue;
+        }
+        camera.viewport = Some(Viewport {
+            physical_position: position,
+            physical_size: size,
+            ..default()
+        });
+    }
+}
+
+/// The point in the world which the camera is centered on. The camera is tweened to each new
+/// target.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct CameraTarget(pub Vec2);
+
+fn adjust_camera_position(
+    target: Res<CameraTarget>,
+    mut cameras: Query<(Entity, &mut Transform), With<Camera>>,
+    settings: Res<GlobalSettings>,
+    time: Res<Time>,
+    mut tweens: Local<HashMap<Entity, Tween<Vec2>>>,
+) {
+    for (e, mut transform) in cameras.iter_mut() {
+        let current = transform.translation.truncate();
+        let mut tween = tweens.remove(&e);
+        let next = animate(
+            &mut tween,
+            current,
+            **target,
+            settings.camera_timing(),
+            time.delta_seconds(),
+        );
+        tweens.extend(tween.map(|tween| (e, tween)));
+        if let Some(position) = next {
+            transform.translation = position.extend(transform.translation.z);
+        }
+    }
+}
+
+/// A small square thrown out of a cleared line, which falls and fades until it disappears.
+#[derive(Component)]
+pub struct Particle {
+    velocity: Vec2,
+    age: f32,
+}
+
+fn spawn_particles(
+    mut commands: Commands,
+    mut clears: EventReader<LineClearEvent>,
+    boards: Query<&Bounds>,
+    particles: Query<(), With<Particle>>,
+    settings: Res<GlobalSettings>,
+    palette: Res<Palette>,
+) {
+    if !settings.particles {
+        clears.clear();
+        return;
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut budget = MAX_PARTICLES.saturating_sub(particles.iter().count());
+
+    for clear in clears.read() {
+        let Ok(bounds) = boards.get(clear.board) else {
+            continue;
+        };
+        let offset = -(bounds.legal_bounds.as_vec2() / 2.);
+
+        let cells = clear
+            .rows
+            .iter()
+            .zip(&clear.contents)
+            .flat_map(|(&y, row)| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, kind)| **kind != MinoKind::E)
+                    .map(move |(x, &kind)| (vec2(x as f32, y as f32), kind))
+            });
+
+        commands.entity(clear.board).with_children(|parent| {
+            for (cell, kind) in cells {
+                let center = (cell + 0.5 + offset) * CELL_SIZE as f32;
+                for _ in 0..PARTICLES_PER_CELL {
+                    if budget == 0 {
+                        return;
+                    }
+                    budget -= 1;
+
+                    let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+                    let speed: f32 = rng.gen_range(100.0..400.0);
+                    parent.spawn((
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color: palette.color(kind),
+                                custom_size: Some(Vec2::splat(PARTICLE_SIZE)),
+                                ..default()
+                            },
+                            transform: Transform::from_translation(center.extend(2.0)),
+                            ..default()
+                        },
+                        Particle {
+                            velocity: Vec2::from_angle(angle) * speed,
+                            age: 0.0,
+                        },
+                    ));
+                }
+            }
+        });
+    }
+}
+
+fn update_particles(
+    mut commands: Commands,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    for (e, mut particle, mut transform, mut sprite) in particles.iter_mut() {
+        particle.age += dt;
+        if particle.age > PARTICLE_LIFETIME {
+            commands.entity(e).despawn_recursive();
+            continue;
+        }
+
+        particle.velocity.y -= PARTICLE_GRAVITY * dt;
+        transform.translation += (particle.velocity * dt).extend(0.0);
+        sprite.color.set_a(1.0 - particle.age / PARTICLE_LIFETIME);
     }
 }
 
@@ -20,9 +248,16 @@ pub struct AnimationPlugin;
 impl Plugin for AnimationPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(CameraZoom(DEFAULT_CAMERA_ZOOM))
+            .init_resource::<CameraFocus>()
+            .init_resource::<CameraTarget>()
             .add_systems(
                 Update,
-                adjust_camera_zoom.run_if(|q: Query<&OrthographicProjection>| !q.is_empty()),
-            );
+                (
+                    adjust_camera_zoom.run_if(|q: Query<&OrthographicProjection>| !q.is_empty()),
+                    adjust_camera_focus,
+                    adjust_camera_position,
+                ),
+            )
+            .add_systems(Update, (spawn_particles, update_particles));
     }
 }
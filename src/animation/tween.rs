@@ -0,0 +1,138 @@
+//! Values which move from one point to another over a fixed time, following an easing curve, and
+//! land exactly on their target once the time is up.
+
+use bevy::prelude::*;
+
+/// The shape of the curve that a tween follows from its start to its end
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    strum::EnumIter,
+    strum::Display,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum Easing {
+    Linear,
+    #[strum(to_string = "Ease In")]
+    EaseInCubic,
+    #[strum(to_string = "Ease Out")]
+    EaseOutCubic,
+    #[default]
+    #[strum(to_string = "Ease In-Out")]
+    EaseInOutCubic,
+}
+
+impl Easing {
+    /// How far along the curve is at the given point in time, both from 0 to 1
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseInCubic => t * t * t,
+            Easing::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOutCubic if t < 0.5 => 4.0 * t * t * t,
+            Easing::EaseInOutCubic => 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0,
+        }
+    }
+}
+
+/// How long a tween takes, and the curve it follows
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Timing {
+    /// Seconds from the start of the tween to its end
+    pub duration: f32,
+    pub easing: Easing,
+}
+
+/// A value which can be tweened, by blending between two of its values
+pub trait Tweenable: Copy + PartialEq {
+    fn blend(from: Self, to: Self, t: f32) -> Self;
+}
+
+impl Tweenable for f32 {
+    fn blend(from: Self, to: Self, t: f32) -> Self {
+        from + (to - from) * t
+    }
+}
+
+impl Tweenable for Vec2 {
+    fn blend(from: Self, to: Self, t: f32) -> Self {
+        from.lerp(to, t)
+    }
+}
+
+impl Tweenable for Rect {
+    fn blend(from: Self, to: Self, t: f32) -> Self {
+        Rect::from_corners(from.min.lerp(to.min, t), from.max.lerp(to.max, t))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Tween<T> {
+    from: T,
+    to: T,
+    elapsed: f32,
+    timing: Timing,
+}
+
+impl<T: Tweenable> Tween<T> {
+    pub fn new(from: T, to: T, timing: Timing) -> Self {
+        Self {
+            from,
+            to,
+            elapsed: 0.0,
+            timing,
+        }
+    }
+
+    pub fn target(&self) -> T {
+        self.to
+    }
+
+    pub fn finished(&self) -> bool {
+        self.elapsed >= self.timing.duration
+    }
+
+    /// Moves the tween along by the given number of seconds, returning its value at the new time.
+    /// Once the tween is finished, this is exactly its target.
+    pub fn advance(&mut self, seconds: f32) -> T {
+        self.elapsed += seconds;
+        if self.finished() {
+            return self.to;
+        }
+        let t = self
+            .timing
+            .easing
+            .apply(self.elapsed / self.timing.duration);
+        T::blend(self.from, self.to, t)
+    }
+}
+
+/// Moves a value toward its target, starting a new tween from where the value is whenever the
+/// target changes. Returns the value for this frame, or nothing once the value rests on its target.
+pub fn animate<T: Tweenable>(
+    tween: &mut Option<Tween<T>>,
+    current: T,
+    target: T,
+    timing: Timing,
+    seconds: f32,
+) -> Option<T> {
+    let retargeted = match tween {
+        Some(tween) => tween.target() != target,
+        None => current != target,
+    };
+    if retargeted {
+        *tween = Some(Tween::new(current, target, timing));
+    }
+
+    let value = tween.as_mut()?.advance(seconds);
+    if tween.as_ref().is_some_and(Tween::finished) {
+        *tween = None;
+    }
+    Some(value)
+}
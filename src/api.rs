@@ -0,0 +1,431 @@
+//! Playing the game without a window, for bots and solvers. A [`Game`] holds a board and steps it
+//! one action or one placement at a time, with the same rules as a run code: pieces only move when
+//! told to, so gravity and lock delay play no part. A game played this way can be turned into a
+//! [`RunCode`], and from there into a record which can be watched in the game.
+//!
+//! ```no_run
+//! use stack_practice::api::{load_default_tables, Game, Move};
+//!
+//! let (shapes, kicks) = load_default_tables().unwrap();
+//! let mut game = Game::new(Default::default(), 0, shapes, kicks);
+//! while !game.is_over() {
+//!     let placement = game.legal_placements()[0];
+//!     game.submit(Move::Place(placement)).unwrap();
+//! }
+//! let (record, stats) = game.record().unwrap();
+//! ```
+
+use bevy::ecs::entity::Entity;
+use bevy::math::IVec2;
+use bevy::utils::thiserror;
+use rand_pcg::Pcg32;
+
+use crate::assets::tables::kick_table::{KickParameters, KickTable, DEFAULT_KICK_TABLE_FILE};
+use crate::assets::tables::shape_table::{ShapeTable, DEFAULT_SHAPE_TABLE_FILE};
+use crate::assets::tables::TableLoadError;
+use crate::board::events::{
+    lock_events, GameEndReason, GameEnded, GameEvent, GameStarted, HoldUsed,
+};
+use crate::board::garbage;
+use crate::board::motion::PieceMotion;
+use crate::board::mouse::reachable_placements;
+use crate::board::queue::{drill_bag, PieceQueue};
+use crate::board::update::{default_mino, goal_reached, has_free_space, kick_search, lock_piece};
+use crate::board::{
+    GameMode, Hold, Matrix, Mino, MinoKind, RotationState, MATRIX_DEFAULT_LEGAL_BOUNDS,
+};
+use crate::replay::code::{RunCode, RunCodeError, RunSettings};
+use crate::replay::record::CompleteRecord;
+use crate::stats::Stats;
+
+/// Pieces of the queue shown in an [`Observation`], as many as the game shows
+pub const QUEUE_WINDOW: usize = 5;
+/// Stands in for the board in the events of a game, since the game has no entity of its own
+const BOARD: Entity = Entity::PLACEHOLDER;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum ApiError {
+    #[error("The game is over")]
+    GameOver,
+    #[error("The active piece cannot move that way")]
+    Blocked,
+    #[error("The hold has already been used for this piece")]
+    HoldUsed,
+    #[error("The piece cannot be brought to that placement")]
+    IllegalPlacement,
+}
+
+/// Reads the shape and kick tables which the game uses by default, from the assets folder
+pub fn load_default_tables() -> Result<(ShapeTable, KickTable), TableLoadError> {
+    let shapes = ShapeTable::from_bytes(&std::fs::read(DEFAULT_SHAPE_TABLE_FILE)?)?;
+    let kicks = KickTable::from_bytes(&std::fs::read(DEFAULT_KICK_TABLE_FILE)?)?;
+    Ok((shapes, kicks))
+}
+
+/// A single input to the active piece. Shifts and rotations move the piece where a keypress would.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    ShiftLeft,
+    ShiftRight,
+    /// Moves the piece as far down as it goes, without locking it
+    SoftDrop,
+    RotateLeft,
+    RotateRight,
+    Rotate180,
+    Hold,
+    HardDrop,
+}
+
+/// What a bot asks the game to do with the active piece
+#[derive(Clone, Debug, PartialEq)]
+pub enum Move {
+    /// Locks a piece at the given placement, holding first if the placement is of the piece which
+    /// hold would bring out. The placement must be reachable, as in
+    /// [`Game::legal_placements`].
+    Place(Mino),
+    /// Plays the given actions in order, stopping at the first which fails
+    Actions(Vec<Action>),
+}
+
+/// The result of locking a piece
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Locked {
+    pub mino: Mino,
+    pub lines: usize,
+    /// Whether the game ended with this piece, by reaching its goal or by topping out
+    pub game_over: bool,
+}
+
+/// What a bot can see of the board
+#[derive(Clone)]
+pub struct Observation<'a> {
+    pub matrix: &'a Matrix,
+    pub active: Option<Mino>,
+    pub hold: Hold,
+    /// The next pieces, the first of which spawns next
+    pub queue: Vec<MinoKind>,
+}
+
+/// A game played without a window
+pub struct Game {
+    shape_table: ShapeTable,
+    kick_table: KickTable,
+    seed: u64,
+    settings: RunSettings,
+    matrix: Matrix,
+    active: Option<Mino>,
+    /// What the active piece has done since it spawned, unless it was placed directly
+    motion: Option<PieceMotion>,
+    hold: Hold,
+    queue: PieceQueue,
+    garbage_rng: Pcg32,
+    placements: Vec<Mino>,
+    stats: Stats,
+    /// Events sent since they were last taken
+    events: Vec<GameEvent>,
+}
+
+impl Game {
+    /// Starts a game with the given settings, dealing its pieces (and any garbage) from the given
+    /// seed. `RunSettings::default()` gives the settings that the game starts with.
+    pub fn new(
+        settings: RunSettings,
+        seed: u64,
+        shape_table: ShapeTable,
+        kick_table: KickTable,
+    ) -> Self {
+        let pieces = drill_bag(shape_table.kinds(), &settings.excluded_pieces);
+        let mut queue = PieceQueue::seeded(settings.queue.clone(), seed, pieces);
+        queue.prepend(&settings.preset_queue);
+        let mut game = Self {
+            shape_table,
+            kick_table,
+            seed,
+            matrix: Matrix::default(),
+            active: None,
+            motion: None,
+            hold: settings.initial_hold.map_or(Hold::Empty, Hold::Ready),
+            queue,
+            garbage_rng: garbage::rng(seed),
+            placements: Vec::new(),
+            stats: Stats::default(),
+            events: Vec::new(),
+            settings,
+        };
+
+        if game.settings.mode.has_cheese() {
+            let height = game
+                .settings
+                .cheese_height
+                .min(MATRIX_DEFAULT_LEGAL_BOUNDS.y as usize);
+            let rows = garbage::cheese(
+                &mut game.garbage_rng,
+                &game.settings.garbage_pattern,
+                game.settings.messiness,
+                height,
+                game.matrix.width(),
+            );
+            for (row, data) in game.matrix.rows_mut().zip(rows) {
+                row.copy_from_slice(&data);
+            }
+        }
+        let first = default_mino(game.queue.take(), &game.shape_table);
+        game.active = Some(first);
+        game.motion = Some(PieceMotion::spawned(first));
+        game.events
+            .push(GameEvent::GameStarted(GameStarted { board: BOARD }));
+        game
+    }
+
+    /// Takes the events sent since they were last taken, in the order they were sent. The events
+    /// are the same as those sent by the board in the game, with a placeholder for the board.
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn observe(&self) -> Observation {
+        Observation {
+            matrix: &self.matrix,
+            active: self.active,
+            hold: self.hold,
+            queue: self
+                .queue
+                .window()
+                .iter()
+                .copied()
+                .take(QUEUE_WINDOW)
+                .collect(),
+        }
+    }
+
+    pub fn shape_table(&self) -> &ShapeTable {
+        &self.shape_table
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.active.is_none()
+    }
+
+    /// The piece which holding would bring out, if holding is allowed and would change the piece
+    fn hold_swap(&self) -> Option<MinoKind> {
+        let active = self.active?.kind;
+        let swapped = match self.hold {
+            Hold::Empty => self.queue.peek(),
+            Hold::Ready(kind) => kind,
+            Hold::Inactive(_) => return None,
+        };
+        // a run code cannot tell a hold of the same kind from no hold at all
+        (swapped != active).then_some(swapped)
+    }
+
+    /// Every placement which the active piece (or the piece brought out of hold) can be locked at
+    pub fn legal_placements(&self) -> Vec<Mino> {
+        let Some(active) = self.active else {
+            return Vec::new();
+        };
+        std::iter::once(active.kind)
+            .chain(self.hold_swap())
+            .flat_map(|kind| {
+                let start = default_mino(kind, &self.shape_table);
+                reachable_placements(&self.matrix, start, &self.shape_table, &self.kick_table)
+            })
+            .collect()
+    }
+
+    /// Carries out the given move. Returns where the piece locked, if the move locked one.
+    pub fn submit(&mut self, mv: Move) -> Result<Option<Locked>, ApiError> {
+        match mv {
+            Move::Place(placement) => {
+                if !self.legal_placements().contains(&placement) {
+                    return Err(ApiError::IllegalPlacement);
+                }
+                if self
+                    .active
+                    .is_some_and(|active| active.kind != placement.kind)
+                {
+                    self.step(Action::Hold)?;
+                }
+                self.active = Some(placement);
+                self.motion = None;
+                self.step(Action::HardDrop)
+            }
+            Move::Actions(actions) => {
+                let mut locked = None;
+                for action in actions {
+                    locked = self.step(action)?.or(locked);
+                }
+                Ok(locked)
+            }
+        }
+    }
+
+    /// Plays a single action. Returns where the piece locked, if the action locked one.
+    pub fn step(&mut self, action: Action) -> Result<Option<Locked>, ApiError> {
+        let active = self.active.ok_or(ApiError::GameOver)?;
+        let moved = |offset: IVec2| Mino {
+            position: active.position + offset,
+            ..active
+        };
+        let rotated = |to: fn(RotationState) -> RotationState| {
+            let to = to(active.rotation);
+            let kicks = self
+                .kick_table
+                .0
+                .get(&KickParameters {
+                    kind: active.kind,
+                    from: active.rotation,
+                    to,
+                })
+                .map_or(&[][..], Vec::as_slice);
+            kick_search(&self.matrix, active, to, kicks, &self.shape_table).map(|(_, m)| m)
+        };
+        let fits = |mino: Mino| has_free_space(&self.matrix, mino, &self.shape_table);
+
+        let next = match action {
+            Action::ShiftLeft => Some(moved(IVec2::NEG_X)).filter(|&m| fits(m)),
+            Action::ShiftRight => Some(moved(IVec2::X)).filter(|&m| fits(m)),
+            Action::SoftDrop => Some(self.dropped(active)),
+            Action::RotateLeft => rotated(RotationState::rotate_left),
+            Action::RotateRight => rotated(RotationState::rotate_right),
+            Action::Rotate180 => rotated(RotationState::rotate_180),
+            Action::Hold => {
+                let kind = self.hold_swap().ok_or(ApiError::HoldUsed)?;
+                if matches!(self.hold, Hold::Empty) {
+                    self.queue.take();
+                }
+                self.hold = Hold::Inactive(active.kind);
+                self.events.push(GameEvent::HoldUsed(HoldUsed {
+                    board: BOARD,
+                    kind: active.kind,
+                    replayed: false,
+                }));
+                let spawned = default_mino(kind, &self.shape_table);
+                self.active = fits(spawned).then_some(spawned);
+                self.motion = Some(PieceMotion::spawned(spawned));
+                if self.active.is_none() {
+                    self.end(GameEndReason::TopOut);
+                }
+                return Ok(None);
+            }
+            Action::HardDrop => {
+                let dropped = self.dropped(active);
+                if let Some(motion) = self.motion.as_mut().filter(|_| dropped != active) {
+                    motion.fell();
+                }
+                return Ok(Some(self.lock(dropped)));
+            }
+        };
+        let next = next.ok_or(ApiError::Blocked)?;
+        if let Some(motion) = &mut self.motion {
+            motion.moved(active, next);
+        }
+        self.active = Some(next);
+        Ok(None)
+    }
+
+    fn dropped(&self, mut mino: Mino) -> Mino {
+        while has_free_space(&self.matrix, mino, &self.shape_table) {
+            mino.position.y -= 1;
+        }
+        mino.position.y += 1;
+        mino
+    }
+
+    /// Locks the given piece and spawns the next, in the same way as playing back a run code
+    fn lock(&mut self, mino: Mino) -> Locked {
+        self.placements.push(mino);
+        let spun = self.motion.map(|motion| motion.spun());
+        let (locked, lines) =
+            lock_events(BOARD, &self.matrix, mino, &self.shape_table, spun, false);
+        self.events.push(GameEvent::PieceLocked(locked));
+        self.events.extend(lines.map(GameEvent::LinesCleared));
+        let holes = self.matrix.holes();
+        let cleared = lock_piece(&mut self.matrix, mino, &self.shape_table);
+        let overflowed = self.settings.adaptive_cheese
+            && !cleared.is_empty()
+            && self.matrix.holes() <= holes
+            && !garbage::shove(
+                &mut self.matrix,
+                &mut self.garbage_rng,
+                &self.settings.garbage_pattern,
+                self.settings.messiness,
+                MATRIX_DEFAULT_LEGAL_BOUNDS.y as usize,
+            );
+        self.stats.pieces += 1;
+        self.stats.lines += cleared.len() as u32;
+        self.stats.record_clear(cleared.len() as u32);
+
+        let placed = self.placements.len();
+        if self
+            .settings
+            .mode
+            .ends_bag(placed, self.queue.pieces().len())
+        {
+            self.matrix.clear();
+            self.hold = self.settings.initial_hold.map_or(Hold::Empty, Hold::Ready);
+        }
+
+        let next = default_mino(self.queue.peek(), &self.shape_table);
+        let continuous = self.settings.continuous && self.settings.mode == GameMode::Freestyle;
+        if !has_free_space(&self.matrix, next, &self.shape_table) {
+            if continuous {
+                self.matrix.clear();
+                if self.settings.wipe_hold {
+                    self.hold = Hold::Empty;
+                }
+                self.stats.record_death();
+            } else if self.settings.lifesaver_rows > 0 {
+                self.matrix.clear_top_rows(self.settings.lifesaver_rows);
+                self.stats.record_lifesaver();
+            }
+        }
+
+        self.hold.activate();
+        self.queue.take();
+        let goal = goal_reached(
+            &self.matrix,
+            self.settings.mode,
+            self.settings.target_height,
+            self.stats.lines,
+        );
+        let spawns = has_free_space(&self.matrix, next, &self.shape_table);
+        self.active = (spawns && !goal && !overflowed).then_some(next);
+        self.motion = Some(PieceMotion::spawned(next));
+        if goal {
+            self.end(GameEndReason::GoalReached);
+        } else if self.active.is_none() {
+            self.end(GameEndReason::TopOut);
+        }
+        Locked {
+            mino,
+            lines: cleared.len(),
+            game_over: self.active.is_none(),
+        }
+    }
+
+    fn end(&mut self, reason: GameEndReason) {
+        self.events.push(GameEvent::GameEnded(GameEnded {
+            board: BOARD,
+            reason,
+        }));
+    }
+
+    /// The game so far as a run code, which can be pasted into the game to watch it
+    pub fn run_code(&self) -> RunCode {
+        RunCode {
+            seed: self.seed,
+            settings: self.settings.clone(),
+            placements: self.placements.clone(),
+        }
+    }
+
+    /// A record of the game so far, which can be saved as a replay with
+    /// [`ReplayFile`](crate::replay::file::ReplayFile) and watched in the game
+    pub fn record(&self) -> Result<(CompleteRecord, Stats), RunCodeError> {
+        self.run_code().resimulate(&self.shape_table)
+    }
+}
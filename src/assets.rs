@@ -1,8 +1,12 @@
+use bevy::prelude::{resource_changed, IntoSystemConfigs, OnEnter, OnExit, World};
 use bevy::sprite::Material2dPlugin;
 use bevy::{
-    app::Plugin,
-    asset::{AssetApp, Handle},
-    ecs::system::Resource,
+    app::{Plugin, Update},
+    asset::{AssetApp, AssetServer, Handle, UntypedAssetLoadFailedEvent, UntypedHandle},
+    ecs::{
+        event::EventReader,
+        system::{ResMut, Resource},
+    },
     render::texture::Image,
 };
 use bevy_asset_loader::prelude::ConfigureLoadingState;
@@ -10,12 +14,16 @@ use bevy_asset_loader::{
     asset_collection::AssetCollection,
     loading_state::{LoadingState, LoadingStateAppExt},
 };
+use strum::IntoEnumIterator;
 
 mod image_tools;
 pub mod matrix_material;
+pub mod palette;
 pub mod tables;
 
 use crate::assets::matrix_material::MatrixMaterial;
+use crate::assets::palette::{apply_palette, Palette};
+use crate::board::MinoKind;
 use crate::state::MainState;
 
 use self::tables::{
@@ -48,19 +56,97 @@ pub struct MinoTextures {
 }
 
 impl MinoTextures {
-    pub fn view(&self) -> [Handle<Image>; 9] {
-        [
-            self.e.clone(),
-            self.t.clone(),
-            self.o.clone(),
-            self.l.clone(),
-            self.j.clone(),
-            self.s.clone(),
-            self.z.clone(),
-            self.i.clone(),
-            self.g.clone(),
-        ]
+    /// The texture of each kind of cell, in order of their ids. The generic slots borrow the
+    /// textures of the standard pieces in turn.
+    pub fn view(&self) -> Vec<Handle<Image>> {
+        MinoKind::iter().map(|kind| self.texture(kind)).collect()
     }
+
+    fn texture(&self, kind: MinoKind) -> Handle<Image> {
+        if let Some(slot) = kind.slot() {
+            let standard = MinoKind::STANDARD[slot as usize % MinoKind::STANDARD.len()];
+            return self.texture(standard);
+        }
+
+        match kind {
+            MinoKind::E => self.e.clone(),
+            MinoKind::T => self.t.clone(),
+            MinoKind::O => self.o.clone(),
+            MinoKind::L => self.l.clone(),
+            MinoKind::J => self.j.clone(),
+            MinoKind::S => self.s.clone(),
+            MinoKind::Z => self.z.clone(),
+            MinoKind::I => self.i.clone(),
+            MinoKind::G => self.g.clone(),
+            _ => unreachable!("slots borrow the textures of the standard pieces"),
+        }
+    }
+}
+
+/// Assets which could not be loaded, by path, along with the reason why
+#[derive(Resource, Default)]
+pub struct LoadingErrors(pub Vec<(String, String)>);
+
+fn collect_loading_errors(
+    mut events: EventReader<UntypedAssetLoadFailedEvent>,
+    mut errors: ResMut<LoadingErrors>,
+) {
+    for event in events.read() {
+        tracing::error!("{}", event.error);
+        errors
+            .0
+            .push((event.path.to_string(), event.error.to_string()));
+    }
+}
+
+/// The handles of each collection being loaded, named so that the loading screen can say what it
+/// is waiting on
+#[derive(Resource, Default)]
+pub struct LoadingProgress(Vec<(&'static str, Vec<UntypedHandle>)>);
+
+impl LoadingProgress {
+    /// The fraction of assets which have finished loading, along with the name of the first
+    /// collection still loading
+    pub fn progress(&self, asset_server: &AssetServer) -> (f32, Option<&'static str>) {
+        let loaded = |handle: &UntypedHandle| asset_server.is_loaded_with_dependencies(handle.id());
+        let total = self
+            .0
+            .iter()
+            .map(|(_, handles)| handles.len())
+            .sum::<usize>();
+        let done = self
+            .0
+            .iter()
+            .flat_map(|(_, handles)| handles)
+            .filter(|handle| loaded(handle))
+            .count();
+        let current = self
+            .0
+            .iter()
+            .find(|(_, handles)| !handles.iter().all(loaded))
+            .map(|(name, _)| *name);
+        let fraction = if total == 0 {
+            0.0
+        } else {
+            done as f32 / total as f32
+        };
+        (fraction, current)
+    }
+}
+
+/// Asks for the same handles that the loading state loads, so that their progress can be followed.
+/// The asset server hands back the assets already being loaded instead of loading them twice.
+fn track_loading(world: &mut World) {
+    let groups = vec![
+        ("Mino textures", MinoTextures::load(world)),
+        ("Shape table", DefaultShapeTable::load(world)),
+        ("Kick table", DefaultKickTable::load(world)),
+    ];
+    world.insert_resource(LoadingProgress(groups));
+}
+
+fn stop_tracking_loading(mut progress: ResMut<LoadingProgress>) {
+    progress.0.clear();
 }
 
 impl Plugin for StackingAssetsPlugin {
@@ -71,11 +157,19 @@ impl Plugin for StackingAssetsPlugin {
             .add_loading_state(
                 LoadingState::new(MainState::Loading)
                     .continue_to_state(MainState::Ready)
+                    .on_failure_continue_to_state(MainState::LoadingFailed)
                     .load_collection::<MinoTextures>()
                     .load_collection::<DefaultShapeTable>()
                     .load_collection::<DefaultKickTable>(),
             )
             .init_asset_loader::<ShapeTableLoader>()
-            .init_asset_loader::<KickTableLoader>();
+            .init_asset_loader::<KickTableLoader>()
+            .init_resource::<LoadingErrors>()
+            .init_resource::<LoadingProgress>()
+            .add_systems(OnEnter(MainState::Loading), track_loading)
+            .add_systems(OnExit(MainState::Loading), stop_tracking_loading)
+            .init_resource::<Palette>()
+            .add_systems(Update, collect_loading_errors)
+            .add_systems(Update, apply_palette.run_if(resource_changed::<Palette>));
     }
 }
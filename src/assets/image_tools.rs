@@ -1,28 +1,47 @@
 use bevy::math::uvec2;
 use bevy::prelude::*;
-use image::{GenericImage, ImageBuffer};
+use image::{DynamicImage, GenericImage, ImageBuffer, Rgba, RgbaImage};
 use tap::Tap;
 
+/// A grayscale copy of the image, brightened so that its brightest pixel is white. Tinting the copy
+/// with a color gives a cell of about that color, whatever the color of the original.
+fn neutral(image: &RgbaImage) -> RgbaImage {
+    let luma = |p: &Rgba<u8>| 0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32;
+    let brightest = image.pixels().map(luma).fold(1.0, f32::max);
+    image.clone().tap_mut(|image| {
+        for p in image.pixels_mut() {
+            let v = (luma(p) / brightest * 255.0) as u8;
+            *p = Rgba([v, v, v, p[3]]);
+        }
+    })
+}
+
 /// Assuming that each texture is equal in size, this function combines them into a single texture
 /// which can be bound as a `texture_2d_array`. If this assumption doesn't pass, the function
 /// panics. It also panics if there are no images to stack.
+///
+/// The array holds each image as it is, followed by a neutral (grayscale) copy of each image in the same
+/// order, so that the copy of layer `i` is layer `i + images.len()`.
 pub fn stack_images(images: &[Handle<Image>], server: &Assets<Image>) -> Image {
     // fetch an image to determine the target size
     let size = server.get(&images[0]).unwrap().size();
-    let buffer_size = size * uvec2(1, images.len() as u32);
+    let layers = 2 * images.len() as u32;
+    let buffer_size = size * uvec2(1, layers);
     // create the buffer from the inferred size
     let mut buffer = ImageBuffer::new(buffer_size.x, buffer_size.y);
 
-    // copy each image into the newly created buffer
+    // copy each image and its neutral copy into the newly created buffer
     for (i, h) in images.iter().enumerate() {
         let image = server.get(h).unwrap();
-        let dyn_image = image.clone().try_into_dynamic().unwrap();
+        let rgba = image.clone().try_into_dynamic().unwrap().to_rgba8();
+        let neutral_layer = i + images.len();
         buffer
-            .copy_from(&dyn_image, 0, size.y * i as u32)
+            .copy_from(&rgba, 0, size.y * i as u32)
+            .and_then(|_| buffer.copy_from(&neutral(&rgba), 0, size.y * neutral_layer as u32))
             .expect("Failed to copy image while creating an image stack");
     }
 
-    Image::from_dynamic(buffer.into(), true, default()).tap_mut(|i| {
-        i.reinterpret_stacked_2d_as_array(images.len() as u32);
+    Image::from_dynamic(DynamicImage::ImageRgba8(buffer), true, default()).tap_mut(|i| {
+        i.reinterpret_stacked_2d_as_array(layers);
     })
 }
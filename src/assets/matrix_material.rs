@@ -1,4 +1,5 @@
 use crate::assets::image_tools::stack_images;
+use crate::assets::palette::Palette;
 use crate::assets::MinoTextures;
 use crate::board::CELL_SIZE;
 use bevy::ecs::system::{EntityCommands, SystemParam};
@@ -9,6 +10,29 @@ use bevy::render::render_resource::{AsBindGroup, ShaderRef};
 use bevy::sprite::{Material2d, MaterialMesh2dBundle, Mesh2dHandle};
 use tap::Pipe;
 
+/// What is drawn behind the empty cells of the matrix, so that the height of the stack can be
+/// judged out of the corner of the eye
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    strum::EnumIter,
+    strum::Display,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum WellBackground {
+    #[default]
+    Plain,
+    /// Brightest at the floor of the matrix, fading out toward the top
+    Gradient,
+    /// Every other cell slightly brighter
+    Checkered,
+}
+
 #[derive(Clone, TypePath, Asset, AsBindGroup)]
 pub struct MatrixMaterial {
     #[uniform(0)]
@@ -18,6 +42,25 @@ pub struct MatrixMaterial {
     pub mino_textures: Handle<Image>,
     #[storage(3, read_only)]
     pub data: Vec<u32>,
+    /// Multiplied with the color of every cell
+    #[uniform(4)]
+    pub tint: Color,
+    /// The color of each kind of cell, from the [`Palette`]
+    #[storage(5, read_only)]
+    pub palette: Vec<Vec4>,
+    /// Whether each kind of piece is marked with its own pattern (as a boolean)
+    #[uniform(6)]
+    pub patterns: u32,
+    /// Whether the well background is drawn behind the empty cells (as a boolean). Only the matrix
+    /// itself turns this on, so that hold, queue and active pieces stay plain.
+    #[uniform(7)]
+    pub well: u32,
+    /// The [`WellBackground`] drawn, by its position in the enum
+    #[uniform(8)]
+    pub well_background: u32,
+    /// The color of the well background, with its intensity as the alpha
+    #[uniform(9)]
+    pub well_color: Color,
 }
 
 impl Material2d for MatrixMaterial {
@@ -33,6 +76,7 @@ pub struct MatrixMaterialSpawner<'w, 's> {
     material_server: ResMut<'w, Assets<MatrixMaterial>>,
     mesh_server: ResMut<'w, Assets<Mesh>>,
     mino_textures: Res<'w, MinoTextures>,
+    palette: Res<'w, Palette>,
 }
 
 fn corners(r: IRect) -> [IVec2; 4] {
@@ -85,6 +129,22 @@ where
         )
     }
 
+    /// Gives a sprite spawned by this spawner a new size, emptying all of its cells
+    pub fn reshape(
+        &mut self,
+        sprite: Entity,
+        material: &Handle<MatrixMaterial>,
+        grid_bounds: IRect,
+    ) {
+        let mesh = self.quad_anchored(grid_bounds);
+        self.commands.entity(sprite).insert(mesh);
+        if let Some(material) = self.material_server.get_mut(material) {
+            let size = grid_bounds.size();
+            material.dimensions = size.as_uvec2();
+            material.data = vec![0; (size.x * size.y) as usize];
+        }
+    }
+
     pub fn spawn_with_data(
         &'all mut self,
         grid_bounds: IRect,
@@ -99,6 +159,12 @@ where
             dimensions: grid_bounds.size().as_uvec2(),
             mino_textures: self.texture_server.add(all_textures),
             data,
+            tint: Color::WHITE,
+            palette: self.palette.material_colors(),
+            patterns: self.palette.patterns() as u32,
+            well: 0,
+            well_background: WellBackground::Plain as u32,
+            well_color: Color::NONE,
         };
         let mesh = self.quad_anchored(grid_bounds);
 
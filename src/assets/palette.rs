@@ -0,0 +1,139 @@
+//! The colors that each kind of cell is drawn in, so that players who find the standard colors hard
+//! to tell apart can pick colors (or patterns) which work for them.
+
+use bevy::prelude::*;
+use strum::IntoEnumIterator;
+
+use crate::assets::matrix_material::MatrixMaterial;
+use crate::board::MinoKind;
+
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    strum::EnumIter,
+    strum::Display,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum PalettePreset {
+    /// The colors of the mino textures
+    #[default]
+    Default,
+    /// Colors which stay apart under red-green color blindness
+    Deuteranopia,
+    /// Fully saturated colors on a neutral base
+    #[strum(to_string = "High Contrast")]
+    HighContrast,
+    /// A single shade for every piece, with a different pattern on each kind of piece
+    Monochrome,
+}
+
+/// The color of each kind of cell, as used by the matrix and everything else which is colored by
+/// piece (drop shadows, particles, statistics).
+#[derive(Resource, Clone, Debug)]
+pub struct Palette {
+    preset: PalettePreset,
+    /// Colors in order of the ids of the kinds
+    colors: Vec<Color>,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::new(PalettePreset::Default)
+    }
+}
+
+impl Palette {
+    pub fn new(preset: PalettePreset) -> Self {
+        let colors = MinoKind::iter()
+            .map(|kind| Self::preset_color(preset, kind))
+            .collect();
+        Self { preset, colors }
+    }
+
+    fn preset_color(preset: PalettePreset, kind: MinoKind) -> Color {
+        use MinoKind::*;
+        let rgb = |r: u8, g: u8, b: u8| Color::rgb_u8(r, g, b);
+        match (preset, kind) {
+            (_, E) => Color::NONE,
+            (PalettePreset::Default, _) => kind.color(),
+
+            // the Okabe-Ito colors
+            (PalettePreset::Deuteranopia, T) => rgb(204, 121, 167),
+            (PalettePreset::Deuteranopia, O) => rgb(240, 228, 66),
+            (PalettePreset::Deuteranopia, L) => rgb(230, 159, 0),
+            (PalettePreset::Deuteranopia, J) => rgb(0, 114, 178),
+            (PalettePreset::Deuteranopia, S) => rgb(86, 180, 233),
+            (PalettePreset::Deuteranopia, Z) => rgb(213, 94, 0),
+            (PalettePreset::Deuteranopia, I) => rgb(0, 158, 115),
+
+            (PalettePreset::HighContrast, T) => rgb(255, 0, 255),
+            (PalettePreset::HighContrast, O) => rgb(255, 255, 0),
+            (PalettePreset::HighContrast, L) => rgb(255, 140, 0),
+            (PalettePreset::HighContrast, J) => rgb(40, 90, 255),
+            (PalettePreset::HighContrast, S) => rgb(0, 255, 0),
+            (PalettePreset::HighContrast, Z) => rgb(255, 0, 0),
+            (PalettePreset::HighContrast, I) => rgb(0, 255, 255),
+            (PalettePreset::HighContrast, G) => rgb(200, 200, 200),
+
+            (PalettePreset::Monochrome, G) => Color::rgb(0.45, 0.45, 0.45),
+            (PalettePreset::Monochrome, _) => Color::rgb(0.85, 0.85, 0.85),
+
+            (_, G) => Color::GRAY,
+            // the slots keep their spread around the color wheel
+            _ => kind.color(),
+        }
+    }
+
+    pub fn preset(&self) -> PalettePreset {
+        self.preset
+    }
+
+    pub fn color(&self, kind: MinoKind) -> Color {
+        self.colors[kind as usize]
+    }
+
+    /// Whether each kind of piece is marked with its own pattern
+    pub fn patterns(&self) -> bool {
+        self.preset == PalettePreset::Monochrome
+    }
+
+    /// The colors that the matrix tints the neutral textures with, in order of the ids of the
+    /// kinds. A color with no alpha leaves the cell with its original texture, which is the case
+    /// for every cell under the default palette.
+    pub fn material_colors(&self) -> Vec<Vec4> {
+        if self.preset == PalettePreset::Default {
+            return vec![Vec4::ZERO; self.colors.len()];
+        }
+        self.colors
+            .iter()
+            .map(|c| Vec4::from(c.as_rgba_f32()))
+            .collect()
+    }
+
+    /// The color of the given section of the replay's progress bar. Each branch takes the color of
+    /// another piece in turn, ordered so that neighbouring sections stand apart. The monochrome
+    /// palette has only the one color for pieces, so it steps between shades of gray instead.
+    pub fn segment_color(&self, ix: usize) -> Color {
+        use MinoKind::*;
+        const ORDER: [MinoKind; 7] = [Z, I, L, T, O, J, S];
+        const SHADES: [f32; 4] = [0.85, 0.55, 0.7, 0.4];
+        if self.preset == PalettePreset::Monochrome {
+            return Color::hsl(0., 0., SHADES[ix % SHADES.len()]);
+        }
+        self.color(ORDER[ix % ORDER.len()])
+    }
+}
+
+/// Brings every matrix drawn so far onto the current palette
+pub(crate) fn apply_palette(palette: Res<Palette>, mut materials: ResMut<Assets<MatrixMaterial>>) {
+    let colors = palette.material_colors();
+    for (_, material) in materials.iter_mut() {
+        material.palette.clone_from(&colors);
+        material.patterns = palette.patterns() as u32;
+    }
+}
@@ -3,6 +3,7 @@ use std::ops::Deref;
 use bevy::{
     asset::Assets,
     ecs::system::{Res, SystemParam},
+    utils::thiserror,
 };
 
 use self::{
@@ -13,6 +14,15 @@ use self::{
 pub mod kick_table;
 pub mod shape_table;
 
+/// Reasons that a shape or kick table could not be loaded
+#[derive(thiserror::Error, Debug)]
+pub enum TableLoadError {
+    #[error("Could not read the table file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not interpret the table at {0}")]
+    Parse(#[from] ron::de::SpannedError),
+}
+
 duplicate::duplicate! {
     [
 n default t;
@@ -9,7 +9,9 @@ use bevy_asset_loader::asset_collection::AssetCollection;
 
 use crate::board::{MinoKind, RotationState};
 
-#[derive(serde::Deserialize, PartialEq, Eq, Hash)]
+use super::TableLoadError;
+
+#[derive(serde::Deserialize, PartialEq, Eq, Hash, Clone, Copy, Debug)]
 #[serde(from = "(MinoKind, RotationState, RotationState)")]
 pub struct KickParameters {
     pub kind: MinoKind,
@@ -23,15 +25,53 @@ impl From<(MinoKind, RotationState, RotationState)> for KickParameters {
     }
 }
 
-#[derive(serde::Deserialize, Asset, TypePath)]
+/// File that the default kick table is loaded from, relative to the working directory
+pub const DEFAULT_KICK_TABLE_FILE: &str = "assets/default.kick-table";
+
+#[derive(serde::Deserialize, Asset, TypePath, Clone)]
 pub struct KickTable(pub HashMap<KickParameters, Vec<IVec2>>);
 
+impl KickTable {
+    /// Reads a table written in the same format as the table files in the assets folder
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TableLoadError> {
+        Ok(ron::de::from_bytes(bytes)?)
+    }
+
+    /// Writes the table out in the same layout as the table files in the assets folder, with the
+    /// transitions of each piece grouped together.
+    pub fn to_ron(&self) -> String {
+        let mut entries = self.0.iter().collect::<Vec<_>>();
+        entries.sort_by_key(|(params, _)| (params.kind as u32, params.from, params.to));
+
+        let mut out = String::from("(\n    {\n");
+        let mut last_kind = None;
+        for (params, kicks) in entries {
+            if last_kind.is_some_and(|kind| kind != params.kind) {
+                out.push('\n');
+            }
+            last_kind = Some(params.kind);
+
+            let kicks = kicks
+                .iter()
+                .map(|k| format!("({}, {})", k.x, k.y))
+                .collect::<Vec<_>>()
+                .join(", ");
+            out += &format!(
+                "        ({:?}, {:?}, {:?}) : [{kicks}],\n",
+                params.kind, params.from, params.to
+            );
+        }
+        out += "    }\n)\n";
+        out
+    }
+}
+
 #[derive(Default)]
 pub struct KickTableLoader;
 impl AssetLoader for KickTableLoader {
     type Asset = KickTable;
     type Settings = ();
-    type Error = &'static str;
+    type Error = TableLoadError;
 
     fn load<'a>(
         &'a self,
@@ -41,14 +81,8 @@ impl AssetLoader for KickTableLoader {
     ) -> bevy::utils::BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
         Box::pin(async move {
             let mut bytes = Vec::new();
-            reader
-                .read_to_end(&mut bytes)
-                .await
-                .map_err(|_| "Could not read from the given file (when loading kick table)")?;
-            ron::de::from_bytes::<KickTable>(&bytes).map_err(|e| {
-                println!("{e}");
-                "Could not interpret the given kick table"
-            })
+            reader.read_to_end(&mut bytes).await?;
+            KickTable::from_bytes(&bytes)
         })
     }
 
@@ -62,3 +96,9 @@ pub struct DefaultKickTable {
     #[asset(path = "default.kick-table")]
     pub(super) table: Handle<KickTable>,
 }
+
+impl DefaultKickTable {
+    pub fn handle(&self) -> &Handle<KickTable> {
+        &self.table
+    }
+}
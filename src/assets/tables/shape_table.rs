@@ -14,6 +14,8 @@ use bevy_asset_loader::asset_collection::AssetCollection;
 
 use crate::board::{Mino, MinoKind, RotationState};
 
+use super::TableLoadError;
+
 #[derive(serde::Deserialize, PartialEq, Eq, Hash, Clone, Copy, Debug)]
 #[serde(from = "(MinoKind, RotationState)")]
 pub struct ShapeParameters {
@@ -39,6 +41,9 @@ impl From<Mino> for ShapeParameters {
     }
 }
 
+/// File that the default shape table is loaded from, relative to the working directory
+pub const DEFAULT_SHAPE_TABLE_FILE: &str = "assets/default.shape-table";
+
 #[derive(serde::Deserialize, Resource, Clone, Debug, Asset, TypePath, Deref)]
 pub struct ShapeTable {
     table: HashMap<ShapeParameters, Vec<IVec2>>,
@@ -50,7 +55,7 @@ pub(crate) struct ShapeTableLoader;
 impl AssetLoader for ShapeTableLoader {
     type Asset = ShapeTable;
     type Settings = ();
-    type Error = &'static str;
+    type Error = TableLoadError;
 
     fn load<'a>(
         &'a self,
@@ -63,14 +68,8 @@ impl AssetLoader for ShapeTableLoader {
             tracing::debug!("beginning shape table load");
 
             let mut bytes = Vec::new();
-            reader
-                .read_to_end(&mut bytes)
-                .await
-                .map_err(|_| "Could not read from the given file (when loading shape table)")?;
-            let shape_table: HashMap<ShapeParameters, Vec<IVec2>> = ron::de::from_bytes(&bytes)
-                .map_err(|_| "Could not interpret the given shape table")?;
-
-            Ok(ShapeTable { table: shape_table })
+            reader.read_to_end(&mut bytes).await?;
+            ShapeTable::from_bytes(&bytes)
         })
     }
 
@@ -80,6 +79,26 @@ impl AssetLoader for ShapeTableLoader {
 }
 
 impl ShapeTable {
+    /// Reads a table written in the same format as the table files in the assets folder
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TableLoadError> {
+        Ok(Self {
+            table: ron::de::from_bytes(bytes)?,
+        })
+    }
+
+    /// Every kind of piece that the table has a shape for, in order of their ids
+    pub fn kinds(&self) -> Vec<MinoKind> {
+        let mut kinds = self
+            .table
+            .keys()
+            .map(|p| p.kind)
+            .filter(|k| k.is_piece())
+            .collect::<Vec<_>>();
+        kinds.sort_by_key(|&k| k as u32);
+        kinds.dedup();
+        kinds
+    }
+
     /// Returns a bounding rectangle on all the coordinates listed in the table. The first coordinate is
     /// less than or equal to all coordinates in the table, and the second one is greater than all
     /// coordinates in the table.
@@ -121,3 +140,10 @@ pub struct DefaultShapeTable {
     #[asset(path = "default.shape-table")]
     pub(super) table: Handle<ShapeTable>,
 }
+
+impl DefaultShapeTable {
+    /// Uses a table that has already been added to the assets, rather than loading the default
+    pub fn new(table: Handle<ShapeTable>) -> Self {
+        Self { table }
+    }
+}
@@ -1,18 +1,38 @@
 use bevy::ecs::query::QueryData;
-use bevy::math::{ivec2, IVec2};
+use bevy::math::{ivec2, vec2, IVec2};
 use bevy::prelude::*;
 use smart_default::SmartDefault;
 
+pub mod events;
+pub mod finesse;
+pub mod garbage;
+pub mod motion;
+pub mod mouse;
+pub mod openers;
 pub mod queue;
 pub mod update;
 
 use crate::assets::tables::QueryShapeTable;
 use crate::board::update::default_mino;
-use crate::controller::process_input;
+use crate::controller::profiles::{Handling, Profiles};
+use crate::controller::{reset_controller, SocdPolicy};
+use crate::pause::not_paused;
 use crate::replay::record::PreviousMatrix;
-use crate::{screens::GlobalSettings, state::MainState};
+use crate::schedule::FrameSet;
+use crate::screens::{apply_settings, GlobalSettings};
+use crate::state::MainState;
+use crate::stats::count_lines;
 
-use self::{queue::PieceQueue, update::update_board};
+use self::{
+    events::{announce_start, GameEnded, GameStarted, HoldUsed, LinesCleared, PieceLocked},
+    finesse::{count_presses, judge_finesse, reset_finesse, FinesseCounter},
+    garbage::{GarbagePattern, GarbageRng},
+    motion::PieceMotion,
+    mouse::mouse_placement,
+    openers::{reset_after_bag, reset_opener_drill, OpenerDrill},
+    queue::{drill_bag, PieceQueue, QueueSource},
+    update::{check_goal, update_board},
+};
 
 #[derive(
 Debug, PartialEq, Eq, Hash, Clone, Copy,
@@ -22,10 +42,44 @@ serde::Serialize, serde::Deserialize, strum::EnumIter
 #[rustfmt::skip]
 pub enum MinoKind {
     E = 0, T, O, L, J, S, Z, I, G,
+    /// Slots for pieces outside of the seven tetrominoes, such as pentominoes. What shape each
+    /// slot has is decided entirely by the shape table.
+    P0, P1, P2, P3, P4, P5, P6, P7, P8, P9, P10, P11, P12, P13, P14, P15, P16, P17,
 }
 
 impl MinoKind {
+    /// The seven tetrominoes
+    pub const STANDARD: [MinoKind; 7] = [
+        MinoKind::T,
+        MinoKind::O,
+        MinoKind::L,
+        MinoKind::J,
+        MinoKind::S,
+        MinoKind::Z,
+        MinoKind::I,
+    ];
+
+    /// Whether this is a piece which can be played, rather than an empty or garbage cell
+    pub fn is_piece(self) -> bool {
+        !matches!(self, MinoKind::E | MinoKind::G)
+    }
+
+    /// The position of this kind among the generic slots, if it is one
+    pub fn slot(self) -> Option<u32> {
+        (self as u32).checked_sub(MinoKind::P0 as u32)
+    }
+
+    pub fn from_slot(slot: u32) -> Option<Self> {
+        use strum::IntoEnumIterator;
+        Self::iter().find(|k| k.slot() == Some(slot))
+    }
+
     pub fn color(&self) -> Color {
+        if let Some(slot) = self.slot() {
+            // spread the slots evenly around the color wheel
+            return Color::hsl(slot as f32 * 360.0 / 18.0, 0.7, 0.55);
+        }
+
         match self {
             MinoKind::T => Color::PURPLE,
             MinoKind::O => Color::YELLOW,
@@ -34,13 +88,17 @@ impl MinoKind {
             MinoKind::S => Color::LIME_GREEN,
             MinoKind::Z => Color::RED,
             MinoKind::I => Color::AQUAMARINE,
-            MinoKind::G => todo!(),
-            MinoKind::E => todo!(),
+            MinoKind::G => Color::GRAY,
+            MinoKind::E => Color::NONE,
+            _ => unreachable!("slots are colored above"),
         }
     }
 }
 
-#[derive(Default, PartialEq, Eq, Hash, serde::Deserialize, Clone, Copy, Debug, PartialOrd, Ord)]
+#[derive(
+Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialOrd,
+Ord, strum::EnumIter
+)]
 #[rustfmt::skip]
 pub enum RotationState {
     #[default] Up, Right, Down, Left
@@ -78,14 +136,14 @@ impl RotationState {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct Mino {
     pub kind: MinoKind,
     pub position: IVec2,
     pub rotation: RotationState,
 }
 
-#[derive(Component, Default, Clone, Copy, Debug)]
+#[derive(Component, Default, Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
 pub enum Hold {
     #[default]
     Empty,
@@ -104,6 +162,104 @@ impl Hold {
 pub const MATRIX_DEFAULT_SIZE: IVec2 = ivec2(10, 40);
 pub const MATRIX_DEFAULT_LEGAL_BOUNDS: IVec2 = ivec2(10, 20);
 pub const CELL_SIZE: u32 = 32;
+/// Lines to clear to finish a sprint
+pub const SPRINT_LINES: u32 = 40;
+/// Rate at which the board is updated when the fixed timestep is enabled, until the settings give
+/// another
+pub const FIXED_TIMESTEP_HZ: f64 = 60.0;
+
+/// Finds the cell of the board under the given point on the screen (such as the cursor), if the
+/// point is over the board at all. The board is centered on its legal area, as it is drawn.
+pub fn screen_to_cell(
+    point: Vec2,
+    (camera, camera_transform): (&Camera, &GlobalTransform),
+    (board_transform, bounds): (&GlobalTransform, &Bounds),
+) -> Option<IVec2> {
+    let world = camera.viewport_to_world_2d(camera_transform, point)?;
+    let local = board_transform
+        .affine()
+        .inverse()
+        .transform_point3(world.extend(0.0))
+        .truncate();
+    let cell = (local / CELL_SIZE as f32 + bounds.legal_bounds.as_vec2() / 2.)
+        .floor()
+        .as_ivec2();
+
+    (cell.cmpge(IVec2::ZERO).all() && cell.cmplt(bounds.true_bounds).all()).then_some(cell)
+}
+
+/// Finds the rectangle of the window (in logical pixels) covering the given rows of the board,
+/// counting up from the bottom of the legal area across its full width.
+pub fn board_screen_rect(
+    rows: i32,
+    (camera, camera_transform): (&Camera, &GlobalTransform),
+    (board_transform, bounds): (&GlobalTransform, &Bounds),
+) -> Option<Rect> {
+    let half = bounds.legal_bounds.as_vec2() / 2. * CELL_SIZE as f32;
+    let top = rows as f32 * CELL_SIZE as f32 - half.y;
+    let offset = camera.logical_viewport_rect()?.min;
+
+    let corner = |local: Vec2| {
+        let world = board_transform.transform_point(local.extend(0.0));
+        camera
+            .world_to_viewport(camera_transform, world)
+            .map(|p| p + offset)
+    };
+    Some(Rect::from_corners(
+        corner(-half)?,
+        corner(vec2(half.x, top))?,
+    ))
+}
+
+/// The piece which could not spawn at the end of the last game, if the game ended by topping out
+#[derive(Resource, Default)]
+pub struct FailedSpawn(pub Option<Mino>);
+
+fn reset_failed_spawn(mut failed: ResMut<FailedSpawn>) {
+    failed.0 = None;
+}
+
+/// Sent whenever locking a piece causes rows of the matrix to be cleared, along with what the rows
+/// held. Unlike [`LinesCleared`], this is only sent during live play.
+#[derive(Event, Clone, Debug)]
+pub struct LineClearEvent {
+    pub board: Entity,
+    /// Indices of the cleared rows, as they were before the matrix collapsed, in increasing order
+    pub rows: Vec<usize>,
+    /// The contents of each cleared row, in the same order as `rows`
+    pub contents: Vec<Vec<MinoKind>>,
+}
+
+/// Sent when the player tries to move the active piece and it cannot move at all
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BlockedMoveEvent {
+    pub board: Entity,
+    pub attempt: BlockedMove,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockedMove {
+    /// No kick of the rotation fit
+    Rotation,
+    /// A freshly pressed shift (left for negative) with the piece already against the wall. Shifts
+    /// which are only repeating are not counted.
+    Shift(i32),
+}
+
+/// Sent when a board in continuous play tops out, and is wiped so that play can go on.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BoardWipeEvent {
+    pub board: Entity,
+}
+
+/// Sent when a piece has no room to spawn, and the top rows of the stack are cleared away so that
+/// play can go on.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct LifesaverUsed {
+    pub board: Entity,
+    /// Rows which were cleared away
+    pub rows: usize,
+}
 
 #[derive(Component, SmartDefault)]
 pub struct Bounds {
@@ -113,50 +269,160 @@ pub struct Bounds {
     pub legal_bounds: IVec2,
 }
 
-#[derive(Component, Default)]
+#[derive(Component, Default, Clone)]
 pub struct Active(pub Option<Mino>);
 
-#[derive(Component)]
+/// The cells of the board, stored row by row from the bottom of the board up.
+#[derive(Component, Clone, Debug, PartialEq)]
 pub struct Matrix {
-    pub data: Vec<Vec<MinoKind>>,
+    data: Vec<MinoKind>,
+    width: usize,
+    height: usize,
 }
 
 impl Default for Matrix {
     fn default() -> Self {
-        Self {
-            data: std::iter::repeat_with(|| vec![MinoKind::E; MATRIX_DEFAULT_SIZE.x as usize])
-                .take(MATRIX_DEFAULT_SIZE.y as usize)
-                .collect(),
-        }
+        Self::new(MATRIX_DEFAULT_SIZE)
     }
 }
 
-#[derive(Component, Default)]
+#[derive(Component, Default, Clone)]
 pub struct DropClock {
     fall: f32,
     lock: f32,
 }
 
+impl DropClock {
+    /// Rows that the active piece has fallen toward, including the part of a row not yet fallen
+    pub fn fall(&self) -> f32 {
+        self.fall
+    }
+
+    /// Seconds that the active piece has spent on the ground toward locking
+    pub fn lock(&self) -> f32 {
+        self.lock
+    }
+}
+
 impl Matrix {
-    fn get(&self, ix: IVec2) -> Option<MinoKind> {
-        if ix.cmpge(ivec2(0, 0)).all() {
-            self.data
-                .get(ix.y as usize)
-                .and_then(|row| row.get(ix.x as usize))
-                .copied()
-        } else {
-            None
+    /// An empty matrix of the given size
+    pub fn new(size: IVec2) -> Self {
+        let (width, height) = (size.x as usize, size.y as usize);
+        Self {
+            data: vec![MinoKind::E; width * height],
+            width,
+            height,
         }
     }
 
-    fn get_mut(&mut self, ix: IVec2) -> Option<&mut MinoKind> {
-        if ix.cmpge(ivec2(0, 0)).all() {
-            self.data
-                .get_mut(ix.y as usize)
-                .and_then(|row| row.get_mut(ix.x as usize))
-        } else {
-            None
-        }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index(&self, ix: IVec2) -> Option<usize> {
+        let in_bounds = ix.cmpge(ivec2(0, 0)).all()
+            && (ix.x as usize) < self.width
+            && (ix.y as usize) < self.height;
+        in_bounds.then(|| ix.y as usize * self.width + ix.x as usize)
+    }
+
+    /// Converts an index into [`Self::cells`] into the location of that cell
+    pub fn location(&self, ix: usize) -> IVec2 {
+        ivec2((ix % self.width) as i32, (ix / self.width) as i32)
+    }
+
+    pub fn get(&self, ix: IVec2) -> Option<MinoKind> {
+        self.index(ix).map(|ix| self.data[ix])
+    }
+
+    pub fn get_mut(&mut self, ix: IVec2) -> Option<&mut MinoKind> {
+        self.index(ix).map(|ix| &mut self.data[ix])
+    }
+
+    pub fn row(&self, y: usize) -> &[MinoKind] {
+        &self.data[y * self.width..(y + 1) * self.width]
+    }
+
+    pub fn row_mut(&mut self, y: usize) -> &mut [MinoKind] {
+        &mut self.data[y * self.width..(y + 1) * self.width]
+    }
+
+    /// Iterates over the rows of the matrix, from the bottom up
+    pub fn rows(&self) -> impl Iterator<Item = &[MinoKind]> {
+        self.data.chunks(self.width)
+    }
+
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [MinoKind]> {
+        self.data.chunks_mut(self.width)
+    }
+
+    /// All cells of the matrix, row by row from the bottom up
+    pub fn cells(&self) -> &[MinoKind] {
+        &self.data
+    }
+
+    pub fn cells_mut(&mut self) -> &mut [MinoKind] {
+        &mut self.data
+    }
+
+    /// Iterates over every cell of the matrix along with its location
+    pub fn iter_cells(&self) -> impl Iterator<Item = (IVec2, MinoKind)> + '_ {
+        self.data
+            .iter()
+            .enumerate()
+            .map(|(ix, &kind)| (self.location(ix), kind))
+    }
+
+    /// Empties every cell of the matrix
+    pub fn clear(&mut self) {
+        self.data.fill(MinoKind::E);
+    }
+
+    /// Counts the empty cells which have a filled cell somewhere above them in the same column
+    pub fn holes(&self) -> usize {
+        (0..self.width)
+            .map(|x| {
+                let column = self.data.iter().skip(x).step_by(self.width);
+                let height = column.clone().rposition(|&k| k != MinoKind::E);
+                height.map_or(0, |top| {
+                    column.take(top).filter(|&&k| k == MinoKind::E).count()
+                })
+            })
+            .sum()
+    }
+
+    /// Pushes the given row in at the bottom of the matrix, moving every row up by one. The top row
+    /// of the matrix is lost.
+    pub fn push_row_from_bottom(&mut self, row: &[MinoKind]) {
+        self.data.rotate_right(self.width);
+        self.data[..self.width].copy_from_slice(row);
+    }
+
+    /// Empties the highest rows which have anything in them, up to the given number of them, and
+    /// returns how many were emptied. Nothing is above these rows, so the rows below stay where they
+    /// are.
+    pub fn clear_top_rows(&mut self, count: usize) -> usize {
+        let Some(top) = self
+            .rows()
+            .rposition(|row| row.iter().any(|&kind| kind != MinoKind::E))
+        else {
+            return 0;
+        };
+        let bottom = (top + 1).saturating_sub(count);
+        self.data[bottom * self.width..(top + 1) * self.width].fill(MinoKind::E);
+        top + 1 - bottom
+    }
+
+    /// Removes the given row, moving every row above it down by one and leaving an empty row at the
+    /// top of the matrix.
+    pub fn collapse_row(&mut self, y: usize) {
+        self.data[y * self.width..].rotate_left(self.width);
+        let top = self.data.len() - self.width;
+        self.data[top..].fill(MinoKind::E);
     }
 }
 
@@ -167,7 +433,7 @@ pub enum MatrixAction {
     Erase,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub struct MatrixUpdate {
     pub loc: IVec2,
     pub old: MinoKind,
@@ -181,18 +447,161 @@ impl MatrixUpdate {
     }
 }
 
-#[derive(Component, Clone, Debug)]
+/// Controls how much of the locked stack is shown during live play. The logical [`Matrix`] is never
+/// affected, only what is drawn of it.
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    strum::EnumIter,
+    strum::Display,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum StackVisibility {
+    #[default]
+    Normal,
+    /// Locked cells are never drawn, except for a brief flash when lines are cleared
+    Invisible,
+    /// Locked cells disappear some time after they are placed
+    Fading,
+}
+
+/// Decides which interactions with a grounded piece give it more time before it locks.
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    strum::EnumIter,
+    strum::Display,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum LockReset {
+    /// Every successful shift or rotation resets the lock delay
+    #[default]
+    Infinite,
+    /// Shifts and rotations reset the lock delay a limited number of times, and the limit is
+    /// refreshed whenever the piece reaches a new lowest row
+    #[strum(to_string = "Move Reset")]
+    MoveReset,
+    /// Only reaching a new lowest row resets the lock delay
+    #[strum(to_string = "Step Reset")]
+    StepReset,
+}
+
+/// The kind of game being played, which decides how the board starts and when the game ends.
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    strum::EnumIter,
+    strum::Display,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum GameMode {
+    /// Play until topping out
+    #[default]
+    Freestyle,
+    /// Start with cheese, and finish once the whole stack is below the target height
+    Downstack,
+    /// Start with cheese, and finish once every row of it has been cleared, using as few pieces as
+    /// possible
+    #[strum(to_string = "Cheese Race")]
+    CheeseRace,
+    /// Finish once a set number of lines have been cleared, as quickly as possible
+    Sprint,
+    /// Play the first bag over and over, with the board starting over after every bag
+    Openers,
+}
+
+impl GameMode {
+    /// Whether the board starts with rows of cheese at the bottom
+    pub fn has_cheese(self) -> bool {
+        matches!(self, GameMode::Downstack | GameMode::CheeseRace)
+    }
+
+    /// Whether the board starts over once the given number of pieces have been placed, as it does
+    /// after every bag of an openers drill
+    pub fn ends_bag(self, placed: usize, bag_size: usize) -> bool {
+        self == GameMode::Openers && bag_size > 0 && placed % bag_size == 0
+    }
+}
+
+#[derive(Component, Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct Settings {
+    /// Multiplier applied to gravity while soft dropping
     pub soft_drop_power: f32,
+    /// Speed at which the active piece falls, in cells per second
     pub gravity_power: f32,
     pub lock_delay: f32,
+    pub lock_reset: LockReset,
+    /// Number of lock delay resets a piece gets under [`LockReset::MoveReset`]
+    pub move_reset_limit: u32,
     pub initial_delay: u32,
     pub repeat_delay: u32,
+    /// Milliseconds for which presses are kept when there is no piece for them to act on
+    pub input_buffer: u32,
+    pub socd: SocdPolicy,
+    pub stack_visibility: StackVisibility,
+    /// Seconds before a locked cell disappears, when the stack is fading
+    pub fade_delay: f32,
+    pub mode: GameMode,
+    /// Number of rows of cheese the board starts with, in the modes which have cheese
+    pub cheese_height: usize,
+    pub garbage_pattern: GarbagePattern,
+    /// Probability that the gap in the cheese moves between rows, from 0 to 1
+    pub messiness: f32,
+    /// After each clear which makes no new holes, push a row of garbage in under the stack
+    pub adaptive_cheese: bool,
+    /// Wipe the board when topping out instead of ending the game. Only freestyle games are
+    /// played continuously, since the other modes end on their own.
+    pub continuous: bool,
+    /// Whether a wipe in continuous play also empties the hold
+    pub wipe_hold: bool,
+    /// Whether each bag of an openers drill deals a new bag, rather than carrying on with the queue
+    pub reseed_bags: bool,
+    /// Rows cleared off the top of the stack when a piece has no room to spawn, so that the game
+    /// goes on instead of ending. None are cleared (and the game ends) when this is zero.
+    pub lifesaver_rows: usize,
+    /// Downstacking is finished once no cells remain at or above this row
+    pub target_height: usize,
+    pub queue: QueueSource,
+    /// Pieces which the randomizer leaves out of its bags, for drilling the rest
+    pub excluded_pieces: Vec<MinoKind>,
+    /// The piece in hold when the game starts
+    pub initial_hold: Option<MinoKind>,
+    /// Pieces dealt first, ahead of the queue
+    pub preset_queue: Vec<MinoKind>,
+    /// Pieces are placed with the mouse, and do not fall or lock on their own
+    pub mouse_mode: bool,
+    /// Ticks per second that the board runs at, when it runs on a fixed timestep
+    pub tick_rate: f64,
+}
+
+impl Settings {
+    /// Whether topping out wipes the board rather than ending the game
+    pub fn is_continuous(&self) -> bool {
+        self.continuous && self.mode == GameMode::Freestyle
+    }
 }
 
 impl Default for Settings {
     fn default() -> Self {
-        (&GlobalSettings::default()).try_into().unwrap()
+        (&GlobalSettings::default(), &Handling::default())
+            .try_into()
+            .unwrap()
     }
 }
 
@@ -208,28 +617,119 @@ pub struct Board {
     hold: Hold,
     queue: PieceQueue,
     drop_clock: DropClock,
+    motion: PieceMotion,
     settings: Settings,
     previous_matrix: PreviousMatrix,
+    garbage_rng: GarbageRng,
+}
+
+impl Board {
+    /// A board with default contents, placed at the given position
+    pub fn at(translation: Vec3) -> Self {
+        Self {
+            transform: Transform::from_translation(translation),
+            ..default()
+        }
+    }
 }
 
 fn respawn_board(
     mut commands: Commands,
     old_boards: Query<Entity, With<Matrix>>,
     settings: Res<GlobalSettings>,
+    profiles: Res<Profiles>,
 ) {
     for e in old_boards.iter() {
         commands.entity(e).despawn_recursive();
     }
     commands.spawn(Board {
-        settings: Settings::try_from(&*settings).unwrap(),
+        // a game is not started until the settings are valid, so the defaults stand in until then
+        settings: Settings::try_from((&*settings, &profiles.active().handling)).unwrap_or_default(),
         ..default()
     });
 }
 
-fn start_game(mut boards: Query<BoardQuery>, shape: QueryShapeTable) {
+/// The seed that the queue of the current (or last) game was started from
+#[derive(Resource, Clone, Copy)]
+pub struct CurrentGameSeed(pub u64);
+
+/// Marks that the next game should deal the same pieces as the last, by starting its queue from
+/// the same seed
+#[derive(Resource)]
+pub struct RetryQueue;
+
+/// Rebuilds the queue of each board waiting for the game to start, so that the queue always begins
+/// from the start of its script (if any) and deals the pieces of the loaded shape table. The preset
+/// pieces and hold are put in place here as well, so that they are part of the board before the
+/// game starts and its first state is recorded.
+fn reset_queue(
+    mut boards: Query<(&mut PieceQueue, &mut Hold, &Settings), Changed<Settings>>,
+    shape_table: QueryShapeTable,
+    retry: Option<Res<RetryQueue>>,
+    seed: Option<Res<CurrentGameSeed>>,
+) {
+    let seed = seed.filter(|_| retry.is_some());
+    for (mut queue, mut hold, settings) in boards.iter_mut() {
+        let pieces = drill_bag(shape_table.kinds(), &settings.excluded_pieces);
+        *queue = match &seed {
+            Some(seed) => PieceQueue::seeded(settings.queue.clone(), seed.0, pieces),
+            None => PieceQueue::new(settings.queue.clone(), pieces),
+        };
+        queue.prepend(&settings.preset_queue);
+        *hold = settings.initial_hold.map_or(Hold::Empty, Hold::Ready);
+    }
+}
+
+/// Keeps the seed of the game being started, so that the same queue can be dealt again
+fn remember_seed(mut commands: Commands, boards: Query<&PieceQueue>) {
+    if let Ok(queue) = boards.get_single() {
+        commands.insert_resource(CurrentGameSeed(queue.seed()));
+    }
+    commands.remove_resource::<RetryQueue>();
+}
+
+/// Whether the board is updated on a fixed timestep rather than once per frame. Either way, the
+/// record is written once per frame in real time, so its timestamps mean the same in both modes.
+pub(crate) fn fixed_timestep(settings: Res<GlobalSettings>) -> bool {
+    settings.fixed_timestep
+}
+
+/// Whether the game has yet to end. A frame can run several ticks of the fixed timestep, and the
+/// game only leaves play at the end of the frame, so the ticks after the game ends are skipped.
+fn game_ongoing(next: Res<NextState<MainState>>) -> bool {
+    next.0.is_none()
+}
+
+/// Runs the fixed timestep at the tick rate in the board's settings
+fn apply_tick_rate(boards: Query<&Settings, Changed<Settings>>, mut fixed: ResMut<Time<Fixed>>) {
+    if let Some(settings) = boards.iter().find(|s| s.tick_rate > 0.0) {
+        fixed.set_timestep_hz(settings.tick_rate);
+    }
+}
+
+pub(crate) fn start_game(mut boards: Query<BoardQuery>, shape: QueryShapeTable) {
     for mut board in boards.iter_mut() {
+        let mut rng = garbage::rng(board.queue.seed());
+        if board.settings.mode.has_cheese() {
+            let height = board
+                .settings
+                .cheese_height
+                .min(board.bounds.legal_bounds.y as usize);
+            let width = board.bounds.true_bounds.x as usize;
+            let rows = garbage::cheese(
+                &mut rng,
+                &board.settings.garbage_pattern,
+                board.settings.messiness,
+                height,
+                width,
+            );
+            board.fill_from_bottom(rows);
+        }
+        // adaptive cheese carries on from the rows generated at the start
+        board.garbage_rng.0 = rng;
+
         let new_piece = board.queue.take();
-        board.spawn_piece(default_mino(new_piece), &shape);
+        board.spawn_piece(default_mino(new_piece, &shape), &shape);
     }
 }
 
@@ -243,26 +743,100 @@ pub struct BoardQuery {
     pub hold: &'static mut Hold,
     pub queue: &'static mut PieceQueue,
     pub drop_clock: &'static mut DropClock,
+    pub motion: &'static mut PieceMotion,
     pub bounds: &'static Bounds,
     pub settings: &'static Settings,
+    pub garbage_rng: &'static mut GarbageRng,
     pub id: Entity,
 }
 
 impl Plugin for BoardPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(MainState::Ready), respawn_board)
+        app.add_event::<LineClearEvent>()
+            .add_event::<PieceLocked>()
+            .add_event::<LinesCleared>()
+            .add_event::<HoldUsed>()
+            .add_event::<GameStarted>()
+            .add_event::<GameEnded>()
+            .add_event::<BoardWipeEvent>()
+            .add_event::<LifesaverUsed>()
+            .add_event::<BlockedMoveEvent>()
+            .init_resource::<FailedSpawn>()
+            .init_resource::<FinesseCounter>()
+            .init_resource::<OpenerDrill>()
+            .add_systems(OnExit(MainState::PostGame), reset_failed_spawn)
+            .add_systems(OnEnter(MainState::Ready), respawn_board)
+            .add_systems(
+                Update,
+                reset_queue
+                    .before(apply_settings)
+                    .run_if(in_state(MainState::Ready)),
+            )
             .add_systems(
                 OnTransition {
                     from: MainState::Ready,
                     to: MainState::Playing,
                 },
-                start_game,
+                (
+                    (start_game, announce_start).chain(),
+                    remember_seed,
+                    reset_finesse,
+                    reset_opener_drill,
+                ),
             )
+            .insert_resource(Time::<Fixed>::from_hz(FIXED_TIMESTEP_HZ))
+            .add_systems(Update, apply_tick_rate.after(apply_settings))
             .add_systems(
                 Update,
-                update_board
-                    .after(process_input)
+                (
+                    update_board.in_set(FrameSet::Logic).run_if(not_paused),
+                    count_lines,
+                    check_goal,
+                )
+                    .chain()
+                    .run_if(in_state(MainState::Playing).and_then(not(fixed_timestep))),
+            )
+            .add_systems(
+                Update,
+                mouse_placement
+                    .in_set(FrameSet::Logic)
+                    .before(update_board)
+                    .run_if(in_state(MainState::Playing).and_then(not_paused)),
+            )
+            .add_systems(
+                Update,
+                (
+                    count_presses
+                        .in_set(FrameSet::Logic)
+                        .before(update_board)
+                        .run_if(not(fixed_timestep).and_then(not_paused)),
+                    (judge_finesse, reset_after_bag)
+                        .chain()
+                        .in_set(FrameSet::Logic)
+                        .after(update_board)
+                        .run_if(not(fixed_timestep)),
+                )
                     .run_if(in_state(MainState::Playing)),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    count_presses,
+                    update_board,
+                    count_lines,
+                    check_goal,
+                    judge_finesse,
+                    reset_after_bag,
+                )
+                    .chain()
+                    .in_set(FrameSet::Logic)
+                    .before(reset_controller)
+                    .run_if(
+                        in_state(MainState::Playing)
+                            .and_then(fixed_timestep)
+                            .and_then(not_paused)
+                            .and_then(game_ongoing),
+                    ),
             );
     }
 }
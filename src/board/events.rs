@@ -0,0 +1,148 @@
+//! Events describing how a game goes, for overlays, stream integrations and other tools which want
+//! to follow along without reaching into the board. These events are the stable surface for such
+//! tools: their fields will only grow, and they are sent the same way no matter where the game is
+//! being played from. They can also be written out with serde, as for stream overlays.
+//!
+//! Locks, clears and holds are sent during live play and while the replay plays forward (but not
+//! while it seeks or rewinds), marked by whether they were replayed. The start and end of a game
+//! are only sent during live play. The headless [`Game`](crate::api::Game) collects the same events
+//! as [`GameEvent`]s.
+
+use bevy::math::ivec2;
+use bevy::prelude::*;
+use serde::Serialize;
+
+use crate::assets::tables::shape_table::ShapeTable;
+use crate::board::update::{has_free_space, lock_piece};
+use crate::board::{Matrix, Mino, MinoKind};
+use crate::replay::ghost::Ghost;
+
+/// Sent whenever a piece locks into the matrix, before any lines are cleared
+#[derive(Event, Clone, Debug, PartialEq, Serialize)]
+pub struct PieceLocked {
+    pub board: Entity,
+    pub kind: MinoKind,
+    /// The piece where it locked
+    pub mino: Mino,
+    /// The cells of the matrix that the piece filled
+    pub cells: Vec<IVec2>,
+    /// How many lines the lock cleared
+    pub clear: usize,
+    /// Whether the piece was stuck in place when it locked, unable to move left, right or up
+    pub spin: bool,
+    pub replayed: bool,
+}
+
+/// Sent whenever locking a piece clears rows of the matrix, after the [`PieceLocked`] of that
+/// piece
+#[derive(Event, Clone, Debug, PartialEq, Serialize)]
+pub struct LinesCleared {
+    pub board: Entity,
+    /// Indices of the cleared rows, as they were before the matrix collapsed, in increasing order
+    pub rows: Vec<usize>,
+    pub replayed: bool,
+}
+
+/// Sent whenever the active piece is swapped into hold
+#[derive(Event, Clone, Debug, PartialEq, Serialize)]
+pub struct HoldUsed {
+    pub board: Entity,
+    /// The piece which was put into hold
+    pub kind: MinoKind,
+    pub replayed: bool,
+}
+
+/// Sent once the first piece of a game has spawned
+#[derive(Event, Clone, Debug, PartialEq, Serialize)]
+pub struct GameStarted {
+    pub board: Entity,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameEndReason {
+    /// A piece could not spawn, or the stack was pushed out of the legal area
+    TopOut,
+    GoalReached,
+    /// The player ended the game early
+    Ended,
+    /// The player abandoned the game to start a new one
+    Restarted,
+}
+
+/// Sent when a game ends, for whatever reason
+#[derive(Event, Clone, Debug, PartialEq, Serialize)]
+pub struct GameEnded {
+    pub board: Entity,
+    pub reason: GameEndReason,
+}
+
+/// Any of the events of a game, in the order that they were sent
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameEvent {
+    PieceLocked(PieceLocked),
+    LinesCleared(LinesCleared),
+    HoldUsed(HoldUsed),
+    GameStarted(GameStarted),
+    GameEnded(GameEnded),
+}
+
+/// Whether the piece cannot move left, right or up from where it is
+pub fn is_spin(matrix: &Matrix, mino: Mino, shape_table: &ShapeTable) -> bool {
+    [ivec2(-1, 0), ivec2(1, 0), ivec2(0, 1)]
+        .into_iter()
+        .all(|offset| {
+            let moved = Mino {
+                position: mino.position + offset,
+                ..mino
+            };
+            !has_free_space(matrix, moved, shape_table)
+        })
+}
+
+/// The events sent when the given piece locks into the matrix, which is as it was before the lock.
+/// Live play, the replay and the headless API all work out their events here, so that they agree.
+///
+/// A spin has to end with a rotation, so the lock is given whether the last move of the piece was
+/// one (see [`PieceMotion::spun`](crate::board::motion::PieceMotion::spun)). When that is not known, as for a piece placed directly, any
+/// piece stuck in place counts as a spin.
+pub(crate) fn lock_events(
+    board: Entity,
+    matrix: &Matrix,
+    mino: Mino,
+    shape_table: &ShapeTable,
+    spun: Option<bool>,
+    replayed: bool,
+) -> (PieceLocked, Option<LinesCleared>) {
+    let rows = lock_piece(&mut matrix.clone(), mino, shape_table)
+        .into_iter()
+        .map(|(row, _)| row)
+        .collect::<Vec<_>>();
+    let locked = PieceLocked {
+        board,
+        kind: mino.kind,
+        mino,
+        cells: shape_table[mino]
+            .iter()
+            .map(|&cell| cell + mino.position)
+            .collect(),
+        clear: rows.len(),
+        spin: spun != Some(false) && is_spin(matrix, mino, shape_table),
+        replayed,
+    };
+    let cleared = (!rows.is_empty()).then_some(LinesCleared {
+        board,
+        rows,
+        replayed,
+    });
+    (locked, cleared)
+}
+
+pub(crate) fn announce_start(
+    boards: Query<Entity, (With<Matrix>, Without<Ghost>)>,
+    mut started: EventWriter<GameStarted>,
+) {
+    for board in boards.iter() {
+        started.send(GameStarted { board });
+    }
+}
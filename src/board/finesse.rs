@@ -0,0 +1,149 @@
+//! Finesse: placing each piece with as few presses as it could have taken. The presses made on each
+//! piece are counted, and a piece placed with more of them than needed is a finesse fault. Only the
+//! moves made from where the piece spawns count towards the least presses, so spins and tucks are
+//! never judged.
+
+use std::collections::{HashSet, VecDeque};
+
+use bevy::prelude::*;
+
+use crate::assets::tables::kick_table::{KickParameters, KickTable};
+use crate::assets::tables::shape_table::ShapeTable;
+use crate::assets::tables::{QueryKickTable, QueryShapeTable};
+use crate::board::events::{HoldUsed, PieceLocked};
+use crate::board::update::{default_mino, has_free_space, kick_search};
+use crate::board::{Matrix, Mino};
+use crate::controller::Controller;
+use crate::stats::Stats;
+
+/// Placements which take more presses than this are not judged
+const MAX_PRESSES: u32 = 8;
+
+/// The cells that the piece would fill if it were dropped straight down on an empty matrix
+fn landing(matrix: &Matrix, mut mino: Mino, shape_table: &ShapeTable) -> Vec<IVec2> {
+    while has_free_space(matrix, mino, shape_table) {
+        mino.position.y -= 1;
+    }
+    mino.position.y += 1;
+    let mut cells = shape_table[mino]
+        .iter()
+        .map(|&cell| cell + mino.position)
+        .collect::<Vec<_>>();
+    cells.sort_by_key(|c| (c.x, c.y));
+    cells
+}
+
+/// The least presses which bring a piece from where it spawns to above the given placement, on an
+/// empty matrix, so that a hard drop locks it there. Tapping a shift, holding a shift into the
+/// wall and each rotation count as one press. Placements which look the same (such as an O piece
+/// in any rotation) are as good as one another. Returns nothing if the placement cannot be reached
+/// by a hard drop.
+pub fn minimum_presses(
+    target: Mino,
+    shape_table: &ShapeTable,
+    kick_table: &KickTable,
+) -> Option<u32> {
+    let matrix = Matrix::default();
+    let goal = landing(&matrix, target, shape_table);
+    let start = default_mino(target.kind, shape_table);
+    let fits = |mino: Mino| has_free_space(&matrix, mino, shape_table);
+
+    let mut seen = HashSet::from([start]);
+    let mut frontier = VecDeque::from([(start, 0)]);
+    while let Some((mino, presses)) = frontier.pop_front() {
+        if landing(&matrix, mino, shape_table) == goal {
+            return Some(presses);
+        }
+        if presses >= MAX_PRESSES {
+            continue;
+        }
+
+        let shifted = |dx: i32| {
+            Some(Mino {
+                position: mino.position + IVec2::X * dx,
+                ..mino
+            })
+            .filter(|&m| fits(m))
+        };
+        let held = |dx: i32| {
+            let mut last = shifted(dx)?;
+            while let Some(next) = Some(Mino {
+                position: last.position + IVec2::X * dx,
+                ..last
+            })
+            .filter(|&m| fits(m))
+            {
+                last = next;
+            }
+            Some(last)
+        };
+        let rotated = [
+            mino.rotation.rotate_left(),
+            mino.rotation.rotate_right(),
+            mino.rotation.rotate_180(),
+        ]
+        .map(|to| {
+            let kicks = kick_table
+                .0
+                .get(&KickParameters {
+                    kind: mino.kind,
+                    from: mino.rotation,
+                    to,
+                })
+                .map_or(&[][..], Vec::as_slice);
+            kick_search(&matrix, mino, to, kicks, shape_table).map(|(_, m)| m)
+        });
+
+        let next = [shifted(-1), shifted(1), held(-1), held(1)]
+            .into_iter()
+            .chain(rotated)
+            .flatten();
+        for next in next {
+            if seen.insert(next) {
+                frontier.push_back((next, presses + 1));
+            }
+        }
+    }
+    None
+}
+
+/// The presses made on the active piece so far
+#[derive(Resource, Default)]
+pub struct FinesseCounter {
+    presses: u32,
+}
+
+/// Counts the fresh shift and rotation presses which reach the board. Runs just before the board
+/// takes in the controller, on whichever schedule the board runs on.
+pub(crate) fn count_presses(mut counter: ResMut<FinesseCounter>, controller: Res<Controller>) {
+    counter.presses += (controller.fresh_shift && controller.shift != 0) as u32;
+    counter.presses += controller.rotation.is_some() as u32;
+}
+
+/// Judges each piece locked against the least presses it could have taken, counting a fault for
+/// each piece placed with more. Holding starts the count over for the piece brought out.
+pub(crate) fn judge_finesse(
+    mut counter: ResMut<FinesseCounter>,
+    mut stats: ResMut<Stats>,
+    mut locks: EventReader<PieceLocked>,
+    mut holds: EventReader<HoldUsed>,
+    shape_table: QueryShapeTable,
+    kick_table: QueryKickTable,
+) {
+    if holds.read().any(|hold| !hold.replayed) {
+        counter.presses = 0;
+    }
+    for lock in locks.read().filter(|lock| !lock.replayed) {
+        let presses = std::mem::take(&mut counter.presses);
+        if lock.spin {
+            continue;
+        }
+        if minimum_presses(lock.mino, &shape_table, &kick_table).is_some_and(|min| presses > min) {
+            stats.finesse_faults += 1;
+        }
+    }
+}
+
+pub(crate) fn reset_finesse(mut counter: ResMut<FinesseCounter>) {
+    counter.presses = 0;
+}
@@ -0,0 +1,266 @@
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy::utils::thiserror;
+use rand::Rng;
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
+
+use super::{Matrix, MinoKind};
+
+/// Stream of the generator used for garbage, kept apart from the stream of the piece queue
+const GARBAGE_STREAM: u64 = 0x6172_6261_6765;
+/// Width of the gap left in each row by [`GarbagePattern::FourWide`]
+const WELL_WIDTH: usize = 4;
+
+/// A generator for garbage which, given the seed of the board's queue, always generates the same
+/// garbage for that board
+pub fn rng(seed: u64) -> Pcg32 {
+    Pcg32::new(seed, GARBAGE_STREAM)
+}
+
+/// The generator which adaptive cheese draws its rows from, continuing from the rows that the board
+/// started with
+#[derive(Component, Clone, Deref, DerefMut)]
+pub struct GarbageRng(pub Pcg32);
+
+impl Default for GarbageRng {
+    fn default() -> Self {
+        Self(rng(0))
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PatternLoadError {
+    #[error("Could not open pattern: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not read pattern: {0}")]
+    Parse(#[from] ron::de::SpannedError),
+    #[error("Pattern has no rows")]
+    Empty,
+    #[error("Pattern contains '{0}', but rows may only contain 'G' and '.'")]
+    UnknownCell(char),
+}
+
+/// The shape of the garbage that a downstacking board starts with.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum GarbagePattern {
+    /// A single hole in each row
+    #[default]
+    Clean,
+    /// A gap four cells wide in each row
+    FourWide,
+    /// Alternating cells, offset by one on each row
+    Checkerboard,
+    /// Alternating columns, the same on each row
+    Comb,
+    /// Rows written out by hand, from top to bottom with `G` for garbage and `.` for empty cells.
+    /// The rows are repeated as many times as needed, keeping the last row at the bottom.
+    Custom(Vec<String>),
+}
+
+impl GarbagePattern {
+    pub const PRESETS: [GarbagePattern; 4] = [
+        GarbagePattern::Clean,
+        GarbagePattern::FourWide,
+        GarbagePattern::Checkerboard,
+        GarbagePattern::Comb,
+    ];
+
+    /// Reads a custom pattern from a RON file containing a list of rows.
+    pub fn load(path: &Path) -> Result<Self, PatternLoadError> {
+        let rows: Vec<String> = ron::from_str(&std::fs::read_to_string(path)?)?;
+        if rows.is_empty() {
+            return Err(PatternLoadError::Empty);
+        }
+        if let Some(c) = rows
+            .iter()
+            .flat_map(|r| r.chars())
+            .find(|&c| c != 'G' && c != '.')
+        {
+            return Err(PatternLoadError::UnknownCell(c));
+        }
+        Ok(Self::Custom(rows))
+    }
+}
+
+impl Display for GarbagePattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GarbagePattern::Clean => write!(f, "Clean"),
+            GarbagePattern::FourWide => write!(f, "4-Wide Well"),
+            GarbagePattern::Checkerboard => write!(f, "Checkerboard"),
+            GarbagePattern::Comb => write!(f, "Comb"),
+            GarbagePattern::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+/// Picks the column of the gap in the next row, out of `columns` possible columns. With
+/// probability `messiness`, the gap moves to a different column, and otherwise it stays put.
+fn next_column(rng: &mut impl Rng, current: usize, columns: usize, messiness: f32) -> usize {
+    if columns <= 1 || !rng.gen_bool(messiness.clamp(0.0, 1.0) as f64) {
+        return current;
+    }
+
+    let next = rng.gen_range(0..columns - 1);
+    if next >= current {
+        next + 1
+    } else {
+        next
+    }
+}
+
+/// Rows with a gap of the given width, moving between rows according to `messiness`.
+fn gap_rows(
+    rng: &mut impl Rng,
+    messiness: f32,
+    gap_width: usize,
+    rows: usize,
+    width: usize,
+) -> Vec<Vec<MinoKind>> {
+    let columns = width.saturating_sub(gap_width) + 1;
+    let mut column = rng.gen_range(0..columns);
+    (0..rows)
+        .map(|_| {
+            column = next_column(rng, column, columns, messiness);
+
+            let mut row = vec![MinoKind::G; width];
+            let end = (column + gap_width).min(width);
+            row[column..end].fill(MinoKind::E);
+            row
+        })
+        .collect()
+}
+
+/// Rows of alternating cells, which are offset by one from each row to the next if `shift_rows` is
+/// set, and otherwise line up into columns.
+fn alternating_rows(
+    rng: &mut impl Rng,
+    shift_rows: bool,
+    rows: usize,
+    width: usize,
+) -> Vec<Vec<MinoKind>> {
+    let parity = rng.gen_range(0..2);
+    (0..rows)
+        .map(|y| {
+            let offset = if shift_rows { y + parity } else { parity };
+            (0..width)
+                .map(|x| {
+                    if (x + offset) % 2 == 0 {
+                        MinoKind::G
+                    } else {
+                        MinoKind::E
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Generates rows of garbage from the bottom up in the given pattern. For the patterns with a gap
+/// in each row, `messiness` is the probability that the gap changes column from one row to the
+/// next, so that at full messiness the gap never stays in the same column twice in a row. The
+/// other patterns are not affected by messiness.
+pub fn cheese(
+    rng: &mut impl Rng,
+    pattern: &GarbagePattern,
+    messiness: f32,
+    rows: usize,
+    width: usize,
+) -> Vec<Vec<MinoKind>> {
+    match pattern {
+        GarbagePattern::Clean => gap_rows(rng, messiness, 1, rows, width),
+        GarbagePattern::FourWide => gap_rows(rng, messiness, WELL_WIDTH, rows, width),
+        GarbagePattern::Checkerboard => alternating_rows(rng, true, rows, width),
+        GarbagePattern::Comb => alternating_rows(rng, false, rows, width),
+        GarbagePattern::Custom(pattern) => pattern
+            .iter()
+            .rev()
+            .cycle()
+            .take(rows)
+            .map(|line| custom_row(line, width))
+            .collect(),
+    }
+}
+
+/// A row of a custom pattern, cut or padded to the given width
+fn custom_row(line: &str, width: usize) -> Vec<MinoKind> {
+    let mut row = vec![MinoKind::E; width];
+    for (cell, c) in row.iter_mut().zip(line.chars()) {
+        if c == 'G' {
+            *cell = MinoKind::G;
+        }
+    }
+    row
+}
+
+/// The row which continues the pattern beneath the given row, as if it had been generated along
+/// with it. If the given row holds no garbage, the pattern is started over.
+fn row_below(
+    rng: &mut impl Rng,
+    pattern: &GarbagePattern,
+    messiness: f32,
+    above: &[MinoKind],
+) -> Vec<MinoKind> {
+    let width = above.len();
+    if !above.contains(&MinoKind::G) {
+        return cheese(rng, pattern, messiness, 1, width).remove(0);
+    }
+
+    let filled = |garbage: bool| if garbage { MinoKind::G } else { MinoKind::E };
+    match pattern {
+        GarbagePattern::Clean | GarbagePattern::FourWide => {
+            let gap_width = if *pattern == GarbagePattern::Clean {
+                1
+            } else {
+                WELL_WIDTH
+            };
+            let columns = width.saturating_sub(gap_width) + 1;
+            let current = above.iter().position(|&k| k != MinoKind::G).unwrap_or(0);
+            let column = next_column(rng, current.min(columns - 1), columns, messiness);
+
+            let mut row = vec![MinoKind::G; width];
+            row[column..(column + gap_width).min(width)].fill(MinoKind::E);
+            row
+        }
+        GarbagePattern::Checkerboard => above.iter().map(|&k| filled(k != MinoKind::G)).collect(),
+        GarbagePattern::Comb => above.iter().map(|&k| filled(k == MinoKind::G)).collect(),
+        GarbagePattern::Custom(lines) => {
+            // the pattern runs from top to bottom, so the row below a line is the line after it
+            let matches = |line: &String| {
+                let row = custom_row(line, width);
+                row.iter()
+                    .zip(above)
+                    .all(|(&a, &b)| (a == MinoKind::G) == (b == MinoKind::G))
+            };
+            let next = lines.iter().position(matches).map_or(0, |i| i + 1);
+            custom_row(&lines[next % lines.len()], width)
+        }
+    }
+}
+
+/// Pushes a row of garbage in under the stack for adaptive cheese, continuing the pattern of the
+/// bottom row. Returns false, leaving the matrix alone, if this would push any cell out of the
+/// legal area.
+pub fn shove(
+    matrix: &mut Matrix,
+    rng: &mut impl Rng,
+    pattern: &GarbagePattern,
+    messiness: f32,
+    legal_height: usize,
+) -> bool {
+    let overflows = matrix
+        .rows()
+        .skip(legal_height.saturating_sub(1))
+        .flatten()
+        .any(|&k| k != MinoKind::E);
+    if overflows {
+        return false;
+    }
+
+    let row = row_below(rng, pattern, messiness, matrix.row(0));
+    matrix.push_row_from_bottom(&row);
+    true
+}
@@ -0,0 +1,125 @@
+//! What the active piece has done since it spawned, kept in one place for the lock delay and for
+//! telling spins apart from pieces which only happen to be stuck where they lock.
+
+use bevy::prelude::*;
+
+use super::{LockReset, Mino};
+
+/// A way in which the active piece can move
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::Display)]
+pub enum PieceAction {
+    Shift,
+    Rotate,
+    /// Falling by gravity, soft drop or firm drop
+    Fall,
+}
+
+impl PieceAction {
+    /// The move which takes the piece from the one position to the other, if it moved at all. A
+    /// turn counts as a rotation even if the piece was also shifted on the same frame, since a kick
+    /// can move a piece sideways as it turns.
+    pub fn between(from: Mino, to: Mino) -> Option<Self> {
+        if from.rotation != to.rotation {
+            Some(Self::Rotate)
+        } else if from.position.x != to.position.x {
+            Some(Self::Shift)
+        } else if to.position.y < from.position.y {
+            Some(Self::Fall)
+        } else {
+            None
+        }
+    }
+}
+
+/// The history of the active piece since it spawned
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct PieceMotion {
+    /// The lowest row the piece has reached
+    lowest: i32,
+    last_action: Option<PieceAction>,
+    /// How far the last successful rotation was kicked from where the piece turned
+    last_kick: Option<IVec2>,
+    /// Number of times the lock delay has been reset by moving the piece
+    resets: u32,
+}
+
+impl PieceMotion {
+    /// The motion of a piece which has just spawned at the given place
+    pub fn spawned(piece: Mino) -> Self {
+        Self {
+            lowest: piece.position.y,
+            ..default()
+        }
+    }
+
+    pub fn lowest(&self) -> i32 {
+        self.lowest
+    }
+
+    pub fn last_action(&self) -> Option<PieceAction> {
+        self.last_action
+    }
+
+    pub fn last_kick(&self) -> Option<IVec2> {
+        self.last_kick
+    }
+
+    pub fn resets(&self) -> u32 {
+        self.resets
+    }
+
+    /// Whether the last thing the piece did was to rotate, which a spin has to end with
+    pub fn spun(&self) -> bool {
+        self.last_action == Some(PieceAction::Rotate)
+    }
+
+    pub fn shifted(&mut self) {
+        self.last_action = Some(PieceAction::Shift);
+    }
+
+    /// Notes a rotation which moved the piece by the given kick (zero for no kick)
+    pub fn rotated(&mut self, kick: IVec2) {
+        self.last_action = Some(PieceAction::Rotate);
+        self.last_kick = Some(kick);
+    }
+
+    pub fn fell(&mut self) {
+        self.last_action = Some(PieceAction::Fall);
+    }
+
+    /// Notes whichever move takes the piece from the one position to the other, for when only the
+    /// positions are known
+    pub fn moved(&mut self, from: Mino, to: Mino) {
+        match PieceAction::between(from, to) {
+            Some(PieceAction::Shift) => self.shifted(),
+            Some(PieceAction::Rotate) => self.rotated(to.position - from.position),
+            Some(PieceAction::Fall) => self.fell(),
+            None => (),
+        }
+    }
+
+    /// Whether the lock delay should start over, now that the piece is on the given row, given
+    /// whether it was just shifted or rotated and how the board resets the lock delay. Reaching a
+    /// new lowest row always starts the delay over and gives back every move reset.
+    pub fn lock_reset(&mut self, y: i32, moved: bool, reset: LockReset, limit: u32) -> bool {
+        if y < self.lowest {
+            self.lowest = y;
+            self.resets = 0;
+            return true;
+        }
+        if !moved {
+            return false;
+        }
+        match reset {
+            LockReset::Infinite => true,
+            LockReset::MoveReset => {
+                let allowed = self.resets < limit;
+                if allowed {
+                    self.resets += 1;
+                }
+                allowed
+            }
+            LockReset::StepReset => false,
+        }
+    }
+}
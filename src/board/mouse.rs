@@ -0,0 +1,176 @@
+//! Placing pieces with the mouse. The active piece follows the cursor, settling into the nearest
+//! placement which it could be moved into from where it spawned, and a click locks it there.
+
+use std::collections::{HashSet, VecDeque};
+
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::assets::tables::{
+    kick_table::{KickParameters, KickTable},
+    shape_table::ShapeTable,
+    QueryKickTable, QueryShapeTable,
+};
+use crate::replay::code::Placements;
+use crate::replay::ghost::Ghost;
+
+use super::update::{default_mino, has_free_space, kick_search, BoardEvents, TopOut};
+use super::{screen_to_cell, BoardQuery, Matrix, Mino, RotationState};
+
+/// Every placement where the given piece rests on the stack and which the piece can be brought to
+/// from where it is by shifting, soft dropping and rotating, just as it could be from the keyboard.
+pub fn reachable_placements(
+    matrix: &Matrix,
+    start: Mino,
+    shape_table: &ShapeTable,
+    kick_table: &KickTable,
+) -> Vec<Mino> {
+    let fits = |mino: Mino| has_free_space(matrix, mino, shape_table);
+    if !fits(start) {
+        return Vec::new();
+    }
+
+    let mut seen = HashSet::from([(start.position, start.rotation)]);
+    let mut frontier = VecDeque::from([start]);
+    let mut resting = Vec::new();
+    while let Some(mino) = frontier.pop_front() {
+        let moved = |offset: IVec2| {
+            Some(Mino {
+                position: mino.position + offset,
+                ..mino
+            })
+            .filter(|&m| fits(m))
+        };
+        let below = moved(IVec2::NEG_Y);
+        if below.is_none() {
+            resting.push(mino);
+        }
+
+        let rotations = [
+            mino.rotation.rotate_left(),
+            mino.rotation.rotate_right(),
+            mino.rotation.rotate_180(),
+        ]
+        .map(|to| {
+            let kicks = kick_table
+                .0
+                .get(&KickParameters {
+                    kind: mino.kind,
+                    from: mino.rotation,
+                    to,
+                })
+                .map_or(&[][..], Vec::as_slice);
+            kick_search(matrix, mino, to, kicks, shape_table).map(|(_, m)| m)
+        });
+
+        let neighbours = [below, moved(IVec2::NEG_X), moved(IVec2::X)]
+            .into_iter()
+            .chain(rotations)
+            .flatten();
+        for next in neighbours {
+            if seen.insert((next.position, next.rotation)) {
+                frontier.push_back(next);
+            }
+        }
+    }
+    resting
+}
+
+/// The middle of the cells covered by the given piece, in cells
+fn center(mino: Mino, shape_table: &ShapeTable) -> Vec2 {
+    let cells = &shape_table[mino];
+    cells
+        .iter()
+        .map(|&cell| (cell + mino.position).as_vec2() + 0.5)
+        .sum::<Vec2>()
+        / cells.len() as f32
+}
+
+/// Out of the given placements in the given rotation, the one centered closest to the given cell
+fn nearest_placement(
+    placements: &[Mino],
+    rotation: RotationState,
+    cell: IVec2,
+    shape_table: &ShapeTable,
+) -> Option<Mino> {
+    let target = cell.as_vec2() + 0.5;
+    let distance = |mino: &Mino| center(*mino, shape_table).distance_squared(target);
+    placements
+        .iter()
+        .filter(|mino| mino.rotation == rotation)
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+        .copied()
+}
+
+/// While the board is in mouse mode, moves the active piece to the reachable placement nearest the
+/// cursor, turning it with the scroll wheel. A left click locks the piece where it is, and a right
+/// click trades the piece for the next one in the queue.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn mouse_placement(
+    mut boards: Query<(BoardQuery, &GlobalTransform), Without<Ghost>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    mut wheel: EventReader<MouseWheel>,
+    shape_table: QueryShapeTable,
+    kick_table: QueryKickTable,
+    mut top_out: TopOut,
+    mut events: BoardEvents,
+    mut placements: ResMut<Placements>,
+) {
+    let scroll: f32 = wheel.read().map(|scroll| scroll.y).sum();
+    let Ok((mut board, board_transform)) = boards.get_single_mut() else {
+        return;
+    };
+    let Some(active) = board.active.0.filter(|_| board.settings.mouse_mode) else {
+        return;
+    };
+
+    if buttons.just_pressed(MouseButton::Right) {
+        let next = default_mino(board.queue.peek(), &shape_table);
+        if has_free_space(&board.matrix, next, &shape_table) {
+            board.queue.cycle(active.kind);
+            board.spawn_piece(next, &shape_table);
+            // the pieces are no longer played in the order the queue dealt them
+            placements.branched = true;
+        }
+        return;
+    }
+
+    let hovered = if_chain::if_chain! {
+        if let Ok(window) = windows.get_single();
+        if let Some(cursor) = window.cursor_position();
+        if let Ok(camera) = cameras.get_single();
+        then {
+            screen_to_cell(cursor, camera, (board_transform, board.bounds))
+        } else {
+            None
+        }
+    };
+    let Some(cell) = hovered else {
+        return;
+    };
+
+    let rotation = if scroll > 0.0 {
+        active.rotation.rotate_right()
+    } else if scroll < 0.0 {
+        active.rotation.rotate_left()
+    } else {
+        active.rotation
+    };
+    let start = default_mino(active.kind, &shape_table);
+    let reachable = reachable_placements(&board.matrix, start, &shape_table, &kick_table);
+    let Some(placement) = nearest_placement(&reachable, rotation, cell, &shape_table)
+        .or_else(|| nearest_placement(&reachable, active.rotation, cell, &shape_table))
+    else {
+        return;
+    };
+
+    if placement.position != active.position || placement.rotation != active.rotation {
+        board.active.0 = Some(placement);
+    }
+    if buttons.just_pressed(MouseButton::Left) {
+        board.hard_drop(&shape_table, &mut top_out, &mut events);
+    }
+}
@@ -0,0 +1,102 @@
+//! Openers drill: the first bag is played over and over, with the board starting over after every
+//! bag so that the same opener can be practiced again straight away.
+
+use bevy::prelude::*;
+
+use crate::assets::tables::QueryShapeTable;
+use crate::board::events::PieceLocked;
+use crate::board::queue::PieceQueue;
+use crate::board::update::default_mino;
+use crate::board::{BoardQuery, Hold};
+use crate::replay::code::Placements;
+use crate::stats::Stats;
+
+/// How one repetition of the drill went
+#[derive(Clone, Copy, Debug)]
+pub struct BagRun {
+    /// Seconds from the first piece of the bag to the last lock
+    pub time: f32,
+    pub finesse_faults: u32,
+}
+
+/// The repetitions of the openers drill played so far this game
+#[derive(Resource, Default)]
+pub struct OpenerDrill {
+    /// Pieces locked in the bag being played
+    placed: usize,
+    /// When the bag being played started, in seconds of play
+    started: f32,
+    /// Finesse faults made before the bag being played started
+    faults_before: u32,
+    pub bags: Vec<BagRun>,
+}
+
+impl OpenerDrill {
+    /// The bag being played, counting from one
+    pub fn repetition(&self) -> usize {
+        self.bags.len() + 1
+    }
+
+    /// Seconds spent on the bag being played
+    pub fn bag_time(&self, stats: &Stats) -> f32 {
+        stats.time - self.started
+    }
+
+    /// Mean time of the finished bags, or nothing if none have finished
+    pub fn average_time(&self) -> Option<f32> {
+        (!self.bags.is_empty())
+            .then(|| self.bags.iter().map(|bag| bag.time).sum::<f32>() / self.bags.len() as f32)
+    }
+}
+
+pub(crate) fn reset_opener_drill(mut drill: ResMut<OpenerDrill>) {
+    *drill = default();
+}
+
+/// Starts the board over once a bag's worth of pieces have locked in an openers drill. The matrix is
+/// cleared and the hold is put back as the game started, all on the board itself, so that the record
+/// picks the reset up as it does any other change. The queue carries on from where it was, unless
+/// each bag is re-seeded, in which case a new queue is dealt and the game can no longer be written as
+/// a run code.
+pub(crate) fn reset_after_bag(
+    mut boards: Query<BoardQuery>,
+    mut drill: ResMut<OpenerDrill>,
+    mut locks: EventReader<PieceLocked>,
+    mut placements: ResMut<Placements>,
+    stats: Res<Stats>,
+    shape_table: QueryShapeTable,
+) {
+    for lock in locks.read().filter(|lock| !lock.replayed) {
+        let Ok(mut board) = boards.get_mut(lock.board) else {
+            continue;
+        };
+        drill.placed += 1;
+        if !board
+            .settings
+            .mode
+            .ends_bag(drill.placed, board.queue.pieces().len())
+        {
+            continue;
+        }
+
+        board.matrix.clear();
+        *board.hold = board.settings.initial_hold.map_or(Hold::Empty, Hold::Ready);
+        if board.settings.reseed_bags {
+            let mut queue =
+                PieceQueue::new(board.queue.source().clone(), board.queue.pieces().to_vec());
+            queue.prepend(&board.settings.preset_queue);
+            let first = queue.take();
+            *board.queue = queue;
+            board.spawn_piece(default_mino(first, &shape_table), &shape_table);
+            placements.branched = true;
+        }
+
+        drill.placed = 0;
+        drill.bags.push(BagRun {
+            time: drill.bag_time(&stats),
+            finesse_faults: stats.finesse_faults - drill.faults_before,
+        });
+        drill.started = stats.time;
+        drill.faults_before = stats.finesse_faults;
+    }
+}
@@ -1,56 +1,326 @@
-use std::{collections::VecDeque, iter::repeat_with};
+use std::{collections::VecDeque, fmt::Display, iter::repeat_with, str::FromStr};
 
+use bevy::utils::thiserror;
 use bevy::{ecs::component::Component, utils::default};
-use rand::{seq::SliceRandom, thread_rng, SeedableRng};
+use rand::{seq::SliceRandom, thread_rng, Rng, SeedableRng};
 use rand_pcg::Pcg32;
 use serde::{Deserialize, Serialize};
 use tap::Tap;
 
 use super::MinoKind;
 
+/// How pieces are generated once nothing else decides them
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub enum Randomizer {
+    /// Each piece of the piece set once, shuffled, over and over. With the standard pieces, this is
+    /// the usual seven-bag.
+    #[default]
+    SevenBag,
+}
+
+/// The order that the standard pieces go into a bag before it is shuffled. Changing this changes
+/// the queue dealt for every seed.
+const STANDARD_BAG_ORDER: [MinoKind; 7] = {
+    use MinoKind::*;
+    [Z, S, T, L, J, I, O]
+};
+
+/// Puts the pieces of a bag in the order that they are shuffled from, so that a bag of the standard
+/// pieces is shuffled the same way no matter where the pieces came from.
+fn bag_order(kind: &MinoKind) -> u32 {
+    STANDARD_BAG_ORDER
+        .iter()
+        .position(|k| k == kind)
+        .map_or(STANDARD_BAG_ORDER.len() as u32 + *kind as u32, |ix| {
+            ix as u32
+        })
+}
+
+fn standard_pieces() -> Vec<MinoKind> {
+    STANDARD_BAG_ORDER.to_vec()
+}
+
+/// Bags of a drill hold at least this many pieces, so that a drill of a few pieces still deals
+/// them in a varied order
+const MIN_DRILL_BAG_SIZE: usize = 7;
+
+/// The pieces making up a bag of the given pieces, leaving out the excluded pieces for drills. The
+/// pieces left are repeated until the bag is about as large as a full bag. Excluding every piece
+/// excludes none of them, since a bag cannot be empty.
+pub fn drill_bag(pieces: Vec<MinoKind>, excluded: &[MinoKind]) -> Vec<MinoKind> {
+    let kept = pieces
+        .iter()
+        .copied()
+        .filter(|kind| !excluded.contains(kind))
+        .collect::<Vec<_>>();
+    if kept.is_empty() {
+        return pieces;
+    }
+    kept.repeat(MIN_DRILL_BAG_SIZE.div_ceil(kept.len()))
+}
+
+/// Where the pieces in the queue come from
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum QueueSource {
+    Random(Randomizer),
+    /// A fixed sequence of pieces, served before the randomizer takes over. If the sequence repeats,
+    /// the randomizer is never reached.
+    Scripted {
+        sequence: Vec<MinoKind>,
+        repeat: bool,
+        then: Randomizer,
+    },
+}
+
+impl Default for QueueSource {
+    fn default() -> Self {
+        Self::Random(default())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum QueueParseError {
+    #[error("'{0}' is not a piece")]
+    UnknownPiece(char),
+    #[error("'P{0}' is not a piece slot")]
+    UnknownSlot(u32),
+    #[error("'*' can only appear at the end of the queue")]
+    MisplacedRepeat,
+    #[error("There are no pieces to repeat")]
+    EmptyRepeat,
+}
+
+/// Parses a queue written as a sequence of piece letters (e.g. "ILJO TSZ"), ignoring whitespace.
+/// Pieces in the generic slots are written as `P` followed by the number of the slot (e.g. "P0P12").
+/// A trailing `*` repeats the sequence forever. An empty queue is served entirely by the randomizer.
+impl FromStr for QueueSource {
+    type Err = QueueParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.trim();
+        let (text, repeat) = match text.strip_suffix('*') {
+            Some(rest) => (rest, true),
+            None => (text, false),
+        };
+
+        let mut chars = text.chars().filter(|c| !c.is_whitespace()).peekable();
+        let mut sequence = Vec::new();
+        while let Some(c) = chars.next() {
+            sequence.push(match c.to_ascii_uppercase() {
+                'T' => MinoKind::T,
+                'O' => MinoKind::O,
+                'L' => MinoKind::L,
+                'J' => MinoKind::J,
+                'S' => MinoKind::S,
+                'Z' => MinoKind::Z,
+                'I' => MinoKind::I,
+                'P' => {
+                    let mut slot = None::<u32>;
+                    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                        chars.next();
+                        slot = Some(slot.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+                    }
+                    let slot = slot.ok_or(QueueParseError::UnknownPiece(c))?;
+                    MinoKind::from_slot(slot).ok_or(QueueParseError::UnknownSlot(slot))?
+                }
+                '*' => return Err(QueueParseError::MisplacedRepeat),
+                other => return Err(QueueParseError::UnknownPiece(other)),
+            });
+        }
+
+        match (sequence.is_empty(), repeat) {
+            (true, true) => Err(QueueParseError::EmptyRepeat),
+            (true, false) => Ok(Self::default()),
+            (false, _) => Ok(Self::Scripted {
+                sequence,
+                repeat,
+                then: default(),
+            }),
+        }
+    }
+}
+
+/// Writes the queue in the notation read by [`QueueSource::from_str`]
+impl Display for QueueSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Self::Scripted {
+            sequence, repeat, ..
+        } = self
+        {
+            for kind in sequence {
+                write!(f, "{kind:?}")?;
+            }
+            if *repeat {
+                write!(f, "*")?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Component, Clone, Serialize, Deserialize, Debug)]
 pub struct PieceQueue {
     window: VecDeque<MinoKind>,
     window_size: usize,
     rng: Pcg32,
+    /// The seed which the randomizer started from
+    #[serde(default)]
+    seed: u64,
+    source: QueueSource,
+    /// How much of a scripted sequence has been served
+    script_position: usize,
+    /// How many pieces of the current bag have been taken, when dealing bags
+    #[serde(default)]
+    bag_dealt: usize,
+    /// The pieces which make up a bag
+    #[serde(default = "standard_pieces")]
+    pieces: Vec<MinoKind>,
+    /// How many pieces at the front of the window were put there ahead of the source, and so are
+    /// not part of any bag
+    #[serde(default)]
+    prepended: usize,
 }
 
 impl Default for PieceQueue {
     fn default() -> Self {
+        Self::new(default(), standard_pieces())
+    }
+}
+
+// TODO should not assume that there will be a piece in the queue
+impl PieceQueue {
+    /// A queue which deals bags made of the given pieces, usually those of the shape table
+    pub fn new(source: QueueSource, pieces: Vec<MinoKind>) -> Self {
+        Self::seeded(source, thread_rng().gen(), pieces)
+    }
+
+    /// A queue which always deals the same pieces for the same source, seed and piece set
+    pub fn seeded(source: QueueSource, seed: u64, mut pieces: Vec<MinoKind>) -> Self {
+        pieces.sort_by_key(bag_order);
         Self {
             window: default(),
             window_size: 5,
-            rng: Pcg32::from_rng(thread_rng()).expect("could not construct an rng"),
+            rng: Pcg32::seed_from_u64(seed),
+            seed,
+            source,
+            script_position: 0,
+            bag_dealt: 0,
+            pieces,
+            prepended: 0,
         }
         .tap_mut(|a| a.refill_window())
     }
-}
 
-// TODO should not assume that there will be a piece in the queue
-impl PieceQueue {
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn source(&self) -> &QueueSource {
+        &self.source
+    }
+
     pub fn window(&self) -> &VecDeque<MinoKind> {
         &self.window
     }
 
-    pub fn peek(&mut self) -> MinoKind {
+    pub fn peek(&self) -> MinoKind {
         *self.window.front().unwrap()
     }
 
+    /// The pieces which make up a bag
+    pub fn pieces(&self) -> &[MinoKind] {
+        &self.pieces
+    }
+
+    /// The kinds of piece which can be dealt, once each
+    pub fn kinds(&self) -> Vec<MinoKind> {
+        self.pieces.clone().tap_mut(|kinds| {
+            kinds.sort_by_key(|&k| k as u32);
+            kinds.dedup();
+        })
+    }
+
+    /// Puts the given pieces at the front of the queue, in order, ahead of everything the source
+    /// deals. The source carries on from where it was once they have been taken.
+    pub fn prepend(&mut self, pieces: &[MinoKind]) {
+        for &kind in pieces.iter().rev() {
+            self.window.push_front(kind);
+        }
+        self.prepended += pieces.len();
+    }
+
+    /// Whether every piece comes from a bag, so that bags can be counted
+    fn deals_bags(&self) -> bool {
+        self.source == QueueSource::Random(Randomizer::SevenBag)
+    }
+
+    /// The pieces of the current bag which have not been taken yet, or `None` if the queue does not
+    /// deal pieces in bags.
+    pub fn bag_remaining(&self) -> Option<impl Iterator<Item = MinoKind> + '_> {
+        self.deals_bags().then(|| {
+            self.window
+                .iter()
+                .copied()
+                .skip(self.prepended)
+                .take(self.pieces.len() - self.bag_dealt)
+        })
+    }
+
     pub fn take(&mut self) -> MinoKind {
+        if self.prepended > 0 {
+            self.prepended -= 1;
+        } else if self.deals_bags() {
+            self.bag_dealt = (self.bag_dealt + 1) % self.pieces.len();
+        }
         let ret = self.window.pop_front().unwrap();
         self.refill_window();
         ret
     }
 
+    /// Trades the next piece for the given piece, which goes to the back of the visible part of the
+    /// queue. Trading again and again comes back around to the given piece.
+    pub fn cycle(&mut self, kind: MinoKind) {
+        self.window.pop_front();
+        self.prepended = self.prepended.saturating_sub(1);
+        let back = (self.window_size - 1).min(self.window.len());
+        self.window.insert(back, kind);
+    }
+
     fn refill_window(&mut self) {
+        if let QueueSource::Scripted {
+            sequence, repeat, ..
+        } = &self.source
+        {
+            while self.window_size > self.window.len() {
+                if *repeat && self.script_position == sequence.len() {
+                    self.script_position = 0;
+                }
+                let Some(&next) = sequence.get(self.script_position) else {
+                    break;
+                };
+                self.window.push_back(next);
+                self.script_position += 1;
+            }
+        }
+
+        let randomizer = match self.source {
+            QueueSource::Random(randomizer) => randomizer,
+            QueueSource::Scripted { then, .. } => then,
+        };
+
         if self.window_size > self.window.len() {
-            let bags_needed = (self.window_size - self.window.len() + 6) / 7;
-            use MinoKind::*;
-            self.window.extend(
-                repeat_with(|| [Z, S, T, L, J, I, O].tap_mut(|s| s.shuffle(&mut self.rng)))
-                    .take(bags_needed)
-                    .flatten(),
-            )
+            match randomizer {
+                Randomizer::SevenBag => {
+                    let bags_needed =
+                        (self.window_size - self.window.len()).div_ceil(self.pieces.len());
+                    let pieces = &self.pieces;
+                    self.window.extend(
+                        repeat_with(|| pieces.clone().tap_mut(|s| s.shuffle(&mut self.rng)))
+                            .take(bags_needed)
+                            .flatten(),
+                    )
+                }
+            }
         }
     }
 }
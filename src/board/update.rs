@@ -1,5 +1,6 @@
 use std::ops::Deref;
 
+use bevy::ecs::system::SystemParam;
 use bevy::math::ivec2;
 use bevy::prelude::*;
 use tap::{Tap, TapOptional};
@@ -9,38 +10,120 @@ use crate::assets::tables::{
     shape_table::ShapeTable,
     QueryKickTable, QueryShapeTable,
 };
-use crate::controller::{Controller, RotateCommand};
+use crate::controller::{BufferedInput, BufferedInputs, Controller, RotateCommand};
 use crate::state::MainState;
+use crate::stats::Stats;
 
-use super::{BoardQuery, BoardQueryItem, Hold, Matrix, Mino, MinoKind, RotationState};
+use super::events::{lock_events, GameEndReason, GameEnded, HoldUsed, LinesCleared, PieceLocked};
+use super::{
+    garbage, motion::PieceMotion, BlockedMove, BlockedMoveEvent, BoardQuery, BoardQueryItem,
+    BoardWipeEvent, DropClock, FailedSpawn, GameMode, Hold, LifesaverUsed, LineClearEvent, Matrix,
+    Mino, MinoKind, RotationState, Settings, MATRIX_DEFAULT_LEGAL_BOUNDS, SPRINT_LINES,
+};
+
+/// Events which the board sends out as the game progresses
+#[derive(SystemParam)]
+pub(crate) struct BoardEvents<'w> {
+    clears: EventWriter<'w, LineClearEvent>,
+    locks: EventWriter<'w, PieceLocked>,
+    lines: EventWriter<'w, LinesCleared>,
+    holds: EventWriter<'w, HoldUsed>,
+    blocked: EventWriter<'w, BlockedMoveEvent>,
+}
+
+/// Ends the game when a piece cannot spawn, remembering where the piece tried to spawn. In
+/// continuous play, the board is wiped instead, and with lifesavers, the top of the stack is
+/// cleared away.
+#[derive(SystemParam)]
+pub(crate) struct TopOut<'w> {
+    state: ResMut<'w, NextState<MainState>>,
+    failed: ResMut<'w, FailedSpawn>,
+    wipes: EventWriter<'w, BoardWipeEvent>,
+    lifesavers: EventWriter<'w, LifesaverUsed>,
+    ended: EventWriter<'w, GameEnded>,
+}
+
+impl<'w> TopOut<'w> {
+    fn top_out(&mut self, board: Entity, piece: Mino) {
+        self.overflow(board);
+        self.failed.0 = Some(piece);
+    }
+
+    /// Ends the game because the stack was pushed out of the legal area, rather than because a
+    /// piece could not spawn
+    fn overflow(&mut self, board: Entity) {
+        self.state.0 = Some(MainState::PostGame);
+        self.ended.send(GameEnded {
+            board,
+            reason: GameEndReason::TopOut,
+        });
+    }
+
+    fn wipe(&mut self, board: Entity) {
+        self.wipes.send(BoardWipeEvent { board });
+    }
+
+    fn lifesaver(&mut self, board: Entity, rows: usize) {
+        self.lifesavers.send(LifesaverUsed { board, rows });
+    }
+}
 
 /// Checks if the matrix can accommodate the given piece.
-fn has_free_space(matrix: &Matrix, mino: Mino, shape_table: &ShapeTable) -> bool {
+pub(crate) fn has_free_space(matrix: &Matrix, mino: Mino, shape_table: &ShapeTable) -> bool {
     shape_table[mino]
         .iter()
         .map(|&shape_offset| shape_offset + mino.position)
         .all(|position| matrix.get(position) == Some(MinoKind::E))
 }
 
+/// Rotates the given piece to the given rotation, first in place and then moved by each of the kicks
+/// in order, settling on the first position where it fits. Returns the number of the kick used
+/// (zero for no kick) along with the rotated piece, or nothing if the piece fits nowhere.
+pub(crate) fn kick_search(
+    matrix: &Matrix,
+    mino: Mino,
+    rotation: RotationState,
+    kicks: &[IVec2],
+    shape_table: &ShapeTable,
+) -> Option<(usize, Mino)> {
+    std::iter::once(ivec2(0, 0))
+        .chain(kicks.iter().copied())
+        .map(|o| {
+            mino.tap_mut(|m| {
+                m.rotation = rotation;
+                m.position += o;
+            })
+        })
+        .enumerate()
+        .find(|(_, m)| has_free_space(matrix, *m, shape_table))
+}
+
 /// Lock the given piece into the matrix, at the position and rotation it comes with. If there were
 /// any filled cells that take up the same space as the given mino, those cells are overwritten with
 /// the new piece. Line clears are also applied to the matrix, and any updates to the texture of the
-/// matrix are also registered.
-fn lock_piece(matrix: &mut Matrix, mino: Mino, shape_table: &ShapeTable) {
+/// matrix are also registered. Returns the rows which were cleared (indexed as they were before the
+/// matrix collapsed) along with their contents.
+pub(crate) fn lock_piece(
+    matrix: &mut Matrix,
+    mino: Mino,
+    shape_table: &ShapeTable,
+) -> Vec<(usize, Vec<MinoKind>)> {
     for &p in &shape_table[mino] {
         *(matrix.get_mut(p + mino.position).unwrap()) = mino.kind;
     }
 
     // line clears
+    let mut cleared = Vec::new();
     let mut real_ix = 0;
-    for _ in 0..matrix.data.len() {
-        if matrix.data[real_ix].iter().all(|&e| e != MinoKind::E) {
-            matrix.data[real_ix..].rotate_left(1);
-            matrix.data.last_mut().unwrap().fill(MinoKind::E);
+    for original_ix in 0..matrix.height() {
+        if matrix.row(real_ix).iter().all(|&e| e != MinoKind::E) {
+            cleared.push((original_ix, matrix.row(real_ix).to_vec()));
+            matrix.collapse_row(real_ix);
         } else {
             real_ix += 1;
         }
     }
+    cleared
 }
 
 /// Functions within this impl block will panic if the active piece does not exist.
@@ -93,18 +176,19 @@ impl<'world> BoardQueryItem<'world> {
         (shift_size != 0).tap(|&shifting| {
             if shifting {
                 self.active_mut().position.x += shift_size;
+                self.motion.shifted();
             }
         })
     }
 
     fn rotate(
         &mut self,
-        controller: &Controller,
+        command: Option<RotateCommand>,
         kick_table: &KickTable,
         shape_table: &ShapeTable,
     ) -> bool {
         let original_rotation = self.active().rotation;
-        let Some(new_rotation) = controller.rotation.map(|command| match command {
+        let Some(new_rotation) = command.map(|command| match command {
             RotateCommand::Left => original_rotation.rotate_left(),
             RotateCommand::Right => original_rotation.rotate_right(),
             RotateCommand::R180 => original_rotation.rotate_180(),
@@ -117,39 +201,127 @@ impl<'world> BoardQueryItem<'world> {
             from: original_rotation,
             to: new_rotation,
         };
-        let kicks = kick_table.0.get(&kick_params);
-        let offsets =
-            std::iter::once(ivec2(0, 0)).chain(kicks.iter().flat_map(|p| p.iter()).copied());
-
-        let successful_rot = offsets
-            .map(|o| {
-                self.active().tap_mut(|m| {
-                    m.rotation = new_rotation;
-                    m.position += o;
-                })
-            })
-            .find(|m| has_free_space(self.matrix.deref(), *m, shape_table));
+        let kicks = kick_table
+            .0
+            .get(&kick_params)
+            .map_or(&[][..], Vec::as_slice);
+        let successful_rot = kick_search(
+            self.matrix.deref(),
+            self.active(),
+            new_rotation,
+            kicks,
+            shape_table,
+        );
 
+        let original_position = self.active().position;
         successful_rot
-            .tap_some(|&rot| {
+            .tap_some(|&(_, rot)| {
                 *self.active_mut() = rot;
+                self.motion.rotated(rot.position - original_position);
             })
             .is_some()
     }
 
-    fn hard_drop(&mut self, shape_table: &ShapeTable, state: &mut NextState<MainState>) {
+    pub(super) fn hard_drop(
+        &mut self,
+        shape_table: &ShapeTable,
+        top_out: &mut TopOut,
+        events: &mut BoardEvents,
+    ) {
         let mut active = self.take_active();
-        active.position.y -= self.drop_height(shape_table, active);
-        lock_piece(&mut self.matrix, active, shape_table);
-        let new_piece = self.queue.peek();
-        if !self.spawn_piece(default_mino(new_piece), shape_table) {
-            state.0 = Some(MainState::PostGame);
-        } else {
+        let distance = self.drop_height(shape_table, active);
+        if distance > 0 {
+            active.position.y -= distance;
+            self.motion.fell();
+        }
+        // a piece placed with the mouse is put straight into place, so how it got there is unknown
+        let spun = (!self.settings.mouse_mode).then(|| self.motion.spun());
+        let (locked, lines) = lock_events(self.id, &self.matrix, active, shape_table, spun, false);
+        events.locks.send(locked);
+        events.lines.send_batch(lines);
+        let holes = self.matrix.holes();
+        let cleared = lock_piece(&mut self.matrix, active, shape_table);
+        if !cleared.is_empty() {
+            let (rows, contents) = cleared.into_iter().unzip();
+            events.clears.send(LineClearEvent {
+                board: self.id,
+                rows,
+                contents,
+            });
+
+            // the garbage goes in before the next piece spawns, so that it never moves a piece
+            // which is already in play
+            if self.settings.adaptive_cheese
+                && self.matrix.holes() <= holes
+                && !garbage::shove(
+                    &mut self.matrix,
+                    &mut self.garbage_rng.0,
+                    &self.settings.garbage_pattern,
+                    self.settings.messiness,
+                    self.bounds.legal_bounds.y as usize,
+                )
+            {
+                top_out.overflow(self.id);
+                return;
+            }
+        }
+        let new_piece = default_mino(self.queue.peek(), shape_table);
+        if self.spawn_or_top_out(new_piece, shape_table, top_out) {
             self.queue.take();
             self.hold.activate();
         }
     }
 
+    /// Spawns the given piece, returning whether it spawned. If there is no room for it, the game
+    /// ends, unless the board is in continuous play, where the board is wiped to make room, or has
+    /// lifesavers, where the top rows of the stack are cleared to make room. The matrix is changed
+    /// on the board itself either way, so that the record picks the change up.
+    fn spawn_or_top_out(
+        &mut self,
+        piece: Mino,
+        shape_table: &ShapeTable,
+        top_out: &mut TopOut,
+    ) -> bool {
+        if self.spawn_piece(piece, shape_table) {
+            return true;
+        }
+
+        if self.settings.is_continuous() {
+            self.matrix.clear();
+            if self.settings.wipe_hold {
+                *self.hold = Hold::Empty;
+            }
+            top_out.wipe(self.id);
+            if self.spawn_piece(piece, shape_table) {
+                return true;
+            }
+        } else if self.settings.lifesaver_rows > 0 {
+            let rows = self.matrix.clear_top_rows(self.settings.lifesaver_rows);
+            top_out.lifesaver(self.id, rows);
+            if self.spawn_piece(piece, shape_table) {
+                return true;
+            }
+        }
+        top_out.top_out(self.id, piece);
+        false
+    }
+
+    /// Swaps the active piece into hold and spawns the piece it is replaced with, ending the game if
+    /// that piece cannot spawn. Does nothing if hold is not allowed right now.
+    fn hold(&mut self, shape_table: &ShapeTable, top_out: &mut TopOut, events: &mut BoardEvents) {
+        if let Some(replace) = self.switch_hold_active() {
+            if let Hold::Inactive(kind) = *self.hold {
+                events.holds.send(HoldUsed {
+                    board: self.id,
+                    kind,
+                    replayed: false,
+                });
+            }
+            let replace = default_mino(replace, shape_table);
+            self.spawn_or_top_out(replace, shape_table, top_out);
+        }
+    }
+
     /// Switches the held piece and the active piece, if it is allowed. By this point, the active
     /// piece must exist.
     fn switch_hold_active(&mut self) -> Option<MinoKind> {
@@ -167,6 +339,16 @@ impl<'world> BoardQueryItem<'world> {
         }
     }
 
+    /// Resets the lock delay as allowed by the board's [`LockReset`](super::LockReset) behaviour, given whether the
+    /// active piece was just shifted or rotated. Reaching a new lowest row always resets the delay.
+    fn reset_lock_delay(&mut self, moved: bool) {
+        let y = self.active().position.y;
+        let (reset, limit) = (self.settings.lock_reset, self.settings.move_reset_limit);
+        if self.motion.lock_reset(y, moved, reset, limit) {
+            self.drop_clock.lock = 0.0;
+        }
+    }
+
     /// Reset the board to its original state (matrix, hold, queue)
     pub fn clear_board(&mut self) {
         *(self.hold) = Hold::Empty;
@@ -174,11 +356,19 @@ impl<'world> BoardQueryItem<'world> {
         *self.queue = default(); // TODO empty the queue instead of filling it with arbitrary data
     }
 
+    /// Replaces the bottom rows of the matrix with the given rows, listed from the bottom up.
+    pub fn fill_from_bottom(&mut self, rows: Vec<Vec<MinoKind>>) {
+        for (row, data) in self.matrix.rows_mut().zip(rows) {
+            row.copy_from_slice(&data);
+        }
+    }
+
     /// Attempts to spawn the given piece on the board, returning whether spawning was successful.
     pub fn spawn_piece(&mut self, piece: Mino, shape_table: &ShapeTable) -> bool {
         has_free_space(&self.matrix, piece, shape_table).tap(|&has_free_space| {
             if has_free_space {
                 *self.drop_clock = default();
+                *self.motion = PieceMotion::spawned(piece);
                 self.active.0 = Some(piece);
             }
         })
@@ -186,30 +376,116 @@ impl<'world> BoardQueryItem<'world> {
 }
 
 // TODO this should be determined at runtime
-pub fn default_mino(kind: MinoKind) -> Mino {
+/// The piece of the given kind as it spawns, centered over the matrix (leaning left when it cannot be
+/// centered exactly) just above the legal area.
+pub fn default_mino(kind: MinoKind, shape_table: &ShapeTable) -> Mino {
+    let bounds = shape_table.bounds(|p| p.kind == kind && p.rotation == RotationState::Up);
+    let x = (MATRIX_DEFAULT_LEGAL_BOUNDS.x - bounds.size().x) / 2 - bounds.min.x;
     Mino {
         kind,
-        position: ivec2(4, 22),
+        position: ivec2(x, 22),
         rotation: RotationState::Up,
     }
 }
 
+/// Keeps the presses of this frame which could not be applied to the board, so that they can be
+/// applied to the next piece instead.
+fn buffer_presses(
+    buffer: &mut BufferedInputs,
+    controller: &Controller,
+    now: f32,
+    include_hard_drop: bool,
+) {
+    let presses = [
+        controller.rotation.map(BufferedInput::Rotate),
+        controller.hold.then_some(BufferedInput::Hold),
+        (include_hard_drop && controller.hard_drop).then_some(BufferedInput::HardDrop),
+    ];
+    buffer.extend(presses.into_iter().flatten().map(|input| (now, input)));
+}
+
+/// Applies the inputs buffered before the active piece existed, in the order they were pressed,
+/// dropping those older than the board's input buffer. Returns false if the active piece was
+/// hard dropped, in which case the remaining inputs are kept for the next piece.
+fn apply_buffered(
+    board: &mut BoardQueryItem,
+    buffer: &mut BufferedInputs,
+    now: f32,
+    tables: (&ShapeTable, &KickTable),
+    top_out: &mut TopOut,
+    events: &mut BoardEvents,
+) -> bool {
+    let (shape_table, kick_table) = tables;
+    let max_age = board.settings.input_buffer as f32 / 1000.0;
+    buffer.retain(|&(pressed, _)| now - pressed <= max_age);
+
+    while let Some((pressed, input)) = buffer.pop_front() {
+        if board.active.0.is_none() {
+            // kept with the time it was pressed, so that it still runs out on time
+            buffer.push_front((pressed, input));
+            break;
+        }
+        match input {
+            BufferedInput::Rotate(command) => {
+                board.rotate(Some(command), kick_table, shape_table);
+            }
+            BufferedInput::Hold => board.hold(shape_table, top_out, events),
+            BufferedInput::HardDrop => {
+                board.hard_drop(shape_table, top_out, events);
+                return false;
+            }
+        }
+    }
+    true
+}
+
 /// Update the state of the memory-representation of the board using player input
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn update_board(
     mut boards: Query<BoardQuery>,
     controller: Res<Controller>,
+    mut buffer: ResMut<BufferedInputs>,
     shape_table: QueryShapeTable,
     kick_table: QueryKickTable,
     time: Res<Time>,
-    mut state: ResMut<NextState<MainState>>,
+    mut top_out: TopOut,
+    mut events: BoardEvents,
 ) {
+    let now = time.elapsed_seconds();
     for mut board in boards.iter_mut() {
         if board.active.deref().0.is_none() {
+            buffer_presses(&mut buffer, &controller, now, true);
+            continue;
+        }
+
+        let tables = (&*shape_table, &*kick_table);
+        if !apply_buffered(
+            &mut board,
+            &mut buffer,
+            now,
+            tables,
+            &mut top_out,
+            &mut events,
+        ) || board.active.0.is_none()
+        {
             continue;
         }
 
+        // a firm drop takes the piece to the floor without locking it, leaving it free to move
+        if controller.firm_drop {
+            let distance = board.drop_height(&shape_table, board.active());
+            if distance > 0 {
+                board.active_mut().position.y -= distance;
+                board.motion.fell();
+            }
+            board.reset_lock_delay(false);
+        }
+
         if controller.hard_drop {
-            board.hard_drop(&shape_table, &mut state);
+            board.hard_drop(&shape_table, &mut top_out, &mut events);
+            // anything else pressed on this frame was meant for the piece that was dropped or the
+            // next one, so it is given to the next one
+            buffer_presses(&mut buffer, &controller, now, false);
             continue;
         }
 
@@ -219,41 +495,98 @@ pub(crate) fn update_board(
         // that the player has time to interact with the piece when it hits the bottom, for a
         // frame at the very least. Later, we may want to rethink this for zero lock delay, if
         // such a thing makes sense.
-        if farthest_legal_drop == 0 {
+        if board.settings.mouse_mode {
+            // a piece placed with the mouse waits for a click, so it neither falls nor locks
+        } else if farthest_legal_drop == 0 {
             board.drop_clock.lock += time.delta_seconds();
             if board.drop_clock.lock > board.settings.lock_delay {
-                board.hard_drop(&shape_table, &mut state);
+                board.hard_drop(&shape_table, &mut top_out, &mut events);
                 continue;
             }
         } else {
-            board.drop_clock.fall += if controller.soft_drop {
+            let gravity = if controller.soft_drop {
                 board.settings.soft_drop_power * board.settings.gravity_power
             } else {
                 board.settings.gravity_power
             };
+            board.drop_clock.fall += gravity * time.delta_seconds();
             let old_drop_clock = board.drop_clock.deref().fall;
             if old_drop_clock > 1.0 {
                 board.drop_clock.fall = old_drop_clock.fract();
                 let drop_distance =
                     std::cmp::min(old_drop_clock.trunc() as i32, farthest_legal_drop);
                 board.active_mut().position.y -= drop_distance;
+                board.motion.fell();
             }
         }
 
-        let rotation_success = board.rotate(&controller, &kick_table, &shape_table);
+        let rotation_success = board.rotate(controller.rotation, &kick_table, &shape_table);
         let shift_success = board.shift(&controller, &shape_table);
-
-        if rotation_success || shift_success {
-            // TODO also modify a lock reset counter
-            board.drop_clock.lock = 0.0;
+        let blocked = [
+            (controller.rotation.is_some() && !rotation_success).then_some(BlockedMove::Rotation),
+            (controller.fresh_shift && controller.shift != 0 && !shift_success)
+                .then_some(BlockedMove::Shift(controller.shift.signum())),
+        ];
+        for attempt in blocked.into_iter().flatten() {
+            events.blocked.send(BlockedMoveEvent {
+                board: board.id,
+                attempt,
+            });
         }
 
+        board.reset_lock_delay(rotation_success || shift_success);
+
         if controller.hold {
-            if let Some(replace) = board.switch_hold_active() {
-                if !board.spawn_piece(default_mino(replace), &shape_table) {
-                    state.0 = Some(MainState::PostGame);
-                }
-            }
+            board.hold(&shape_table, &mut top_out, &mut events);
+        }
+    }
+}
+
+/// Whether the goal of the given mode has been reached on the given matrix, with the given number of
+/// lines cleared. Freestyle has no goal.
+pub(crate) fn goal_reached(
+    matrix: &Matrix,
+    mode: GameMode,
+    target_height: usize,
+    lines: u32,
+) -> bool {
+    match mode {
+        GameMode::Freestyle => false,
+        GameMode::Downstack => matrix
+            .rows()
+            .skip(target_height)
+            .flatten()
+            .all(|&kind| kind == MinoKind::E),
+        // garbage cells can only leave the matrix by being cleared, so once none are left, every
+        // row of cheese has been cleared
+        GameMode::CheeseRace => matrix.rows().flatten().all(|&kind| kind != MinoKind::G),
+        GameMode::Sprint => lines >= SPRINT_LINES,
+        GameMode::Openers => false,
+    }
+}
+
+/// Ends the game once the board's goal has been reached. Runs right after the board updates, on
+/// whichever schedule the board runs on, so that no piece is played past the goal.
+pub(crate) fn check_goal(
+    boards: Query<(Entity, Ref<Matrix>, &Settings)>,
+    mut stats: ResMut<Stats>,
+    mut state: ResMut<NextState<MainState>>,
+    mut ended: EventWriter<GameEnded>,
+) {
+    // lines are counted between the board updating and this check, so a sprint finishes on the
+    // clear which reaches its goal
+    let lines_changed = stats.is_changed();
+    for (board, matrix, settings) in boards.iter() {
+        if !(matrix.is_changed() || lines_changed) {
+            continue;
+        }
+        if goal_reached(&matrix, settings.mode, settings.target_height, stats.lines) {
+            stats.goal_reached = true;
+            state.0 = Some(MainState::PostGame);
+            ended.send(GameEnded {
+                board,
+                reason: GameEndReason::GoalReached,
+            });
         }
     }
 }
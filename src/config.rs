@@ -0,0 +1,97 @@
+//! Files kept between sessions carry the version of the format they were written in, so that files
+//! written by older versions of the game are brought up to date as they load instead of being lost.
+
+use std::cmp::Ordering;
+
+use bevy::prelude::*;
+use bevy::utils::thiserror;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::toasts::Toasts;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Parse(#[from] ron::de::SpannedError),
+    #[error("{0}")]
+    Serialize(#[from] ron::Error),
+    #[error("There is no way to read format version {0}")]
+    UnknownVersion(u32),
+}
+
+/// A file as it is written to disk, along with the version of its format
+#[derive(Serialize, Deserialize)]
+struct VersionedFile<T> {
+    version: u32,
+    data: T,
+}
+
+/// The version of a file, read before the rest so that the data can be read in the right format.
+/// Files from before versions were written have none, and count as version 0.
+#[derive(Deserialize)]
+struct Header {
+    #[serde(default)]
+    version: u32,
+}
+
+/// Data which is saved between sessions. Renamed fields are best kept readable with
+/// `#[serde(alias)]` and new fields with `#[serde(default)]`, so that the version only needs to
+/// change when the data is reorganized.
+pub trait Versioned: Serialize + DeserializeOwned {
+    /// The version of the format which is written
+    const VERSION: u32;
+
+    /// Reads a file written in an older version of the format. Each version is migrated by reading
+    /// it in its own format and converting it into the next, up to the current version. Version 0
+    /// is a file from before versions were written, which holds the data alone.
+    fn migrate(version: u32, text: &str) -> Result<Self, ConfigError>;
+}
+
+/// Reads the data out of a versioned file, in the given format
+pub fn read_data<T: DeserializeOwned>(text: &str) -> Result<T, ConfigError> {
+    Ok(ron::from_str::<VersionedFile<T>>(text)?.data)
+}
+
+/// Loads the file at the given path, migrating it if it was written in an older format. A file from
+/// a newer version of the game is read as far as the current format allows, along with a warning
+/// that some of it may have been left out.
+pub fn load<T: Versioned>(path: &str) -> Result<(T, Option<String>), ConfigError> {
+    let text = std::fs::read_to_string(path)?;
+    let version = ron::from_str::<Header>(&text)?.version;
+    match version.cmp(&T::VERSION) {
+        Ordering::Equal => Ok((read_data(&text)?, None)),
+        Ordering::Less => Ok((T::migrate(version, &text)?, None)),
+        Ordering::Greater => Ok((
+            read_data(&text)?,
+            Some(format!(
+                "{path} was saved by a newer version of the game, so some of it may not have loaded"
+            )),
+        )),
+    }
+}
+
+/// Writes the given data to the given path in the current version of its format
+pub fn save<T: Versioned>(path: &str, data: &T) -> Result<(), ConfigError> {
+    let file = VersionedFile {
+        version: T::VERSION,
+        data,
+    };
+    std::fs::write(path, ron::ser::to_string_pretty(&file, default())?)?;
+    Ok(())
+}
+
+/// Problems found while loading files as the game starts, which are shown once the game is running
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct ConfigWarnings(pub Vec<String>);
+
+pub(crate) fn report_config_warnings(
+    mut warnings: ResMut<ConfigWarnings>,
+    mut toasts: ResMut<Toasts>,
+) {
+    for warning in warnings.drain(..) {
+        toasts.warn(warning);
+    }
+}
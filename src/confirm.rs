@@ -0,0 +1,132 @@
+//! Confirmation by holding, for actions which cannot be taken back. A button or key must be held
+//! down for a moment before the action goes through, with a ring filling up to show how long is
+//! left, so that a stray click or key press does nothing.
+
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::state::MainState;
+
+/// Seconds that a button or key must be held down to confirm its action
+pub const HOLD_TO_CONFIRM: f32 = 0.5;
+
+const RING_COLOR: egui::Color32 = egui::Color32::from_rgb(240, 100, 100);
+
+/// How long a button or key has been held down toward confirming its action
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ConfirmHold {
+    held: f32,
+    /// Set once the hold confirms, so that it confirms only once until let go
+    done: bool,
+}
+
+impl ConfirmHold {
+    /// Moves the hold along by the given number of seconds while held, or starts it over once let
+    /// go. Returns whether the hold has just been held long enough.
+    pub fn update(&mut self, held: bool, dt: f32) -> bool {
+        if !held {
+            *self = default();
+            return false;
+        }
+        self.held += dt;
+        let confirmed = !self.done && self.held >= HOLD_TO_CONFIRM;
+        self.done |= confirmed;
+        confirmed
+    }
+
+    /// How far the hold is toward confirming, from 0 to 1. A hold which has confirmed stays empty
+    /// until let go.
+    pub fn progress(&self) -> f32 {
+        if self.done {
+            0.0
+        } else {
+            (self.held / HOLD_TO_CONFIRM).min(1.0)
+        }
+    }
+}
+
+/// Draws a ring around the given point, filled clockwise from the top as far as the progress
+fn paint_ring(painter: &egui::Painter, center: egui::Pos2, radius: f32, progress: f32) {
+    painter.circle_stroke(center, radius, (1.0, egui::Color32::from_gray(90)));
+    if progress <= 0.0 {
+        return;
+    }
+    let segments = (progress * 32.0).ceil() as usize;
+    let points = (0..=segments)
+        .map(|i| {
+            let angle = progress * TAU * i as f32 / segments as f32 - TAU / 4.0;
+            center + radius * egui::vec2(angle.cos(), angle.sin())
+        })
+        .collect();
+    painter.add(egui::Shape::line(points, (2.5, RING_COLOR)));
+}
+
+/// A button which must be held down to confirm its action, either with the pointer or, while it
+/// has keyboard focus, with space or enter. Returns whether the hold has just confirmed.
+pub fn hold_button(ui: &mut egui::Ui, text: impl Into<egui::WidgetText>) -> bool {
+    ui.horizontal(|ui| {
+        let response = ui.button(text).on_hover_text("Hold to confirm");
+        let id = response.id.with("confirm_hold");
+        let held = response.is_pointer_button_down_on()
+            || response.has_focus()
+                && ui.input(|i| i.key_down(egui::Key::Space) || i.key_down(egui::Key::Enter));
+        let dt = ui.input(|i| i.stable_dt);
+
+        let mut hold = ui.data_mut(|d| *d.get_temp_mut_or_default::<ConfirmHold>(id));
+        let confirmed = hold.update(held, dt);
+        ui.data_mut(|d| d.insert_temp(id, hold));
+
+        let size = egui::Vec2::splat(response.rect.height());
+        let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+        if held {
+            paint_ring(ui.painter(), rect.center(), size.x * 0.35, hold.progress());
+            ui.ctx().request_repaint();
+        }
+        confirmed
+    })
+    .inner
+}
+
+/// The hold on the key which leaves the replay for a new game, which is drawn in the middle of the
+/// window while it is held
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct NewGameHold(ConfirmHold);
+
+fn reset_new_game_hold(mut hold: ResMut<NewGameHold>) {
+    **hold = default();
+}
+
+fn new_game_hold_indicator(mut contexts: EguiContexts, hold: Res<NewGameHold>) {
+    let progress = hold.progress();
+    if progress <= 0.0 {
+        return;
+    }
+    egui::Area::new("new_game_hold")
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    let (rect, _) =
+                        ui.allocate_exact_size(egui::Vec2::splat(24.0), egui::Sense::hover());
+                    paint_ring(ui.painter(), rect.center(), 9.0, progress);
+                    ui.label("Keep holding to leave the replay for a new game");
+                });
+            });
+        });
+}
+
+pub struct ConfirmPlugin;
+
+impl Plugin for ConfirmPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NewGameHold>()
+            .add_systems(
+                Update,
+                new_game_hold_indicator.run_if(in_state(MainState::PostGame)),
+            )
+            .add_systems(OnExit(MainState::PostGame), reset_new_game_hold);
+    }
+}
@@ -1,6 +1,19 @@
-use crate::board::Settings;
+use crate::board::{fixed_timestep, Settings};
+use crate::config::ConfigWarnings;
+use crate::pause::not_paused;
+use crate::schedule::FrameSet;
 use crate::screens::GlobalSettings;
+use crate::state::MainState;
 use bevy::prelude::*;
+use std::collections::VecDeque;
+
+use self::keybinds::{
+    capture_rebinding, learn_layout, not_rebinding, Action, BoundInput, KeyLayout, Rebinding,
+};
+use self::profiles::{nudge_handling, save_profiles, switch_profile, Profiles};
+
+pub mod keybinds;
+pub mod profiles;
 
 #[rustfmt::skip]
 #[derive(Copy, Clone)]
@@ -10,13 +23,88 @@ pub enum RotateCommand {
     R180,
 }
 
+/// A press which is kept for a short while when there is no piece for it to act on, so that it can
+/// act on the next piece instead
+#[derive(Clone, Copy)]
+pub enum BufferedInput {
+    Rotate(RotateCommand),
+    Hold,
+    HardDrop,
+}
+
+/// Presses waiting for the next piece, in the order they were pressed, along with the time (in
+/// seconds) that they were pressed
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct BufferedInputs(VecDeque<(f32, BufferedInput)>);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+}
+
+/// Decides which way the piece shifts while both shift keys are held (simultaneous opposite
+/// directions, or SOCD).
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    strum::EnumIter,
+    strum::Display,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum SocdPolicy {
+    /// The key pressed last wins, preferring left if both were pressed at once
+    #[default]
+    #[strum(to_string = "Last Input")]
+    LastInput,
+    /// The key pressed first wins, preferring left if both were pressed at once
+    #[strum(to_string = "First Input")]
+    FirstInput,
+    /// Neither key shifts until one is released
+    Neutral,
+    #[strum(to_string = "Always Left")]
+    AbsoluteLeft,
+    #[strum(to_string = "Always Right")]
+    AbsoluteRight,
+}
+
+impl SocdPolicy {
+    /// The direction to shift in, given the time at which each shift key was pressed if it is held
+    pub fn resolve(self, left: Option<f32>, right: Option<f32>) -> Option<Direction> {
+        let (left, right) = match (left, right) {
+            (Some(left), Some(right)) => (left, right),
+            (Some(_), None) => return Some(Direction::Left),
+            (None, Some(_)) => return Some(Direction::Right),
+            (None, None) => return None,
+        };
+        match self {
+            SocdPolicy::LastInput if left < right => Some(Direction::Right),
+            SocdPolicy::LastInput => Some(Direction::Left),
+            SocdPolicy::FirstInput if right < left => Some(Direction::Right),
+            SocdPolicy::FirstInput => Some(Direction::Left),
+            SocdPolicy::Neutral => None,
+            SocdPolicy::AbsoluteLeft => Some(Direction::Left),
+            SocdPolicy::AbsoluteRight => Some(Direction::Right),
+        }
+    }
+}
+
 #[derive(Resource, Default)]
 pub struct Controller {
     pub shift: i32,
+    /// Whether a shift key was pressed this frame, rather than only repeating
+    pub fresh_shift: bool,
     repeater_left: Repeatable,
     repeater_right: Repeatable,
 
     pub hard_drop: bool,
+    /// Signals that the active piece should move to the floor without locking
+    pub firm_drop: bool,
     pub soft_drop: bool,
 
     /// Signals that the active piece should rotate the piece to the left. The meaning of "rotate"
@@ -33,6 +121,7 @@ impl Controller {
         [
             self.shift != 0,
             self.hard_drop,
+            self.firm_drop,
             self.soft_drop,
             self.rotation.is_some(),
             self.hold,
@@ -40,6 +129,15 @@ impl Controller {
         .into_iter()
         .any(std::convert::identity)
     }
+
+    /// How far the shift in the given direction is through its initial delay, from 0 to 1. Once
+    /// the shift is repeating, this is always 1. If the shift is not held, returns `None`.
+    pub fn das_progress(&self, direction: Direction) -> Option<f32> {
+        match direction {
+            Direction::Left => self.repeater_left.progress(),
+            Direction::Right => self.repeater_right.progress(),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Default)]
@@ -47,6 +145,12 @@ struct Repeatable {
     /// Used to determine which repeater activated first.
     activated_at: f32,
     repeat_at: Option<u32>,
+    /// The initial delay in effect when the key was pressed
+    charge_time: u32,
+    /// Milliseconds that the key has been held for toward its initial delay
+    charged: u32,
+    /// Whether the initial delay has passed and the key is now repeating
+    repeating: bool,
 }
 
 impl Repeatable {
@@ -58,6 +162,20 @@ impl Repeatable {
         }
     }
 
+    /// When the key was pressed, if it is held
+    fn held_since(&self) -> Option<f32> {
+        self.repeat_at.map(|_| self.activated_at)
+    }
+
+    fn progress(&self) -> Option<f32> {
+        self.repeat_at?;
+        if self.repeating || self.charge_time == 0 {
+            Some(1.0)
+        } else {
+            Some((self.charged as f32 / self.charge_time as f32).min(1.0))
+        }
+    }
+
     /// Each time this is called, returns the number of activations that should be registered.
     fn update(&mut self, time: &Res<Time>, settings: &Settings, activation: bool) -> u32 {
         if activation {
@@ -66,19 +184,25 @@ impl Repeatable {
                 self.repeat_at = Some(delta.abs_diff(time_to_repeat) % settings.repeat_delay);
                 if time_to_repeat < delta {
                     tracing::debug!("registered a repeat activation");
+                    self.repeating = true;
                     let activations = (delta - time_to_repeat) / settings.repeat_delay + 1;
                     return activations;
                 }
+                self.charged += delta;
             } else {
                 // key has been pressed for the first time
                 tracing::debug!("registered a single activation");
-                self.repeat_at = Some(self.initial_delay(settings));
+                self.charge_time = self.initial_delay(settings);
+                self.charged = 0;
+                self.repeat_at = Some(self.charge_time);
+                self.repeating = false;
                 self.activated_at = time.elapsed_seconds_wrapped();
                 return 1;
             }
         } else {
             // key was released, deactivate repeats
             self.repeat_at = None;
+            self.repeating = false;
         }
 
         0
@@ -87,36 +211,43 @@ impl Repeatable {
 
 /// Turns raw kb input into controller input which directly maps to actions on the board
 pub fn process_input(
-    keys: Res<ButtonInput<KeyCode>>,
+    keys: BoundInput,
     time: Res<Time>,
     settings: Res<GlobalSettings>,
+    profiles: Res<Profiles>,
     mut cached_settings: Local<Settings>,
     mut controller: ResMut<Controller>,
 ) {
     tracing::debug_span!(module_path!());
 
-    if keys.just_pressed(KeyCode::Space) {
+    if keys.just_pressed(Action::HardDrop) {
         controller.hard_drop = true;
     }
-    if keys.pressed(KeyCode::KeyS) {
+    if keys.just_pressed(Action::FirmDrop) {
+        controller.firm_drop = true;
+    }
+    if keys.pressed(Action::SoftDrop) {
         controller.soft_drop = true;
     }
-    if keys.just_pressed(KeyCode::Comma) {
+    if keys.just_pressed(Action::RotateLeft) {
         controller.rotation = Some(RotateCommand::Left);
     }
-    if keys.just_pressed(KeyCode::Slash) {
+    if keys.just_pressed(Action::RotateRight) {
         controller.rotation = Some(RotateCommand::Right);
     }
-    if keys.just_pressed(KeyCode::Period) {
+    if keys.just_pressed(Action::Rotate180) {
         controller.rotation = Some(RotateCommand::R180);
     }
-    if keys.just_pressed(KeyCode::ShiftLeft) {
+    if keys.just_pressed(Action::Hold) {
         controller.hold = true;
     }
+    if keys.just_pressed(Action::ShiftLeft) || keys.just_pressed(Action::ShiftRight) {
+        controller.fresh_shift = true;
+    }
 
     if_chain::if_chain! {
-        if settings.is_changed();
-        if let Ok(global) = Settings::try_from(&*settings);
+        if settings.is_changed() || profiles.is_changed();
+        if let Ok(global) = Settings::try_from((&*settings, &profiles.active().handling));
         then {
             *cached_settings = global;
         }
@@ -126,24 +257,22 @@ pub fn process_input(
     let shift_left =
         -(controller
             .repeater_left
-            .update(&time, &cached_settings, keys.pressed(KeyCode::KeyA)) as i32);
+            .update(&time, &cached_settings, keys.pressed(Action::ShiftLeft)) as i32);
     let shift_right =
         controller
             .repeater_right
-            .update(&time, &cached_settings, keys.pressed(KeyCode::KeyD)) as i32;
-
-    // if both left and right shift is active, take the one activated latest, or, if they were activated around the same
-    // time, prefer left.
-    if controller.repeater_left.repeat_at.is_some() && controller.repeater_right.repeat_at.is_some()
-    {
-        if controller.repeater_left.activated_at < controller.repeater_right.activated_at {
-            controller.shift = shift_right;
-        } else {
-            controller.shift = shift_left;
-        }
-    } else {
-        controller.shift = shift_left + shift_right;
-    }
+            .update(&time, &cached_settings, keys.pressed(Action::ShiftRight)) as i32;
+
+    // if both left and right shift are held, the policy decides which of them (if either) shifts
+    let direction = cached_settings.socd.resolve(
+        controller.repeater_left.held_since(),
+        controller.repeater_right.held_since(),
+    );
+    controller.shift += match direction {
+        Some(Direction::Left) => shift_left,
+        Some(Direction::Right) => shift_right,
+        None => 0,
+    };
 }
 
 pub fn reset_controller(mut controller: ResMut<Controller>) {
@@ -165,9 +294,43 @@ pub struct ControllerPlugin;
 
 impl Plugin for ControllerPlugin {
     fn build(&self, app: &mut App) {
+        let (profiles, warning) = Profiles::load();
+        app.world
+            .get_resource_or_insert_with(ConfigWarnings::default)
+            .extend(warning);
+
         app.init_resource::<Controller>()
+            .init_resource::<BufferedInputs>()
             .init_resource::<ControllerFrozen>()
-            .add_systems(Update, process_input.run_if(not_frozen)) // could be an issue if bevy decides to change the order of run condition execution
-            .add_systems(PostUpdate, reset_controller.run_if(not_frozen));
+            .init_resource::<KeyLayout>()
+            .init_resource::<Rebinding>()
+            .insert_resource(profiles)
+            .add_systems(
+                PreUpdate,
+                (
+                    learn_layout,
+                    capture_rebinding,
+                    // read before the fixed timestep runs, so that its ticks see this frame's presses
+                    process_input.run_if(not_frozen.and_then(not_rebinding).and_then(not_paused)),
+                )
+                    .chain()
+                    .in_set(FrameSet::Input),
+            )
+            .add_systems(Update, save_profiles)
+            .add_systems(
+                Update,
+                (switch_profile, nudge_handling)
+                    .run_if(in_state(MainState::Ready).and_then(not_rebinding)),
+            )
+            .add_systems(
+                PostUpdate,
+                reset_controller.run_if(not_frozen.and_then(not(fixed_timestep))),
+            )
+            // with a fixed timestep, the board may tick any number of times in a frame, so the
+            // input is kept until a tick has consumed it
+            .add_systems(
+                FixedUpdate,
+                reset_controller.run_if(not_frozen.and_then(fixed_timestep)),
+            );
     }
 }
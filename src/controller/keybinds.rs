@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use bevy::ecs::system::SystemParam;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::profiles::Profiles;
+
+/// Where keybinds were saved before they became part of a [`Profile`](super::profiles::Profile)
+pub const KEYBINDS_PATH: &str = "keybinds.ron";
+
+/// Everything the player can do to the board (or the replay of it) through the keyboard.
+#[rustfmt::skip]
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Hash,
+    Serialize, Deserialize, strum::EnumIter, strum::Display
+)]
+pub enum Action {
+    #[strum(to_string = "Shift Left")] ShiftLeft,
+    #[strum(to_string = "Shift Right")] ShiftRight,
+    #[strum(to_string = "Soft Drop")] SoftDrop,
+    #[strum(to_string = "Hard Drop")] HardDrop,
+    /// Moves the piece to the floor without locking it
+    #[strum(to_string = "Firm Drop")] FirmDrop,
+    #[strum(to_string = "Rotate Left")] RotateLeft,
+    #[strum(to_string = "Rotate Right")] RotateRight,
+    #[strum(to_string = "Rotate 180")] Rotate180,
+    Hold,
+    /// Kept apart from the board actions, since those branch the replay into a new game
+    #[strum(to_string = "Pause Replay")] PauseReplay,
+    /// Seeks the replay back to where the last piece before the current frame spawned
+    #[strum(to_string = "Previous Spawn")] PreviousSpawn,
+    #[strum(to_string = "Next Spawn")] NextSpawn,
+}
+
+impl Action {
+    /// Where the action does something
+    pub fn context(self) -> BindingContext {
+        match self {
+            Action::PauseReplay | Action::PreviousSpawn | Action::NextSpawn => {
+                BindingContext::Replay
+            }
+            _ => BindingContext::Playing,
+        }
+    }
+}
+
+/// The part of the game in which a key does something, for listing the keys by where they apply
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::EnumIter, strum::Display)]
+pub enum BindingContext {
+    Anywhere,
+    /// Waiting for the game to start
+    Ready,
+    Playing,
+    Replay,
+    #[strum(to_string = "Kick Editor")]
+    Editor,
+}
+
+/// A key which is fixed to something rather than assigned through the keybinds. Each module
+/// keeps a table of its hotkeys next to the keys themselves, so that they can be listed alongside
+/// the keybinds.
+pub struct Hotkey {
+    /// The keys which do the same thing, such as the keys selecting each save slot
+    pub keys: &'static [KeyCode],
+    pub name: &'static str,
+    pub context: BindingContext,
+}
+
+/// A key assigned to an action. Physical bindings stay on the same key no matter the keyboard
+/// layout, while logical bindings follow the symbol that the layout puts on a key.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Binding {
+    Physical(KeyCode),
+    Logical(Key),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Keybinds {
+    /// Whether newly assigned bindings are physical (rather than logical)
+    pub physical: bool,
+    pub bindings: HashMap<Action, Binding>,
+}
+
+impl Default for Keybinds {
+    fn default() -> Self {
+        use Action::*;
+        let bindings = [
+            (ShiftLeft, KeyCode::KeyA),
+            (ShiftRight, KeyCode::KeyD),
+            (SoftDrop, KeyCode::KeyS),
+            (HardDrop, KeyCode::Space),
+            (FirmDrop, KeyCode::KeyW),
+            (RotateLeft, KeyCode::Comma),
+            (RotateRight, KeyCode::Slash),
+            (Rotate180, KeyCode::Period),
+            (Hold, KeyCode::ShiftLeft),
+            (PauseReplay, KeyCode::KeyP),
+            (PreviousSpawn, KeyCode::BracketLeft),
+            (NextSpawn, KeyCode::BracketRight),
+        ]
+        .into_iter()
+        .map(|(action, key)| (action, Binding::Physical(key)))
+        .collect();
+
+        Self {
+            physical: true,
+            bindings,
+        }
+    }
+}
+
+fn key_name(key: &Key) -> String {
+    match key {
+        Key::Character(c) => c.to_string(),
+        other => format!("{other:?}"),
+    }
+}
+
+impl Keybinds {
+    /// Gives the default binding to each action which has none, as happens for actions added after
+    /// the keybinds were saved, unless another action already uses that key.
+    pub fn fill_missing(&mut self) {
+        for (action, binding) in Self::default().bindings {
+            if !self.bindings.values().any(|b| *b == binding) {
+                self.bindings.entry(action).or_insert(binding);
+            }
+        }
+    }
+
+    /// Names the key bound to the given action, both by the symbol it produces and by its location
+    /// on the keyboard. Whichever of the two is not stored in the binding is taken from the layout
+    /// as it has been observed so far.
+    pub fn describe(&self, action: Action, layout: &KeyLayout) -> String {
+        let (logical, physical) = match self.bindings.get(&action) {
+            None => return "Unbound".into(),
+            Some(Binding::Physical(code)) => (layout.0.get(code).map(key_name), Some(*code)),
+            Some(Binding::Logical(key)) => (
+                Some(key_name(key)),
+                layout
+                    .0
+                    .iter()
+                    .find_map(|(code, k)| (k == key).then_some(*code)),
+            ),
+        };
+
+        format!(
+            "{} / {}",
+            logical.unwrap_or_else(|| "?".into()),
+            physical.map_or_else(|| "?".into(), |code| format!("{code:?}")),
+        )
+    }
+}
+
+/// Remembers which symbol each physical key produced the last time it was pressed.
+#[derive(Resource, Default)]
+pub struct KeyLayout(pub HashMap<KeyCode, Key>);
+
+/// The action waiting to be assigned the next key pressed, if any.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct Rebinding(pub Option<Action>);
+
+pub fn not_rebinding(rebinding: Res<Rebinding>) -> bool {
+    rebinding.is_none()
+}
+
+/// Keyboard state as seen through the player's keybinds.
+#[derive(SystemParam)]
+pub struct BoundInput<'w> {
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    layout: Res<'w, KeyLayout>,
+    profiles: Res<'w, Profiles>,
+}
+
+impl<'w> BoundInput<'w> {
+    fn check<'a, I>(&'a self, action: Action, physical: impl Fn(KeyCode) -> bool, held: I) -> bool
+    where
+        I: IntoIterator<Item = &'a KeyCode>,
+    {
+        match self.profiles.active().keybinds.bindings.get(&action) {
+            Some(Binding::Physical(code)) => physical(*code),
+            Some(Binding::Logical(key)) => held
+                .into_iter()
+                .any(|code| self.layout.0.get(code) == Some(key)),
+            None => false,
+        }
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.check(
+            action,
+            |code| self.keys.pressed(code),
+            self.keys.get_pressed(),
+        )
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.check(
+            action,
+            |code| self.keys.just_pressed(code),
+            self.keys.get_just_pressed(),
+        )
+    }
+}
+
+pub(crate) fn learn_layout(
+    mut layout: ResMut<KeyLayout>,
+    mut events: EventReader<KeyboardInput>,
+    keys: Res<ButtonInput<KeyCode>>,
+) {
+    // shift changes the symbol that most keys produce, so only unshifted symbols are remembered
+    let shifted = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    for event in events.read() {
+        let is_shift = matches!(event.key_code, KeyCode::ShiftLeft | KeyCode::ShiftRight);
+        if event.state == ButtonState::Pressed && (!shifted || is_shift) {
+            layout.0.insert(event.key_code, event.logical_key.clone());
+        }
+    }
+}
+
+/// Assigns the next pressed key to the action waiting in [`Rebinding`]. Escape cancels.
+pub(crate) fn capture_rebinding(
+    mut rebinding: ResMut<Rebinding>,
+    mut profiles: ResMut<Profiles>,
+    mut events: EventReader<KeyboardInput>,
+) {
+    let Some(action) = **rebinding else {
+        events.clear();
+        return;
+    };
+
+    if let Some(event) = events.read().find(|e| e.state == ButtonState::Pressed) {
+        if event.key_code != KeyCode::Escape {
+            let keybinds = &mut profiles.active_mut().keybinds;
+            let binding = if keybinds.physical {
+                Binding::Physical(event.key_code)
+            } else {
+                Binding::Logical(event.logical_key.clone())
+            };
+            keybinds.bindings.insert(action, binding);
+        }
+        **rebinding = None;
+    }
+}
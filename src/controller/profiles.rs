@@ -0,0 +1,303 @@
+//! Named sets of keybinds and handling, so that the player can switch between ways of playing
+//! without setting everything up again.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use smart_default::SmartDefault;
+
+use crate::config::{self, ConfigError, Versioned};
+use crate::toasts::{ToastLevel, Toasts};
+
+use super::keybinds::{Action, Binding, BindingContext, Hotkey, Keybinds, KEYBINDS_PATH};
+use super::SocdPolicy;
+
+pub const PROFILES_PATH: &str = "profiles.ron";
+/// Switches to the next profile while waiting for the game to start
+pub const PROFILE_SWITCH_KEY: KeyCode = KeyCode::F2;
+
+/// Held to step the handling of the active profile with the keys of [`HANDLING_NUDGES`]
+pub const NUDGE_MODIFIER: KeyCode = KeyCode::AltLeft;
+/// Seconds that the new value of a nudged setting is shown for
+const NUDGE_TOAST_DURATION: f32 = 1.0;
+
+pub(crate) const HOTKEYS: &[Hotkey] = &[
+    Hotkey {
+        keys: &[PROFILE_SWITCH_KEY],
+        name: "Next Profile",
+        context: BindingContext::Ready,
+    },
+    Hotkey {
+        keys: &[NUDGE_MODIFIER, KeyCode::ArrowLeft, KeyCode::ArrowRight],
+        name: "Nudge Initial Delay",
+        context: BindingContext::Ready,
+    },
+    Hotkey {
+        keys: &[NUDGE_MODIFIER, KeyCode::ArrowDown, KeyCode::ArrowUp],
+        name: "Nudge Repeat Delay",
+        context: BindingContext::Ready,
+    },
+    Hotkey {
+        keys: &[
+            NUDGE_MODIFIER,
+            KeyCode::ShiftLeft,
+            KeyCode::ArrowDown,
+            KeyCode::ArrowUp,
+        ],
+        name: "Nudge Soft Drop Power",
+        context: BindingContext::Ready,
+    },
+];
+
+/// A handling setting which can be stepped down and up from the keyboard while waiting for the
+/// game to start, by holding [`NUDGE_MODIFIER`] (and shift, if the nudge is shifted)
+pub struct HandlingNudge {
+    pub name: &'static str,
+    pub decrease: KeyCode,
+    pub increase: KeyCode,
+    pub shifted: bool,
+    pub step: f32,
+    pub min: f32,
+    pub max: f32,
+    field: fn(&mut Handling) -> &mut String,
+}
+
+pub const HANDLING_NUDGES: &[HandlingNudge] = &[
+    HandlingNudge {
+        name: "Initial Delay",
+        decrease: KeyCode::ArrowLeft,
+        increase: KeyCode::ArrowRight,
+        shifted: false,
+        step: 10.0,
+        min: 0.0,
+        max: 2000.0,
+        field: |h| &mut h.initial_delay,
+    },
+    HandlingNudge {
+        name: "Repeat Delay",
+        decrease: KeyCode::ArrowDown,
+        increase: KeyCode::ArrowUp,
+        shifted: false,
+        step: 5.0,
+        min: 1.0,
+        max: 500.0,
+        field: |h| &mut h.repeat_delay,
+    },
+    HandlingNudge {
+        name: "Soft Drop Power",
+        decrease: KeyCode::ArrowDown,
+        increase: KeyCode::ArrowUp,
+        shifted: true,
+        step: 1.0,
+        min: 1.0,
+        max: 100.0,
+        field: |h| &mut h.soft_drop_power,
+    },
+];
+
+impl HandlingNudge {
+    /// Steps the setting in the given handling by the given number of steps, returning its new
+    /// value. A setting which does not parse is stepped from its default.
+    fn apply(&self, handling: &mut Handling, steps: f32) -> f32 {
+        let field = (self.field)(handling);
+        let current = field
+            .parse()
+            .ok()
+            .or_else(|| (self.field)(&mut Handling::default()).parse().ok())
+            .unwrap_or(self.min);
+        let new = (current + steps * self.step).clamp(self.min, self.max);
+        *field = new.to_string();
+        new
+    }
+}
+
+/// The settings which decide how the controls feel, kept in text form like
+/// [`GlobalSettings`](crate::screens::GlobalSettings) so that they can be edited directly.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, SmartDefault)]
+#[serde(default)]
+pub struct Handling {
+    #[default = "10"]
+    pub soft_drop_power: String,
+    #[default = "1000"]
+    pub initial_delay: String,
+    #[default = "100"]
+    pub repeat_delay: String,
+    #[default = "0"]
+    pub input_buffer: String,
+    /// Which way the piece shifts while both shift keys are held
+    pub socd: SocdPolicy,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub keybinds: Keybinds,
+    pub handling: Handling,
+}
+
+impl Profile {
+    fn new(name: &str, keybinds: Keybinds) -> Self {
+        Self {
+            name: name.into(),
+            keybinds,
+            handling: default(),
+        }
+    }
+}
+
+/// Every profile the player has made. The active profile is the one whose keybinds are read by the
+/// controller and whose handling is applied to the board.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct Profiles {
+    pub profiles: Vec<Profile>,
+    pub active: usize,
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        use Action::*;
+        let arrow_keys = Keybinds {
+            physical: true,
+            bindings: [
+                (ShiftLeft, KeyCode::ArrowLeft),
+                (ShiftRight, KeyCode::ArrowRight),
+                (SoftDrop, KeyCode::ArrowDown),
+                (HardDrop, KeyCode::Space),
+                (FirmDrop, KeyCode::ArrowUp),
+                (RotateLeft, KeyCode::KeyZ),
+                (RotateRight, KeyCode::KeyX),
+                (Rotate180, KeyCode::KeyA),
+                (Hold, KeyCode::KeyC),
+                (PauseReplay, KeyCode::KeyP),
+                (PreviousSpawn, KeyCode::BracketLeft),
+                (NextSpawn, KeyCode::BracketRight),
+            ]
+            .into_iter()
+            .map(|(action, key)| (action, Binding::Physical(key)))
+            .collect(),
+        };
+
+        Self {
+            profiles: vec![
+                Profile::new("default", default()),
+                Profile::new("arrow keys", arrow_keys),
+            ],
+            active: 0,
+        }
+    }
+}
+
+impl Profiles {
+    /// Loads the profiles saved by a previous session, along with anything the player should be
+    /// warned about. Keybinds saved before profiles existed become the default profile.
+    pub fn load() -> (Self, Option<String>) {
+        let warning = match config::load::<Self>(PROFILES_PATH) {
+            Ok((mut profiles, warning)) if !profiles.profiles.is_empty() => {
+                for profile in &mut profiles.profiles {
+                    profile.keybinds.fill_missing();
+                }
+                let profiles = Self {
+                    active: profiles.active.min(profiles.profiles.len() - 1),
+                    ..profiles
+                };
+                return (profiles, warning);
+            }
+            Ok(_) => Some("Saved profiles are empty".to_string()),
+            Err(ConfigError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => Some(format!("Could not read saved profiles: {e}")),
+        };
+        if let Some(warning) = &warning {
+            tracing::warn!("{warning}");
+        }
+
+        let mut profiles = Self::default();
+        if let Some(keybinds) = std::fs::read_to_string(KEYBINDS_PATH)
+            .ok()
+            .and_then(|s| ron::from_str(&s).ok())
+        {
+            profiles.profiles[0].keybinds = keybinds;
+            profiles.profiles[0].keybinds.fill_missing();
+        }
+        (profiles, warning)
+    }
+
+    pub fn active(&self) -> &Profile {
+        &self.profiles[self.active]
+    }
+
+    pub fn active_mut(&mut self) -> &mut Profile {
+        &mut self.profiles[self.active]
+    }
+
+    /// Adds a copy of the active profile and makes it active.
+    pub fn duplicate_active(&mut self) {
+        let mut copy = self.active().clone();
+        copy.name = format!("{} (copy)", copy.name);
+        self.profiles.push(copy);
+        self.active = self.profiles.len() - 1;
+    }
+
+    /// Adds a profile with the default keybinds and handling and makes it active.
+    pub fn create(&mut self) {
+        self.profiles.push(Profile::new("new profile", default()));
+        self.active = self.profiles.len() - 1;
+    }
+
+    /// Deletes the active profile, unless it is the only one left.
+    pub fn delete_active(&mut self) {
+        if self.profiles.len() > 1 {
+            self.profiles.remove(self.active);
+            self.active = self.active.min(self.profiles.len() - 1);
+        }
+    }
+}
+
+impl Versioned for Profiles {
+    const VERSION: u32 = 1;
+
+    fn migrate(version: u32, text: &str) -> Result<Self, ConfigError> {
+        match version {
+            0 => Ok(ron::from_str(text)?),
+            _ => Err(ConfigError::UnknownVersion(version)),
+        }
+    }
+}
+
+pub(crate) fn switch_profile(mut profiles: ResMut<Profiles>, keys: Res<ButtonInput<KeyCode>>) {
+    if keys.just_pressed(PROFILE_SWITCH_KEY) {
+        profiles.active = (profiles.active + 1) % profiles.profiles.len();
+    }
+}
+
+/// Steps the handling of the active profile for each nudge whose keys were pressed, showing the
+/// new value
+pub(crate) fn nudge_handling(
+    mut profiles: ResMut<Profiles>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut toasts: ResMut<Toasts>,
+) {
+    if !keys.pressed(NUDGE_MODIFIER) {
+        return;
+    }
+    let shifted = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    for nudge in HANDLING_NUDGES.iter().filter(|n| n.shifted == shifted) {
+        let steps =
+            keys.just_pressed(nudge.increase) as i32 - keys.just_pressed(nudge.decrease) as i32;
+        if steps == 0 {
+            continue;
+        }
+        let new = nudge.apply(&mut profiles.active_mut().handling, steps as f32);
+        toasts.push(
+            format!("{}: {new}", nudge.name),
+            ToastLevel::Info,
+            NUDGE_TOAST_DURATION,
+        );
+    }
+}
+
+pub(crate) fn save_profiles(profiles: Res<Profiles>, mut toasts: ResMut<Toasts>) {
+    if profiles.is_changed() && !profiles.is_added() {
+        if let Err(e) = config::save(PROFILES_PATH, &*profiles) {
+            toasts.error(format!("Could not save profiles: {e}"));
+        }
+    }
+}
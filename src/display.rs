@@ -1,26 +1,61 @@
 use bevy::prelude::*;
+use bevy::render::view::VisibilitySystems;
 use bevy::sprite::Material2dPlugin;
 use bevy::transform::TransformSystem;
 
-use crate::state::MainState;
+use crate::controller::keybinds::not_rebinding;
+use crate::controller::{not_frozen, process_input};
+use crate::pause::not_paused;
+use crate::schedule::FrameSet;
+use crate::state::{assets_loaded, MainState};
 
+use self::action_text::{spawn_action_text, update_action_text};
 use self::active::spawn_active_sprite;
-use self::hold::spawn_hold_sprite;
+use self::bag::{spawn_bag_tracker, update_bag_tracker};
+use self::bag_time::{spawn_bag_time_text, update_bag_time_text};
+use self::blocked::{apply_shake, blocked_move_feedback, undo_shake};
+use self::das::{spawn_das_indicator, update_das_indicator};
+use self::efficiency::{spawn_efficiency_text, update_efficiency_text};
+use self::failed::{display_failed_spawn, spawn_failed_spawn_sprite};
+use self::flash::{spawn_lock_flash, update_lock_flash};
+use self::goal::{spawn_target_line, update_target_line};
+use self::hold::{click_hold, pulse_hold, spawn_hold_sprite};
+use self::hud::hide_hud;
 use self::matrix::spawn_matrix_sprite;
-use self::queue::spawn_queue_sprite;
+use self::queue::{relayout_queue, spawn_queue_sprite};
+use self::ruler::{spawn_ruler, update_ruler};
+use self::timers::{spawn_timer_overlay, update_timer_overlay};
 use self::{
     active::display_active,
     floor::{spawn_drop_shadow, update_drop_shadow, DropShadowMaterial},
     hold::display_held,
-    matrix::{center_board, redraw_board},
+    matrix::{apply_well_background, center_board, redraw_board},
     queue::display_queue,
 };
 
+mod action_text;
 mod active;
+mod bag;
+mod bag_time;
+mod blocked;
+mod das;
+mod efficiency;
+mod failed;
+mod flash;
 mod floor;
+mod goal;
 mod hold;
+mod hud;
 mod matrix;
 mod queue;
+mod ruler;
+mod timers;
+
+pub use self::action_text::{
+    ActionKind, ActionTextPosition, ActionTextSettings, PreviewActionText,
+};
+pub use self::hud::HideHud;
+pub use self::queue::{PreviewOrientation, QueueLayout};
 
 #[derive(SystemSet, Hash, Debug, PartialEq, Eq, Clone)]
 pub enum DisplayEntitySet {
@@ -31,11 +66,31 @@ pub enum DisplayEntitySet {
     Update,
 }
 
+/// Logs, once for the system calling it, that a board was skipped because one of its display
+/// children could not be found. A board spawned or despawned partway through a frame has no
+/// children for that frame, so this is not an error.
+pub(crate) fn warn_missing_child(warned: &mut bool, board: Entity, child: &str) {
+    if !std::mem::replace(warned, true) {
+        warn!("Board {board:?} has no {child}, so it was not drawn this frame");
+    }
+}
+
 pub struct DisplayPlugin;
 
 impl Plugin for DisplayPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.add_plugins(Material2dPlugin::<DropShadowMaterial>::default())
+            .add_event::<PreviewActionText>()
+            .init_resource::<HideHud>()
+            .configure_sets(
+                PostUpdate,
+                (
+                    DisplayEntitySet::Spawn,
+                    DisplayEntitySet::ApplyBuffers,
+                    DisplayEntitySet::Update,
+                )
+                    .chain(),
+            )
             .add_systems(
                 PostUpdate,
                 (
@@ -44,29 +99,97 @@ impl Plugin for DisplayPlugin {
                     spawn_active_sprite,
                     spawn_queue_sprite,
                     spawn_hold_sprite,
+                    spawn_target_line,
+                    spawn_das_indicator,
+                    spawn_bag_tracker,
+                    spawn_ruler,
+                    spawn_failed_spawn_sprite,
+                    spawn_efficiency_text,
+                    spawn_bag_time_text,
+                    spawn_timer_overlay,
                 )
                     .in_set(DisplayEntitySet::Spawn)
                     .before(DisplayEntitySet::ApplyBuffers)
-                    .run_if(not(in_state(MainState::Loading))),
+                    .run_if(assets_loaded),
             )
             .add_systems(
                 PostUpdate,
                 apply_deferred.in_set(DisplayEntitySet::ApplyBuffers),
             )
+            .add_systems(
+                PostUpdate,
+                // a new shake replaces the old one, so the old one is undone first
+                (undo_shake, blocked_move_feedback)
+                    .chain()
+                    .in_set(DisplayEntitySet::Spawn),
+            )
+            .add_systems(
+                PostUpdate,
+                apply_shake
+                    .after(display_active)
+                    .in_set(DisplayEntitySet::Update)
+                    .before(TransformSystem::TransformPropagate),
+            )
             .add_systems(
                 PostUpdate,
                 (
                     update_drop_shadow,
                     center_board,
                     redraw_board,
+                    apply_well_background,
                     display_active,
                     display_queue,
                     display_held,
+                    pulse_hold,
+                    update_target_line,
+                    update_das_indicator,
+                    update_bag_tracker,
+                    update_ruler,
+                    display_failed_spawn,
+                    update_efficiency_text,
+                    update_bag_time_text,
+                    update_timer_overlay,
+                    spawn_lock_flash,
+                    update_lock_flash,
                 )
                     .in_set(DisplayEntitySet::Update)
                     .after(DisplayEntitySet::ApplyBuffers)
                     .before(TransformSystem::TransformPropagate)
-                    .run_if(not(in_state(MainState::Loading))),
+                    .run_if(assets_loaded),
+            )
+            .add_systems(
+                PostUpdate,
+                hide_hud
+                    .after(DisplayEntitySet::Update)
+                    .before(VisibilitySystems::VisibilityPropagate),
+            )
+            .add_systems(
+                PostUpdate,
+                (spawn_action_text, update_action_text)
+                    .chain()
+                    .in_set(DisplayEntitySet::Update)
+                    .before(TransformSystem::TransformPropagate)
+                    .run_if(assets_loaded),
+            )
+            .add_systems(
+                PreUpdate,
+                click_hold
+                    .after(process_input)
+                    .in_set(FrameSet::Input)
+                    .run_if(
+                        assets_loaded
+                            .and_then(not_frozen)
+                            .and_then(not_rebinding)
+                            .and_then(not_paused),
+                    ),
+            )
+            .add_systems(
+                PostUpdate,
+                relayout_queue
+                    .after(DisplayEntitySet::ApplyBuffers)
+                    .before(DisplayEntitySet::Update)
+                    .before(TransformSystem::TransformPropagate)
+                    .run_if(in_state(MainState::Ready)),
             );
     }
 }
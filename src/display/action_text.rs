@@ -0,0 +1,231 @@
+use bevy::math::vec2;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use serde::{Deserialize, Serialize};
+use smart_default::SmartDefault;
+
+use crate::board::events::PieceLocked;
+use crate::board::{Bounds, Matrix, MinoKind, CELL_SIZE};
+use crate::screens::GlobalSettings;
+use crate::window::PresentationMode;
+
+/// Size of the text at a scale of one
+const ACTION_TEXT_FONT_SIZE: f32 = 28.0;
+/// Distance between the matrix and the text, when the text is placed beside or above it
+const ACTION_TEXT_GAP: f32 = 24.0;
+/// How far the text rises over its lifetime, at a scale of one
+const ACTION_TEXT_RISE: f32 = 16.0;
+
+/// Where the text is placed, relative to the matrix
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    strum::EnumIter,
+    strum::Display,
+    Serialize,
+    Deserialize,
+)]
+pub enum ActionTextPosition {
+    /// Over the middle of the matrix
+    #[default]
+    Center,
+    /// Beside the matrix, on the side of the hold box
+    Left,
+    /// Above the matrix
+    Top,
+}
+
+impl ActionTextPosition {
+    /// Where the text goes relative to the board, and which point of the text is put there. The
+    /// place is worked out from the bounds of the board, so that it follows the size of the matrix.
+    fn place(self, bounds: &Bounds) -> (Vec2, Anchor) {
+        let half = bounds.legal_bounds.as_vec2() / 2. * CELL_SIZE as f32;
+        match self {
+            Self::Center => (Vec2::ZERO, Anchor::Center),
+            Self::Left => (vec2(-half.x - ACTION_TEXT_GAP, 0.0), Anchor::CenterRight),
+            Self::Top => (vec2(0.0, half.y + ACTION_TEXT_GAP), Anchor::BottomCenter),
+        }
+    }
+}
+
+/// How the text announcing clears, spins and perfect clears is shown
+#[derive(Clone, Debug, PartialEq, SmartDefault, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActionTextSettings {
+    pub position: ActionTextPosition,
+    /// Size of the text, relative to its usual size
+    #[default = 1.0]
+    pub scale: f32,
+    /// Seconds that the text stays up
+    #[default = 1.0]
+    pub duration: f32,
+    /// Announce clears which are not spins
+    #[default = true]
+    pub clears: bool,
+    /// Announce spins, whether or not they clear
+    #[default = true]
+    pub spins: bool,
+    #[default = true]
+    pub perfect_clears: bool,
+}
+
+/// Something that a lock can be announced for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActionKind {
+    /// A clear of the given number of lines, which was not a spin
+    Clear(usize),
+    /// A spin of the given piece, clearing the given number of lines
+    Spin(MinoKind, usize),
+    PerfectClear,
+}
+
+impl ActionKind {
+    fn shown(self, settings: &ActionTextSettings) -> bool {
+        match self {
+            Self::Clear(_) => settings.clears,
+            Self::Spin(..) => settings.spins,
+            Self::PerfectClear => settings.perfect_clears,
+        }
+    }
+
+    fn text(self) -> String {
+        let lines = |lines: usize| match lines {
+            1 => "Single".to_string(),
+            2 => "Double".to_string(),
+            3 => "Triple".to_string(),
+            4 => "Quad".to_string(),
+            n => format!("{n} Lines"),
+        };
+        match self {
+            Self::Clear(n) => lines(n),
+            Self::Spin(kind, 0) => format!("{kind:?}-Spin"),
+            Self::Spin(kind, n) => format!("{kind:?}-Spin {}", lines(n)),
+            Self::PerfectClear => "Perfect Clear".to_string(),
+        }
+    }
+}
+
+/// Sent from the settings panel to show the given actions on every board, as they would be shown
+/// by a lock, so that the settings can be tried out
+#[derive(Event, Clone, Debug)]
+pub struct PreviewActionText(pub Vec<ActionKind>);
+
+#[derive(Component)]
+pub struct ActionText {
+    age: f32,
+}
+
+/// The actions that a lock is announced for
+fn lock_actions(lock: &PieceLocked, matrix: &Matrix) -> Vec<ActionKind> {
+    let mut actions = Vec::new();
+    if lock.spin {
+        actions.push(ActionKind::Spin(lock.kind, lock.clear));
+    } else if lock.clear > 0 {
+        actions.push(ActionKind::Clear(lock.clear));
+    }
+    // the lock has been applied by now, so a perfect clear leaves the matrix empty
+    if lock.clear > 0 && matrix.cells().iter().all(|&kind| kind == MinoKind::E) {
+        actions.push(ActionKind::PerfectClear);
+    }
+    actions
+}
+
+/// Puts up text announcing the clears, spins and perfect clears of each lock, replacing the text
+/// put up for the lock before it. Only the actions chosen in the settings are announced.
+pub(crate) fn spawn_action_text(
+    mut commands: Commands,
+    mut locks: EventReader<PieceLocked>,
+    mut previews: EventReader<PreviewActionText>,
+    boards: Query<(Entity, &Matrix, &Bounds)>,
+    texts: Query<(Entity, &Parent), With<ActionText>>,
+    settings: Res<GlobalSettings>,
+    presentation: Res<PresentationMode>,
+) {
+    let settings = &settings.action_text;
+    let scale = settings.scale * presentation.scale();
+    let mut announced = locks
+        .read()
+        .filter_map(|lock| {
+            let (_, matrix, _) = boards.get(lock.board).ok()?;
+            Some((lock.board, lock_actions(lock, matrix)))
+        })
+        .collect::<Vec<_>>();
+    for preview in previews.read() {
+        announced.extend(boards.iter().map(|(e, _, _)| (e, preview.0.clone())));
+    }
+
+    for (board, actions) in announced {
+        let lines = actions
+            .into_iter()
+            .filter(|action| action.shown(settings))
+            .map(ActionKind::text)
+            .collect::<Vec<_>>();
+        let Ok((_, _, bounds)) = boards.get(board) else {
+            continue;
+        };
+        if lines.is_empty() {
+            continue;
+        }
+        for (text, parent) in texts.iter() {
+            if parent.get() == board {
+                commands.entity(text).despawn_recursive();
+            }
+        }
+
+        let (position, anchor) = settings.position.place(bounds);
+        let text = commands
+            .spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        lines.join("\n"),
+                        TextStyle {
+                            font_size: ACTION_TEXT_FONT_SIZE * scale,
+                            ..default()
+                        },
+                    )
+                    .with_justify(JustifyText::Center),
+                    text_anchor: anchor,
+                    transform: Transform::from_translation(position.extend(2.0)),
+                    ..default()
+                },
+                ActionText { age: 0.0 },
+            ))
+            .id();
+        commands.entity(board).add_child(text);
+    }
+}
+
+/// Fades the text out as it rises, keeping it where the settings place it relative to the board
+pub(crate) fn update_action_text(
+    mut commands: Commands,
+    mut texts: Query<(Entity, &Parent, &mut ActionText, &mut Text, &mut Transform)>,
+    boards: Query<&Bounds>,
+    settings: Res<GlobalSettings>,
+    presentation: Res<PresentationMode>,
+    time: Res<Time>,
+) {
+    let settings = &settings.action_text;
+    let scale = settings.scale * presentation.scale();
+    for (e, parent, mut action, mut text, mut transform) in texts.iter_mut() {
+        action.age += time.delta_seconds();
+        let Ok(bounds) = boards.get(parent.get()) else {
+            continue;
+        };
+        if action.age > settings.duration {
+            commands.entity(e).despawn_recursive();
+            continue;
+        }
+
+        let progress = action.age / settings.duration;
+        let (position, _) = settings.position.place(bounds);
+        let rise = progress * ACTION_TEXT_RISE * scale;
+        transform.translation = (position + Vec2::Y * rise).extend(transform.translation.z);
+        for section in &mut text.sections {
+            section.style.color.set_a(1.0 - progress * progress);
+        }
+    }
+}
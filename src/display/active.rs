@@ -1,11 +1,18 @@
 use bevy::prelude::*;
+use bevy::utils::HashMap;
 
 use crate::{
+    animation::tween::{animate, Tween},
     assets::tables::QueryShapeTable,
-    board::{Active, Bounds, MinoKind, CELL_SIZE},
+    board::{Active, Bounds, Hold, Mino, MinoKind, CELL_SIZE},
+    screens::GlobalSettings,
 };
 
 use crate::assets::matrix_material::{MatrixMaterial, MatrixMaterialSpawner};
+use crate::display::warn_missing_child;
+
+/// Tint of the active piece when it could be swapped with the held piece, when previewing holds
+const SWAPPABLE_TINT: Color = Color::rgb(0.8, 0.8, 0.8);
 
 #[derive(Component)]
 pub struct ActiveSprite;
@@ -29,38 +36,101 @@ pub(crate) fn spawn_active_sprite(
 /// Updates the visual state of the active piece. The active piece is a child of the board,
 /// initialized in the same system that spawns the board. If the active piece becomes `None`, then
 /// the sprite representing it is hidden. If it is modified in any other way, the sprite's position
-/// and kind will be updated to match.
+/// and kind will be updated to match. When hold previews are enabled, the piece is dimmed slightly
+/// while a held piece is ready to be swapped in.
+///
+/// With smooth movement on, the sprite slides toward the position of the piece rather than jumping
+/// there. Only the sprite moves this way, never the piece itself. The sprite still jumps whenever
+/// the shape changes, and when a new piece comes in (which is how a hard drop shows), so that
+/// neither rotations nor drops lag behind.
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn display_active(
-    active: Query<(&Active, &Bounds, &Children), Changed<Active>>,
+    active: Query<(Entity, Ref<Active>, Ref<Hold>, &Bounds, &Children)>,
     mut sprites: Query<
-        (&mut Visibility, &mut Transform, &Handle<MatrixMaterial>),
+        (
+            Entity,
+            &mut Visibility,
+            &mut Transform,
+            &Handle<MatrixMaterial>,
+        ),
         With<ActiveSprite>,
     >,
     shape_table: QueryShapeTable,
     mut material_server: ResMut<Assets<MatrixMaterial>>,
+    settings: Res<GlobalSettings>,
+    time: Res<Time>,
+    mut motion: Local<HashMap<Entity, (Mino, Option<Tween<Vec2>>)>>,
+    mut warned: Local<bool>,
 ) {
     let shape_bounds = shape_table.bounds(|_| true);
-    for (Active(e), bounds, children) in active.iter() {
-        let active_sprite_id = children.iter().copied().find(|&c| sprites.contains(c));
-        let (mut vis, mut pos, tex) = sprites.get_mut(active_sprite_id.unwrap()).unwrap();
-        let mat = material_server.get_mut(tex).unwrap();
+    for (board, active, hold, bounds, children) in active.iter() {
+        let changed = active.is_changed() || hold.is_changed() || settings.is_changed();
+        let sprite = children.iter().copied().find(|&c| sprites.contains(c));
+        let Some((sprite, mut vis, mut pos, tex)) = sprite.and_then(|c| sprites.get_mut(c).ok())
+        else {
+            warn_missing_child(&mut warned, board, "active piece sprite");
+            continue;
+        };
+        let moving = motion
+            .get(&sprite)
+            .is_some_and(|(_, tween)| tween.is_some());
+        if !(changed || moving) {
+            continue;
+        }
 
-        if let Some(piece) = e {
-            *vis = Visibility::Inherited;
+        let Active(e) = &*active;
+        let Some(piece) = e else {
+            *vis = Visibility::Hidden;
+            motion.remove(&sprite);
+            continue;
+        };
+        *vis = Visibility::Inherited;
 
-            let offset = -(bounds.legal_bounds.as_vec2() / 2.);
-            let new_pos = (piece.position.as_vec2() + offset) * CELL_SIZE as f32;
-            pos.translation = new_pos.extend(1.0);
+        let offset = -(bounds.legal_bounds.as_vec2() / 2.);
+        let target = (piece.position.as_vec2() + offset) * CELL_SIZE as f32;
+        let jump = !settings.smooth_movement
+            || motion.get(&sprite).map_or(true, |(previous, _)| {
+                previous.kind != piece.kind
+                    || previous.rotation != piece.rotation
+                    || previous.position.y < piece.position.y
+            });
+        let (previous, tween) = motion.entry(sprite).or_insert((*piece, None));
+        *previous = *piece;
+        let next = if jump {
+            *tween = None;
+            Some(target)
+        } else {
+            animate(
+                tween,
+                pos.translation.truncate(),
+                target,
+                settings.movement_timing(),
+                time.delta_seconds(),
+            )
+        };
+        if let Some(next) = next {
+            pos.translation = next.extend(1.0);
+        }
 
-            mat.data.fill(MinoKind::E as u32);
-            let shape = &shape_table[*piece];
-            for &p in shape {
-                let loc = p - shape_bounds.min;
-                let ix = loc.y * (shape_bounds.size().x) + loc.x;
-                mat.data[ix as usize] = piece.kind as u32;
-            }
+        if !changed {
+            continue;
+        }
+        let Some(mat) = material_server.get_mut(tex) else {
+            continue;
+        };
+        let swappable = matches!(*hold, Hold::Ready(_));
+        mat.tint = if settings.hold_preview && swappable {
+            SWAPPABLE_TINT
         } else {
-            *vis = Visibility::Hidden
+            Color::WHITE
+        };
+
+        mat.data.fill(MinoKind::E as u32);
+        let shape = &shape_table[*piece];
+        for &p in shape {
+            let loc = p - shape_bounds.min;
+            let ix = loc.y * (shape_bounds.size().x) + loc.x;
+            mat.data[ix as usize] = piece.kind as u32;
         }
     }
 }
@@ -0,0 +1,134 @@
+use bevy::{math::vec2, prelude::*};
+use itertools::Itertools;
+use tap::Tap;
+
+use crate::assets::matrix_material::{MatrixMaterial, MatrixMaterialSpawner};
+use crate::assets::tables::QueryShapeTable;
+use crate::board::MinoKind;
+use crate::display::queue::QueueLayout;
+use crate::screens::GlobalSettings;
+use crate::{
+    assets::tables::shape_table::ShapeParameters,
+    board::{queue::PieceQueue, RotationState, CELL_SIZE},
+};
+
+/// Size of the bag icons relative to the pieces on the board
+const BAG_ICON_SCALE: f32 = 0.5;
+/// Tint of a piece which has already been dealt from the current bag
+const DEALT_TINT: Color = Color::rgba(1.0, 1.0, 1.0, 0.15);
+
+/// One icon of the bag tracker, which is lit while its piece remains in the current bag. The icons
+/// are numbered from the top of the column down.
+#[derive(Component)]
+pub struct BagIcon(MinoKind, usize);
+
+impl BagIcon {
+    pub fn slot(&self) -> usize {
+        self.1
+    }
+}
+
+/// The position of the top right corner of the given icon of the bag tracker, relative to the
+/// middle of the board, for a hold box of the given size in cells. The column of icons hangs under
+/// the hold box, wherever the layout puts it.
+pub fn bag_icon_position(layout: QueueLayout, icon: usize, size: IVec2) -> Vec2 {
+    let hold_height = (size.y + 1) as f32 * CELL_SIZE as f32;
+    let spacing = hold_height * BAG_ICON_SCALE;
+    layout.hold_position(size) - vec2(0., hold_height + spacing * icon as f32)
+}
+
+/// Spawns a column of piece icons under the hold slot, one for each piece of a bag.
+pub(crate) fn spawn_bag_tracker(
+    mut commands: Commands,
+    boards: Query<Entity, Added<PieceQueue>>,
+    shape_table: QueryShapeTable,
+    mut spawner: MatrixMaterialSpawner,
+    settings: Res<GlobalSettings>,
+) {
+    let shape_bounds =
+        shape_table.bounds(|&ShapeParameters { rotation, .. }| rotation == RotationState::Up);
+    let bounds = shape_bounds.tap_mut(|r| {
+        r.min = -r.size();
+        r.max = IVec2::ZERO;
+    });
+    let matrix_size = bounds.size().x;
+    // the icons hang under the hold box, which is sized to fit the pieces as they are previewed
+    let hold_size = settings.preview_orientation.box_size(&shape_table);
+
+    let kinds = shape_table.kinds();
+
+    for e in boards.iter() {
+        let icons = kinds
+            .iter()
+            .enumerate()
+            .map(|(i, &kind)| {
+                let shape = &shape_table[ShapeParameters {
+                    kind,
+                    rotation: RotationState::Up,
+                }];
+                let mut data =
+                    vec![MinoKind::E as u32; (bounds.size().x * bounds.size().y) as usize];
+                for &p in shape {
+                    let loc = p - shape_bounds.min;
+                    data[(loc.y * matrix_size + loc.x) as usize] = kind as u32;
+                }
+
+                let translation = bag_icon_position(settings.queue_layout, i, hold_size);
+                spawner
+                    .spawn_with_data(bounds, data)
+                    .insert((
+                        Transform::from_translation(translation.extend(0.))
+                            .with_scale(Vec3::splat(BAG_ICON_SCALE)),
+                        Visibility::Hidden,
+                        BagIcon(kind, i),
+                    ))
+                    .id()
+            })
+            .collect_vec();
+
+        for icon in icons {
+            commands.entity(e).add_child(icon);
+        }
+    }
+}
+
+/// Lights the icons of the pieces which remain in the current bag. The tracker is hidden when it is
+/// disabled, or when the queue is not dealing bags.
+pub(crate) fn update_bag_tracker(
+    queues: Query<(Ref<PieceQueue>, &Children)>,
+    mut icons: Query<(&BagIcon, &Handle<MatrixMaterial>, &mut Visibility)>,
+    mut mats: ResMut<Assets<MatrixMaterial>>,
+    settings: Res<GlobalSettings>,
+) {
+    for (queue, children) in queues.iter() {
+        if !(queue.is_changed() || settings.is_changed()) {
+            continue;
+        }
+
+        let remaining = queue
+            .bag_remaining()
+            .filter(|_| settings.bag_tracker)
+            .map(|r| r.collect_vec());
+
+        for &child in children.iter() {
+            let Ok((BagIcon(kind, _), handle, mut vis)) = icons.get_mut(child) else {
+                continue;
+            };
+
+            match &remaining {
+                None => *vis = Visibility::Hidden,
+                Some(remaining) => {
+                    *vis = Visibility::Inherited;
+                    let Some(mat) = mats.get_mut(handle) else {
+                        continue;
+                    };
+                    mat.tint = if remaining.contains(kind) {
+                        Color::WHITE
+                    } else {
+                        DEALT_TINT
+                    };
+                }
+            }
+        }
+    }
+}
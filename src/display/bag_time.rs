@@ -0,0 +1,72 @@
+use bevy::math::vec2;
+use bevy::prelude::*;
+
+use crate::board::openers::OpenerDrill;
+use crate::board::{Bounds, GameMode, Matrix, Settings, CELL_SIZE};
+use crate::stats::Stats;
+
+const BAG_TIME_FONT_SIZE: f32 = 18.0;
+/// Distance from the top of the matrix to the center of the text
+const BAG_TIME_GAP: f32 = 20.0;
+
+/// Text above the matrix timing the bag being played in an openers drill, against the average of
+/// the bags before it.
+#[derive(Component)]
+pub struct BagTimeText;
+
+pub(crate) fn spawn_bag_time_text(
+    mut commands: Commands,
+    boards: Query<(Entity, &Bounds), Added<Matrix>>,
+) {
+    for (e, bounds) in boards.iter() {
+        let y = bounds.legal_bounds.y as f32 / 2. * CELL_SIZE as f32 + BAG_TIME_GAP;
+        let text = commands
+            .spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font_size: BAG_TIME_FONT_SIZE,
+                            ..default()
+                        },
+                    ),
+                    transform: Transform::from_translation(vec2(0.0, y).extend(1.5)),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                BagTimeText,
+            ))
+            .id();
+
+        commands.entity(e).add_child(text);
+    }
+}
+
+/// Shows the time of the current bag whenever the board is drilling openers.
+pub(crate) fn update_bag_time_text(
+    boards: Query<&Settings>,
+    mut texts: Query<(&Parent, &mut Text, &mut Visibility), With<BagTimeText>>,
+    drill: Res<OpenerDrill>,
+    stats: Res<Stats>,
+) {
+    for (parent, mut text, mut vis) in texts.iter_mut() {
+        let Ok(settings) = boards.get(parent.get()) else {
+            continue;
+        };
+        if settings.mode != GameMode::Openers {
+            *vis = Visibility::Hidden;
+            continue;
+        }
+
+        let bag = format!(
+            "Bag {}: {:.2}s",
+            drill.repetition(),
+            drill.bag_time(&stats).max(0.0)
+        );
+        text.sections[0].value = match drill.average_time() {
+            Some(average) => format!("{bag} (avg {average:.2}s)"),
+            None => bag,
+        };
+        *vis = Visibility::Inherited;
+    }
+}
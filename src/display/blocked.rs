@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use bevy::audio::{PitchBundle, Volume};
+use bevy::prelude::*;
+
+use crate::board::{BlockedMove, BlockedMoveEvent};
+use crate::display::active::ActiveSprite;
+use crate::screens::GlobalSettings;
+
+/// Seconds that the active piece shakes for after a blocked move
+const SHAKE_DURATION: f32 = 0.12;
+/// Farthest that the shaking piece moves from where it is drawn, in pixels
+const SHAKE_DISTANCE: f32 = 2.0;
+/// Times the piece goes back and forth while shaking
+const SHAKE_SWINGS: f32 = 3.0;
+/// Frequency of the sound played for a blocked move, in hertz
+const BLOCKED_PITCH: f32 = 180.0;
+const BLOCKED_SOUND_LENGTH: Duration = Duration::from_millis(40);
+const BLOCKED_VOLUME: f32 = 0.15;
+
+/// An offset added to the active piece sprite on top of where the piece is drawn, so that shaking
+/// the sprite never moves the piece itself
+#[derive(Component, Default)]
+pub struct Shake {
+    age: f32,
+    /// The direction the piece shakes along, in pixels
+    direction: Vec2,
+    /// The offset last added to the sprite, which is taken back off before the sprite is drawn
+    applied: Vec2,
+}
+
+/// Starts the feedback for each blocked move: a soft sound, and a shake of the active piece along
+/// the direction it was pushed (sideways for a rotation)
+pub(crate) fn blocked_move_feedback(
+    mut commands: Commands,
+    mut blocked: EventReader<BlockedMoveEvent>,
+    sprites: Query<(Entity, &Parent), With<ActiveSprite>>,
+    mut pitches: ResMut<Assets<Pitch>>,
+    mut sound: Local<Option<Handle<Pitch>>>,
+    settings: Res<GlobalSettings>,
+) {
+    for event in blocked.read() {
+        if settings.blocked_sound {
+            let source = sound
+                .get_or_insert_with(|| pitches.add(Pitch::new(BLOCKED_PITCH, BLOCKED_SOUND_LENGTH)))
+                .clone();
+            commands.spawn(PitchBundle {
+                source,
+                settings: PlaybackSettings::DESPAWN.with_volume(Volume::new(BLOCKED_VOLUME)),
+            });
+        }
+
+        if settings.blocked_shake {
+            let direction = match event.attempt {
+                BlockedMove::Rotation => Vec2::X,
+                BlockedMove::Shift(shift) => Vec2::X * shift as f32,
+            };
+            for (sprite, _) in sprites.iter().filter(|(_, p)| p.get() == event.board) {
+                commands.entity(sprite).insert(Shake {
+                    direction,
+                    ..default()
+                });
+            }
+        }
+    }
+}
+
+/// Takes the offset of each shake back off its sprite, before the sprite is moved to the piece
+pub(crate) fn undo_shake(mut sprites: Query<(&mut Transform, &mut Shake)>) {
+    for (mut transform, mut shake) in sprites.iter_mut() {
+        transform.translation -= shake.applied.extend(0.0);
+        shake.applied = Vec2::ZERO;
+    }
+}
+
+/// Adds the offset of each shake to its sprite, ending the shake once its time is up
+pub(crate) fn apply_shake(
+    mut commands: Commands,
+    mut sprites: Query<(Entity, &mut Transform, &mut Shake)>,
+    time: Res<Time>,
+) {
+    for (e, mut transform, mut shake) in sprites.iter_mut() {
+        shake.age += time.delta_seconds();
+        if shake.age >= SHAKE_DURATION {
+            commands.entity(e).remove::<Shake>();
+            continue;
+        }
+
+        let progress = shake.age / SHAKE_DURATION;
+        let swing = (progress * SHAKE_SWINGS * std::f32::consts::TAU).sin();
+        shake.applied = shake.direction * SHAKE_DISTANCE * swing * (1.0 - progress);
+        transform.translation += shake.applied.extend(0.0);
+    }
+}
@@ -0,0 +1,82 @@
+use bevy::math::vec2;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+use crate::board::{Bounds, Matrix, CELL_SIZE};
+use crate::controller::{Controller, Direction};
+use crate::screens::GlobalSettings;
+use crate::state::MainState;
+
+const DAS_BAR_THICKNESS: f32 = 4.0;
+/// Space between the bars, and between the bars and the bottom of the matrix
+const DAS_BAR_GAP: f32 = 6.0;
+const DAS_CHARGING_COLOR: Color = Color::GRAY;
+const DAS_REPEATING_COLOR: Color = Color::WHITE;
+
+/// A bar under the matrix which fills up as the shift in its direction charges its initial delay.
+#[derive(Component)]
+pub struct DasIndicator(Direction);
+
+pub(crate) fn spawn_das_indicator(
+    mut commands: Commands,
+    boards: Query<(Entity, &Bounds), Added<Matrix>>,
+) {
+    for (e, bounds) in boards.iter() {
+        let y = -(bounds.legal_bounds.y as f32 / 2.) * CELL_SIZE as f32 - DAS_BAR_GAP;
+
+        for (direction, anchor, x) in [
+            (Direction::Left, Anchor::CenterRight, -DAS_BAR_GAP / 2.),
+            (Direction::Right, Anchor::CenterLeft, DAS_BAR_GAP / 2.),
+        ] {
+            let bar = commands
+                .spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            anchor,
+                            ..default()
+                        },
+                        transform: Transform::from_translation(vec2(x, y).extend(1.5)),
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                    DasIndicator(direction),
+                ))
+                .id();
+
+            commands.entity(e).add_child(bar);
+        }
+    }
+}
+
+/// Fills each bar according to the charge of its shift. The bars are only shown during live play,
+/// since the record does not hold the inputs that would drive them in a replay.
+pub(crate) fn update_das_indicator(
+    boards: Query<&Bounds>,
+    mut bars: Query<(&Parent, &DasIndicator, &mut Sprite, &mut Visibility)>,
+    controller: Res<Controller>,
+    settings: Res<GlobalSettings>,
+    state: Res<State<MainState>>,
+) {
+    let live = matches!(state.get(), MainState::Ready | MainState::Playing);
+
+    for (parent, &DasIndicator(direction), mut sprite, mut vis) in bars.iter_mut() {
+        let progress = controller.das_progress(direction);
+        let Some(progress) = progress.filter(|_| live && settings.das_indicator) else {
+            *vis = Visibility::Hidden;
+            continue;
+        };
+
+        let Ok(bounds) = boards.get(parent.get()) else {
+            continue;
+        };
+        let full_width = bounds.legal_bounds.x as f32 / 2. * CELL_SIZE as f32 - DAS_BAR_GAP / 2.;
+
+        sprite.custom_size = Some(vec2(full_width * progress, DAS_BAR_THICKNESS));
+        sprite.color = if progress < 1.0 {
+            DAS_CHARGING_COLOR
+        } else {
+            DAS_REPEATING_COLOR
+        };
+        *vis = Visibility::Inherited;
+    }
+}
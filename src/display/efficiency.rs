@@ -0,0 +1,75 @@
+use bevy::math::vec2;
+use bevy::prelude::*;
+
+use crate::board::{Bounds, GameMode, Matrix, Settings, CELL_SIZE};
+use crate::stats::{efficiency_color, Stats};
+
+const EFFICIENCY_FONT_SIZE: f32 = 18.0;
+/// Distance from the top of the matrix to the center of the text
+const EFFICIENCY_GAP: f32 = 20.0;
+
+/// Text above the matrix counting the garbage lines cleared in a cheese race against the pieces
+/// used to clear them.
+#[derive(Component)]
+pub struct EfficiencyText;
+
+pub(crate) fn spawn_efficiency_text(
+    mut commands: Commands,
+    boards: Query<(Entity, &Bounds), Added<Matrix>>,
+) {
+    for (e, bounds) in boards.iter() {
+        let y = bounds.legal_bounds.y as f32 / 2. * CELL_SIZE as f32 + EFFICIENCY_GAP;
+        let text = commands
+            .spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font_size: EFFICIENCY_FONT_SIZE,
+                            ..default()
+                        },
+                    ),
+                    transform: Transform::from_translation(vec2(0.0, y).extend(1.5)),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                EfficiencyText,
+            ))
+            .id();
+
+        commands.entity(e).add_child(text);
+    }
+}
+
+/// Shows the running efficiency whenever the board is in a cheese race, colored by how good it is.
+pub(crate) fn update_efficiency_text(
+    boards: Query<&Settings>,
+    mut texts: Query<(&Parent, &mut Text, &mut Visibility), With<EfficiencyText>>,
+    stats: Res<Stats>,
+) {
+    for (parent, mut text, mut vis) in texts.iter_mut() {
+        let Ok(settings) = boards.get(parent.get()) else {
+            continue;
+        };
+        if settings.mode != GameMode::CheeseRace {
+            *vis = Visibility::Hidden;
+            continue;
+        }
+
+        let section = &mut text.sections[0];
+        match stats.efficiency() {
+            Some(efficiency) => {
+                section.value = format!(
+                    "{} garbage / {} pieces ({efficiency:.2} PPL)",
+                    stats.garbage_lines, stats.pieces
+                );
+                section.style.color = efficiency_color(efficiency);
+            }
+            None => {
+                section.value = format!("0 garbage / {} pieces", stats.pieces);
+                section.style.color = Color::WHITE;
+            }
+        }
+        *vis = Visibility::Inherited;
+    }
+}
@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+
+use crate::assets::matrix_material::{MatrixMaterial, MatrixMaterialSpawner};
+use crate::assets::tables::QueryShapeTable;
+use crate::board::{Active, Bounds, FailedSpawn, MinoKind, CELL_SIZE};
+use crate::display::warn_missing_child;
+use crate::replay::ghost::Ghost;
+
+/// Tint of the piece which could not spawn when the game ended
+const DEATH_TINT: Color = Color::rgb(0.35, 0.35, 0.35);
+
+#[derive(Component)]
+pub struct FailedSpawnSprite;
+
+pub(crate) fn spawn_failed_spawn_sprite(
+    mut commands: Commands,
+    boards: Query<Entity, Added<Active>>,
+    mut mat_spawner: MatrixMaterialSpawner,
+    shape_table: QueryShapeTable,
+) {
+    for e in boards.iter() {
+        let sprite = mat_spawner
+            .spawn(shape_table.bounds(|_| true))
+            .insert((FailedSpawnSprite, Visibility::Hidden))
+            .id();
+
+        commands.entity(e).add_child(sprite);
+    }
+}
+
+/// Shows the piece which failed to spawn where it tried to spawn, greyed out, while the board is
+/// in its final state (that is, while there is no active piece).
+pub(crate) fn display_failed_spawn(
+    boards: Query<(Entity, Ref<Active>, &Bounds, &Children), Without<Ghost>>,
+    mut sprites: Query<
+        (&mut Visibility, &mut Transform, &Handle<MatrixMaterial>),
+        With<FailedSpawnSprite>,
+    >,
+    failed: Res<FailedSpawn>,
+    shape_table: QueryShapeTable,
+    mut material_server: ResMut<Assets<MatrixMaterial>>,
+    mut warned: Local<bool>,
+) {
+    let shape_bounds = shape_table.bounds(|_| true);
+    for (board, active, bounds, children) in boards.iter() {
+        if !(active.is_changed() || failed.is_changed()) {
+            continue;
+        }
+
+        let sprite = children.iter().copied().find(|&c| sprites.contains(c));
+        let Some((mut vis, mut pos, tex)) = sprite.and_then(|c| sprites.get_mut(c).ok()) else {
+            warn_missing_child(&mut warned, board, "failed spawn sprite");
+            continue;
+        };
+
+        let Some(piece) = failed.0.filter(|_| active.0.is_none()) else {
+            *vis = Visibility::Hidden;
+            continue;
+        };
+        *vis = Visibility::Inherited;
+
+        let offset = -(bounds.legal_bounds.as_vec2() / 2.);
+        pos.translation = ((piece.position.as_vec2() + offset) * CELL_SIZE as f32).extend(1.0);
+
+        let Some(mat) = material_server.get_mut(tex) else {
+            continue;
+        };
+        mat.tint = DEATH_TINT;
+        mat.data.fill(MinoKind::E as u32);
+        for &p in &shape_table[piece] {
+            let loc = p - shape_bounds.min;
+            let ix = loc.y * shape_bounds.size().x + loc.x;
+            mat.data[ix as usize] = piece.kind as u32;
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+
+use crate::board::events::PieceLocked;
+use crate::board::{Bounds, CELL_SIZE};
+use crate::screens::GlobalSettings;
+
+/// Seconds that the cells of a locked piece stay lit
+const FLASH_DURATION: f32 = 0.08;
+
+/// A white square over a cell of a piece which just locked, which fades until it disappears.
+#[derive(Component)]
+pub struct LockFlash {
+    age: f32,
+}
+
+/// Lights up the cells of each piece which locked, whether in live play or in the replay.
+pub(crate) fn spawn_lock_flash(
+    mut commands: Commands,
+    mut locks: EventReader<PieceLocked>,
+    boards: Query<&Bounds>,
+    settings: Res<GlobalSettings>,
+) {
+    if !settings.lock_flash {
+        locks.clear();
+        return;
+    }
+
+    for lock in locks.read() {
+        let Ok(bounds) = boards.get(lock.board) else {
+            continue;
+        };
+        let offset = -(bounds.legal_bounds.as_vec2() / 2.);
+
+        commands.entity(lock.board).with_children(|parent| {
+            for &cell in &lock.cells {
+                let center = (cell.as_vec2() + 0.5 + offset) * CELL_SIZE as f32;
+                parent.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::WHITE,
+                            custom_size: Some(Vec2::splat(CELL_SIZE as f32)),
+                            ..default()
+                        },
+                        transform: Transform::from_translation(center.extend(1.8)),
+                        ..default()
+                    },
+                    LockFlash { age: 0.0 },
+                ));
+            }
+        });
+    }
+}
+
+pub(crate) fn update_lock_flash(
+    mut commands: Commands,
+    mut flashes: Query<(Entity, &mut LockFlash, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (e, mut flash, mut sprite) in flashes.iter_mut() {
+        flash.age += time.delta_seconds();
+        if flash.age > FLASH_DURATION {
+            commands.entity(e).despawn_recursive();
+            continue;
+        }
+        sprite.color.set_a(1.0 - flash.age / FLASH_DURATION);
+    }
+}
@@ -6,9 +6,11 @@ use bevy::render::render_resource::{
 use bevy::sprite::{Material2d, MaterialMesh2dBundle};
 use bevy::utils::HashSet;
 
+use crate::assets::palette::Palette;
 use crate::assets::tables::QueryShapeTable;
 
 use crate::board::{Active, Matrix, CELL_SIZE, MATRIX_DEFAULT_LEGAL_BOUNDS};
+use crate::display::warn_missing_child;
 
 #[derive(Clone, TypePath, Asset, AsBindGroup)]
 pub struct DropShadowMaterial {
@@ -69,17 +71,26 @@ pub(crate) fn spawn_drop_shadow(
 }
 
 pub(crate) fn update_drop_shadow(
-    active: Query<(&Active, &Children), Changed<Active>>,
+    active: Query<(Entity, &Active, &Children), Changed<Active>>,
     mat: Query<&Handle<DropShadowMaterial>>,
     mut images: ResMut<Assets<Image>>,
-    mut mats: ResMut<Assets<DropShadowMaterial>>,
+    mats: Res<Assets<DropShadowMaterial>>,
     shape_table: QueryShapeTable,
+    palette: Res<Palette>,
+    mut warned: Local<bool>,
 ) {
-    for (active, children) in active.iter() {
+    for (board, active, children) in active.iter() {
         if let Some(active) = active.0 {
-            let child = children.iter().find_map(|e| mat.get(*e).ok()).unwrap();
-            let material = mats.get_mut(child).unwrap();
-            let image = images.get_mut(material.base.clone()).unwrap();
+            let Some(child) = children.iter().find_map(|e| mat.get(*e).ok()) else {
+                warn_missing_child(&mut warned, board, "drop shadow");
+                continue;
+            };
+            let Some(image) = mats
+                .get(child)
+                .and_then(|material| images.get_mut(material.base.clone()))
+            else {
+                continue;
+            };
 
             let contained: HashSet<_> = shape_table[active]
                 .iter()
@@ -88,7 +99,7 @@ pub(crate) fn update_drop_shadow(
 
             for (i, chunk) in image.data.chunks_mut(4).enumerate() {
                 let fill = if contained.contains(&i) {
-                    active.kind.color()
+                    palette.color(active.kind)
                 } else {
                     Color::WHITE
                 };
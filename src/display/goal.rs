@@ -0,0 +1,58 @@
+use bevy::math::vec2;
+use bevy::prelude::*;
+
+use crate::board::{Bounds, GameMode, Matrix, Settings, CELL_SIZE};
+use crate::display::warn_missing_child;
+
+const TARGET_LINE_THICKNESS: f32 = 2.0;
+
+/// A line across the matrix marking the height which the stack must be brought under.
+#[derive(Component)]
+pub struct TargetLine;
+
+pub(crate) fn spawn_target_line(mut commands: Commands, boards: Query<Entity, Added<Matrix>>) {
+    for e in boards.iter() {
+        let line = commands
+            .spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::ORANGE_RED,
+                        ..default()
+                    },
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                TargetLine,
+            ))
+            .id();
+
+        commands.entity(e).add_child(line);
+    }
+}
+
+/// Shows the target line at the configured height whenever the board is in downstack mode.
+pub(crate) fn update_target_line(
+    boards: Query<(Entity, &Settings, &Bounds, &Children), Changed<Settings>>,
+    mut lines: Query<(&mut Sprite, &mut Transform, &mut Visibility), With<TargetLine>>,
+    mut warned: Local<bool>,
+) {
+    for (board, settings, bounds, children) in boards.iter() {
+        let child = children.iter().copied().find(|&c| lines.contains(c));
+        let Some((mut sprite, mut transform, mut vis)) = child.and_then(|c| lines.get_mut(c).ok())
+        else {
+            warn_missing_child(&mut warned, board, "target line");
+            continue;
+        };
+
+        if settings.mode == GameMode::Downstack {
+            let legal = bounds.legal_bounds.as_vec2();
+            let y = (settings.target_height as f32 - legal.y / 2.) * CELL_SIZE as f32;
+
+            sprite.custom_size = Some(vec2(legal.x * CELL_SIZE as f32, TARGET_LINE_THICKNESS));
+            transform.translation = vec2(0.0, y).extend(1.5);
+            *vis = Visibility::Inherited;
+        } else {
+            *vis = Visibility::Hidden;
+        }
+    }
+}
@@ -1,31 +1,82 @@
-use bevy::{math::vec2, prelude::*};
-use tap::Tap;
+use bevy::math::vec2;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy::window::PrimaryWindow;
+use bevy_egui::EguiContexts;
 
+use crate::animation::tween::{Easing, Timing, Tween};
 use crate::assets::matrix_material::{MatrixMaterial, MatrixMaterialSpawner};
 use crate::assets::tables::QueryShapeTable;
-use crate::board::MinoKind;
-use crate::{
-    assets::tables::shape_table::ShapeParameters,
-    board::{Hold, RotationState, CELL_SIZE, MATRIX_DEFAULT_LEGAL_BOUNDS},
-};
+use crate::board::{queue::PieceQueue, Hold, MinoKind, CELL_SIZE};
+use crate::controller::Controller;
+use crate::display::queue::{draw_preview, hold_bounds};
+use crate::display::warn_missing_child;
+use crate::replay::ghost::Ghost;
+use crate::replay::replay::ReplayInfo;
+use crate::screens::GlobalSettings;
+use crate::state::MainState;
 
 #[derive(Component)]
 pub struct HoldSprite;
 
+/// Width of the border pulsed around the hold box
+const PULSE_WIDTH: f32 = 3.0;
+/// How bright the border is at the start of a pulse, from 0 to 1
+const PULSE_PEAK: f32 = 0.8;
+const PULSE_TIMING: Timing = Timing {
+    duration: 0.2,
+    easing: Easing::EaseOutCubic,
+};
+
+/// One edge of the border around the hold box, which lights up and fades whenever hold can be used
+/// again
+#[derive(Component)]
+pub struct HoldPulse {
+    /// Which of the edges from [`border_edges`] this is
+    edge: usize,
+    tween: Option<Tween<f32>>,
+}
+
+impl HoldPulse {
+    /// Fits this edge to the border around a hold box covering the given cells, as when the box is
+    /// resized
+    pub(crate) fn fit(&self, transform: &mut Transform, sprite: &mut Sprite, cells: IRect) {
+        let (center, size) = border_edges(cells)[self.edge];
+        transform.translation = center.extend(transform.translation.z);
+        sprite.custom_size = Some(size);
+    }
+}
+
+/// The four edges of the border around a box covering the given cells, as their centers and sizes
+fn border_edges(cells: IRect) -> [(Vec2, Vec2); 4] {
+    let min = cells.min.as_vec2() * CELL_SIZE as f32 - PULSE_WIDTH / 2.;
+    let max = cells.max.as_vec2() * CELL_SIZE as f32 + PULSE_WIDTH / 2.;
+    let center = (min + max) / 2.;
+    let size = max - min;
+    [
+        (
+            vec2(center.x, min.y),
+            vec2(size.x + PULSE_WIDTH, PULSE_WIDTH),
+        ),
+        (
+            vec2(center.x, max.y),
+            vec2(size.x + PULSE_WIDTH, PULSE_WIDTH),
+        ),
+        (vec2(min.x, center.y), vec2(PULSE_WIDTH, size.y)),
+        (vec2(max.x, center.y), vec2(PULSE_WIDTH, size.y)),
+    ]
+}
+
 pub(crate) fn spawn_hold_sprite(
     mut commands: Commands,
     boards: Query<Entity, Added<Hold>>,
     shape_table: QueryShapeTable,
     mut spawner: MatrixMaterialSpawner,
+    settings: Res<GlobalSettings>,
 ) {
-    let hold_offset =
-        MATRIX_DEFAULT_LEGAL_BOUNDS.as_vec2() / 2.0 * vec2(-1., 1.) * CELL_SIZE as f32;
-    let bounds = shape_table
-        .bounds(|&ShapeParameters { rotation, .. }| rotation == RotationState::Up)
-        .tap_mut(|r| {
-            r.min = -r.size();
-            r.max = IVec2::ZERO;
-        });
+    let size = settings.preview_orientation.box_size(&shape_table);
+    let bounds = hold_bounds(size);
+    let hold_offset = settings.queue_layout.hold_position(size);
 
     for e in boards.iter() {
         let hold_sprite = spawner
@@ -34,57 +85,176 @@ pub(crate) fn spawn_hold_sprite(
                 Transform::from_translation(hold_offset.extend(0.)),
                 HoldSprite,
             ))
+            .with_children(|parent| {
+                for (edge, (center, size)) in border_edges(bounds).into_iter().enumerate() {
+                    parent.spawn((
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color: Color::NONE,
+                                custom_size: Some(size),
+                                ..default()
+                            },
+                            transform: Transform::from_translation(center.extend(0.1)),
+                            ..default()
+                        },
+                        HoldPulse { edge, tween: None },
+                    ));
+                }
+            })
             .id();
 
         commands.entity(e).add_child(hold_sprite);
     }
 }
 
+/// Holds when the hold box is clicked, by pressing hold on the controller just as the hold key
+/// would. The hold is then recorded, follows the hold rules and branches the replay like any other
+/// press. Clicks on the queue do nothing.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn click_hold(
+    mut contexts: EguiContexts,
+    buttons: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    sprites: Query<(&GlobalTransform, &Parent), With<HoldSprite>>,
+    ghosts: Query<(), With<Ghost>>,
+    shape_table: QueryShapeTable,
+    settings: Res<GlobalSettings>,
+    mut controller: ResMut<Controller>,
+) {
+    if !buttons.just_pressed(MouseButton::Left) || contexts.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let world = if_chain::if_chain! {
+        if let Ok(window) = windows.get_single();
+        if let Some(cursor) = window.cursor_position();
+        if let Ok((camera, camera_transform)) = cameras.get_single();
+        then {
+            camera.viewport_to_world_2d(camera_transform, cursor)
+        } else {
+            None
+        }
+    };
+    let Some(world) = world else {
+        return;
+    };
+
+    let cells = hold_bounds(settings.preview_orientation.box_size(&shape_table));
+    let area = Rect::from_corners(
+        cells.min.as_vec2() * CELL_SIZE as f32,
+        cells.max.as_vec2() * CELL_SIZE as f32,
+    );
+    let clicked = sprites
+        .iter()
+        .filter(|(_, parent)| !ghosts.contains(parent.get()))
+        .any(|(transform, _)| {
+            let local = transform
+                .affine()
+                .inverse()
+                .transform_point3(world.extend(0.0))
+                .truncate();
+            area.contains(local)
+        });
+    if clicked {
+        controller.hold = true;
+    }
+}
+
+/// Tint of the next piece shown in an empty hold slot, when previewing holds
+const EMPTY_PREVIEW_TINT: Color = Color::rgba(1.0, 1.0, 1.0, 0.35);
+/// Tint of an inactive held piece, when previewing holds
+const LOCKED_TINT: Color = Color::rgb(1.0, 0.55, 0.55);
+
 /// Displays the held piece. Greys the texture of the associated sprite if it is inactive, or keeps
-/// it at its normal color if it is not. The sprite is hidden if the hold slot is empty.
+/// it at its normal color if it is not. The sprite is hidden if the hold slot is empty, unless hold
+/// previews are enabled, in which case the piece that holding would store is shown faded.
 pub(crate) fn display_held(
-    hold: Query<(&Hold, &Children), Changed<Hold>>,
+    hold: Query<(Entity, Ref<Hold>, Ref<PieceQueue>, &Children)>,
     shape_table: QueryShapeTable,
     mut sprites: Query<(&mut Visibility, &Handle<MatrixMaterial>), With<HoldSprite>>,
     mut mats: ResMut<Assets<MatrixMaterial>>,
+    settings: Res<GlobalSettings>,
+    mut warned: Local<bool>,
 ) {
-    let bounds =
-        shape_table.bounds(|&ShapeParameters { rotation, .. }| rotation == RotationState::Up);
-    let matrix_size = bounds.size().x;
-    for (hold, children) in hold.iter() {
-        let child = children
-            .iter()
-            .copied()
-            .find(|&c| sprites.contains(c))
-            .unwrap();
-        let (mut vis, han) = sprites.get_mut(child).unwrap();
-        let mat = mats.get_mut(han).unwrap();
-
-        match hold {
-            Hold::Empty => {
+    for (board, hold, queue, children) in hold.iter() {
+        if !(hold.is_changed() || queue.is_changed() || settings.is_changed()) {
+            continue;
+        }
+
+        let sprite = children.iter().copied().find(|&c| sprites.contains(c));
+        let Some((mut vis, han)) = sprite.and_then(|c| sprites.get_mut(c).ok()) else {
+            warn_missing_child(&mut warned, board, "hold sprite");
+            continue;
+        };
+        let Some(mat) = mats.get_mut(han) else {
+            continue;
+        };
+
+        let shown = match *hold {
+            Hold::Empty if settings.hold_preview => Some((queue.peek(), EMPTY_PREVIEW_TINT)),
+            Hold::Empty => None,
+            Hold::Inactive(kind) if settings.hold_preview => Some((kind, LOCKED_TINT)),
+            Hold::Inactive(kind) | Hold::Ready(kind) => Some((kind, Color::WHITE)),
+        };
+
+        match shown {
+            None => {
                 *vis = Visibility::Hidden;
             }
-            &Hold::Inactive(kind) | &Hold::Ready(kind) => {
-                mat.data.fill(MinoKind::E as u32);
-
-                let shape = &shape_table[ShapeParameters {
-                    kind,
-                    rotation: RotationState::Up,
-                }];
-                for &p in shape {
-                    let fill_kind = if matches!(hold, Hold::Inactive(_)) {
-                        MinoKind::G
-                    } else {
-                        kind
-                    };
-
-                    let loc = p - bounds.min;
-                    let ix = loc.y * matrix_size + loc.x;
-                    mat.data[ix as usize] = fill_kind as u32;
-                }
+            Some((kind, tint)) => {
+                mat.tint = tint;
+                let fill = if matches!(*hold, Hold::Inactive(_)) {
+                    MinoKind::G
+                } else {
+                    kind
+                };
+                draw_preview(mat, kind, fill, settings.preview_orientation, &shape_table);
 
                 *vis = Visibility::Inherited;
             }
         }
     }
 }
+
+/// Pulses the border of the hold box once whenever hold goes from used to ready again, so that it
+/// is harder to miss that hold has come back. No pulse is started while the replay is being
+/// scrubbed, since hold changes back and forth rapidly then.
+pub(crate) fn pulse_hold(
+    holds: Query<(Entity, Ref<Hold>, &Children)>,
+    hold_sprites: Query<&Children, With<HoldSprite>>,
+    mut edges: Query<(&mut HoldPulse, &mut Sprite)>,
+    settings: Res<GlobalSettings>,
+    state: Res<State<MainState>>,
+    replay_info: Option<Res<ReplayInfo>>,
+    time: Res<Time>,
+    mut was_inactive: Local<HashMap<Entity, bool>>,
+) {
+    let scrubbing =
+        *state.get() == MainState::PostGame && replay_info.map_or(true, |info| info.is_scrubbing());
+    for (board, hold, children) in holds.iter() {
+        let inactive = matches!(*hold, Hold::Inactive(_));
+        let recharged =
+            was_inactive.insert(board, inactive) == Some(true) && matches!(*hold, Hold::Ready(_));
+        if !(hold.is_changed() && recharged && settings.hold_pulse && !scrubbing) {
+            continue;
+        }
+
+        let sprite_children = children.iter().find_map(|&c| hold_sprites.get(c).ok());
+        for &edge in sprite_children.into_iter().flatten() {
+            if let Ok((mut pulse, _)) = edges.get_mut(edge) {
+                pulse.tween = Some(Tween::new(PULSE_PEAK, 0.0, PULSE_TIMING));
+            }
+        }
+    }
+
+    for (mut pulse, mut sprite) in edges.iter_mut() {
+        let Some(tween) = &mut pulse.tween else {
+            continue;
+        };
+        let brightness = tween.advance(time.delta_seconds());
+        if tween.finished() {
+            pulse.tween = None;
+        }
+        sprite.color = Color::rgba(1.0, 1.0, 1.0, brightness);
+    }
+}
@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+
+use crate::progress_bar::ProgressBar;
+
+use super::action_text::ActionText;
+use super::bag::BagIcon;
+use super::bag_time::BagTimeText;
+use super::das::DasIndicator;
+use super::efficiency::EfficiencyText;
+use super::goal::TargetLine;
+use super::ruler::Ruler;
+use super::timers::TimerOverlay;
+
+/// The text and indicators drawn around the board, as opposed to the board itself
+type Hud = Or<(
+    With<ActionText>,
+    With<BagIcon>,
+    With<BagTimeText>,
+    With<DasIndicator>,
+    With<EfficiencyText>,
+    With<TargetLine>,
+    With<Ruler>,
+    With<TimerOverlay>,
+    With<ProgressBar>,
+)>;
+
+/// Hides the text and indicators drawn around the board for a single frame, such as for a
+/// screenshot of the board alone
+#[derive(Resource, Default)]
+pub struct HideHud {
+    requested: bool,
+    /// The parts which were hidden on the last frame, with how visible they were before
+    hidden: Vec<(Entity, Visibility)>,
+}
+
+impl HideHud {
+    /// Hides the overlays on this frame, as long as this is asked before the display is updated
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+}
+
+/// Brings back whatever was hidden on the last frame, and hides everything if asked to. This runs
+/// after the rest of the display has been updated, so that nothing shows the overlays again before
+/// the frame is drawn.
+pub(crate) fn hide_hud(mut hide: ResMut<HideHud>, mut hud: Query<(Entity, &mut Visibility), Hud>) {
+    let HideHud { requested, hidden } = &mut *hide;
+    for (e, visibility) in hidden.drain(..) {
+        if let Ok((_, mut current)) = hud.get_mut(e) {
+            *current = visibility;
+        }
+    }
+    if std::mem::take(requested) {
+        *hidden = hud
+            .iter_mut()
+            .map(|(e, mut visibility)| (e, std::mem::replace(&mut *visibility, Visibility::Hidden)))
+            .collect();
+    }
+}
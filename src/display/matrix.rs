@@ -1,11 +1,50 @@
 use crate::assets::matrix_material::{MatrixMaterial, MatrixMaterialSpawner};
+use crate::screens::GlobalSettings;
 use bevy::prelude::*;
+use itertools::Itertools;
 
-use crate::board::{Bounds, Matrix, CELL_SIZE, MATRIX_DEFAULT_SIZE};
+use crate::board::{
+    Bounds, LineClearEvent, Matrix, MinoKind, Settings, StackVisibility, CELL_SIZE,
+    MATRIX_DEFAULT_SIZE,
+};
+use crate::display::warn_missing_child;
+use crate::state::MainState;
+
+/// How long the stack stays visible after a line clear, when it is otherwise invisible
+const REVEAL_DURATION: f32 = 0.3;
 
 #[derive(Component)]
 pub struct MatrixSprite;
 
+/// Remembers when each cell of the matrix was filled, so that the stack can be hidden according to
+/// the board's [`StackVisibility`] without touching the logical matrix.
+#[derive(Component)]
+pub struct StackMemory {
+    kinds: Vec<MinoKind>,
+    placed_at: Vec<f32>,
+    revealed_until: f32,
+}
+
+impl StackMemory {
+    fn new(size: IVec2) -> Self {
+        let cells = (size.x * size.y) as usize;
+        Self {
+            kinds: vec![MinoKind::E; cells],
+            placed_at: vec![0.0; cells],
+            revealed_until: 0.0,
+        }
+    }
+
+    /// Mirrors the collapse of the matrix when the given row is cleared
+    fn forget_row(&mut self, row: usize, width: usize) {
+        self.kinds.drain(row * width..(row + 1) * width);
+        self.kinds
+            .extend(std::iter::repeat(MinoKind::E).take(width));
+        self.placed_at.drain(row * width..(row + 1) * width);
+        self.placed_at.extend(std::iter::repeat(0.0).take(width));
+    }
+}
+
 pub(crate) fn spawn_matrix_sprite(
     mut commands: Commands,
     boards: Query<Entity, Added<Matrix>>,
@@ -14,48 +53,136 @@ pub(crate) fn spawn_matrix_sprite(
     for e in boards.iter() {
         let matrix_sprite = mesh_spawner
             .spawn_centered(MATRIX_DEFAULT_SIZE)
-            .insert(MatrixSprite)
+            .insert((MatrixSprite, StackMemory::new(MATRIX_DEFAULT_SIZE)))
             .id();
 
         commands.entity(e).add_child(matrix_sprite);
     }
 }
 
+/// Turns on the well background for each new matrix, and brings every matrix onto the background
+/// in the settings
+pub(crate) fn apply_well_background(
+    settings: Res<GlobalSettings>,
+    sprites: Query<Ref<Handle<MatrixMaterial>>, With<MatrixSprite>>,
+    mut material_server: ResMut<Assets<MatrixMaterial>>,
+) {
+    let [r, g, b] = settings.well_color;
+    for handle in sprites.iter() {
+        if !(settings.is_changed() || handle.is_added()) {
+            continue;
+        }
+        let Some(material) = material_server.get_mut(&*handle) else {
+            continue;
+        };
+        material.well = 1;
+        material.well_background = settings.well_background as u32;
+        material.well_color = Color::rgba(r, g, b, settings.well_intensity);
+    }
+}
+
 /// Creates/removes the tiles on the screen given the state of the board at the time. A variant of
 /// each cell exists on the screen, and this system reads the currently active variant of tetromino
-/// at that location and enables the visibility of that sprite accordingly.
+/// at that location and enables the visibility of that sprite accordingly. During live play, cells
+/// may be drawn as empty if the board's [`StackVisibility`] hides them.
 pub(crate) fn redraw_board(
-    board: Query<(&Matrix, &Bounds, &Children), Changed<Matrix>>,
-    children: Query<&Handle<MatrixMaterial>, With<MatrixSprite>>,
+    board: Query<(Entity, Ref<Matrix>, &Bounds, Ref<Settings>, &Children)>,
+    mut children: Query<(&Handle<MatrixMaterial>, &mut StackMemory), With<MatrixSprite>>,
+    mut clears: EventReader<LineClearEvent>,
+    state: Res<State<MainState>>,
+    time: Res<Time>,
     mut material_server: ResMut<Assets<MatrixMaterial>>,
+    mut warned: Local<bool>,
 ) {
-    for (board, bounds, ch) in board.iter() {
-        let material_id = ch.iter().find_map(|c| children.get(*c).ok()).unwrap();
-        let material = material_server.get_mut(material_id).unwrap();
-
-        let cells = board.data.iter().enumerate().flat_map(|(y, r)| {
-            r.iter()
-                .enumerate()
-                .map(move |(x, c)| (y * bounds.true_bounds.x as usize + x, c))
-        });
-
-        for (ix, data) in cells {
-            material.data[ix] = *data as u32;
+    let now = time.elapsed_seconds();
+    let clears = clears.read().collect_vec();
+
+    for (id, board, bounds, settings, ch) in board.iter() {
+        let child = ch.iter().copied().find(|&c| children.contains(c));
+        let Some((material_id, mut memory)) = child.and_then(|c| children.get_mut(c).ok()) else {
+            warn_missing_child(&mut warned, id, "matrix sprite");
+            continue;
+        };
+        let width = bounds.true_bounds.x as usize;
+
+        for clear in clears.iter().filter(|c| c.board == id) {
+            for &row in clear.rows.iter().rev() {
+                memory.forget_row(row, width);
+            }
+            memory.revealed_until = now + REVEAL_DURATION;
+        }
+
+        if board.is_changed() {
+            for (ix, &kind) in board.cells().iter().enumerate() {
+                if memory.kinds[ix] != kind {
+                    memory.kinds[ix] = kind;
+                    memory.placed_at[ix] = now;
+                }
+            }
+        }
+
+        // replays (and anything outside of live play) are always shown in full
+        let visibility = if *state.get() == MainState::Playing {
+            settings.stack_visibility
+        } else {
+            StackVisibility::Normal
+        };
+        let hidden = |ix: usize| match visibility {
+            StackVisibility::Normal => false,
+            StackVisibility::Invisible => now > memory.revealed_until,
+            StackVisibility::Fading => now - memory.placed_at[ix] > settings.fade_delay,
+        };
+
+        // the cells only have to be worked out again when the stack changes, or while they fade or
+        // are revealed, since then they change with time alone
+        let timed = match visibility {
+            StackVisibility::Normal => false,
+            StackVisibility::Invisible => now - time.delta_seconds() <= memory.revealed_until,
+            StackVisibility::Fading => true,
+        };
+        let changed = board.is_changed()
+            || memory.is_changed()
+            || settings.is_changed()
+            || state.is_changed();
+        if !(timed || changed) {
+            continue;
+        }
+
+        let drawn = memory
+            .kinds
+            .iter()
+            .enumerate()
+            .map(|(ix, &kind)| (if hidden(ix) { MinoKind::E } else { kind }) as u32)
+            .collect_vec();
+
+        // only taken mutably when it changes, since that sends the material to the GPU again
+        if material_server
+            .get(material_id)
+            .is_some_and(|material| material.data != drawn)
+        {
+            if let Some(material) = material_server.get_mut(material_id) {
+                material.data = drawn;
+            }
         }
     }
 }
 
 /// Centers the legal part of the matrix rather than the entire matrix.
 pub(crate) fn center_board(
-    boards: Query<(&Bounds, &Children), Changed<Bounds>>,
+    boards: Query<(Entity, &Bounds, &Children), Changed<Bounds>>,
     mut sprites: Query<&mut Transform, With<MatrixSprite>>,
+    mut warned: Local<bool>,
 ) {
-    for (board, children) in boards.iter() {
+    for (id, board, children) in boards.iter() {
         let board_bounds = board.true_bounds.as_vec2();
         let legal_bounds = board.legal_bounds.as_vec2();
         let offset = (board_bounds / 2. - legal_bounds / 2.) * (CELL_SIZE as f32);
 
-        let child = *children.iter().find(|q| sprites.contains(**q)).unwrap();
-        sprites.get_mut(child).unwrap().translation = offset.extend(0.0);
+        let child = children.iter().copied().find(|&c| sprites.contains(c));
+        let Some(mut transform) = child.and_then(|c| sprites.get_mut(c).ok()) else {
+            warn_missing_child(&mut warned, id, "matrix sprite");
+            continue;
+        };
+        transform.translation = offset.extend(0.0);
     }
 }
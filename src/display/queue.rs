@@ -1,37 +1,200 @@
 use bevy::math::ivec2;
 use bevy::{math::vec2, prelude::*};
 use itertools::Itertools;
-use tap::Tap;
 
 use crate::assets::matrix_material::{MatrixMaterial, MatrixMaterialSpawner};
+use crate::assets::tables::shape_table::ShapeTable;
 use crate::assets::tables::QueryShapeTable;
+use crate::board::update::default_mino;
 use crate::board::MinoKind;
+use crate::display::bag::{bag_icon_position, BagIcon};
+use crate::display::hold::{HoldPulse, HoldSprite};
+use crate::display::warn_missing_child;
+use crate::screens::GlobalSettings;
 use crate::{
     assets::tables::shape_table::ShapeParameters,
     board::{queue::PieceQueue, RotationState, CELL_SIZE, MATRIX_DEFAULT_LEGAL_BOUNDS},
 };
 
+/// Number of pieces shown in the queue
+const QUEUE_SLOTS: usize = 5;
+/// Space between the board and the queue beside it, in pixels
+const QUEUE_GAP: Vec2 = vec2(24., 2.);
+
+/// Where the queue is drawn around the board
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    strum::EnumIter,
+    strum::Display,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum QueueLayout {
+    #[default]
+    #[strum(to_string = "Right")]
+    VerticalRight,
+    /// Beside the board on the left, with the hold box moved over to the right
+    #[strum(to_string = "Left")]
+    VerticalLeft,
+    /// In a row above the board, centered over it
+    #[strum(to_string = "Top")]
+    HorizontalTop,
+}
+
+impl QueueLayout {
+    /// The position of the top left corner of the given slot of the queue, relative to the middle
+    /// of the board, for slots of the given size in cells
+    pub fn slot_position(self, slot: usize, size: IVec2) -> Vec2 {
+        let cell = CELL_SIZE as f32;
+        let board = MATRIX_DEFAULT_LEGAL_BOUNDS.as_vec2() / 2. * cell;
+        let space_vert = vec2(0., -cell * (size.y + 1) as f32);
+        let slot = slot as f32;
+        match self {
+            Self::VerticalRight => board + QUEUE_GAP + slot * space_vert,
+            Self::VerticalLeft => {
+                vec2(
+                    -board.x - QUEUE_GAP.x - cell * size.x as f32,
+                    board.y + QUEUE_GAP.y,
+                ) + slot * space_vert
+            }
+            Self::HorizontalTop => {
+                let space_horiz = cell * (size.x + 1) as f32;
+                let width = space_horiz * QUEUE_SLOTS as f32 - cell;
+                vec2(
+                    -width / 2. + slot * space_horiz,
+                    board.y + cell * (size.y + 1) as f32,
+                )
+            }
+        }
+    }
+
+    /// The position of the top right corner of the hold box, relative to the middle of the board,
+    /// for a box of the given size in cells
+    pub fn hold_position(self, size: IVec2) -> Vec2 {
+        let cell = CELL_SIZE as f32;
+        let board = MATRIX_DEFAULT_LEGAL_BOUNDS.as_vec2() / 2. * cell;
+        match self {
+            Self::VerticalLeft => vec2(board.x + cell * size.x as f32, board.y),
+            Self::VerticalRight | Self::HorizontalTop => vec2(-board.x, board.y),
+        }
+    }
+
+    /// Whether the layout swaps the sides of the queue and the hold box, so that the rest of the
+    /// display (such as the progress bar of the replay) should swap sides as well
+    pub fn mirrored(self) -> bool {
+        self == Self::VerticalLeft
+    }
+}
+
+/// Which way the pieces in the hold box and the queue are turned
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    strum::EnumIter,
+    strum::Display,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum PreviewOrientation {
+    /// As the shape table draws each piece without rotation
+    #[default]
+    Up,
+    Right,
+    Down,
+    Left,
+    /// As each piece is turned when it spawns
+    Spawn,
+}
+
+impl PreviewOrientation {
+    /// The rotation that the given piece is previewed in
+    pub fn rotation(self, kind: MinoKind, shape_table: &ShapeTable) -> RotationState {
+        match self {
+            Self::Up => RotationState::Up,
+            Self::Right => RotationState::Right,
+            Self::Down => RotationState::Down,
+            Self::Left => RotationState::Left,
+            Self::Spawn => default_mino(kind, shape_table).rotation,
+        }
+    }
+
+    /// The size in cells of a preview box which fits every piece of the shape table in this
+    /// orientation. The rotations of a piece need not all be the same size, so each piece is
+    /// measured in the rotation it is previewed in.
+    pub fn box_size(self, shape_table: &ShapeTable) -> IVec2 {
+        shape_table
+            .kinds()
+            .into_iter()
+            .map(|kind| {
+                let rotation = self.rotation(kind, shape_table);
+                shape_table
+                    .bounds(|p| p.kind == kind && p.rotation == rotation)
+                    .size()
+            })
+            .fold(IVec2::ONE, IVec2::max)
+    }
+}
+
+/// Draws the given piece into a preview box, turned by the given orientation and centered in the
+/// box (leaning left and down where it cannot be centered exactly). The cells of the piece are
+/// filled with the given kind, and the rest of the box is emptied.
+pub fn draw_preview(
+    material: &mut MatrixMaterial,
+    kind: MinoKind,
+    fill: MinoKind,
+    orientation: PreviewOrientation,
+    shape_table: &ShapeTable,
+) {
+    let rotation = orientation.rotation(kind, shape_table);
+    let bounds = shape_table.bounds(|p| p.kind == kind && p.rotation == rotation);
+    let size = material.dimensions.as_ivec2();
+    let offset = (size - bounds.size()) / 2 - bounds.min;
+
+    material.data.fill(MinoKind::E as u32);
+    for &p in &shape_table[ShapeParameters { kind, rotation }] {
+        let loc = p + offset;
+        if loc.cmpge(IVec2::ZERO).all() && loc.cmplt(size).all() {
+            material.data[(loc.y * size.x + loc.x) as usize] = fill as u32;
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct QueueSprite(usize);
 
+/// The bounds of each slot of the queue, which are anchored by their top left corner
+fn slot_bounds(size: IVec2) -> IRect {
+    IRect::from_corners(IVec2::ZERO, size * ivec2(1, -1))
+}
+
+/// The bounds of the hold box, which is anchored by its top right corner
+pub(crate) fn hold_bounds(size: IVec2) -> IRect {
+    IRect::from_corners(-size, IVec2::ZERO)
+}
+
 pub(crate) fn spawn_queue_sprite(
     mut commands: Commands,
     mut spawner: MatrixMaterialSpawner,
     shape_table: QueryShapeTable,
     boards: Query<Entity, Added<PieceQueue>>,
+    settings: Res<GlobalSettings>,
 ) {
-    let bounds = shape_table
-        .bounds(|&ShapeParameters { rotation, .. }| rotation == RotationState::Up)
-        .tap_mut(|r| *r = IRect::from_corners(IVec2::ZERO, r.size() * ivec2(1, -1)));
-
-    let offset = MATRIX_DEFAULT_LEGAL_BOUNDS.as_vec2() / 2. * (CELL_SIZE as f32);
-    let space_horiz = vec2(24., 2.);
-    let space_vert = vec2(0., -(CELL_SIZE as f32 * (bounds.size().y + 1) as f32));
+    let size = settings.preview_orientation.box_size(&shape_table);
+    let bounds = slot_bounds(size);
 
     for e in boards.iter() {
-        let queue_sprites = (0..5)
+        let queue_sprites = (0..QUEUE_SLOTS)
             .map(|i| {
-                let transform = (offset + space_horiz + (i as f32) * space_vert).extend(0.);
+                let transform = settings.queue_layout.slot_position(i, size).extend(0.);
                 spawner
                     .spawn(bounds)
                     .insert((Transform::from_translation(transform), QueueSprite(i)))
@@ -45,43 +208,106 @@ pub(crate) fn spawn_queue_sprite(
     }
 }
 
+/// Moves the queue, the hold box and the bag tracker under it to where the chosen layout puts them,
+/// when the layout changes before the game starts. Each layout anchors the slots by the same
+/// corner, so the slots only need new meshes when the preview orientation changes, in which case
+/// the queue slots and the hold box are resized to fit the pieces as they are now turned.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub(crate) fn relayout_queue(
+    settings: Res<GlobalSettings>,
+    shape_table: QueryShapeTable,
+    mut spawner: MatrixMaterialSpawner,
+    mut queue_sprites: Query<
+        (
+            Entity,
+            &mut Transform,
+            &QueueSprite,
+            &Handle<MatrixMaterial>,
+        ),
+        Without<HoldSprite>,
+    >,
+    mut hold_sprites: Query<
+        (Entity, &mut Transform, &Handle<MatrixMaterial>, &Children),
+        (With<HoldSprite>, Without<BagIcon>),
+    >,
+    mut pulses: Query<
+        (&mut Transform, &mut Sprite, &HoldPulse),
+        (Without<HoldSprite>, Without<QueueSprite>, Without<BagIcon>),
+    >,
+    mut bag_icons: Query<(&mut Transform, &BagIcon), (Without<HoldSprite>, Without<QueueSprite>)>,
+    mut last: Local<Option<(QueueLayout, PreviewOrientation)>>,
+) {
+    let (layout, orientation) = (settings.queue_layout, settings.preview_orientation);
+    let resized = last.is_some_and(|(_, o)| o != orientation);
+    if *last == Some((layout, orientation)) {
+        return;
+    }
+    *last = Some((layout, orientation));
+
+    let size = orientation.box_size(&shape_table);
+    for (e, mut transform, QueueSprite(i), material) in queue_sprites.iter_mut() {
+        transform.translation = layout
+            .slot_position(*i, size)
+            .extend(transform.translation.z);
+        if resized {
+            spawner.reshape(e, material, slot_bounds(size));
+        }
+    }
+    for (e, mut transform, material, children) in hold_sprites.iter_mut() {
+        transform.translation = layout.hold_position(size).extend(transform.translation.z);
+        if resized {
+            spawner.reshape(e, material, hold_bounds(size));
+            for &child in children.iter() {
+                if let Ok((mut transform, mut sprite, pulse)) = pulses.get_mut(child) {
+                    pulse.fit(&mut transform, &mut sprite, hold_bounds(size));
+                }
+            }
+        }
+    }
+    for (mut transform, icon) in bag_icons.iter_mut() {
+        transform.translation =
+            bag_icon_position(*layout, icon.slot(), size).extend(transform.translation.z);
+    }
+}
+
 // TODO: This function does not react to changes to queue window size
 // TODO: This function does not react to changes in matrix bounds
 /// Updates the visual state of the piece queue. When the queue changes, each piece in the queue has
 /// its texture updated to match its intended state.
 pub(crate) fn display_queue(
-    queue: Query<(&PieceQueue, &Children), Changed<PieceQueue>>,
-    mut sprites: Query<(&Handle<MatrixMaterial>, &QueueSprite)>,
+    queue: Query<(Entity, Ref<PieceQueue>, &Children)>,
+    sprites: Query<(&Handle<MatrixMaterial>, &QueueSprite)>,
     mut mats: ResMut<Assets<MatrixMaterial>>,
     shape_table: QueryShapeTable,
+    settings: Res<GlobalSettings>,
+    mut warned: Local<bool>,
 ) {
-    let bounds =
-        shape_table.bounds(|&ShapeParameters { rotation, .. }| rotation == RotationState::Up);
-    let matrix_size = bounds.size().x;
+    for (board, queue, children) in queue.iter() {
+        if !(queue.is_changed() || settings.is_changed()) {
+            continue;
+        }
 
-    for (queue, children) in queue.iter() {
-        for e in children
+        let slots = children
             .iter()
-            .copied()
-            .filter(|&e| sprites.contains(e))
-            .collect_vec()
-        {
-            let (mat, QueueSprite(n)) = sprites.get_mut(e).unwrap();
-            let material = mats.get_mut(mat).unwrap();
+            .filter_map(|&e| sprites.get(e).ok())
+            .collect_vec();
+        if slots.is_empty() {
+            warn_missing_child(&mut warned, board, "queue sprites");
+            continue;
+        }
+        for (mat, QueueSprite(n)) in slots {
+            let Some(material) = mats.get_mut(mat) else {
+                continue;
+            };
 
             let kind = queue.window()[*n];
-            let selector = ShapeParameters {
-                rotation: RotationState::Up,
+            draw_preview(
+                material,
                 kind,
-            };
-            let shape = &shape_table[selector];
-
-            material.data.fill(MinoKind::E as u32);
-            for &p in shape {
-                let loc = p - bounds.min;
-                let ix = loc.y * matrix_size + loc.x;
-                material.data[ix as usize] = kind as u32;
-            }
+                kind,
+                settings.preview_orientation,
+                &shape_table,
+            );
         }
     }
 }
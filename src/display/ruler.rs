@@ -0,0 +1,87 @@
+use bevy::math::vec2;
+use bevy::prelude::*;
+
+use crate::board::{Bounds, CELL_SIZE};
+use crate::screens::GlobalSettings;
+
+const RULER_FONT_SIZE: f32 = 12.0;
+/// Dim enough to stay out of the way during live play
+const RULER_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.35);
+/// Distance from the edge of the matrix to the center of the labels
+const RULER_GAP: f32 = 20.0;
+
+/// Holds the column and row labels of a board. The labels are numbered the same way as the cells
+/// of the matrix, starting from zero at the bottom left.
+#[derive(Component)]
+pub struct Ruler;
+
+/// Builds the labels for each board from its bounds, rebuilding them whenever the bounds change.
+pub(crate) fn spawn_ruler(
+    mut commands: Commands,
+    boards: Query<(Entity, &Bounds, Option<&Children>), Changed<Bounds>>,
+    rulers: Query<Entity, With<Ruler>>,
+) {
+    for (e, bounds, children) in boards.iter() {
+        for &child in children.iter().flat_map(|c| c.iter()) {
+            if rulers.contains(child) {
+                commands.entity(child).despawn_recursive();
+            }
+        }
+
+        let legal = bounds.legal_bounds;
+        let half = legal.as_vec2() / 2. * CELL_SIZE as f32;
+        let style = TextStyle {
+            font_size: RULER_FONT_SIZE,
+            color: RULER_COLOR,
+            ..default()
+        };
+
+        let columns = (0..legal.x).map(|x| {
+            let pos = vec2(
+                (x as f32 + 0.5) * CELL_SIZE as f32 - half.x,
+                -half.y - RULER_GAP,
+            );
+            (x, pos)
+        });
+        let rows = (0..legal.y).map(|y| {
+            let pos = vec2(
+                -half.x - RULER_GAP,
+                (y as f32 + 0.5) * CELL_SIZE as f32 - half.y,
+            );
+            (y, pos)
+        });
+
+        let ruler = commands
+            .spawn((SpatialBundle::HIDDEN_IDENTITY, Ruler))
+            .with_children(|ruler| {
+                for (n, pos) in columns.chain(rows) {
+                    ruler.spawn(Text2dBundle {
+                        text: Text::from_section(n.to_string(), style.clone()),
+                        transform: Transform::from_translation(pos.extend(1.5)),
+                        ..default()
+                    });
+                }
+            })
+            .id();
+
+        commands.entity(e).add_child(ruler);
+    }
+}
+
+/// Shows or hides the labels as the setting is toggled.
+pub(crate) fn update_ruler(
+    mut rulers: Query<(Ref<Ruler>, &mut Visibility)>,
+    settings: Res<GlobalSettings>,
+) {
+    for (ruler, mut vis) in rulers.iter_mut() {
+        if !(settings.is_changed() || ruler.is_added()) {
+            continue;
+        }
+
+        *vis = if settings.rulers {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
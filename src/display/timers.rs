@@ -0,0 +1,165 @@
+//! A debugging overlay of the timers behind gravity and locking, drawn just above the active piece
+//! so that the lock delay can be tuned by watching it run out. Above the timers is what the piece
+//! last did, which decides whether its lock counts as a spin.
+
+use bevy::math::vec2;
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+use crate::assets::tables::QueryShapeTable;
+use crate::board::motion::PieceMotion;
+use crate::board::{Active, Bounds, DropClock, LockReset, Matrix, Settings, CELL_SIZE};
+use crate::replay::ghost::Ghost;
+use crate::screens::GlobalSettings;
+use crate::state::MainState;
+
+const TIMER_BAR_WIDTH: f32 = CELL_SIZE as f32 * 2.0;
+const TIMER_BAR_THICKNESS: f32 = 3.0;
+/// Space between the bars, and between the bars and the top of the active piece
+const TIMER_BAR_GAP: f32 = 3.0;
+const FALL_COLOR: Color = Color::rgb(0.5, 0.7, 1.0);
+const RESETS_FONT_SIZE: f32 = 12.0;
+
+/// What a part of the overlay shows
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum TimerOverlay {
+    /// How far the piece is toward falling another row
+    Fall,
+    /// How much of the lock delay has passed
+    Lock,
+    /// The move resets left before the piece locks regardless
+    Resets,
+    /// The last move of the piece, the kick of its last rotation, and the lowest row it reached
+    Motion,
+}
+
+pub(crate) fn spawn_timer_overlay(
+    mut commands: Commands,
+    boards: Query<Entity, (Added<Matrix>, Without<Ghost>)>,
+) {
+    for e in boards.iter() {
+        let bars = [TimerOverlay::Fall, TimerOverlay::Lock].map(|timer| {
+            commands
+                .spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            anchor: Anchor::BottomLeft,
+                            ..default()
+                        },
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                    timer,
+                ))
+                .id()
+        });
+        let labels = [
+            (TimerOverlay::Resets, Anchor::BottomRight),
+            (TimerOverlay::Motion, Anchor::BottomLeft),
+        ]
+        .map(|(timer, anchor)| {
+            commands
+                .spawn((
+                    Text2dBundle {
+                        text: Text::from_section(
+                            "",
+                            TextStyle {
+                                font_size: RESETS_FONT_SIZE,
+                                ..default()
+                            },
+                        ),
+                        text_anchor: anchor,
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                    timer,
+                ))
+                .id()
+        });
+
+        commands
+            .entity(e)
+            .push_children(&bars)
+            .push_children(&labels);
+    }
+}
+
+/// Fills the bars from the board's drop clock, turning the lock bar red as the lock delay runs
+/// out. The overlay is only shown during live play, so it never appears in screenshots of the
+/// results.
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_timer_overlay(
+    boards: Query<(&Active, &DropClock, &PieceMotion, &Settings, &Bounds)>,
+    mut parts: Query<(
+        &Parent,
+        &TimerOverlay,
+        &mut Transform,
+        &mut Visibility,
+        Option<&mut Sprite>,
+        Option<&mut Text>,
+    )>,
+    shape_table: QueryShapeTable,
+    global_settings: Res<GlobalSettings>,
+    state: Res<State<MainState>>,
+) {
+    let live = *state.get() == MainState::Playing && global_settings.timer_overlay;
+
+    for (parent, &timer, mut transform, mut vis, sprite, text) in parts.iter_mut() {
+        let Ok((active, clock, motion, settings, bounds)) = boards.get(parent.get()) else {
+            continue;
+        };
+        let Some(piece) = active.0.filter(|_| live) else {
+            *vis = Visibility::Hidden;
+            continue;
+        };
+
+        // the overlay sits above the highest cell of the piece, starting from its leftmost cell
+        let cells = &shape_table[piece];
+        let left = cells.iter().map(|c| c.x).min().unwrap_or(0);
+        let top = cells.iter().map(|c| c.y).max().unwrap_or(0) + 1;
+        let corner =
+            (piece.position + IVec2::new(left, top)).as_vec2() - bounds.legal_bounds.as_vec2() / 2.;
+        let corner = corner * CELL_SIZE as f32 + vec2(0.0, TIMER_BAR_GAP);
+
+        let row = match timer {
+            TimerOverlay::Motion => 2.0,
+            TimerOverlay::Fall => 1.0,
+            TimerOverlay::Lock | TimerOverlay::Resets => 0.0,
+        };
+        transform.translation =
+            (corner + vec2(0.0, row * (TIMER_BAR_THICKNESS + TIMER_BAR_GAP))).extend(1.5);
+
+        match (timer, sprite, text) {
+            (TimerOverlay::Fall, Some(mut sprite), _) => {
+                let fall = clock.fall().fract();
+                sprite.custom_size = Some(vec2(TIMER_BAR_WIDTH * fall, TIMER_BAR_THICKNESS));
+                sprite.color = FALL_COLOR;
+            }
+            (TimerOverlay::Lock, Some(mut sprite), _) => {
+                let lock = (clock.lock() / settings.lock_delay.max(f32::EPSILON)).clamp(0.0, 1.0);
+                sprite.custom_size = Some(vec2(TIMER_BAR_WIDTH * lock, TIMER_BAR_THICKNESS));
+                sprite.color = Color::rgb(1.0, 1.0 - lock, 1.0 - lock);
+            }
+            (TimerOverlay::Resets, _, Some(mut text)) => {
+                if settings.lock_reset != LockReset::MoveReset {
+                    *vis = Visibility::Hidden;
+                    continue;
+                }
+                let left = settings.move_reset_limit.saturating_sub(motion.resets());
+                text.sections[0].value = left.to_string();
+                transform.translation.x -= TIMER_BAR_GAP;
+            }
+            (TimerOverlay::Motion, _, Some(mut text)) => {
+                let action = motion
+                    .last_action()
+                    .map_or_else(|| "Spawn".to_string(), |action| action.to_string());
+                let kick = motion
+                    .last_kick()
+                    .map_or_else(String::new, |kick| format!(" ({}, {})", kick.x, kick.y));
+                text.sections[0].value = format!("{action}{kick} low {}", motion.lowest());
+            }
+            _ => (),
+        }
+        *vis = Visibility::Inherited;
+    }
+}
@@ -0,0 +1,320 @@
+//! Options for the images exported from the game, and the watermark which can be drawn onto them.
+//! The watermark is drawn with a small built-in pixel font, so that it needs no font files and looks
+//! the same at any window size.
+
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use smart_default::SmartDefault;
+
+/// How much of the window an export covers
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    strum::EnumIter,
+    strum::Display,
+    Serialize,
+    Deserialize,
+)]
+pub enum ExportCrop {
+    /// Only the matrix, along with a few rows above the legal area
+    #[default]
+    Board,
+    #[strum(to_string = "Full Window")]
+    Window,
+}
+
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    strum::EnumIter,
+    strum::Display,
+    Serialize,
+    Deserialize,
+)]
+pub enum WatermarkCorner {
+    #[strum(to_string = "Top Left")]
+    TopLeft,
+    #[strum(to_string = "Top Right")]
+    TopRight,
+    #[strum(to_string = "Bottom Left")]
+    BottomLeft,
+    #[default]
+    #[strum(to_string = "Bottom Right")]
+    BottomRight,
+}
+
+#[derive(Clone, Debug, PartialEq, SmartDefault, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportSettings {
+    pub crop: ExportCrop,
+    /// Whether the text and indicators drawn around the board are kept in the export. The panels
+    /// of the interface are not part of these, and only the board crop leaves them out.
+    #[default = true]
+    pub include_hud: bool,
+    pub watermark: bool,
+    /// Name written in the watermark, such as the handle of the player
+    pub watermark_name: String,
+    /// Whether the watermark also gives the date that the run ended
+    #[default = true]
+    pub watermark_date: bool,
+    pub watermark_corner: WatermarkCorner,
+}
+
+impl ExportSettings {
+    /// The text of the watermark for a run which ended at the given time (in seconds since the
+    /// unix epoch), or nothing if there is no watermark
+    pub fn watermark_text(&self, ended_at: u64) -> Option<String> {
+        if !self.watermark {
+            return None;
+        }
+        let date = self.watermark_date.then(|| date(ended_at));
+        let text = [Some(self.watermark_name.trim().to_string()), date]
+            .into_iter()
+            .flatten()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        (!text.is_empty()).then_some(text)
+    }
+}
+
+/// The date of the given time (in seconds since the unix epoch) as year, month and day
+fn date(seconds: u64) -> String {
+    // counted in eras of 400 years from the 1st of March in the year 0, so that leap days fall at
+    // the end of each year
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+/// Size of each pixel of the font, in pixels of the image
+const GLYPH_SCALE: u32 = 2;
+/// Space around the text, and between the text and the edges of the image, in pixels of the font
+const WATERMARK_MARGIN: u32 = 2;
+const WATERMARK_COLOR: Rgba<u8> = Rgba([255, 255, 255, 220]);
+const WATERMARK_BACKING: Rgba<u8> = Rgba([0, 0, 0, 140]);
+
+/// The rows of the given character in the pixel font, from the top down, with the leftmost pixel
+/// in the highest of the five bits. Letters are drawn as capitals, and characters the font lacks
+/// are drawn as question marks.
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        'A' => [
+            0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+        ],
+        'B' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110,
+        ],
+        'C' => [
+            0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110,
+        ],
+        'D' => [
+            0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110,
+        ],
+        'E' => [
+            0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111,
+        ],
+        'F' => [
+            0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000,
+        ],
+        'G' => [
+            0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111,
+        ],
+        'H' => [
+            0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+        ],
+        'I' => [
+            0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+        'J' => [
+            0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100,
+        ],
+        'K' => [
+            0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001,
+        ],
+        'L' => [
+            0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111,
+        ],
+        'M' => [
+            0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001,
+        ],
+        'N' => [
+            0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001,
+        ],
+        'O' => [
+            0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+        ],
+        'P' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000,
+        ],
+        'Q' => [
+            0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101,
+        ],
+        'R' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001,
+        ],
+        'S' => [
+            0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110,
+        ],
+        'T' => [
+            0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100,
+        ],
+        'U' => [
+            0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+        ],
+        'V' => [
+            0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100,
+        ],
+        'W' => [
+            0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010,
+        ],
+        'X' => [
+            0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001,
+        ],
+        'Y' => [
+            0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100,
+        ],
+        'Z' => [
+            0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111,
+        ],
+        '0' => [
+            0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110,
+        ],
+        '1' => [
+            0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+        '2' => [
+            0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111,
+        ],
+        '3' => [
+            0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110,
+        ],
+        '4' => [
+            0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010,
+        ],
+        '5' => [
+            0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110,
+        ],
+        '6' => [
+            0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110,
+        ],
+        '7' => [
+            0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000,
+        ],
+        '8' => [
+            0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110,
+        ],
+        '9' => [
+            0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100,
+        ],
+        ' ' => [0; 7],
+        '-' => [
+            0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000,
+        ],
+        '.' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100,
+        ],
+        ':' => [
+            0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000,
+        ],
+        '/' => [
+            0b00001, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b10000,
+        ],
+        '_' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111,
+        ],
+        '@' => [
+            0b01110, 0b10001, 0b00001, 0b01101, 0b10101, 0b10101, 0b01110,
+        ],
+        '#' => [
+            0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010,
+        ],
+        _ => [
+            0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100,
+        ],
+    }
+}
+
+/// Blends the given color over a pixel of the image, by the alpha of the color
+fn blend(image: &mut RgbaImage, x: u32, y: u32, color: Rgba<u8>) {
+    let Some(pixel) = image.get_pixel_mut_checked(x, y) else {
+        return;
+    };
+    let alpha = color[3] as u32;
+    for channel in 0..3 {
+        let mixed = (color[channel] as u32 * alpha + pixel[channel] as u32 * (255 - alpha)) / 255;
+        pixel[channel] = mixed as u8;
+    }
+    pixel[3] = pixel[3].max(color[3]);
+}
+
+/// Draws the given text into a corner of the image, in white over a dark backing so that it can be
+/// read over the board. Text too wide for the image is cut off at the far side.
+pub fn draw_watermark(image: &mut RgbaImage, text: &str, corner: WatermarkCorner) {
+    let chars = text.chars().count() as u32;
+    // one pixel of the font between the characters
+    let text_width = (chars * (GLYPH_WIDTH + 1)).saturating_sub(1) * GLYPH_SCALE;
+    let text_height = GLYPH_HEIGHT * GLYPH_SCALE;
+    let margin = WATERMARK_MARGIN * GLYPH_SCALE;
+    let (box_width, box_height) = (text_width + 2 * margin, text_height + 2 * margin);
+
+    let left = match corner {
+        WatermarkCorner::TopLeft | WatermarkCorner::BottomLeft => margin,
+        WatermarkCorner::TopRight | WatermarkCorner::BottomRight => {
+            image.width().saturating_sub(box_width + margin)
+        }
+    };
+    let top = match corner {
+        WatermarkCorner::TopLeft | WatermarkCorner::TopRight => margin,
+        WatermarkCorner::BottomLeft | WatermarkCorner::BottomRight => {
+            image.height().saturating_sub(box_height + margin)
+        }
+    };
+
+    for y in top..top + box_height {
+        for x in left..left + box_width {
+            blend(image, x, y, WATERMARK_BACKING);
+        }
+    }
+
+    let (left, top) = (left + margin, top + margin);
+    for (ix, c) in text.chars().enumerate() {
+        let glyph_left = left + ix as u32 * (GLYPH_WIDTH + 1) * GLYPH_SCALE;
+        for (row, bits) in glyph(c).into_iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - column)) == 0 {
+                    continue;
+                }
+                let (x, y) = (
+                    glyph_left + column * GLYPH_SCALE,
+                    top + row as u32 * GLYPH_SCALE,
+                );
+                for (dx, dy) in itertools::iproduct!(0..GLYPH_SCALE, 0..GLYPH_SCALE) {
+                    blend(image, x + dx, y + dy, WATERMARK_COLOR);
+                }
+            }
+        }
+    }
+}
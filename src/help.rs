@@ -0,0 +1,163 @@
+//! An overlay listing every key that the game responds to, grouped by where each key applies. The
+//! list is built from the keybinds of the active profile and from the hotkey tables kept beside the
+//! keys themselves, so that it always shows the keys as they really are.
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use itertools::Itertools;
+use strum::IntoEnumIterator;
+
+use crate::controller::keybinds::{Action, BindingContext, Hotkey, KeyLayout};
+use crate::controller::profiles::{self, Profiles};
+use crate::kick_editor::KickEditor;
+use crate::state::MainState;
+use crate::{kick_editor, pause, replay, save_slots, screens, screenshot_import, window};
+
+/// Opens and closes the help overlay, as does typing a question mark
+pub const HELP_KEY: KeyCode = KeyCode::F1;
+
+const HOTKEYS: &[Hotkey] = &[Hotkey {
+    keys: &[HELP_KEY],
+    name: "Help",
+    context: BindingContext::Anywhere,
+}];
+
+/// The hotkey tables of every module, in the order their keys are listed
+const HOTKEY_TABLES: &[&[Hotkey]] = &[
+    HOTKEYS,
+    screens::HOTKEYS,
+    profiles::HOTKEYS,
+    save_slots::HOTKEYS,
+    pause::HOTKEYS,
+    replay::record::HOTKEYS,
+    replay::replay::HOTKEYS,
+    replay::bookmarks::HOTKEYS,
+    replay::chapter::HOTKEYS,
+    replay::focus::HOTKEYS,
+    replay::trail::HOTKEYS,
+    kick_editor::HOTKEYS,
+    screenshot_import::HOTKEYS,
+    window::HOTKEYS,
+];
+
+/// Whether the help overlay is open
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct HelpOverlay(bool);
+
+fn toggle_help(
+    mut shown: ResMut<HelpOverlay>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut events: EventReader<KeyboardInput>,
+) {
+    let question_mark = events.read().any(|e| {
+        e.state == ButtonState::Pressed
+            && matches!(&e.logical_key, Key::Character(c) if c.as_str() == "?")
+    });
+    if keys.just_pressed(HELP_KEY) || question_mark {
+        **shown = !**shown;
+    } else if keys.just_pressed(KeyCode::Escape) {
+        **shown = false;
+    }
+}
+
+/// Whether the keys of the given context do anything at the moment
+fn context_active(context: BindingContext, state: &MainState, editor: &KickEditor) -> bool {
+    match context {
+        BindingContext::Anywhere => true,
+        BindingContext::Ready => *state == MainState::Ready,
+        BindingContext::Playing => *state == MainState::Playing,
+        BindingContext::Replay => *state == MainState::PostGame,
+        BindingContext::Editor => editor.is_open(),
+    }
+}
+
+/// Dims the game and lists the keys over it. The keys which do nothing at the moment are still
+/// listed, but faded.
+fn help_overlay(
+    mut contexts: EguiContexts,
+    mut shown: ResMut<HelpOverlay>,
+    profiles: Res<Profiles>,
+    layout: Res<KeyLayout>,
+    state: Res<State<MainState>>,
+    editor: Res<KickEditor>,
+) {
+    if !**shown {
+        return;
+    }
+    let ctx = contexts.ctx_mut();
+    let screen = ctx.screen_rect();
+    ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("help_dim"),
+    ))
+    .rect_filled(screen, 0.0, egui::Color32::from_black_alpha(160));
+
+    let keybinds = &profiles.active().keybinds;
+    egui::Area::new("help")
+        .order(egui::Order::Tooltip)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            egui::Frame::window(ui.style()).show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading("Keys");
+                    if ui.button("Close").clicked() {
+                        **shown = false;
+                    }
+                });
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .max_height(screen.height() * 0.75)
+                    .show(ui, |ui| {
+                        for context in BindingContext::iter() {
+                            let active = context_active(context, state.get(), &editor);
+                            let text = |s: String| {
+                                let text = egui::RichText::new(s);
+                                if active {
+                                    text
+                                } else {
+                                    text.weak()
+                                }
+                            };
+
+                            let bound = Action::iter()
+                                .filter(|action| action.context() == context)
+                                .map(|action| {
+                                    (action.to_string(), keybinds.describe(action, &layout))
+                                });
+                            let fixed = HOTKEY_TABLES
+                                .iter()
+                                .flat_map(|table| table.iter())
+                                .filter(|hotkey| hotkey.context == context)
+                                .map(|hotkey| {
+                                    let keys = hotkey.keys.iter().map(|k| format!("{k:?}"));
+                                    (hotkey.name.to_string(), keys.join(", "))
+                                });
+
+                            ui.label(text(context.to_string()).strong());
+                            egui::Grid::new(("help_keys", context as u8))
+                                .num_columns(2)
+                                .show(ui, |ui| {
+                                    for (name, keys) in bound.chain(fixed) {
+                                        ui.label(text(name));
+                                        ui.label(text(keys).monospace());
+                                        ui.end_row();
+                                    }
+                                });
+                            ui.add_space(8.0);
+                        }
+                    });
+            });
+        });
+}
+
+pub struct HelpPlugin;
+
+impl Plugin for HelpPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HelpOverlay>()
+            .add_systems(Update, (toggle_help, help_overlay).chain());
+    }
+}
@@ -0,0 +1,397 @@
+//! An editor for the kick table, with a small board for trying out the kicks as they are edited.
+
+use bevy::math::ivec2;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy_egui::{egui, EguiContexts};
+use strum::IntoEnumIterator;
+
+use crate::assets::palette::Palette;
+use crate::assets::tables::kick_table::{
+    DefaultKickTable, KickParameters, KickTable, DEFAULT_KICK_TABLE_FILE,
+};
+use crate::assets::tables::shape_table::ShapeTable;
+use crate::assets::tables::QueryShapeTable;
+use crate::board::update::{has_free_space, kick_search};
+use crate::board::{Matrix, Mino, MinoKind, RotationState};
+use crate::controller::keybinds::{BindingContext, Hotkey};
+use crate::state::assets_loaded;
+
+const EDITOR_TOGGLE_KEY: KeyCode = KeyCode::F7;
+
+pub(crate) const HOTKEYS: &[Hotkey] = &[Hotkey {
+    keys: &[EDITOR_TOGGLE_KEY],
+    name: "Open or Close",
+    context: BindingContext::Editor,
+}];
+const PREVIEW_SIZE: IVec2 = IVec2::new(10, 8);
+/// Size of each cell of the preview board, in points
+const PREVIEW_CELL_SIZE: f32 = 16.0;
+
+#[derive(Resource)]
+pub struct KickEditor {
+    open: bool,
+    /// The table being edited, which only replaces the loaded table once it is saved
+    table: Option<KickTable>,
+    kind: MinoKind,
+    from: RotationState,
+    to: RotationState,
+    /// The preview board, in which only garbage is ever placed
+    obstructions: Matrix,
+    /// Where the piece sits on the preview board before it is rotated
+    position: IVec2,
+    /// Problems found with the table when it was last saved, which must be confirmed before the
+    /// table is actually saved
+    issues: Vec<String>,
+    status: Option<String>,
+}
+
+impl Default for KickEditor {
+    fn default() -> Self {
+        Self {
+            open: false,
+            table: None,
+            kind: MinoKind::T,
+            from: RotationState::Up,
+            to: RotationState::Right,
+            obstructions: Matrix::new(PREVIEW_SIZE),
+            position: ivec2(4, 3),
+            issues: Vec::new(),
+            status: None,
+        }
+    }
+}
+
+impl KickEditor {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+}
+
+enum KickAction {
+    Raise(usize),
+    Lower(usize),
+    Remove(usize),
+}
+
+fn toggle_kick_editor(mut editor: ResMut<KickEditor>, keys: Res<ButtonInput<KeyCode>>) {
+    if keys.just_pressed(EDITOR_TOGGLE_KEY) {
+        editor.open = !editor.open;
+    }
+}
+
+/// Lists the problems with the table which should be confirmed before saving it: transitions
+/// without any kicks, and transitions which try the same offset more than once. Rotating in place
+/// is always tried first, so a kick of `(0, 0)` counts as a repeat.
+fn validate(table: &KickTable) -> Vec<String> {
+    let mut entries = table.0.iter().collect::<Vec<_>>();
+    entries.sort_by_key(|(params, _)| (params.kind as u32, params.from, params.to));
+
+    let mut issues = Vec::new();
+    for (params, kicks) in entries {
+        let name = format!("{:?} {:?} to {:?}", params.kind, params.from, params.to);
+        if kicks.is_empty() {
+            issues.push(format!("{name} has no kicks"));
+        }
+
+        let mut seen = HashSet::from([IVec2::ZERO]);
+        for kick in kicks {
+            if !seen.insert(*kick) {
+                issues.push(format!(
+                    "{name} tries ({}, {}) more than once",
+                    kick.x, kick.y
+                ));
+            }
+        }
+    }
+    issues
+}
+
+/// Writes the table over the default kick table, and replaces the loaded table so that the new
+/// kicks are used straight away. Returns a message saying how it went.
+fn save(table: &KickTable, handle: &Handle<KickTable>, assets: &mut Assets<KickTable>) -> String {
+    match std::fs::write(DEFAULT_KICK_TABLE_FILE, table.to_ron()) {
+        Ok(()) => {
+            assets.insert(handle.id(), table.clone());
+            format!("Saved to {DEFAULT_KICK_TABLE_FILE}")
+        }
+        Err(e) => format!("Could not save kick table: {e}"),
+    }
+}
+
+fn kick_editor_window(
+    mut contexts: EguiContexts,
+    mut editor: ResMut<KickEditor>,
+    default_table: Res<DefaultKickTable>,
+    mut kick_tables: ResMut<Assets<KickTable>>,
+    shape_table: QueryShapeTable,
+    palette: Res<Palette>,
+) {
+    if !editor.open {
+        return;
+    }
+
+    let KickEditor {
+        open,
+        table,
+        kind,
+        from,
+        to,
+        obstructions,
+        position,
+        issues,
+        status,
+    } = &mut *editor;
+    let table = table.get_or_insert_with(|| {
+        kick_tables
+            .get(default_table.handle())
+            .expect("the kick table is loaded before the editor can open")
+            .clone()
+    });
+    let kinds = shape_table.kinds();
+    if !kinds.contains(kind) {
+        *kind = kinds[0];
+    }
+
+    egui::Window::new("Kick Table Editor")
+        .open(open)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_label("Piece")
+                    .selected_text(format!("{kind:?}"))
+                    .show_ui(ui, |ui| {
+                        for &k in &kinds {
+                            ui.selectable_value(kind, k, format!("{k:?}"));
+                        }
+                    });
+                egui::ComboBox::from_label("From")
+                    .selected_text(format!("{from:?}"))
+                    .show_ui(ui, |ui| {
+                        for r in RotationState::iter() {
+                            ui.selectable_value(from, r, format!("{r:?}"));
+                        }
+                    });
+                if *to == *from {
+                    *to = from.rotate_right();
+                }
+                egui::ComboBox::from_label("To")
+                    .selected_text(format!("{to:?}"))
+                    .show_ui(ui, |ui| {
+                        for r in RotationState::iter().filter(|r| r != from) {
+                            ui.selectable_value(to, r, format!("{r:?}"));
+                        }
+                    });
+            });
+
+            let params = KickParameters {
+                kind: *kind,
+                from: *from,
+                to: *to,
+            };
+            let existing = table.0.get(&params).cloned();
+            let mut kicks = existing.clone().unwrap_or_default();
+
+            ui.separator();
+            let mut action = None;
+            egui::Grid::new("kick_editor_list").show(ui, |ui| {
+                let count = kicks.len();
+                for (i, kick) in kicks.iter_mut().enumerate() {
+                    ui.label((i + 1).to_string());
+                    ui.add(egui::DragValue::new(&mut kick.x).prefix("x: "));
+                    ui.add(egui::DragValue::new(&mut kick.y).prefix("y: "));
+                    if ui.add_enabled(i > 0, egui::Button::new("⏶")).clicked() {
+                        action = Some(KickAction::Raise(i));
+                    }
+                    if ui
+                        .add_enabled(i + 1 < count, egui::Button::new("⏷"))
+                        .clicked()
+                    {
+                        action = Some(KickAction::Lower(i));
+                    }
+                    if ui.button("✖").clicked() {
+                        action = Some(KickAction::Remove(i));
+                    }
+                    ui.end_row();
+                }
+            });
+            match action {
+                Some(KickAction::Raise(i)) => kicks.swap(i - 1, i),
+                Some(KickAction::Lower(i)) => kicks.swap(i, i + 1),
+                Some(KickAction::Remove(i)) => {
+                    kicks.remove(i);
+                }
+                None => (),
+            }
+
+            let mut removed = false;
+            ui.horizontal(|ui| {
+                if ui.button("Add Kick").clicked() {
+                    kicks.push(IVec2::ZERO);
+                }
+                removed = ui
+                    .add_enabled(existing.is_some(), egui::Button::new("Remove Transition"))
+                    .clicked();
+            });
+            if removed {
+                table.0.remove(&params);
+                kicks.clear();
+            } else if existing.as_ref() != Some(&kicks) && !(existing.is_none() && kicks.is_empty())
+            {
+                table.0.insert(params, kicks.clone());
+            }
+
+            ui.separator();
+            ui.label("Left click to place blocks, right click to move the piece");
+            let piece = Mino {
+                kind: *kind,
+                position: *position,
+                rotation: *from,
+            };
+            let result = kick_search(obstructions, piece, *to, &kicks, &shape_table);
+            preview_board(
+                ui,
+                obstructions,
+                position,
+                piece,
+                result.map(|(_, m)| m),
+                &shape_table,
+                &palette,
+            );
+
+            if !has_free_space(obstructions, piece, &shape_table) {
+                ui.label("The piece overlaps the blocks, or leaves the board");
+            }
+            ui.label(match result {
+                Some((0, _)) => "Rotates without a kick".to_string(),
+                Some((n, _)) => {
+                    let kick = kicks[n - 1];
+                    format!("Uses kick {n}: ({}, {})", kick.x, kick.y)
+                }
+                None => "The rotation fails".to_string(),
+            });
+            if ui.button("Clear Blocks").clicked() {
+                *obstructions = Matrix::new(PREVIEW_SIZE);
+            }
+
+            ui.separator();
+            if issues.is_empty() {
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        *issues = validate(table);
+                        if issues.is_empty() {
+                            *status = Some(save(table, default_table.handle(), &mut kick_tables));
+                        }
+                    }
+                    if ui.button("Revert").clicked() {
+                        if let Some(loaded) = kick_tables.get(default_table.handle()) {
+                            *table = loaded.clone();
+                        }
+                        *status = None;
+                    }
+                });
+            } else {
+                ui.colored_label(egui::Color32::YELLOW, "The table has some problems:");
+                for issue in issues.iter() {
+                    ui.label(issue);
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Save Anyway").clicked() {
+                        *status = Some(save(table, default_table.handle(), &mut kick_tables));
+                        issues.clear();
+                    }
+                    if ui.button("Cancel").clicked() {
+                        issues.clear();
+                    }
+                });
+            }
+            if let Some(status) = status {
+                ui.label(status.as_str());
+            }
+        });
+}
+
+/// Draws the preview board with the piece before rotating outlined, and the piece after rotating
+/// filled in. Clicking on the board places blocks or moves the piece.
+fn preview_board(
+    ui: &mut egui::Ui,
+    obstructions: &mut Matrix,
+    position: &mut IVec2,
+    piece: Mino,
+    rotated: Option<Mino>,
+    shape_table: &ShapeTable,
+    palette: &Palette,
+) {
+    let size = PREVIEW_SIZE.as_vec2() * PREVIEW_CELL_SIZE;
+    let (response, painter) = ui.allocate_painter(egui::vec2(size.x, size.y), egui::Sense::click());
+    let rect = response.rect;
+
+    let cell_rect = |cell: IVec2| {
+        egui::Rect::from_min_size(
+            egui::pos2(
+                rect.min.x + cell.x as f32 * PREVIEW_CELL_SIZE,
+                rect.max.y - (cell.y + 1) as f32 * PREVIEW_CELL_SIZE,
+            ),
+            egui::Vec2::splat(PREVIEW_CELL_SIZE),
+        )
+    };
+    let color = |kind: MinoKind, alpha: u8| {
+        let [r, g, b, _] = palette.color(kind).as_rgba_u8();
+        egui::Color32::from_rgba_unmultiplied(r, g, b, alpha)
+    };
+
+    painter.rect_filled(rect, 0.0, egui::Color32::from_gray(20));
+    for y in 0..PREVIEW_SIZE.y {
+        for x in 0..PREVIEW_SIZE.x {
+            let cell = ivec2(x, y);
+            let r = cell_rect(cell).shrink(1.0);
+            if obstructions.get(cell) == Some(MinoKind::G) {
+                painter.rect_filled(r, 0.0, egui::Color32::GRAY);
+            } else {
+                painter.rect_stroke(r, 0.0, (1.0, egui::Color32::from_gray(40)));
+            }
+        }
+    }
+    if let Some(rotated) = rotated {
+        for &offset in &shape_table[rotated] {
+            let r = cell_rect(offset + rotated.position).shrink(1.0);
+            painter.rect_filled(r, 0.0, color(rotated.kind, 160));
+        }
+    }
+    for &offset in &shape_table[piece] {
+        let r = cell_rect(offset + piece.position).shrink(2.0);
+        painter.rect_stroke(r, 0.0, (2.0, color(piece.kind, 255)));
+    }
+
+    let Some(pointer) = response.interact_pointer_pos() else {
+        return;
+    };
+    let cell = ivec2(
+        ((pointer.x - rect.min.x) / PREVIEW_CELL_SIZE) as i32,
+        ((rect.max.y - pointer.y) / PREVIEW_CELL_SIZE) as i32,
+    );
+    if response.clicked() {
+        if let Some(kind) = obstructions.get_mut(cell) {
+            *kind = if *kind == MinoKind::G {
+                MinoKind::E
+            } else {
+                MinoKind::G
+            };
+        }
+    } else if response.secondary_clicked() {
+        *position = cell;
+    }
+}
+
+pub struct KickEditorPlugin;
+
+impl Plugin for KickEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<KickEditor>().add_systems(
+            Update,
+            (toggle_kick_editor, kick_editor_window)
+                .chain()
+                .run_if(assets_loaded),
+        );
+    }
+}
@@ -2,21 +2,37 @@ use bevy::app::PluginGroupBuilder;
 use bevy::prelude::PluginGroup;
 
 pub mod animation;
+pub mod api;
 pub mod assets;
 pub mod board;
+pub mod config;
+pub mod confirm;
+pub mod controller;
 pub mod display;
+pub mod export;
+pub mod help;
+pub mod kick_editor;
+#[cfg(feature = "overlay")]
+pub mod overlay;
+pub mod pause;
 pub mod replay;
+pub mod save_slots;
+pub mod schedule;
 pub mod screens;
+pub mod screenshot_import;
 pub mod state;
+pub mod stats;
+pub mod toasts;
+pub mod window;
 
-mod controller;
 mod progress_bar;
 
 pub struct StackPracticePlugins;
 
 impl PluginGroup for StackPracticePlugins {
     fn build(self) -> PluginGroupBuilder {
-        PluginGroupBuilder::start::<Self>()
+        let group = PluginGroupBuilder::start::<Self>()
+            .add(schedule::SchedulePlugin)
             .add(progress_bar::ProgressBarPlugin)
             .add(assets::StackingAssetsPlugin)
             .add(controller::ControllerPlugin)
@@ -26,5 +42,17 @@ impl PluginGroup for StackPracticePlugins {
             .add(state::StatePlugin)
             .add(screens::ScreensPlugin)
             .add(animation::AnimationPlugin)
+            .add(stats::StatsPlugin)
+            .add(save_slots::SaveSlotsPlugin)
+            .add(kick_editor::KickEditorPlugin)
+            .add(screenshot_import::ScreenshotImportPlugin)
+            .add(toasts::ToastsPlugin)
+            .add(help::HelpPlugin)
+            .add(pause::PausePlugin)
+            .add(window::WindowOptionsPlugin)
+            .add(confirm::ConfirmPlugin);
+        #[cfg(feature = "overlay")]
+        let group = group.add(overlay::OverlayPlugin);
+        group
     }
 }
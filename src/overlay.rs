@@ -0,0 +1,270 @@
+//! Sends the events of the game to stream overlays, such as browser sources in OBS, over a
+//! WebSocket on this machine. Only built with the `overlay` feature, and only running while turned
+//! on in the settings.
+//!
+//! Each message is a JSON object with a `type` field naming the event, alongside the fields of the
+//! event from [`events`](crate::board::events): `piece_locked`, `lines_cleared`, `hold_used`,
+//! `game_started` and `game_ended`. While a game is being played, a `stats` message is also sent
+//! every second. `examples/overlay_client.html` shows the messages as they come in.
+//!
+//! The server runs on a thread of its own, fed through a bounded channel, so that a slow overlay
+//! never holds up the game. Messages which do not fit into the channel are dropped.
+
+use std::io;
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, RecvTimeoutError, SyncSender, TrySendError};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use smart_default::SmartDefault;
+use tungstenite::{Message, WebSocket};
+
+use crate::board::events::{GameEnded, GameStarted, HoldUsed, LinesCleared, PieceLocked};
+use crate::screens::GlobalSettings;
+use crate::state::MainState;
+use crate::stats::Stats;
+use crate::toasts::Toasts;
+
+/// Messages waiting to be sent before newer ones are dropped
+const CHANNEL_SIZE: usize = 256;
+/// How often the server thread looks for new connections when there is nothing to send
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// How long an overlay can take to finish connecting or to take a message before it is dropped
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(1);
+/// Seconds between stats messages
+const STATS_INTERVAL: f32 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, SmartDefault, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OverlaySettings {
+    pub enabled: bool,
+    /// The port on localhost that overlays connect to
+    #[default = 9410]
+    pub port: u16,
+}
+
+/// The stats of the game being played, as sent to overlays
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct StatsSnapshot {
+    pub pieces: u32,
+    pub lines: u32,
+    pub garbage_lines: u32,
+    /// Seconds spent in play
+    pub time: f32,
+    pub pps: f32,
+    pub combo: u32,
+    pub b2b: u32,
+    pub finesse_faults: u32,
+}
+
+impl From<&Stats> for StatsSnapshot {
+    fn from(stats: &Stats) -> Self {
+        Self {
+            pieces: stats.pieces,
+            lines: stats.lines,
+            garbage_lines: stats.garbage_lines,
+            time: stats.time,
+            pps: if stats.time > 0.0 {
+                stats.pieces as f32 / stats.time
+            } else {
+                0.0
+            },
+            combo: stats.chain.combo,
+            b2b: stats.chain.b2b,
+            finesse_faults: stats.finesse_faults,
+        }
+    }
+}
+
+/// Everything that is sent to overlays
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OverlayMessage {
+    PieceLocked(PieceLocked),
+    LinesCleared(LinesCleared),
+    HoldUsed(HoldUsed),
+    GameStarted(GameStarted),
+    GameEnded(GameEnded),
+    Stats(StatsSnapshot),
+}
+
+/// A running server. Dropping it closes the connections of every overlay and waits for the server
+/// thread to finish.
+#[derive(Resource)]
+pub struct OverlayServer {
+    port: u16,
+    sender: Option<SyncSender<String>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl OverlayServer {
+    /// Starts listening on the given port of localhost. Passing port 0 lets the system pick one,
+    /// which can then be found with [`port`](Self::port).
+    pub fn start(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        listener.set_nonblocking(true)?;
+        let port = listener.local_addr()?.port();
+        let (sender, receiver) = mpsc::sync_channel::<String>(CHANNEL_SIZE);
+
+        let thread = std::thread::Builder::new()
+            .name("overlay server".into())
+            .spawn(move || {
+                let mut clients = Vec::new();
+                loop {
+                    accept_clients(&listener, &mut clients);
+                    match receiver.recv_timeout(POLL_INTERVAL) {
+                        Ok(text) => clients
+                            .retain_mut(|client| client.send(Message::Text(text.clone())).is_ok()),
+                        Err(RecvTimeoutError::Timeout) => (),
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                for mut client in clients {
+                    // the overlay may already be gone, in which case there is nobody to tell
+                    let _ = client.close(None).and_then(|_| client.flush());
+                }
+            })?;
+
+        Ok(Self {
+            port,
+            sender: Some(sender),
+            thread: Some(thread),
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Queues a message for every connected overlay. Returns false if the message was dropped
+    /// because the server has fallen behind.
+    pub fn send(&self, message: &OverlayMessage) -> bool {
+        let Some(sender) = &self.sender else {
+            return false;
+        };
+        let text = match serde_json::to_string(message) {
+            Ok(text) => text,
+            Err(e) => {
+                tracing::warn!("Could not write a message for overlays: {e}");
+                return false;
+            }
+        };
+        match sender.try_send(text) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) => false,
+        }
+    }
+}
+
+impl Drop for OverlayServer {
+    fn drop(&mut self) {
+        // the server thread stops once the channel is closed
+        self.sender = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Takes in the overlays which have connected since the last look
+fn accept_clients(listener: &TcpListener, clients: &mut Vec<WebSocket<TcpStream>>) {
+    while let Ok((stream, _)) = listener.accept() {
+        let accepted = stream
+            .set_nonblocking(false)
+            .and_then(|_| stream.set_read_timeout(Some(CLIENT_TIMEOUT)))
+            .and_then(|_| stream.set_write_timeout(Some(CLIENT_TIMEOUT)));
+        if accepted.is_err() {
+            continue;
+        }
+        match tungstenite::accept(stream) {
+            Ok(client) => clients.push(client),
+            Err(e) => tracing::warn!("An overlay could not connect: {e}"),
+        }
+    }
+}
+
+/// Starts, stops or moves the server to follow the settings. The old server is stopped right away
+/// rather than through commands, so that it lets go of its port before a new one is started.
+fn manage_server(world: &mut World) {
+    let settings = world.resource::<GlobalSettings>().overlay;
+    let wanted = settings.enabled.then_some(settings.port);
+    let running = world
+        .get_resource::<OverlayServer>()
+        .map(OverlayServer::port);
+    if wanted == running {
+        return;
+    }
+
+    // dropping the server closes its connections
+    world.remove_resource::<OverlayServer>();
+    let Some(port) = wanted else {
+        return;
+    };
+    match OverlayServer::start(port) {
+        Ok(server) => {
+            world
+                .resource_mut::<Toasts>()
+                .info(format!("Overlays can connect to ws://localhost:{port}"));
+            world.insert_resource(server);
+        }
+        Err(e) => {
+            world
+                .resource_mut::<Toasts>()
+                .error(format!("Could not start the overlay server: {e}"));
+            // rather than trying again on every change to the settings
+            world.resource_mut::<GlobalSettings>().overlay.enabled = false;
+        }
+    }
+}
+
+fn send_events(
+    server: Res<OverlayServer>,
+    mut locks: EventReader<PieceLocked>,
+    mut clears: EventReader<LinesCleared>,
+    mut holds: EventReader<HoldUsed>,
+    mut starts: EventReader<GameStarted>,
+    mut ends: EventReader<GameEnded>,
+) {
+    let messages = starts
+        .read()
+        .cloned()
+        .map(OverlayMessage::GameStarted)
+        .chain(locks.read().cloned().map(OverlayMessage::PieceLocked))
+        .chain(clears.read().cloned().map(OverlayMessage::LinesCleared))
+        .chain(holds.read().cloned().map(OverlayMessage::HoldUsed))
+        .chain(ends.read().cloned().map(OverlayMessage::GameEnded));
+    for message in messages {
+        server.send(&message);
+    }
+}
+
+fn send_stats(
+    server: Res<OverlayServer>,
+    stats: Res<Stats>,
+    time: Res<Time>,
+    mut since_last: Local<f32>,
+) {
+    *since_last += time.delta_seconds();
+    if *since_last >= STATS_INTERVAL {
+        *since_last = 0.0;
+        server.send(&OverlayMessage::Stats(StatsSnapshot::from(&*stats)));
+    }
+}
+
+pub struct OverlayPlugin;
+
+impl Plugin for OverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            manage_server.run_if(resource_changed::<GlobalSettings>),
+        )
+        .add_systems(
+            PostUpdate,
+            (send_events, send_stats.run_if(in_state(MainState::Playing)))
+                .run_if(resource_exists::<OverlayServer>),
+        );
+    }
+}
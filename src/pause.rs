@@ -0,0 +1,107 @@
+//! Pausing the game in the middle of play. The game is paused by stopping virtual time, so that the
+//! drop clock, the stats and the times of the record all stand still without knowing about the
+//! pause, and by holding back the systems which would still act on input. The game also pauses
+//! itself when the window loses focus, and waits for the pause key before carrying on, so that the
+//! player is not dropped back into play the moment they click back in.
+
+use bevy::prelude::*;
+use bevy::window::WindowFocused;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::controller::keybinds::{BindingContext, Hotkey};
+use crate::schedule::FrameSet;
+use crate::screens::GlobalSettings;
+use crate::state::MainState;
+
+pub const PAUSE_KEY: KeyCode = KeyCode::F8;
+
+pub(crate) const HOTKEYS: &[Hotkey] = &[Hotkey {
+    keys: &[PAUSE_KEY],
+    name: "Pause",
+    context: BindingContext::Playing,
+}];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PauseReason {
+    Key,
+    FocusLost,
+}
+
+/// Why the game is paused, if it is
+#[derive(Resource, Default, Deref)]
+pub struct Paused(Option<PauseReason>);
+
+pub fn not_paused(paused: Res<Paused>) -> bool {
+    paused.is_none()
+}
+
+fn toggle_pause(keys: Res<ButtonInput<KeyCode>>, mut paused: ResMut<Paused>) {
+    if keys.just_pressed(PAUSE_KEY) {
+        paused.0 = match paused.0 {
+            Some(_) => None,
+            None => Some(PauseReason::Key),
+        };
+    }
+}
+
+fn pause_on_focus_loss(
+    mut focus: EventReader<WindowFocused>,
+    settings: Res<GlobalSettings>,
+    mut paused: ResMut<Paused>,
+) {
+    let lost = focus.read().any(|event| !event.focused);
+    if lost && settings.pause_on_focus_loss && paused.is_none() {
+        paused.0 = Some(PauseReason::FocusLost);
+    }
+}
+
+fn apply_pause(paused: Res<Paused>, mut time: ResMut<Time<Virtual>>) {
+    match paused.0 {
+        Some(_) if !time.is_paused() => time.pause(),
+        None if time.is_paused() => time.unpause(),
+        _ => (),
+    }
+}
+
+/// Leaving play (by ending or restarting the game) always leaves the pause with it
+fn resume(mut paused: ResMut<Paused>, mut time: ResMut<Time<Virtual>>) {
+    paused.0 = None;
+    time.unpause();
+}
+
+fn pause_overlay(mut contexts: EguiContexts, paused: Res<Paused>) {
+    let Some(reason) = paused.0 else {
+        return;
+    };
+    let title = match reason {
+        PauseReason::Key => "Paused",
+        PauseReason::FocusLost => "Paused (focus lost)",
+    };
+    egui::Area::new("pause_overlay")
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.heading(title);
+                    ui.label(format!("Press {PAUSE_KEY:?} to carry on"));
+                });
+            });
+        });
+}
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Paused>()
+            .add_systems(
+                Update,
+                (toggle_pause, pause_on_focus_loss, apply_pause)
+                    .chain()
+                    .before(FrameSet::Logic)
+                    .run_if(in_state(MainState::Playing)),
+            )
+            .add_systems(Update, pause_overlay.run_if(in_state(MainState::Playing)))
+            .add_systems(OnExit(MainState::Playing), resume);
+    }
+}
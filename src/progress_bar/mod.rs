@@ -15,13 +15,13 @@ impl Plugin for ProgressBarPlugin {
             "progress_shader.wgsl",
             Shader::from_wgsl
         );
-        app.add_systems(Update, update_progress_bar)
+        app.add_systems(Update, (update_progress_bar, update_progress_bar_label))
             .add_plugins(UiMaterialPlugin::<ProgressBarMaterial>::default());
     }
 }
 
 #[repr(u8)]
-#[derive(Default, Copy, Clone)]
+#[derive(Default, Copy, Clone, PartialEq, Eq)]
 #[allow(unused)]
 pub enum Orientation {
     /// Horizontal, and progress bar moves toward the left
@@ -51,6 +51,87 @@ pub struct ProgressBar {
     #[default(Color::NONE)]
     pub empty_color: Color,
     pub orientation: Orientation,
+    /// Text shown alongside the bar, if any
+    pub label: Option<ProgressBarLabel>,
+    /// If set, the bar is drawn moving toward its progress over roughly this many seconds, rather
+    /// than jumping to it immediately
+    pub smoothing: Option<f32>,
+    /// The color that each section fades into by its end, by the index of the section. Sections
+    /// without an end color are filled with a single color.
+    pub section_end_colors: Vec<Option<Color>>,
+}
+
+/// The progress which a bar with smoothing is currently drawn at
+#[derive(Component, Deref, DerefMut)]
+struct DisplayedProgress(f32);
+
+/// How the progress of a bar is written out in its label
+#[derive(Default)]
+pub enum LabelFormat {
+    /// The progress as a percentage, e.g. "42%"
+    #[default]
+    Percent,
+    /// The progress as a frame out of the given total, e.g. "frame 1234 / 5678"
+    Frames(u64),
+    /// Any text, computed from the progress
+    Custom(Box<dyn Fn(f32) -> String + Send + Sync>),
+}
+
+impl LabelFormat {
+    fn format(&self, progress: f32) -> String {
+        match self {
+            Self::Percent => format!("{:.0}%", progress * 100.0),
+            Self::Frames(total) => {
+                let frame = (progress * *total as f32).round() as u64;
+                format!("frame {frame} / {total}")
+            }
+            Self::Custom(f) => f(progress),
+        }
+    }
+}
+
+/// Where the label sits relative to its bar
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub enum LabelPlacement {
+    /// Over the middle of the bar
+    #[default]
+    Center,
+    /// Just past the end of the bar that progress moves toward
+    End,
+}
+
+#[derive(Default)]
+pub struct ProgressBarLabel {
+    pub format: LabelFormat,
+    pub placement: LabelPlacement,
+    pub style: TextStyle,
+}
+
+/// The node holding a bar's label text, spawned as a child of the bar
+#[derive(Component)]
+struct ProgressBarLabelNode;
+
+fn label_node_style(orientation: Orientation, placement: LabelPlacement) -> Style {
+    let zero = Val::Px(0.0);
+    let past_end = Val::Percent(100.0);
+    let (left, right, top, bottom) = match (placement, orientation) {
+        (LabelPlacement::Center, _) => (zero, zero, zero, zero),
+        (LabelPlacement::End, Orientation::Left) => (Val::Auto, past_end, zero, zero),
+        (LabelPlacement::End, Orientation::Right) => (past_end, Val::Auto, zero, zero),
+        (LabelPlacement::End, Orientation::Up) => (zero, zero, Val::Auto, past_end),
+        (LabelPlacement::End, Orientation::Down) => (zero, zero, past_end, Val::Auto),
+    };
+
+    Style {
+        position_type: PositionType::Absolute,
+        left,
+        right,
+        top,
+        bottom,
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    }
 }
 
 #[derive(Bundle)]
@@ -79,16 +160,30 @@ pub struct ProgressBarMaterial {
     sections_count: u32,
     #[uniform(5)]
     orientation: u32,
+    /// The color at the end of each section, which is the same as the start for solid sections
+    #[storage(6, read_only)]
+    sections_end_color: Vec<Color>,
 }
 
 impl From<&ProgressBar> for ProgressBarMaterial {
     fn from(bar: &ProgressBar) -> Self {
         let total_amount: u32 = bar.sections.iter().map(|(amount, _)| amount).sum();
-        let (section_start_percentages, section_colors) = bar
+        let (section_start_percentages, section_colors): (_, Vec<_>) = bar
             .sections
             .iter()
             .map(|(amount, color)| (*amount as f32 / total_amount as f32, *color))
             .unzip();
+        let section_end_colors = section_colors
+            .iter()
+            .enumerate()
+            .map(|(ix, &start)| {
+                bar.section_end_colors
+                    .get(ix)
+                    .copied()
+                    .flatten()
+                    .unwrap_or(start)
+            })
+            .collect();
 
         Self {
             empty_color: bar.empty_color,
@@ -97,6 +192,7 @@ impl From<&ProgressBar> for ProgressBarMaterial {
             sections_color: section_colors,
             sections_start_percentage: section_start_percentages,
             orientation: bar.orientation as u32,
+            sections_end_color: section_end_colors,
         }
     }
 }
@@ -114,12 +210,93 @@ impl UiMaterial for ProgressBarMaterial {
 }
 
 fn update_progress_bar(
-    bar_query: Query<(&ProgressBar, &Handle<ProgressBarMaterial>)>,
+    mut commands: Commands,
+    mut bar_query: Query<(
+        Entity,
+        &ProgressBar,
+        &Handle<ProgressBarMaterial>,
+        Option<&mut DisplayedProgress>,
+    )>,
     mut materials: ResMut<Assets<ProgressBarMaterial>>,
+    time: Res<Time>,
 ) {
-    for (bar, handle) in bar_query.iter() {
+    for (e, bar, handle, displayed) in bar_query.iter_mut() {
+        let progress = match (bar.smoothing, displayed) {
+            (Some(smoothing), Some(mut displayed)) => {
+                let approach = 1.0 - (-time.delta_seconds() / smoothing.max(f32::EPSILON)).exp();
+                **displayed += (bar.progress - **displayed) * approach;
+                if (bar.progress - **displayed).abs() < 1e-4 {
+                    **displayed = bar.progress;
+                }
+                **displayed
+            }
+            (Some(_), None) => {
+                commands.entity(e).insert(DisplayedProgress(bar.progress));
+                bar.progress
+            }
+            (None, displayed) => {
+                if displayed.is_some() {
+                    commands.entity(e).remove::<DisplayedProgress>();
+                }
+                bar.progress
+            }
+        };
+
         if let Some(material) = materials.get_mut(handle) {
             *material = bar.into();
+            material.progress = progress;
+        }
+    }
+}
+
+/// Spawns, updates, and removes the label of each bar to match its [`ProgressBarLabel`].
+fn update_progress_bar_label(
+    mut commands: Commands,
+    bar_query: Query<(Entity, &ProgressBar, Option<&Children>), Changed<ProgressBar>>,
+    mut label_nodes: Query<(&mut Style, &Children), With<ProgressBarLabelNode>>,
+    mut texts: Query<&mut Text>,
+) {
+    for (e, bar, children) in bar_query.iter() {
+        let label_node = children
+            .map(|c| c.iter())
+            .into_iter()
+            .flatten()
+            .copied()
+            .find(|c| label_nodes.contains(*c));
+
+        match (&bar.label, label_node) {
+            (None, None) => (),
+            (None, Some(node)) => {
+                commands.entity(node).despawn_recursive();
+            }
+            (Some(label), None) => {
+                let text = label.format.format(bar.progress);
+                commands.entity(e).with_children(|parent| {
+                    parent
+                        .spawn((
+                            NodeBundle {
+                                style: label_node_style(bar.orientation, label.placement),
+                                ..default()
+                            },
+                            ProgressBarLabelNode,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(text, label.style.clone()));
+                        });
+                });
+            }
+            (Some(label), Some(node)) => {
+                let (mut style, node_children) = label_nodes.get_mut(node).unwrap();
+                *style = label_node_style(bar.orientation, label.placement);
+
+                let text_id = node_children.iter().copied().find(|c| texts.contains(*c));
+                let mut text = texts.get_mut(text_id.unwrap()).unwrap();
+                let value = label.format.format(bar.progress);
+                if text.sections[0].value != value {
+                    text.sections[0].value = value;
+                }
+                text.sections[0].style = label.style.clone();
+            }
         }
     }
 }
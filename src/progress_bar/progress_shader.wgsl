@@ -6,6 +6,7 @@
 @group(1) @binding(3) var<storage> amount: array<f32>;
 @group(1) @binding(4) var<uniform> count: u32;
 @group(1) @binding(5) var<uniform> orientation: u32;
+@group(1) @binding(6) var<storage> segment_ends: array<vec4<f32>>;
 
 @fragment
 fn fragment(
@@ -28,7 +29,9 @@ fn fragment(
     for (var i = 0u; i < count; i++) {
         current_amount += amount[i] ;
         if current_amount > position {
-            return segments[i];
+            // fade from the start color to the end color across the section
+            let t = (position - (current_amount - amount[i])) / amount[i];
+            return mix(segments[i], segment_ends[i], t);
         }
     }
     return empty_color;
@@ -0,0 +1,235 @@
+//! Bookmarks left on frames of the replay, so that the moments worth coming back to can be found
+//! again. Each bookmark is marked on the progress bar and listed in a panel from which the replay
+//! can jump to it. A bookmark belongs to the chain of segments it was made on, so after switching
+//! to a branch which parts from that chain before the bookmarked frame, the bookmark is greyed out.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use serde::{Deserialize, Serialize};
+
+use crate::controller::keybinds::{BindingContext, Hotkey};
+use crate::replay::record::{CompleteRecord, FrameRate};
+use crate::replay::replay::{ReplayBar, ReplayInfo};
+
+pub const BOOKMARK_KEY: KeyCode = KeyCode::KeyB;
+
+pub(crate) const HOTKEYS: &[Hotkey] = &[Hotkey {
+    keys: &[BOOKMARK_KEY],
+    name: "Bookmark Frame",
+    context: BindingContext::Replay,
+}];
+
+/// The longest label that a bookmark can be given
+const LABEL_LENGTH: usize = 32;
+const BOOKMARK_COLOR: Color = Color::rgb(0.3, 0.9, 0.6);
+const UNREACHABLE_COLOR: Color = Color::rgba(0.5, 0.5, 0.5, 0.6);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Bookmark {
+    pub frame: u64,
+    #[serde(default)]
+    pub label: String,
+    /// The chain of segments that the bookmark was made on. Bookmarks read from a file have none,
+    /// and are taken to belong to whichever record they are shown with.
+    #[serde(skip)]
+    chain: Option<CompleteRecord>,
+}
+
+impl Bookmark {
+    pub fn new(frame: u64, label: String, record: &CompleteRecord) -> Self {
+        Self {
+            frame,
+            label,
+            chain: Some(record.clone()),
+        }
+    }
+
+    /// Whether the given record passes through the bookmarked frame of the chain the bookmark was
+    /// made on
+    pub fn reachable(&self, record: &CompleteRecord) -> bool {
+        let Some(chain) = &self.chain else {
+            return self.frame <= record.last_frame();
+        };
+        chain
+            .divergence(record)
+            .map_or(true, |divergence| self.frame < divergence)
+    }
+
+    fn name(&self, rate: FrameRate) -> String {
+        let time = format!("{:.2}s", rate.seconds(self.frame));
+        if self.label.is_empty() {
+            time
+        } else {
+            format!("{time} {}", self.label)
+        }
+    }
+}
+
+/// The bookmarks of the record being viewed, in order of their frames
+#[derive(Resource, Default, Deref)]
+pub struct Bookmarks(Vec<Bookmark>);
+
+impl Bookmarks {
+    pub fn add(&mut self, bookmark: Bookmark) {
+        let ix = self.0.partition_point(|b| b.frame <= bookmark.frame);
+        self.0.insert(ix, bookmark);
+    }
+
+    /// The bookmarks to write into a replay file of the given record, which are those it passes
+    /// through
+    pub fn reachable(&self, record: &CompleteRecord) -> Vec<Bookmark> {
+        self.0
+            .iter()
+            .filter(|b| b.reachable(record))
+            .cloned()
+            .collect()
+    }
+}
+
+pub(crate) fn reset_bookmarks(mut bookmarks: ResMut<Bookmarks>) {
+    bookmarks.0.clear();
+}
+
+/// A mark on the progress bar where a bookmark was left
+#[derive(Component)]
+pub struct BookmarkMarker;
+
+/// Marks the bookmarks on the progress bar again whenever they, the record, or the bar change
+pub(crate) fn mark_bookmarks(
+    mut commands: Commands,
+    bookmarks: Res<Bookmarks>,
+    record: Res<CompleteRecord>,
+    bars: Query<Entity, Added<ReplayBar>>,
+    bar: Query<Entity, With<ReplayBar>>,
+    markers: Query<Entity, With<BookmarkMarker>>,
+) {
+    if !bookmarks.is_changed() && !record.is_changed() && bars.is_empty() {
+        return;
+    }
+    for marker in markers.iter() {
+        commands.entity(marker).despawn_recursive();
+    }
+    let Ok(bar) = bar.get_single() else {
+        return;
+    };
+
+    let last_frame = record.last_frame().max(1) as f32;
+    commands.entity(bar).with_children(|parent| {
+        for bookmark in bookmarks.iter() {
+            let color = if bookmark.reachable(&record) {
+                BOOKMARK_COLOR
+            } else {
+                UNREACHABLE_COLOR
+            };
+            let progress = (bookmark.frame as f32 / last_frame).min(1.0);
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Percent(progress * 100.0),
+                        left: Val::Px(-8.0),
+                        width: Val::Px(8.0),
+                        height: Val::Px(2.0),
+                        ..default()
+                    },
+                    background_color: color.into(),
+                    ..default()
+                },
+                BookmarkMarker,
+            ));
+        }
+    });
+}
+
+/// Leaves a bookmark on the current frame when the bookmark key is pressed, asking for its label in
+/// a popup first, and lists the bookmarks with buttons to jump to each
+pub(crate) fn bookmark_panel(
+    mut contexts: EguiContexts,
+    mut bookmarks: ResMut<Bookmarks>,
+    mut pending: Local<Option<(u64, String)>>,
+    mut replay_info: ResMut<ReplayInfo>,
+    record: Res<CompleteRecord>,
+    keys: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+) {
+    let ctx = contexts.ctx_mut();
+    if keys.just_pressed(BOOKMARK_KEY) && pending.is_none() && !ctx.wants_keyboard_input() {
+        *pending = Some((replay_info.frame, String::new()));
+    }
+
+    if let Some((frame, label)) = pending.as_mut() {
+        let mut done = None;
+        egui::Window::new("New Bookmark")
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("At {:.2}s", record.frame_rate.seconds(*frame)));
+                let text_edit = ui.add(
+                    egui::TextEdit::singleline(label)
+                        .char_limit(LABEL_LENGTH)
+                        .hint_text("Label (optional)"),
+                );
+                text_edit.request_focus();
+                ui.horizontal(|ui| {
+                    let entered = ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    if ui.button("Add").clicked() || entered {
+                        done = Some(true);
+                    }
+                    let escaped = ui.input(|i| i.key_pressed(egui::Key::Escape));
+                    if ui.button("Cancel").clicked() || escaped {
+                        done = Some(false);
+                    }
+                });
+            });
+        match done {
+            Some(true) => {
+                let bookmark = Bookmark::new(*frame, label.trim().to_string(), &record);
+                bookmarks.add(bookmark);
+                *pending = None;
+            }
+            Some(false) => *pending = None,
+            None => (),
+        }
+    }
+
+    if bookmarks.is_empty() {
+        return;
+    }
+    let mut seek = None;
+    let mut remove = None;
+    egui::Window::new("Bookmarks")
+        .anchor(egui::Align2::LEFT_BOTTOM, [10.0, -10.0])
+        .default_open(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("bookmarks").show(ui, |ui| {
+                        for (ix, bookmark) in bookmarks.iter().enumerate() {
+                            let reachable = bookmark.reachable(&record);
+                            ui.add_enabled_ui(reachable, |ui| {
+                                ui.label(bookmark.name(record.frame_rate));
+                                if ui.button("Jump").clicked() {
+                                    seek = Some(bookmark.frame);
+                                }
+                            })
+                            .response
+                            .on_disabled_hover_text("Not on the branch being viewed");
+                            if ui.small_button("x").clicked() {
+                                remove = Some(ix);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+        });
+
+    if let Some(ix) = remove {
+        bookmarks.0.remove(ix);
+    }
+    if let Some(frame) = seek {
+        replay_info.seek(frame, &record, &time);
+    }
+}
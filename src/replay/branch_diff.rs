@@ -0,0 +1,219 @@
+//! Compares two branches of the same game, for when a record has been branched to try out other
+//! continuations. The placements made after the branches part are listed side by side, along with
+//! where each branch ended up. Clicking a placement seeks the replay to it, switching the replay
+//! over to its branch first if needed.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use duplicate::duplicate;
+
+use crate::assets::tables::shape_table::ShapeTable;
+use crate::assets::tables::QueryShapeTable;
+use crate::board::BoardQuery;
+use crate::replay::ghost::Ghost;
+use crate::replay::record::CompleteRecord;
+use crate::replay::replay::ReplayInfo;
+use crate::stats::{final_matrix, lock_outcomes, LockOutcome};
+
+/// Where a branch ended up, worked out from its placements
+pub struct BranchSummary {
+    pub outcomes: Vec<LockOutcome>,
+    pub lines: u32,
+    pub attack: u32,
+    /// Holes left in the matrix at the end of the branch
+    pub holes: usize,
+}
+
+impl BranchSummary {
+    fn new(record: &CompleteRecord, shape_table: &ShapeTable) -> Self {
+        let outcomes = lock_outcomes(record, shape_table);
+        Self {
+            lines: outcomes.iter().map(|o| o.lines).sum(),
+            attack: outcomes.iter().map(|o| o.attack).sum(),
+            holes: final_matrix(record).holes(),
+            outcomes,
+        }
+    }
+}
+
+/// Every branch of the record being viewed, and the two picked for comparison
+#[derive(Resource, Default)]
+pub struct BranchComparison {
+    branches: Vec<(CompleteRecord, BranchSummary)>,
+    picked: [usize; 2],
+}
+
+/// Finds the branches of the record whenever it changes. Switching the replay to another branch
+/// leaves the branches as they were, so they are only worked out again when a branch is added.
+pub(crate) fn track_branches(
+    mut comparison: ResMut<BranchComparison>,
+    record: Res<CompleteRecord>,
+    shape_table: QueryShapeTable,
+) {
+    if !record.is_changed() {
+        return;
+    }
+    let branches = record.branches();
+    let unchanged = branches.len() == comparison.branches.len()
+        && branches
+            .iter()
+            .zip(&comparison.branches)
+            .all(|(a, (b, _))| a.same_chain(b));
+    if unchanged {
+        return;
+    }
+
+    // the branch being viewed is compared against the one after it
+    let viewed = branches
+        .iter()
+        .position(|b| b.same_chain(&record))
+        .unwrap_or(0);
+    comparison.picked = [viewed, (viewed + 1) % branches.len().max(1)];
+    comparison.branches = branches
+        .into_iter()
+        .map(|b| {
+            let summary = BranchSummary::new(&b, &shape_table);
+            (b, summary)
+        })
+        .collect();
+}
+
+fn placement_text(outcome: &LockOutcome) -> String {
+    let mino = outcome.lock.mino;
+    let mut text = format!(
+        "{:?} at column {}, {:?}",
+        mino.kind, mino.position.x, mino.rotation
+    );
+    if outcome.wiped {
+        text.push_str(" (topped out)");
+    } else if outcome.perfect_clear {
+        text.push_str(" (perfect clear)");
+    } else if outcome.lines > 0 {
+        text.push_str(&format!(" ({} lines)", outcome.lines));
+    }
+    text
+}
+
+pub(crate) fn branch_diff_panel(
+    mut contexts: EguiContexts,
+    mut comparison: ResMut<BranchComparison>,
+    mut record: ResMut<CompleteRecord>,
+    mut replay_info: ResMut<ReplayInfo>,
+    mut boards: Query<BoardQuery, Without<Ghost>>,
+    time: Res<Time>,
+) {
+    if comparison.branches.len() < 2 {
+        return;
+    }
+
+    let branches = &comparison.branches;
+    let mut picked = comparison.picked;
+    let mut seek = None;
+    egui::Window::new("Compare Branches")
+        .anchor(egui::Align2::LEFT_CENTER, [10.0, 0.0])
+        .default_open(false)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let label = |ix: usize| {
+                let (branch, summary) = &branches[ix];
+                let viewing = if branch.same_chain(&record) {
+                    ", viewing"
+                } else {
+                    ""
+                };
+                format!(
+                    "Branch {} ({} pieces{viewing})",
+                    ix + 1,
+                    summary.outcomes.len()
+                )
+            };
+            ui.horizontal(|ui| {
+                for (side, pick) in picked.iter_mut().enumerate() {
+                    egui::ComboBox::from_id_source(("compared_branch", side))
+                        .selected_text(label(*pick))
+                        .show_ui(ui, |ui| {
+                            for ix in 0..branches.len() {
+                                ui.selectable_value(pick, ix, label(ix));
+                            }
+                        });
+                }
+            });
+
+            let [(left, left_summary), (right, right_summary)] = picked.map(|ix| &branches[ix]);
+            let Some(divergence) = left.divergence(right) else {
+                ui.label("These are the same branch");
+                return;
+            };
+            ui.label(format!(
+                "The branches part at {:.2}s",
+                left.frame_rate.seconds(divergence)
+            ));
+            ui.separator();
+
+            egui::Grid::new("branch_diff_totals").show(ui, |ui| {
+                duplicate! {
+                    [
+                        field       name;
+                        [lines]     ["Lines"];
+                        [attack]    ["Attack"];
+                        [holes]     ["Holes"];
+                    ]
+                    ui.label(name);
+                    ui.label(left_summary.field.to_string());
+                    ui.label(right_summary.field.to_string());
+                    ui.end_row();
+                }
+            });
+            ui.separator();
+
+            let divergent = [left_summary, right_summary].map(|summary| {
+                summary
+                    .outcomes
+                    .iter()
+                    .filter(|o| o.lock.frame >= divergence)
+                    .collect::<Vec<_>>()
+            });
+            let rows = divergent[0].len().max(divergent[1].len());
+            egui::ScrollArea::vertical()
+                .max_height(240.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("branch_diff_placements").show(ui, |ui| {
+                        for row in 0..rows {
+                            ui.label(format!("{}", row + 1));
+                            for (side, placements) in divergent.iter().enumerate() {
+                                match placements.get(row) {
+                                    Some(outcome) => {
+                                        let viewed = replay_info.frame == outcome.lock.frame
+                                            && [left, right][side].same_chain(&record);
+                                        let text = placement_text(outcome);
+                                        if ui.selectable_label(viewed, text).clicked() {
+                                            seek = Some((picked[side], outcome.lock.frame));
+                                        }
+                                    }
+                                    None => {
+                                        ui.label("");
+                                    }
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+        });
+    comparison.picked = picked;
+
+    let Some((ix, frame)) = seek else {
+        return;
+    };
+    let branch = &comparison.branches[ix].0;
+    if record.same_chain(branch) {
+        replay_info.seek(frame, &record, &time);
+        return;
+    }
+    let Ok(mut board) = boards.get_single_mut() else {
+        return;
+    };
+    *record = branch.clone();
+    board.matrix.clear();
+    replay_info.restart(frame, &record, &time);
+}
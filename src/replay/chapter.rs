@@ -0,0 +1,121 @@
+//! A range of frames marked out in the replay, which can be saved as a replay of its own. The
+//! start and end of the range are marked on the progress bar.
+
+use std::ops::RangeInclusive;
+
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use crate::controller::keybinds::{BindingContext, Hotkey};
+use crate::replay::record::CompleteRecord;
+use crate::replay::replay::{ReplayBar, ReplayInfo};
+
+pub const CHAPTER_START_KEY: KeyCode = KeyCode::KeyI;
+pub const CHAPTER_END_KEY: KeyCode = KeyCode::KeyO;
+
+pub(crate) const HOTKEYS: &[Hotkey] = &[
+    Hotkey {
+        keys: &[CHAPTER_START_KEY],
+        name: "Mark Chapter Start",
+        context: BindingContext::Replay,
+    },
+    Hotkey {
+        keys: &[CHAPTER_END_KEY],
+        name: "Mark Chapter End",
+        context: BindingContext::Replay,
+    },
+];
+
+const CHAPTER_COLOR: Color = Color::rgb(0.4, 0.7, 1.0);
+
+/// The start and end of the chapter, as far as they have been marked
+#[derive(Resource, Default, Debug, Clone, Copy)]
+pub struct Chapter {
+    pub start: Option<u64>,
+    pub end: Option<u64>,
+}
+
+impl Chapter {
+    /// The frames of the chapter, once both ends have been marked with the start before the end
+    pub fn frames(&self) -> Option<RangeInclusive<u64>> {
+        let (start, end) = self.start.zip(self.end)?;
+        (start < end).then_some(start..=end)
+    }
+}
+
+pub(crate) fn reset_chapter(mut chapter: ResMut<Chapter>) {
+    *chapter = Chapter::default();
+}
+
+/// Marks the start or end of the chapter on the current frame. Marking one end past the other
+/// clears the other, rather than leaving the chapter backward.
+pub(crate) fn mark_chapter(
+    mut contexts: EguiContexts,
+    mut chapter: ResMut<Chapter>,
+    info: Res<ReplayInfo>,
+    keys: Res<ButtonInput<KeyCode>>,
+) {
+    if contexts.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    let frame = info.frame;
+    if keys.just_pressed(CHAPTER_START_KEY) {
+        chapter.start = Some(frame);
+        if chapter.end.is_some_and(|end| end <= frame) {
+            chapter.end = None;
+        }
+    }
+    if keys.just_pressed(CHAPTER_END_KEY) {
+        chapter.end = Some(frame);
+        if chapter.start.is_some_and(|start| start >= frame) {
+            chapter.start = None;
+        }
+    }
+}
+
+/// A mark on the progress bar at one end of the chapter
+#[derive(Component)]
+pub struct ChapterMarker;
+
+/// Marks the ends of the chapter on the progress bar again whenever they, the record, or the bar
+/// change
+pub(crate) fn mark_chapter_ends(
+    mut commands: Commands,
+    chapter: Res<Chapter>,
+    record: Res<CompleteRecord>,
+    bars: Query<Entity, Added<ReplayBar>>,
+    bar: Query<Entity, With<ReplayBar>>,
+    markers: Query<Entity, With<ChapterMarker>>,
+) {
+    if !chapter.is_changed() && !record.is_changed() && bars.is_empty() {
+        return;
+    }
+    for marker in markers.iter() {
+        commands.entity(marker).despawn_recursive();
+    }
+    let Ok(bar) = bar.get_single() else {
+        return;
+    };
+
+    let last_frame = record.last_frame().max(1) as f32;
+    commands.entity(bar).with_children(|parent| {
+        for frame in [chapter.start, chapter.end].into_iter().flatten() {
+            let progress = (frame as f32 / last_frame).min(1.0);
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Percent(progress * 100.0),
+                        left: Val::Px(2.0),
+                        width: Val::Px(8.0),
+                        height: Val::Px(2.0),
+                        ..default()
+                    },
+                    background_color: CHAPTER_COLOR.into(),
+                    ..default()
+                },
+                ChapterMarker,
+            ));
+        }
+    });
+}
@@ -0,0 +1,519 @@
+//! Run codes: a compact text form of a game, holding only what is needed to play it back again.
+//!
+//! A run code stores the seed of the queue, the settings which decide how the board starts, and the
+//! final placement of every piece. Playing the code back locks each placement in turn, so the time
+//! spent on each piece is not kept.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use bevy::math::ivec2;
+use bevy::prelude::*;
+use bevy::utils::thiserror;
+use strum::IntoEnumIterator;
+
+use crate::assets::tables::shape_table::ShapeTable;
+use crate::board::events::PieceLocked;
+use crate::board::garbage::{self, GarbagePattern};
+use crate::board::queue::{drill_bag, PieceQueue, QueueSource};
+use crate::board::update::{default_mino, goal_reached, has_free_space, lock_piece};
+use crate::board::{
+    GameMode, Hold, Matrix, Mino, MinoKind, RotationState, Settings, MATRIX_DEFAULT_LEGAL_BOUNDS,
+};
+use crate::replay::record::{
+    diff_and_copy, initial_state, CompleteRecord, FrameRate, RecordData, RecordItem, RecordSegment,
+};
+use crate::stats::{compute_stats, is_garbage_row, Stats};
+
+const MAGIC: &[u8; 2] = b"SP";
+/// The version of the format written by [`RunCode::encode`]
+const VERSION: u8 = 7;
+/// Written in place of a piece where there is none
+const NO_PIECE: u8 = u8::MAX;
+/// Frames given to each piece when a run code is played back
+pub const PLACEMENT_FRAMES: u64 = 30;
+
+#[derive(thiserror::Error, Debug)]
+pub enum RunCodeError {
+    #[error("Run code is not valid base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("This is not a run code")]
+    BadMagic,
+    #[error("Run code is from a newer version (format {0})")]
+    UnsupportedVersion(u8),
+    #[error("Run code ends unexpectedly")]
+    Truncated,
+    #[error("Run code contains an invalid value")]
+    InvalidValue,
+    #[error("Run code has an invalid queue: {0}")]
+    InvalidQueue(#[from] ron::de::SpannedError),
+    #[error("Could not write settings into run code: {0}")]
+    Serialize(#[from] ron::Error),
+    #[error("Piece {0} of the run code cannot be placed")]
+    IllegalPlacement(usize),
+}
+
+/// The settings which decide how the board starts, and so must match for a run to play back.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunSettings {
+    pub mode: GameMode,
+    pub cheese_height: usize,
+    pub garbage_pattern: GarbagePattern,
+    pub messiness: f32,
+    pub adaptive_cheese: bool,
+    pub continuous: bool,
+    pub wipe_hold: bool,
+    pub lifesaver_rows: usize,
+    pub target_height: usize,
+    pub queue: QueueSource,
+    pub excluded_pieces: Vec<MinoKind>,
+    pub initial_hold: Option<MinoKind>,
+    pub preset_queue: Vec<MinoKind>,
+}
+
+/// The settings that the game starts with
+impl Default for RunSettings {
+    fn default() -> Self {
+        Self::from(&Settings::default())
+    }
+}
+
+impl From<&Settings> for RunSettings {
+    fn from(settings: &Settings) -> Self {
+        Self {
+            mode: settings.mode,
+            cheese_height: settings.cheese_height,
+            garbage_pattern: settings.garbage_pattern.clone(),
+            messiness: settings.messiness,
+            adaptive_cheese: settings.adaptive_cheese,
+            continuous: settings.continuous,
+            wipe_hold: settings.wipe_hold,
+            lifesaver_rows: settings.lifesaver_rows,
+            target_height: settings.target_height,
+            queue: settings.queue.clone(),
+            excluded_pieces: settings.excluded_pieces.clone(),
+            initial_hold: settings.initial_hold,
+            preset_queue: settings.preset_queue.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunCode {
+    pub seed: u64,
+    pub settings: RunSettings,
+    pub placements: Vec<Mino>,
+}
+
+/// Reads the pieces of a run code one field at a time
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], RunCodeError> {
+        if self.0.len() < n {
+            return Err(RunCodeError::Truncated);
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, RunCodeError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, RunCodeError> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, RunCodeError> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, RunCodeError> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, RunCodeError> {
+        Ok(f32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    /// Reads a string preceded by its length
+    fn str(&mut self) -> Result<&'a str, RunCodeError> {
+        let len = self.u16()? as usize;
+        std::str::from_utf8(self.bytes(len)?).map_err(|_| RunCodeError::InvalidValue)
+    }
+}
+
+fn mode_to_byte(mode: GameMode) -> u8 {
+    match mode {
+        GameMode::Freestyle => 0,
+        GameMode::Downstack => 1,
+        GameMode::CheeseRace => 2,
+        GameMode::Sprint => 3,
+        GameMode::Openers => 4,
+    }
+}
+
+fn mode_from_byte(byte: u8) -> Result<GameMode, RunCodeError> {
+    match byte {
+        0 => Ok(GameMode::Freestyle),
+        1 => Ok(GameMode::Downstack),
+        2 => Ok(GameMode::CheeseRace),
+        3 => Ok(GameMode::Sprint),
+        4 => Ok(GameMode::Openers),
+        _ => Err(RunCodeError::InvalidValue),
+    }
+}
+
+fn rotation_to_bits(rotation: RotationState) -> u8 {
+    match rotation {
+        RotationState::Up => 0,
+        RotationState::Right => 1,
+        RotationState::Down => 2,
+        RotationState::Left => 3,
+    }
+}
+
+fn rotation_from_bits(bits: u8) -> RotationState {
+    match bits & 0b11 {
+        0 => RotationState::Up,
+        1 => RotationState::Right,
+        2 => RotationState::Down,
+        _ => RotationState::Left,
+    }
+}
+
+fn kind_from_bits(bits: u8) -> Result<MinoKind, RunCodeError> {
+    MinoKind::iter()
+        .find(|&k| k.is_piece() && k as u8 == bits)
+        .ok_or(RunCodeError::InvalidValue)
+}
+
+impl RunCode {
+    /// Writes the run as base64 text. Each placement takes three bytes: the kind and rotation of the
+    /// piece packed together, then its column and row.
+    pub fn encode(&self) -> Result<String, RunCodeError> {
+        let queue = ron::to_string(&self.settings.queue)?;
+        let pattern = ron::to_string(&self.settings.garbage_pattern)?;
+
+        let capacity = 32 + queue.len() + pattern.len() + 3 * self.placements.len();
+        let mut bytes = Vec::with_capacity(capacity);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.push(mode_to_byte(self.settings.mode));
+        bytes.push(self.settings.cheese_height as u8);
+        bytes.push(self.settings.target_height as u8);
+        for text in [&queue, &pattern] {
+            bytes.extend_from_slice(&(text.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(text.as_bytes());
+        }
+        bytes.extend_from_slice(&self.settings.messiness.to_le_bytes());
+        bytes.push(self.settings.adaptive_cheese as u8);
+        bytes.push(self.settings.continuous as u8 | (self.settings.wipe_hold as u8) << 1);
+        bytes.push(self.settings.excluded_pieces.len() as u8);
+        bytes.extend(self.settings.excluded_pieces.iter().map(|&kind| kind as u8));
+        bytes.push(
+            self.settings
+                .initial_hold
+                .map_or(NO_PIECE, |kind| kind as u8),
+        );
+        bytes.push(self.settings.preset_queue.len() as u8);
+        bytes.extend(self.settings.preset_queue.iter().map(|&kind| kind as u8));
+        bytes.push(self.settings.lifesaver_rows as u8);
+        bytes.extend_from_slice(&(self.placements.len() as u32).to_le_bytes());
+        for mino in &self.placements {
+            bytes.push(((mino.kind as u8) << 2) | rotation_to_bits(mino.rotation));
+            bytes.push(mino.position.x as i8 as u8);
+            bytes.push(mino.position.y as u8);
+        }
+
+        Ok(URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    pub fn decode(text: &str) -> Result<Self, RunCodeError> {
+        let bytes = URL_SAFE_NO_PAD.decode(text.trim())?;
+        let mut reader = Reader(&bytes);
+
+        if reader.bytes(2)? != MAGIC {
+            return Err(RunCodeError::BadMagic);
+        }
+        match reader.u8()? {
+            version @ (1..=7) => Self::decode_versioned(reader, version),
+            version => Err(RunCodeError::UnsupportedVersion(version)),
+        }
+    }
+
+    /// Reads a run code of the given version. Version 1 predates garbage patterns, so its garbage
+    /// is always clean with full messiness, versions before 3 predate adaptive cheese, versions
+    /// before 4 predate continuous play, versions before 5 predate drills, versions before 6
+    /// predate preset holds and queues, and versions before 7 predate lifesavers.
+    fn decode_versioned(mut reader: Reader, version: u8) -> Result<Self, RunCodeError> {
+        let seed = reader.u64()?;
+        let mode = mode_from_byte(reader.u8()?)?;
+        let cheese_height = reader.u8()? as usize;
+        let target_height = reader.u8()? as usize;
+        let queue = ron::from_str(reader.str()?)?;
+        let (garbage_pattern, messiness) = if version >= 2 {
+            let pattern = ron::from_str(reader.str()?)?;
+            (pattern, reader.f32()?)
+        } else {
+            (GarbagePattern::Clean, 1.0)
+        };
+        let adaptive_cheese = version >= 3 && reader.u8()? != 0;
+        let flags = if version >= 4 { reader.u8()? } else { 0 };
+        let (continuous, wipe_hold) = (flags & 1 != 0, flags & 2 != 0);
+        let excluded_pieces = if version >= 5 {
+            let count = reader.u8()?;
+            (0..count)
+                .map(|_| kind_from_bits(reader.u8()?))
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            Vec::new()
+        };
+        let (initial_hold, preset_queue) = if version >= 6 {
+            let hold = match reader.u8()? {
+                NO_PIECE => None,
+                bits => Some(kind_from_bits(bits)?),
+            };
+            let count = reader.u8()?;
+            let preset = (0..count)
+                .map(|_| kind_from_bits(reader.u8()?))
+                .collect::<Result<Vec<_>, _>>()?;
+            (hold, preset)
+        } else {
+            (None, Vec::new())
+        };
+        let lifesaver_rows = if version >= 7 {
+            reader.u8()? as usize
+        } else {
+            0
+        };
+
+        let count = reader.u32()? as usize;
+        let placements = (0..count)
+            .map(|_| {
+                let packed = reader.u8()?;
+                let x = reader.u8()? as i8 as i32;
+                let y = reader.u8()? as i32;
+                Ok(Mino {
+                    kind: kind_from_bits(packed >> 2)?,
+                    rotation: rotation_from_bits(packed),
+                    position: ivec2(x, y),
+                })
+            })
+            .collect::<Result<Vec<_>, RunCodeError>>()?;
+
+        Ok(Self {
+            seed,
+            settings: RunSettings {
+                mode,
+                cheese_height,
+                garbage_pattern,
+                messiness,
+                adaptive_cheese,
+                continuous,
+                wipe_hold,
+                lifesaver_rows,
+                target_height,
+                queue,
+                excluded_pieces,
+                initial_hold,
+                preset_queue,
+            },
+            placements,
+        })
+    }
+
+    /// Plays the run back from the beginning, producing a record of it along with its stats. Fails
+    /// if a placement is impossible, meaning that the piece is neither the next piece nor reachable
+    /// through hold, or that the piece overlaps the stack.
+    pub fn resimulate(
+        &self,
+        shape_table: &ShapeTable,
+    ) -> Result<(CompleteRecord, Stats), RunCodeError> {
+        let pieces = drill_bag(shape_table.kinds(), &self.settings.excluded_pieces);
+        let mut queue = PieceQueue::seeded(self.settings.queue.clone(), self.seed, pieces);
+        queue.prepend(&self.settings.preset_queue);
+        let mut matrix = Matrix::default();
+        let mut previous = Matrix::default();
+        let mut hold = self.settings.initial_hold.map_or(Hold::Empty, Hold::Ready);
+        let mut stats = Stats::default();
+        let mut segment = RecordSegment::default();
+
+        let mut rng = garbage::rng(self.seed);
+        if self.settings.mode.has_cheese() {
+            let height = self
+                .settings
+                .cheese_height
+                .min(MATRIX_DEFAULT_LEGAL_BOUNDS.y as usize);
+            let rows = garbage::cheese(
+                &mut rng,
+                &self.settings.garbage_pattern,
+                self.settings.messiness,
+                height,
+                matrix.width(),
+            );
+            for (row, data) in matrix.rows_mut().zip(rows) {
+                row.copy_from_slice(&data);
+            }
+        }
+
+        // run codes keep no times, so their records are taken at the usual rate
+        let rate = FrameRate::default();
+        let mut push = |time: u64, data: RecordData| {
+            segment.push(RecordItem {
+                time,
+                micros: rate.frame_to_micros(time),
+                tick: None,
+                data,
+            })
+        };
+
+        for data in initial_state(&queue, hold) {
+            push(0, data);
+        }
+        let mut active = queue.take();
+        push(0, RecordData::QueueChange(queue.clone()));
+        push(
+            0,
+            RecordData::ActiveChange(Some(default_mino(active, shape_table))),
+        );
+        for update in diff_and_copy(&matrix, &mut previous) {
+            push(0, RecordData::MatrixChange(update));
+        }
+
+        for (i, &placement) in self.placements.iter().enumerate() {
+            let start = i as u64 * PLACEMENT_FRAMES;
+            let end = start + PLACEMENT_FRAMES;
+
+            if placement.kind != active {
+                match hold {
+                    Hold::Empty if queue.peek() == placement.kind => {
+                        hold = Hold::Inactive(active);
+                        active = queue.take();
+                        push(start + 1, RecordData::QueueChange(queue.clone()));
+                    }
+                    Hold::Ready(held) if held == placement.kind => {
+                        hold = Hold::Inactive(active);
+                        active = held;
+                    }
+                    _ => return Err(RunCodeError::IllegalPlacement(i)),
+                }
+                push(start + 1, RecordData::Hold(hold));
+            }
+
+            if !has_free_space(&matrix, placement, shape_table) {
+                return Err(RunCodeError::IllegalPlacement(i));
+            }
+            push(end - 1, RecordData::ActiveChange(Some(placement)));
+
+            let holes = matrix.holes();
+            let cleared = lock_piece(&mut matrix, placement, shape_table);
+            let overflowed = self.settings.adaptive_cheese
+                && !cleared.is_empty()
+                && matrix.holes() <= holes
+                && !garbage::shove(
+                    &mut matrix,
+                    &mut rng,
+                    &self.settings.garbage_pattern,
+                    self.settings.messiness,
+                    MATRIX_DEFAULT_LEGAL_BOUNDS.y as usize,
+                );
+            stats.pieces += 1;
+            stats.lines += cleared.len() as u32;
+            stats.record_clear(cleared.len() as u32);
+            stats.garbage_lines += cleared
+                .iter()
+                .filter(|(_, row)| is_garbage_row(row))
+                .count() as u32;
+            for update in diff_and_copy(&matrix, &mut previous) {
+                push(end, RecordData::MatrixChange(update));
+            }
+            if overflowed {
+                if i + 1 < self.placements.len() {
+                    return Err(RunCodeError::IllegalPlacement(i + 1));
+                }
+                break;
+            }
+
+            // openers drills start over after every bag. Re-seeded bags cannot be written as run
+            // codes, so the queue always carries on here.
+            if self.settings.mode.ends_bag(i + 1, queue.pieces().len()) {
+                matrix.clear();
+                hold = self.settings.initial_hold.map_or(Hold::Empty, Hold::Ready);
+                for update in diff_and_copy(&matrix, &mut previous) {
+                    push(end, RecordData::MatrixChange(update));
+                }
+            }
+
+            // continuous play wipes the board once the next piece has no room to spawn, and
+            // lifesavers clear away the top of the stack
+            let continuous = self.settings.continuous && self.settings.mode == GameMode::Freestyle;
+            let next = default_mino(queue.peek(), shape_table);
+            if !has_free_space(&matrix, next, shape_table) {
+                if continuous {
+                    matrix.clear();
+                    if self.settings.wipe_hold {
+                        hold = Hold::Empty;
+                    }
+                    stats.record_death();
+                } else if self.settings.lifesaver_rows > 0 {
+                    matrix.clear_top_rows(self.settings.lifesaver_rows);
+                    stats.record_lifesaver();
+                }
+                for update in diff_and_copy(&matrix, &mut previous) {
+                    push(end, RecordData::MatrixChange(update));
+                }
+            }
+
+            hold.activate();
+            active = queue.take();
+            push(end, RecordData::Hold(hold));
+            push(end, RecordData::QueueChange(queue.clone()));
+            push(
+                end,
+                RecordData::ActiveChange(Some(default_mino(active, shape_table))),
+            );
+        }
+
+        let last_frame = self.placements.len() as u64 * PLACEMENT_FRAMES;
+        stats.time = rate.seconds(last_frame);
+        stats.goal_reached = goal_reached(
+            &matrix,
+            self.settings.mode,
+            self.settings.target_height,
+            stats.lines,
+        );
+
+        let mut record = CompleteRecord::default();
+        record.add_segment(segment);
+        stats.session = compute_stats(&record);
+        Ok((record, stats))
+    }
+}
+
+/// The final placement of every piece locked so far this game, for writing run codes.
+#[derive(Resource, Default)]
+pub struct Placements {
+    pub minos: Vec<Mino>,
+    /// Set once the game branches from a replay, since the placements then no longer describe a
+    /// single run from the start
+    pub branched: bool,
+}
+
+pub(crate) fn reset_placements(mut placements: ResMut<Placements>) {
+    *placements = default();
+}
+
+pub(crate) fn mark_branched(mut placements: ResMut<Placements>) {
+    placements.branched = true;
+}
+
+pub(crate) fn collect_placements(
+    mut placements: ResMut<Placements>,
+    mut locks: EventReader<PieceLocked>,
+) {
+    let live = locks.read().filter(|lock| !lock.replayed);
+    placements.minos.extend(live.map(|lock| lock.mino));
+}
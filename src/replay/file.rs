@@ -0,0 +1,307 @@
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use bevy::render::view::screenshot::ScreenshotManager;
+use bevy::utils::thiserror;
+use serde::{Deserialize, Serialize};
+
+use crate::board::MinoKind;
+use crate::export::{draw_watermark, ExportSettings};
+use crate::replay::bookmarks::{Bookmark, Bookmarks};
+use crate::replay::record::{CompleteRecord, FrameRate, RecordData, RecordItem, SettingsSnapshot};
+
+pub const REPLAYS_DIR: &str = "replays";
+/// Ending given to the names of replays imported from TETR.IO, which keep only the placements of
+/// the original run
+pub const IMPORTED_SUFFIX: &str = ".tetrio";
+
+/// Written at the start of every replay file, followed by the version of the format it is in
+const MAGIC: &str = "stack-practice-replay";
+/// The version of the format that replays are written in
+pub const FORMAT_VERSION: u32 = 3;
+/// The oldest version of the format that can still be read. Files from before the format was
+/// versioned have no header at all, and are taken to be version 1.
+pub const OLDEST_VERSION: u32 = 1;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReplayFileError {
+    #[error("Could not access replay file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not read replay file: {0}")]
+    Parse(#[from] ron::de::SpannedError),
+    #[error("Could not write replay file: {0}")]
+    Serialize(#[from] ron::Error),
+    #[error("Replay file has a malformed header")]
+    BadHeader,
+    #[error("Replay from a newer version of the game (format version {0})")]
+    NewerVersion(u32),
+    #[error("Replay is in format {0}, which is too old to be read")]
+    OlderVersion(u32),
+    #[error("A screenshot is already being taken")]
+    ScreenshotPending,
+}
+
+/// The time at which the last game ended, in seconds since the unix epoch. Files saved from the
+/// same game are named after this time, so that they can be matched up.
+#[derive(Resource, Clone, Copy)]
+pub struct RunTimestamp(pub u64);
+
+impl RunTimestamp {
+    pub fn now() -> Self {
+        Self(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        )
+    }
+
+    fn path(&self, extension: &str) -> PathBuf {
+        Path::new(REPLAYS_DIR).join(format!("{}.{extension}", self.0))
+    }
+}
+
+pub(crate) fn stamp_run(mut commands: Commands) {
+    commands.insert_resource(RunTimestamp::now());
+}
+
+/// Where a replay file came from, to help make sense of replays sent in with reports
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct ReplayMetadata {
+    /// Version of the game that saved the replay
+    pub game_version: String,
+    /// When the replay was saved, in seconds since the unix epoch
+    pub saved_at: u64,
+    /// The rate of the frames that the times of the items are given in. This is why version 3 of
+    /// the format was needed, as older versions would read the times at the wrong rate; files in
+    /// those versions were all taken at the default rate.
+    #[serde(default)]
+    pub frame_rate: FrameRate,
+}
+
+impl ReplayMetadata {
+    /// The metadata of a replay of the given record being saved by this version of the game
+    fn now(record: &CompleteRecord) -> Self {
+        Self {
+            game_version: env!("CARGO_PKG_VERSION").to_string(),
+            saved_at: RunTimestamp::now().0,
+            frame_rate: record.frame_rate,
+        }
+    }
+}
+
+/// A record as it is saved to disk. Only the chain of segments being viewed is kept, flattened into
+/// a single list of items.
+///
+/// On disk, the record is preceded by a header line naming the version of the format. Every field
+/// added since the first version has a default, so that older files are read into the same struct,
+/// with whatever they lack filled in.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ReplayFile {
+    pub items: Vec<RecordItem>,
+    /// Bookmarks left on frames of the replay. Files saved before bookmarks existed have none.
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
+    /// The settings that the game was started with, if they were known
+    #[serde(default)]
+    pub settings: Option<SettingsSnapshot>,
+    /// Files from before the format was versioned have no metadata
+    #[serde(default)]
+    pub metadata: ReplayMetadata,
+}
+
+/// Splits the text of a replay file into the version of its format and the record after the
+/// header
+fn split_header(text: &str) -> Result<(u32, &str), ReplayFileError> {
+    let Some(rest) = text.strip_prefix(MAGIC) else {
+        return Ok((1, text));
+    };
+    let (version, body) = rest.split_once('\n').unwrap_or((rest, ""));
+    let version = version
+        .trim()
+        .parse()
+        .map_err(|_| ReplayFileError::BadHeader)?;
+    Ok((version, body))
+}
+
+impl ReplayFile {
+    pub fn from_record(record: &CompleteRecord) -> Self {
+        Self {
+            items: record.get(0..record.len()).iter().cloned().collect(),
+            bookmarks: Vec::new(),
+            settings: record.snapshot.clone(),
+            metadata: ReplayMetadata::now(record),
+        }
+    }
+
+    /// Keeps the bookmarks which the saved chain passes through
+    pub fn with_bookmarks(mut self, bookmarks: &Bookmarks, record: &CompleteRecord) -> Self {
+        self.bookmarks = bookmarks.reachable(record);
+        self
+    }
+
+    /// A replay of only the given frames of the record, as a file of its own which begins on the
+    /// first of them. The bookmarks in the range are kept, moved along with the items.
+    pub fn from_chapter(
+        record: &CompleteRecord,
+        bookmarks: &Bookmarks,
+        frames: RangeInclusive<u64>,
+    ) -> Self {
+        let (start, end) = frames.clone().into_inner();
+        Self {
+            items: record.chapter(start, end),
+            bookmarks: bookmarks
+                .reachable(record)
+                .into_iter()
+                .filter(|bookmark| frames.contains(&bookmark.frame))
+                .map(|mut bookmark| {
+                    bookmark.frame -= start;
+                    bookmark
+                })
+                .collect(),
+            settings: record.snapshot.clone(),
+            metadata: ReplayMetadata::now(record),
+        }
+    }
+
+    /// The items of the replay, with their times moved to frames of the given rate, so that they
+    /// line up with a record taken at that rate
+    pub fn items_at(&self, rate: FrameRate) -> Vec<RecordItem> {
+        let from = self.metadata.frame_rate;
+        self.items
+            .iter()
+            .map(|item| RecordItem {
+                time: from.convert(item.time, rate),
+                ..item.clone()
+            })
+            .collect()
+    }
+
+    /// The pieces dealt in the replay, if the game was a drill of only some of the given pieces. The
+    /// queue is written into the replay whole, so the pieces of its bags are always known.
+    pub fn drill_pieces(&self, all: &[MinoKind]) -> Option<Vec<MinoKind>> {
+        let kinds = self.items.iter().find_map(|item| match &item.data {
+            RecordData::QueueChange(queue) => Some(queue.kinds()),
+            _ => None,
+        })?;
+        (kinds != all).then_some(kinds)
+    }
+
+    /// Reads a replay from the text of a replay file in any version of the format from
+    /// [`OLDEST_VERSION`] up to [`FORMAT_VERSION`], along with the version it was in
+    pub fn parse(text: &str) -> Result<(u32, Self), ReplayFileError> {
+        let (version, body) = split_header(text)?;
+        if version > FORMAT_VERSION {
+            return Err(ReplayFileError::NewerVersion(version));
+        }
+        if version < OLDEST_VERSION {
+            return Err(ReplayFileError::OlderVersion(version));
+        }
+        Ok((version, ron::from_str(body)?))
+    }
+
+    /// Writes the replay as the text of a replay file, in the current version of the format
+    pub fn to_text(&self) -> Result<String, ReplayFileError> {
+        Ok(format!(
+            "{MAGIC} {FORMAT_VERSION}\n{}",
+            ron::to_string(self)?
+        ))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ReplayFileError> {
+        Self::parse(&std::fs::read_to_string(path)?).map(|(_, file)| file)
+    }
+
+    /// Writes the replay into the replay directory, named after the time the run ended, and returns
+    /// the path that was written to.
+    pub fn save(&self, run: RunTimestamp) -> Result<PathBuf, ReplayFileError> {
+        self.save_to(run.path("ron"))
+    }
+
+    /// Writes the replay beside the replay of the same run, marked as having had its idle time
+    /// trimmed
+    pub fn save_trimmed(&self, run: RunTimestamp) -> Result<PathBuf, ReplayFileError> {
+        self.save_to(run.path("trimmed.ron"))
+    }
+
+    /// Writes a chapter of a replay beside the replay of the same run, named after the frames it
+    /// covers
+    pub fn save_chapter(
+        &self,
+        run: RunTimestamp,
+        frames: RangeInclusive<u64>,
+    ) -> Result<PathBuf, ReplayFileError> {
+        self.save_to(run.path(&format!("{}-{}.ron", frames.start(), frames.end())))
+    }
+
+    /// Writes a replay imported from another game into the replay directory, named after the file
+    /// it was imported from and marked as imported
+    pub fn save_imported(&self, stem: &str) -> Result<PathBuf, ReplayFileError> {
+        self.save_to(Path::new(REPLAYS_DIR).join(format!("{stem}{IMPORTED_SUFFIX}.ron")))
+    }
+
+    fn save_to(&self, path: PathBuf) -> Result<PathBuf, ReplayFileError> {
+        std::fs::create_dir_all(REPLAYS_DIR)?;
+        std::fs::write(&path, self.to_text()?)?;
+        Ok(path)
+    }
+}
+
+/// Captures the given region of the window (in logical pixels) and writes it as a PNG into the
+/// replay directory, beside the replay of the same run, with the watermark of the export settings
+/// drawn on. The image is written once the frame has been rendered, so failing to write it is only
+/// logged.
+pub fn save_screenshot(
+    screenshots: &mut ScreenshotManager,
+    (window, scale_factor): (Entity, f32),
+    region: Rect,
+    export: &ExportSettings,
+    run: RunTimestamp,
+) -> Result<PathBuf, ReplayFileError> {
+    let path = run.path("png");
+    std::fs::create_dir_all(REPLAYS_DIR)?;
+
+    let region = URect::from_corners(
+        (region.min * scale_factor).as_uvec2(),
+        (region.max * scale_factor).as_uvec2(),
+    );
+    let target = path.clone();
+    let watermark = export.watermark_text(run.0);
+    let corner = export.watermark_corner;
+    screenshots
+        .take_screenshot(window, move |image| {
+            let saved = image
+                .try_into_dynamic()
+                .map_err(|e| e.to_string())
+                .and_then(|image| {
+                    let mut image = image
+                        .crop_imm(region.min.x, region.min.y, region.width(), region.height())
+                        .to_rgba8();
+                    if let Some(text) = &watermark {
+                        draw_watermark(&mut image, text, corner);
+                    }
+                    image.save(&target).map_err(|e| e.to_string())
+                });
+            if let Err(e) = saved {
+                tracing::warn!("Could not save screenshot: {e}");
+            }
+        })
+        .map_err(|_| ReplayFileError::ScreenshotPending)?;
+    Ok(path)
+}
+
+/// Lists the replay files in the replay directory, most recent first.
+pub fn list_replays() -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(REPLAYS_DIR) else {
+        return Vec::new();
+    };
+
+    let mut replays: Vec<_> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "ron"))
+        .collect();
+    replays.sort_unstable_by(|a, b| b.cmp(a));
+    replays
+}
@@ -0,0 +1,81 @@
+//! Zooming the camera in on the active piece during the replay, so that fine movements can be seen.
+
+use bevy::prelude::*;
+
+use crate::animation::{CameraTarget, CameraZoom, REPLAY_CAMERA_ZOOM};
+use crate::assets::tables::QueryShapeTable;
+use crate::board::{Active, Bounds, CELL_SIZE};
+use crate::controller::keybinds::{BindingContext, Hotkey};
+use crate::replay::ghost::Ghost;
+use crate::screens::GlobalSettings;
+
+/// Toggles whether the camera follows the active piece in the replay
+pub const FOCUS_TOGGLE_KEY: KeyCode = KeyCode::KeyF;
+
+pub(crate) const HOTKEYS: &[Hotkey] = &[Hotkey {
+    keys: &[FOCUS_TOGGLE_KEY],
+    name: "Follow Active Piece",
+    context: BindingContext::Replay,
+}];
+
+/// Whether the camera follows the active piece in the replay
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct FocusActivePiece(bool);
+
+pub(crate) fn toggle_focus(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut focus: ResMut<FocusActivePiece>,
+    mut zoom: ResMut<CameraZoom>,
+    mut target: ResMut<CameraTarget>,
+    settings: Res<GlobalSettings>,
+) {
+    if keys.just_pressed(FOCUS_TOGGLE_KEY) {
+        **focus = !**focus;
+        if !**focus {
+            **zoom = REPLAY_CAMERA_ZOOM;
+            **target = Vec2::ZERO;
+        }
+    }
+    if **focus && (focus.is_changed() || settings.is_changed()) {
+        **zoom = REPLAY_CAMERA_ZOOM / settings.focus_zoom;
+    }
+}
+
+/// Points the camera at the middle of the active piece, or at the middle of the board while there
+/// is no active piece.
+pub(crate) fn follow_active_piece(
+    focus: Res<FocusActivePiece>,
+    boards: Query<(&Active, &GlobalTransform, &Bounds), Without<Ghost>>,
+    shape_table: QueryShapeTable,
+    mut target: ResMut<CameraTarget>,
+) {
+    if !**focus {
+        return;
+    }
+    let Ok((active, board_transform, bounds)) = boards.get_single() else {
+        return;
+    };
+
+    let local = active.0.map_or(Vec2::ZERO, |mino| {
+        let cells = &shape_table[mino];
+        let center = cells
+            .iter()
+            .map(|&cell| (cell + mino.position).as_vec2() + 0.5)
+            .sum::<Vec2>()
+            / cells.len() as f32;
+        (center - bounds.legal_bounds.as_vec2() / 2.) * CELL_SIZE as f32
+    });
+    let world = board_transform
+        .transform_point(local.extend(0.0))
+        .truncate();
+    if **target != world {
+        **target = world;
+    }
+}
+
+/// Leaves the replay with the camera framing the whole board again, for the zoom of the next state
+/// to take over from
+pub(crate) fn reset_focus(mut focus: ResMut<FocusActivePiece>, mut target: ResMut<CameraTarget>) {
+    **focus = false;
+    **target = Vec2::ZERO;
+}
@@ -0,0 +1,91 @@
+//! A second record played back on its own board beside the main one, for comparing attempts.
+
+use bevy::prelude::*;
+
+use crate::board::{Board, BoardQuery, CELL_SIZE};
+use crate::replay::record::RecordItem;
+use crate::replay::replay::ReplayInfo;
+
+/// Horizontal distance from the main board to the ghost board, far enough that the hold and queue
+/// of both boards are clear of each other
+const GHOST_OFFSET: f32 = 20.0 * CELL_SIZE as f32;
+
+/// Marks the board which plays the comparison record. Systems that assume the board is unique should
+/// exclude this board.
+#[derive(Component)]
+pub struct Ghost;
+
+/// The record played on the ghost board, and how far into it the ghost board has been brought.
+#[derive(Resource)]
+pub struct GhostReplay {
+    items: Vec<RecordItem>,
+    /// Number of items which have been applied to the ghost board
+    ix: usize,
+    frame: u64,
+    /// The board playing this record, once it has been spawned
+    board: Option<Entity>,
+}
+
+impl GhostReplay {
+    pub fn new(items: Vec<RecordItem>) -> Self {
+        Self {
+            items,
+            ix: 0,
+            frame: 0,
+            board: None,
+        }
+    }
+}
+
+/// Spawns a fresh ghost board whenever a comparison record is loaded, replacing any older one.
+pub(crate) fn spawn_ghost_board(
+    mut commands: Commands,
+    mut ghost: ResMut<GhostReplay>,
+    ghosts: Query<Entity, With<Ghost>>,
+) {
+    if ghost.board.is_none() {
+        for e in ghosts.iter() {
+            commands.entity(e).despawn_recursive();
+        }
+        ghost.board = Some(
+            commands
+                .spawn((Board::at(Vec3::X * GHOST_OFFSET), Ghost))
+                .id(),
+        );
+    }
+}
+
+pub(crate) fn remove_ghost_board(mut commands: Commands, ghosts: Query<Entity, With<Ghost>>) {
+    for e in ghosts.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+    commands.remove_resource::<GhostReplay>();
+}
+
+/// Brings the ghost board to the frame that the main replay is on. Moving forward applies the items
+/// in between, while moving backward rebuilds the ghost board from the start of its record. Once
+/// the end of the record is reached, the ghost board simply stays as it is.
+pub(crate) fn drive_ghost(
+    info: Res<ReplayInfo>,
+    mut ghost: ResMut<GhostReplay>,
+    mut boards: Query<BoardQuery, With<Ghost>>,
+) {
+    let Some(Ok(mut board)) = ghost.board.map(|e| boards.get_mut(e)) else {
+        return;
+    };
+
+    if info.frame < ghost.frame {
+        board.matrix.clear();
+        board.clear_board();
+        ghost.ix = 0;
+    }
+    ghost.frame = info.frame;
+
+    let GhostReplay {
+        items, ix, frame, ..
+    } = &mut *ghost;
+    for item in items[*ix..].iter().take_while(|item| item.time <= *frame) {
+        board.apply_record(item);
+        *ix += 1;
+    }
+}
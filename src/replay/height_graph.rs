@@ -0,0 +1,124 @@
+//! A graph of the height of the stack over the course of the record, drawn beside the replay's
+//! progress bar. Clicking the graph seeks the replay to that point.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::board::{Matrix, MinoKind};
+use crate::replay::record::{CompleteRecord, RecordData};
+use crate::replay::replay::ReplayInfo;
+
+/// Width of the graph, in logical pixels
+const GRAPH_WIDTH: f32 = 48.0;
+/// Space between the graph and the progress bar, in logical pixels
+const GRAPH_GAP: f32 = 8.0;
+/// The stack height at which the graph is full, so that the graph keeps the same scale between
+/// records
+const GRAPH_MAX_HEIGHT: u32 = 20;
+
+/// The height of the stack after each frame on which the matrix changed, in order of time
+#[derive(Resource, Default)]
+pub struct HeightHistory(pub Vec<(u64, u32)>);
+
+impl HeightHistory {
+    /// Plays the changes to the matrix through the record, counting the filled cells in each row
+    /// so that the height of the stack is known at every change
+    pub fn from_record(record: &CompleteRecord) -> Self {
+        let mut filled = vec![0u32; Matrix::default().height()];
+        let mut history: Vec<(u64, u32)> = Vec::new();
+
+        for item in record.get(0..record.len()).iter() {
+            let RecordData::MatrixChange(update) = &item.data else {
+                continue;
+            };
+            let Some(count) = filled.get_mut(update.loc.y as usize) else {
+                continue;
+            };
+            *count = (*count + (update.new != MinoKind::E) as u32)
+                .saturating_sub((update.old != MinoKind::E) as u32);
+
+            let height = filled
+                .iter()
+                .rposition(|&n| n > 0)
+                .map_or(0, |y| y as u32 + 1);
+            match history.last_mut() {
+                Some((time, h)) if *time == item.time => *h = height,
+                _ => history.push((item.time, height)),
+            }
+        }
+
+        Self(history)
+    }
+}
+
+pub(crate) fn track_heights(mut history: ResMut<HeightHistory>, record: Res<CompleteRecord>) {
+    if record.is_changed() {
+        *history = HeightHistory::from_record(&record);
+    }
+}
+
+/// Draws the graph with time running downward alongside the progress bar, and the height of the
+/// stack growing leftward from it.
+pub(crate) fn height_graph(
+    mut contexts: EguiContexts,
+    history: Res<HeightHistory>,
+    record: Res<CompleteRecord>,
+    mut replay_info: ResMut<ReplayInfo>,
+    time: Res<Time>,
+) {
+    let last_frame = record.last_frame().max(1) as f32;
+    let ctx = contexts.ctx_mut();
+    let screen = ctx.screen_rect();
+
+    // matches the placement of the progress bar
+    let right = screen.width() * 0.95 - GRAPH_GAP;
+    let rect = egui::Rect::from_min_max(
+        egui::pos2(right - GRAPH_WIDTH, screen.height() * 0.025),
+        egui::pos2(right, screen.height() * 0.975),
+    );
+
+    egui::Area::new("height_graph")
+        .fixed_pos(rect.min)
+        .show(ctx, |ui| {
+            let response = ui.allocate_rect(rect, egui::Sense::click_and_drag());
+            let painter = ui.painter_at(rect);
+            painter.rect_filled(rect, 2.0, egui::Color32::from_black_alpha(120));
+
+            let point = |frame: u64, height: u32| {
+                let x = (height.min(GRAPH_MAX_HEIGHT) as f32) / GRAPH_MAX_HEIGHT as f32;
+                let y = frame as f32 / last_frame;
+                egui::pos2(
+                    rect.right() - x * rect.width(),
+                    rect.top() + y * rect.height(),
+                )
+            };
+            // the height holds until the next change, so the line steps between changes
+            let mut points = Vec::with_capacity(history.0.len() * 2);
+            let mut previous = 0;
+            for &(frame, height) in &history.0 {
+                points.push(point(frame, previous));
+                points.push(point(frame, height));
+                previous = height;
+            }
+            points.push(point(record.last_frame(), previous));
+            painter.add(egui::Shape::line(
+                points,
+                egui::Stroke::new(1.5, egui::Color32::from_rgb(240, 160, 80)),
+            ));
+
+            let now = rect.top() + replay_info.frame as f32 / last_frame * rect.height();
+            painter.hline(
+                rect.x_range(),
+                now,
+                egui::Stroke::new(1.0, egui::Color32::WHITE),
+            );
+
+            if let Some(pos) = response.interact_pointer_pos() {
+                let t = ((pos.y - rect.top()) / rect.height()).clamp(0.0, 1.0);
+                let frame = (t * last_frame).round() as u64;
+                if frame != replay_info.frame {
+                    replay_info.seek(frame, &record, &time);
+                }
+            }
+        });
+}
@@ -0,0 +1,166 @@
+//! The games finished this session, kept in memory so that an earlier game can be replayed (and
+//! branched from) without having been saved. Only the most recent games are kept.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::board::{BoardQuery, Settings};
+use crate::replay::code::{Placements, RunSettings};
+use crate::replay::ghost::Ghost;
+use crate::replay::record::{CompleteRecord, RecordItem};
+use crate::screens::GlobalSettings;
+use crate::state::MainState;
+use crate::stats::Stats;
+
+/// Games kept in the history before the oldest is forgotten
+pub const HISTORY_LENGTH: usize = 10;
+
+/// A finished game, as it was when the player left its replay
+pub struct HistoryEntry {
+    /// The number of the game within the session, counting from 1
+    pub game: u32,
+    pub record: CompleteRecord,
+    pub stats: Stats,
+    pub placements: Placements,
+    pub settings: RunSettings,
+}
+
+impl HistoryEntry {
+    /// Roughly how many bytes the record takes up. Segments shared with other records are counted
+    /// in full, and the memory held by each item beyond its own size is left out.
+    pub fn approximate_size(&self) -> usize {
+        let items: usize = self.record.segments.iter().map(|s| s.len()).sum();
+        items * std::mem::size_of::<RecordItem>()
+    }
+}
+
+/// The most recently finished games, newest first
+#[derive(Resource, Default)]
+pub struct SessionHistory {
+    pub entries: VecDeque<HistoryEntry>,
+    games: u32,
+    /// The number of the game loaded out of the history, which it keeps when it goes back in
+    viewing: Option<u32>,
+}
+
+impl SessionHistory {
+    /// Adds a game to the front of the history, forgetting the oldest game if the history is full
+    pub fn push(&mut self, mut entry: HistoryEntry) {
+        if entry.game == 0 {
+            self.games += 1;
+            entry.game = self.games;
+        }
+        self.entries.push_front(entry);
+        self.entries.truncate(HISTORY_LENGTH);
+    }
+}
+
+/// A game taken out of the history, which is loaded once the board has been reset for it
+#[derive(Resource)]
+pub struct PendingHistoryLoad(HistoryEntry);
+
+/// Moves the game which was just left into the history, before the record is reset for the next
+pub(crate) fn archive_record(
+    mut history: ResMut<SessionHistory>,
+    mut record: ResMut<CompleteRecord>,
+    mut placements: ResMut<Placements>,
+    stats: Res<Stats>,
+    boards: Query<&Settings, Without<Ghost>>,
+) {
+    if record.len() == 0 {
+        return;
+    }
+    let Ok(settings) = boards.get_single() else {
+        return;
+    };
+    let game = history.viewing.take().unwrap_or(0);
+    history.push(HistoryEntry {
+        game,
+        record: std::mem::take(&mut *record),
+        stats: stats.clone(),
+        placements: std::mem::take(&mut *placements),
+        settings: RunSettings::from(settings),
+    });
+}
+
+/// Lists the games in the history. Picking one while a replay is open leaves the replay (which
+/// puts it in the history in turn) and loads the picked game once the board is back.
+pub(crate) fn history_panel(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    mut history: ResMut<SessionHistory>,
+    state: Res<State<MainState>>,
+    mut next_state: ResMut<NextState<MainState>>,
+) {
+    if history.entries.is_empty() {
+        return;
+    }
+
+    let mut picked = None;
+    egui::Window::new("Session")
+        .anchor(egui::Align2::RIGHT_CENTER, [-10.0, 0.0])
+        .default_open(false)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Grid::new("session_history_inner").show(ui, |ui| {
+                for (ix, entry) in history.entries.iter().enumerate() {
+                    ui.label(format!("Game {}", entry.game));
+                    ui.label(entry.settings.mode.to_string());
+                    ui.label(format!(
+                        "{} pieces, {:.1}s",
+                        entry.stats.pieces, entry.stats.time
+                    ));
+                    ui.weak(format!("~{} KB", entry.approximate_size().div_ceil(1024)));
+                    if ui.button("Load").clicked() {
+                        picked = Some(ix);
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+
+    let Some(entry) = picked.and_then(|ix| history.entries.remove(ix)) else {
+        return;
+    };
+    commands.insert_resource(PendingHistoryLoad(entry));
+    if *state.get() == MainState::PostGame {
+        next_state.set(MainState::Ready);
+    }
+}
+
+/// Brings the board to the end of the pending game and opens its replay, just as loading a run
+/// code does
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn load_pending(
+    mut commands: Commands,
+    pending: Option<ResMut<PendingHistoryLoad>>,
+    mut history: ResMut<SessionHistory>,
+    mut boards: Query<BoardQuery, Without<Ghost>>,
+    mut settings: ResMut<GlobalSettings>,
+    mut record: ResMut<CompleteRecord>,
+    mut stats: ResMut<Stats>,
+    mut placements: ResMut<Placements>,
+    mut next_state: ResMut<NextState<MainState>>,
+) {
+    let Some(mut pending) = pending else {
+        return;
+    };
+    // the board is respawned on entering Ready, so it may not be back yet
+    let Ok(mut board) = boards.get_single_mut() else {
+        return;
+    };
+    let entry = &mut pending.0;
+    for item in entry.record.get(0..entry.record.len()).iter() {
+        board.apply_record(item);
+    }
+
+    history.viewing = Some(entry.game);
+    settings.load_run_settings(&entry.settings);
+    *record = std::mem::take(&mut entry.record);
+    *stats = entry.stats.clone();
+    *placements = std::mem::take(&mut entry.placements);
+    commands.remove_resource::<PendingHistoryLoad>();
+    next_state.set(MainState::PostGame);
+}
@@ -1,32 +1,148 @@
-use crate::replay::record::{record, CompleteRecord, FirstFrame, PartialRecord};
+use crate::replay::bookmarks::Bookmarks;
+use crate::replay::branch_diff::BranchComparison;
+use crate::replay::chapter::Chapter;
+use crate::replay::code::Placements;
+use crate::replay::focus::FocusActivePiece;
+use crate::replay::ghost::GhostReplay;
+use crate::replay::height_graph::HeightHistory;
+use crate::replay::history::SessionHistory;
+use crate::replay::move_list::MoveList;
+use crate::replay::pace_graph::PaceHistory;
+use crate::replay::record::{record, CompleteRecord, FirstFrame, FixedTick, PartialRecord};
 use crate::replay::replay::{replay, DeferUnfreeze, ReplayInfo};
+use crate::replay::trail::AlwaysShowTrail;
+use crate::schedule::FrameSet;
 use crate::state::MainState;
 use crate::{board, controller};
 use bevy::prelude::*;
 
+pub mod bookmarks;
+pub mod branch_diff;
+pub mod chapter;
+pub mod code;
+pub mod file;
+pub mod focus;
+pub mod ghost;
+pub mod height_graph;
+pub mod history;
+pub mod move_list;
+pub mod pace_graph;
 pub mod record;
 pub mod replay;
+pub mod segment_legend;
+pub mod tetrio;
+pub mod trail;
+pub mod verify;
 
 pub struct ReplayPlugin;
 
 impl Plugin for ReplayPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<CompleteRecord>()
+            .init_resource::<Placements>()
             .init_resource::<PartialRecord>()
+            .init_resource::<HeightHistory>()
+            .init_resource::<MoveList>()
+            .init_resource::<PaceHistory>()
+            .init_resource::<BranchComparison>()
+            .init_resource::<Bookmarks>()
+            .init_resource::<Chapter>()
+            .init_resource::<SessionHistory>()
+            .init_resource::<FixedTick>()
+            .init_resource::<FocusActivePiece>()
+            .init_resource::<AlwaysShowTrail>()
             .add_event::<DeferUnfreeze>()
             .add_systems(
                 Update,
                 replay
                     .run_if(in_state(MainState::PostGame).and_then(resource_changed::<ReplayInfo>)),
             )
+            .add_systems(
+                Update,
+                (height_graph::track_heights, height_graph::height_graph)
+                    .chain()
+                    .run_if(in_state(MainState::PostGame)),
+            )
+            .add_systems(
+                Update,
+                (move_list::track_moves, move_list::move_list_panel)
+                    .chain()
+                    .run_if(in_state(MainState::PostGame)),
+            )
+            .add_systems(
+                Update,
+                (pace_graph::track_pace, pace_graph::pace_graph)
+                    .chain()
+                    .run_if(in_state(MainState::PostGame)),
+            )
+            .add_systems(
+                Update,
+                segment_legend::segment_legend
+                    .after(move_list::track_moves)
+                    .run_if(in_state(MainState::PostGame)),
+            )
+            .add_systems(
+                Update,
+                (branch_diff::track_branches, branch_diff::branch_diff_panel)
+                    .chain()
+                    .run_if(in_state(MainState::PostGame)),
+            )
+            .add_systems(
+                Update,
+                (bookmarks::bookmark_panel, bookmarks::mark_bookmarks)
+                    .chain()
+                    .run_if(in_state(MainState::PostGame)),
+            )
+            .add_systems(
+                Update,
+                (chapter::mark_chapter, chapter::mark_chapter_ends)
+                    .chain()
+                    .run_if(in_state(MainState::PostGame)),
+            )
+            .add_systems(
+                Update,
+                replay::fade_seek_marker.run_if(in_state(MainState::PostGame)),
+            )
+            .add_systems(
+                Update,
+                (focus::toggle_focus, focus::follow_active_piece)
+                    .chain()
+                    .after(replay)
+                    .run_if(in_state(MainState::PostGame)),
+            )
+            .add_systems(
+                Update,
+                (trail::toggle_trail, trail::update_trail)
+                    .chain()
+                    .after(replay)
+                    .run_if(in_state(MainState::PostGame)),
+            )
             .add_systems(
                 PostUpdate,
-                record.run_if(resource_exists::<FirstFrame>.and_then(in_state(MainState::Playing))),
+                record.in_set(FrameSet::Record).run_if(
+                    resource_exists::<FirstFrame>
+                        .and_then(in_state(MainState::Playing))
+                        .and_then(not(board::fixed_timestep)),
+                ),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    record::advance_tick,
+                    record::record_tick.run_if(
+                        resource_exists::<FirstFrame>
+                            .and_then(in_state(MainState::Playing))
+                            .and_then(board::fixed_timestep),
+                    ),
+                )
+                    .chain()
+                    .after(board::update::update_board),
             )
             .add_systems(
                 PostUpdate,
                 (
                     replay::adjust_replay,
+                    replay::jump_to_spawn,
                     replay::advance_frame,
                     replay::update_progress,
                     replay::exit_replay.before(controller::reset_controller),
@@ -34,6 +150,23 @@ impl Plugin for ReplayPlugin {
                     .chain()
                     .run_if(in_state(MainState::PostGame)),
             )
+            .add_systems(
+                PostUpdate,
+                (ghost::spawn_ghost_board, ghost::drive_ghost)
+                    .chain()
+                    .after(replay::advance_frame)
+                    .run_if(in_state(MainState::PostGame).and_then(resource_exists::<GhostReplay>)),
+            )
+            .add_systems(
+                PostUpdate,
+                code::collect_placements
+                    .in_set(FrameSet::Record)
+                    .run_if(in_state(MainState::Playing)),
+            )
+            .add_systems(
+                Update,
+                record::checkpoint.run_if(in_state(MainState::Playing)),
+            )
             .add_systems(OnExit(MainState::Playing), record::finalize_record)
             // systems which run when starting a clean record
             .add_systems(
@@ -41,14 +174,43 @@ impl Plugin for ReplayPlugin {
                     from: MainState::PostGame,
                     to: MainState::Ready,
                 },
-                record::reset_record,
+                (
+                    (history::archive_record, record::reset_record).chain(),
+                    bookmarks::reset_bookmarks,
+                    chapter::reset_chapter,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    history::history_panel
+                        .run_if(in_state(MainState::Ready).or_else(in_state(MainState::PostGame))),
+                    // the pending game waits for the board to be reset before it loads
+                    history::load_pending.run_if(in_state(MainState::Ready)),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                OnTransition {
+                    from: MainState::Playing,
+                    to: MainState::Ready,
+                },
+                (
+                    record::reset_record,
+                    bookmarks::reset_bookmarks,
+                    chapter::reset_chapter,
+                ),
             )
             .add_systems(
                 OnTransition {
                     from: MainState::Ready,
                     to: MainState::Playing,
                 },
-                record::initialize_time,
+                (
+                    record::initialize_time.before(board::start_game),
+                    record::snapshot_settings,
+                    code::reset_placements,
+                ),
             )
             // systems which run when beginning a new segment into a record
             .add_systems(
@@ -56,7 +218,12 @@ impl Plugin for ReplayPlugin {
                     from: MainState::PostGame,
                     to: MainState::Playing,
                 },
-                record::begin_new_segment,
+                (
+                    record::begin_new_segment,
+                    record::check_branch_frame_rate,
+                    record::apply_snapshot,
+                    code::mark_branched,
+                ),
             )
             .add_systems(
                 Update,
@@ -67,11 +234,22 @@ impl Plugin for ReplayPlugin {
             // common systems which run on each entrance into/exit from replay
             .add_systems(
                 OnEnter(MainState::PostGame),
-                (replay::initialize_replay, replay::setup_progress_bar),
+                (
+                    replay::initialize_replay,
+                    replay::setup_progress_bar,
+                    trail::spawn_trail,
+                    file::stamp_run,
+                ),
             )
             .add_systems(
                 OnExit(MainState::PostGame),
-                (replay::cleanup_replay, replay::remove_progress_bar),
+                (
+                    replay::cleanup_replay,
+                    replay::remove_progress_bar,
+                    focus::reset_focus,
+                    ghost::remove_ghost_board,
+                    trail::remove_trail,
+                ),
             );
     }
 }
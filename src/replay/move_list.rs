@@ -0,0 +1,149 @@
+//! A list of the pieces placed over the record, marking those which could have been held instead
+//! and the combo and back to back chain standing after each. Runs of clears in a row are bracketed,
+//! and clicking a bracket seeks the replay to the start of its run. Clicking a piece seeks the
+//! replay to where it locked.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::assets::tables::QueryShapeTable;
+use crate::replay::record::CompleteRecord;
+use crate::replay::replay::ReplayInfo;
+use crate::stats::{lock_outcomes, LockOutcome, BIG_COMBO};
+
+const COMBO_COLOR: egui::Color32 = egui::Color32::from_rgb(90, 170, 255);
+const BIG_COMBO_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 150, 40);
+const BRACKET_WIDTH: f32 = 8.0;
+
+/// What each piece of the record being viewed did, worked out again whenever the record changes
+#[derive(Resource, Default)]
+pub struct MoveList(pub Vec<LockOutcome>);
+
+impl MoveList {
+    /// The first piece of the run of clears that the given piece belongs to, along with whether
+    /// the piece is the last of the run. Nothing if the piece cleared nothing.
+    fn run(&self, ix: usize) -> Option<(usize, bool)> {
+        let combo = self.0[ix].combo as usize;
+        let last = self.0.get(ix + 1).map_or(true, |next| next.combo <= 1);
+        (combo > 0).then(|| (ix + 1 - combo, last))
+    }
+}
+
+pub(crate) fn track_moves(
+    mut moves: ResMut<MoveList>,
+    record: Res<CompleteRecord>,
+    shape_table: QueryShapeTable,
+) {
+    if record.is_changed() {
+        moves.0 = lock_outcomes(&record, &shape_table);
+    }
+}
+
+/// Draws the part of a bracket beside one piece of a run of clears. Returns whether it was clicked.
+fn bracket(ui: &mut egui::Ui, first: bool, last: bool, color: egui::Color32) -> egui::Response {
+    let height = ui.spacing().interact_size.y;
+    let (rect, response) =
+        ui.allocate_exact_size(egui::vec2(BRACKET_WIDTH, height), egui::Sense::click());
+    // reach over the spacing between rows, so that the bracket is drawn unbroken
+    let gap = ui.spacing().item_spacing.y / 2.0;
+    let top = if first {
+        rect.center().y
+    } else {
+        rect.top() - gap
+    };
+    let bottom = if last {
+        rect.center().y
+    } else {
+        rect.bottom() + gap
+    };
+    let x = rect.center().x;
+    let stroke = egui::Stroke::new(2.0, color);
+    let painter = ui.painter();
+    painter.line_segment([egui::pos2(x, top), egui::pos2(x, bottom)], stroke);
+    for (end, y) in [(first, top), (last, bottom)] {
+        if end {
+            painter.line_segment([egui::pos2(x, y), egui::pos2(rect.right(), y)], stroke);
+        }
+    }
+    response
+}
+
+pub(crate) fn move_list_panel(
+    mut contexts: EguiContexts,
+    moves: Res<MoveList>,
+    record: Res<CompleteRecord>,
+    mut replay_info: ResMut<ReplayInfo>,
+    time: Res<Time>,
+) {
+    let mut seek = None;
+    egui::Window::new("Placements")
+        .anchor(egui::Align2::LEFT_BOTTOM, [10.0, -10.0])
+        .default_open(false)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let holds_ignored = moves.0.iter().filter(|o| o.lock.hold_available).count();
+            ui.label(format!(
+                "Holds ignored: {holds_ignored} of {} pieces",
+                moves.0.len()
+            ));
+            ui.separator();
+            egui::ScrollArea::vertical()
+                .max_height(240.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("move_list_inner").show(ui, |ui| {
+                        for (i, outcome) in moves.0.iter().enumerate() {
+                            let lock = outcome.lock;
+                            match moves.run(i) {
+                                // a single clear makes no run worth bracketing
+                                Some((start, last)) if !(start == i && last) => {
+                                    let end = (i..moves.0.len())
+                                        .find(|&ix| moves.run(ix).map_or(true, |(_, last)| last))
+                                        .unwrap_or(i);
+                                    let length = moves.0[end].combo;
+                                    let color = if length >= BIG_COMBO {
+                                        BIG_COMBO_COLOR
+                                    } else {
+                                        COMBO_COLOR
+                                    };
+                                    let clicked = bracket(ui, start == i, last, color)
+                                        .on_hover_text(format!("{length} clears in a row"))
+                                        .clicked();
+                                    if clicked {
+                                        seek = Some(moves.0[start].lock.frame);
+                                    }
+                                }
+                                _ => {
+                                    ui.label("");
+                                }
+                            }
+                            if ui.button(format!("{}", i + 1)).clicked() {
+                                seek = Some(lock.frame);
+                            }
+                            ui.label(format!("{:?}", lock.mino.kind));
+                            ui.label(format!("frame {}", lock.frame));
+                            if outcome.combo > 1 {
+                                ui.label(format!("combo {}", outcome.combo - 1));
+                            } else {
+                                ui.label("");
+                            }
+                            if outcome.lines > 0 && outcome.b2b > 1 {
+                                ui.label(format!("B2B {}", outcome.b2b - 1));
+                            } else {
+                                ui.label("");
+                            }
+                            if lock.hold_available {
+                                ui.colored_label(egui::Color32::YELLOW, "hold unused")
+                                    .on_hover_text("This piece could have been swapped into hold");
+                            } else {
+                                ui.label("");
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+        });
+
+    if let Some(frame) = seek {
+        replay_info.seek(frame, &record, &time);
+    }
+}
@@ -0,0 +1,141 @@
+//! A graph of the pieces per second and attack per minute over the course of the record, so that
+//! the places where the pace fell apart can be found. Hovering the graph shows the values under the
+//! pointer, and clicking it seeks the replay to that point.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::assets::tables::QueryShapeTable;
+use crate::replay::record::CompleteRecord;
+use crate::replay::replay::ReplayInfo;
+use crate::stats::{pace, Pace, PaceMarker, PacePoint, PACE_WINDOW};
+
+const GRAPH_SIZE: egui::Vec2 = egui::vec2(320.0, 120.0);
+const PPS_COLOR: egui::Color32 = egui::Color32::from_rgb(110, 200, 250);
+const APM_COLOR: egui::Color32 = egui::Color32::from_rgb(240, 120, 160);
+/// The least that the top of each scale can be, so that a slow game is not stretched to fill the
+/// graph
+const MIN_PPS_SCALE: f32 = 1.0;
+const MIN_APM_SCALE: f32 = 30.0;
+
+/// The pace of the record being viewed, worked out again whenever the record changes
+#[derive(Resource, Default)]
+pub struct PaceHistory(pub Pace);
+
+pub(crate) fn track_pace(
+    mut pace_history: ResMut<PaceHistory>,
+    record: Res<CompleteRecord>,
+    shape_table: QueryShapeTable,
+) {
+    if record.is_changed() {
+        pace_history.0 = pace(&record, &shape_table);
+    }
+}
+
+fn marker_style(marker: PaceMarker) -> (egui::Color32, String) {
+    match marker {
+        PaceMarker::TopOut => (egui::Color32::RED, "Top out".into()),
+        PaceMarker::PerfectClear => (egui::Color32::GOLD, "Perfect clear".into()),
+        PaceMarker::Combo(clears) => (egui::Color32::LIGHT_GREEN, format!("{clears} combo")),
+    }
+}
+
+pub(crate) fn pace_graph(
+    mut contexts: EguiContexts,
+    pace_history: Res<PaceHistory>,
+    record: Res<CompleteRecord>,
+    mut replay_info: ResMut<ReplayInfo>,
+    time: Res<Time>,
+) {
+    let pace = &pace_history.0;
+    if pace.points.is_empty() {
+        return;
+    }
+    let last_frame = record.last_frame().max(1) as f32;
+    let pps_scale = pace
+        .points
+        .iter()
+        .map(|p| p.pps)
+        .fold(MIN_PPS_SCALE, f32::max);
+    let apm_scale = pace
+        .points
+        .iter()
+        .map(|p| p.apm)
+        .fold(MIN_APM_SCALE, f32::max);
+
+    egui::Window::new("Pace")
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+        .default_open(false)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.colored_label(PPS_COLOR, format!("PPS (up to {pps_scale:.2})"));
+                ui.colored_label(APM_COLOR, format!("APM (up to {apm_scale:.0})"));
+            });
+            ui.weak(format!("Averaged over {PACE_WINDOW:.0}s"));
+
+            let (response, painter) =
+                ui.allocate_painter(GRAPH_SIZE, egui::Sense::click_and_drag());
+            let rect = response.rect;
+            painter.rect_filled(rect, 2.0, egui::Color32::from_black_alpha(120));
+
+            let x = |frame: u64| rect.left() + frame as f32 / last_frame * rect.width();
+            let y = |value: f32, scale: f32| rect.bottom() - value / scale * rect.height();
+
+            for &(frame, marker) in &pace.markers {
+                let (color, _) = marker_style(marker);
+                painter.vline(
+                    x(frame),
+                    rect.y_range(),
+                    egui::Stroke::new(1.0, color.gamma_multiply(0.6)),
+                );
+            }
+
+            let line = |value: fn(&PacePoint) -> f32, scale: f32, color: egui::Color32| {
+                let points = pace
+                    .points
+                    .iter()
+                    .map(|p| egui::pos2(x(p.frame), y(value(p), scale)))
+                    .collect();
+                painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, color)));
+            };
+            line(|p| p.pps, pps_scale, PPS_COLOR);
+            line(|p| p.apm, apm_scale, APM_COLOR);
+
+            painter.vline(
+                x(replay_info.frame),
+                rect.y_range(),
+                egui::Stroke::new(1.0, egui::Color32::WHITE),
+            );
+
+            let Some(pos) = response.hover_pos() else {
+                return;
+            };
+            let frame = ((pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0) * last_frame;
+            let frame = frame.round() as u64;
+            // the pace holds from each piece until the next, so the point before the pointer counts
+            let point = pace
+                .points
+                .iter()
+                .rev()
+                .find(|p| p.frame <= frame)
+                .unwrap_or(&pace.points[0]);
+            let near = pace
+                .markers
+                .iter()
+                .filter(|(f, _)| (x(*f) - pos.x).abs() < 4.0)
+                .map(|&(_, marker)| marker_style(marker));
+            let response = response.on_hover_ui_at_pointer(|ui| {
+                ui.label(format!("{:.1}s", record.frame_rate.seconds(point.frame)));
+                ui.colored_label(PPS_COLOR, format!("{:.2} PPS", point.pps));
+                ui.colored_label(APM_COLOR, format!("{:.1} APM", point.apm));
+                for (color, text) in near {
+                    ui.colored_label(color, text);
+                }
+            });
+
+            if (response.clicked() || response.dragged()) && frame != replay_info.frame {
+                replay_info.seek(frame, &record, &time);
+            }
+        });
+}
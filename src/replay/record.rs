@@ -1,12 +1,79 @@
 use crate::board::{
-    queue::PieceQueue, Active, BoardQueryItem, Hold, Matrix, MatrixUpdate, Mino, MinoKind,
+    queue::PieceQueue, Active, BoardQueryItem, Hold, Matrix, MatrixUpdate, Mino, MinoKind, Settings,
 };
+use crate::controller::keybinds::{BindingContext, Hotkey};
+use crate::replay::ghost::Ghost;
 use crate::replay::replay::ReplayInfo;
-use bevy::math::ivec2;
+use crate::screens::GlobalSettings;
+use crate::toasts::Toasts;
 use bevy::prelude::*;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use smart_default::SmartDefault;
 use std::ops::{Index, Range};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub const CHECKPOINT_KEY: KeyCode = KeyCode::F6;
+
+pub(crate) const HOTKEYS: &[Hotkey] = &[Hotkey {
+    keys: &[CHECKPOINT_KEY],
+    name: "Checkpoint Record",
+    context: BindingContext::Playing,
+}];
+/// Frames between the keyframes of a record
+const KEYFRAME_INTERVAL: u64 = 600;
+/// The longest that the record goes without any items before the time counts as idle, in seconds
+pub const IDLE_GAP: f32 = 1.0;
+
+/// How many frames a record counts each second. The times of a record's items are in these frames,
+/// so a higher rate lets the replay be scrubbed more finely. A record keeps the same rate
+/// throughout, across all of its branches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deref, SmartDefault, Serialize, Deserialize)]
+pub struct FrameRate(#[default(60)] u64);
+
+impl FrameRate {
+    /// The rates which records can be taken at
+    pub const CHOICES: [u64; 4] = [30, 60, 120, 240];
+
+    pub fn new(rate: u64) -> Self {
+        Self(rate.max(1))
+    }
+
+    /// Discretizes the time since the game started up into frames
+    pub fn discretize(&self, time: &Time) -> u64 {
+        (time.elapsed().as_micros() * self.0 as u128 / 1_000_000) as u64
+    }
+
+    /// Converts frames into microseconds
+    pub fn frame_to_micros(&self, frame: u64) -> u64 {
+        frame * 1_000_000 / self.0
+    }
+
+    /// Converts ticks of a fixed timestep of the given length into frames
+    pub fn ticks_to_frames(&self, ticks: u64, timestep: Duration) -> u64 {
+        (ticks as u128 * timestep.as_micros() * self.0 as u128 / 1_000_000) as u64
+    }
+
+    /// Converts frames into whole ticks of a fixed timestep of the given length
+    pub fn frames_to_ticks(&self, frames: u64, timestep: Duration) -> u64 {
+        (self.frame_to_micros(frames) as u128 / timestep.as_micros().max(1)) as u64
+    }
+
+    pub fn seconds(&self, frames: u64) -> f32 {
+        frames as f32 / self.0 as f32
+    }
+
+    /// The number of whole frames in the given number of seconds
+    pub fn frames(&self, seconds: f32) -> u64 {
+        (seconds * self.0 as f32) as u64
+    }
+
+    /// Moves a frame of a record taken at this rate to the same time in a record taken at another
+    pub fn convert(&self, frame: u64, to: FrameRate) -> u64 {
+        (frame as u128 * to.0 as u128 / self.0 as u128) as u64
+    }
+}
 
 #[derive(Deref, DerefMut, Default, Debug)]
 pub struct RecordSegment {
@@ -15,40 +82,250 @@ pub struct RecordSegment {
     children: Mutex<Vec<(u64, Arc<RecordSegment>)>>,
 }
 
+impl RecordSegment {
+    /// The segments which branch from this one, with the frame that each begins on
+    pub fn children(&self) -> Vec<(u64, Arc<RecordSegment>)> {
+        self.children.lock().unwrap().clone()
+    }
+
+    /// Whether another segment carries on from the end of this one, rather than branching from
+    /// somewhere inside it
+    fn is_continued(&self) -> bool {
+        let last = self.last().map_or(0, |item| item.time);
+        self.children.lock().unwrap().iter().any(|(t, _)| *t > last)
+    }
+
+    /// The index of the first item which a child beginning on the given frame replaces
+    fn separation(&self, first_frame: u64) -> usize {
+        self.data
+            .iter()
+            .position(|e| e.time >= first_frame)
+            .unwrap_or(self.data.len())
+    }
+}
+
 /// The record being built by the current game
 #[derive(Resource, Deref, DerefMut, Default, Debug)]
 pub struct PartialRecord(RecordSegment);
 
 /// The chain of segments that the player is currently viewing
-#[derive(Resource, Deref, DerefMut, Default, Debug)]
+#[derive(Resource, Deref, DerefMut, Default, Debug, Clone)]
 pub struct CompleteRecord {
     #[deref]
     pub segments: Vec<Arc<RecordSegment>>,
     pub separations: Vec<usize>,
+    /// The settings that the game was started with. Records played back from run codes have none,
+    /// since run codes leave out the handling.
+    pub snapshot: Option<SettingsSnapshot>,
+    pub frame_rate: FrameRate,
+}
+
+/// The settings of the board when a game started, along with the seed that its randomizer was
+/// started from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsSnapshot {
+    pub settings: Settings,
+    pub seed: u64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecordItem {
+    /// Time since the start of the record, in frames of the record's [`FrameRate`]
     pub time: u64,
+    /// Time since the start of the record, in microseconds
+    #[serde(default)]
+    pub micros: u64,
+    /// Ticks of the fixed timestep since the start of the record, if the board ran on a fixed
+    /// timestep. The other times are worked out from this one.
+    #[serde(default)]
+    pub tick: Option<u64>,
     pub data: RecordData,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RecordData {
     ActiveChange(Option<Mino>),
     QueueChange(PieceQueue),
     Hold(Hold),
     MatrixChange(MatrixUpdate),
+    /// The whole state of the board, which adds nothing to the items before it. Playing the record
+    /// skips over keyframes, but seeking can start from one instead of from the start.
+    Keyframe(Box<Keyframe>),
+}
+
+/// The state of the board at one point of the record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keyframe {
+    /// The rows of the matrix from the bottom up, as far as the highest row with anything in it
+    rows: Vec<Vec<MinoKind>>,
+    active: Option<Mino>,
+    hold: Hold,
+    queue: PieceQueue,
+}
+
+impl Keyframe {
+    fn new(matrix: &Matrix, active: Option<Mino>, hold: Hold, queue: &PieceQueue) -> Self {
+        let height = matrix
+            .rows()
+            .rposition(|row| row.iter().any(|&kind| kind != MinoKind::E))
+            .map_or(0, |y| y + 1);
+        Self {
+            rows: matrix.rows().take(height).map(<[_]>::to_vec).collect(),
+            active,
+            hold,
+            queue: queue.clone(),
+        }
+    }
 }
 
 impl CompleteRecord {
+    /// The frame of the last item, or zero if the record is empty (as when the game ends before
+    /// anything is recorded)
     pub fn last_frame(&self) -> u64 {
-        self.last().unwrap().last().unwrap().time
+        self.last()
+            .and_then(|segment| segment.last())
+            .map_or(0, |item| item.time)
     }
 
     #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
-        *self.separations.last().unwrap() + self.segments.last().unwrap().data.len()
+        self.separations
+            .last()
+            .zip(self.segments.last())
+            .map_or(0, |(base, segment)| base + segment.data.len())
+    }
+
+    /// Finds the frame of the first item at or after the given time (in microseconds), so that the
+    /// replay can be sought by real time. Past the end of the record, this is the last frame.
+    pub fn frame_at_micros(&self, micros: u64) -> u64 {
+        self.get(0..self.len())
+            .iter()
+            .find(|item| item.micros >= micros)
+            .map_or_else(|| self.last_frame(), |item| item.time)
+    }
+
+    /// A copy of the record in which every gap between items of the viewed chain longer than the
+    /// given number of frames is cut down to that length. Every item is moved by the same function of
+    /// its time, so the items keep their order and each segment still branches from the same point
+    /// of its parent. Ticks cannot be moved without knowing the timestep, so they are dropped from
+    /// the items after the first cut.
+    pub fn trim_idle(&self, max_gap: u64) -> CompleteRecord {
+        let gaps = self
+            .get(0..self.len())
+            .iter()
+            .map(|item| item.time)
+            .tuple_windows()
+            .filter(|(start, end)| end.saturating_sub(*start) > max_gap)
+            .collect::<Vec<_>>();
+        // a time inside a gap is cut only as far as the gap has gone past its allowed length, so
+        // that no time can be moved past the end of the gap
+        let cut = |time: u64| -> u64 {
+            gaps.iter()
+                .map(|&(start, end)| time.clamp(start + max_gap, end) - (start + max_gap))
+                .sum()
+        };
+
+        let mut trimmed = CompleteRecord {
+            snapshot: self.snapshot.clone(),
+            frame_rate: self.frame_rate,
+            ..default()
+        };
+        for segment in &self.segments {
+            let data = segment
+                .iter()
+                .map(|item| {
+                    let cut = cut(item.time);
+                    RecordItem {
+                        time: item.time - cut,
+                        micros: item
+                            .micros
+                            .saturating_sub(self.frame_rate.frame_to_micros(cut)),
+                        tick: item.tick.filter(|_| cut == 0),
+                        data: item.data.clone(),
+                    }
+                })
+                .collect();
+            trimmed.add_segment(RecordSegment {
+                data,
+                children: default(),
+            });
+        }
+        trimmed
+    }
+
+    /// The items of the viewed chain after the start frame, up to and including the end frame, as a
+    /// record of their own which begins on the start frame. The board as it stood on the start
+    /// frame is worked out by playing the items before it on from the last keyframe, and is written
+    /// on the first frame of the chapter as though the game had begun that way, both as changes to
+    /// an empty matrix and as a keyframe. As with [`Self::trim_idle`], ticks are dropped.
+    pub fn chapter(&self, start: u64, end: u64) -> Vec<RecordItem> {
+        let items = self.get(0..self.len()).iter().collect_vec();
+        let split = items.partition_point(|item| item.time <= start);
+        let from = items[..split]
+            .iter()
+            .rposition(|item| matches!(item.data, RecordData::Keyframe(_)))
+            .unwrap_or(0);
+
+        let mut matrix = Matrix::default();
+        let (mut active, mut hold, mut queue) = (None, Hold::default(), None);
+        for item in &items[from..split] {
+            match &item.data {
+                RecordData::ActiveChange(mino) => active = *mino,
+                RecordData::QueueChange(new_queue) => queue = Some(new_queue.clone()),
+                RecordData::Hold(new_hold) => hold = *new_hold,
+                RecordData::MatrixChange(update) => {
+                    if let Some(cell) = matrix.get_mut(update.loc) {
+                        *cell = update.new;
+                    }
+                }
+                RecordData::Keyframe(keyframe) => {
+                    matrix.clear();
+                    for (row, data) in matrix.rows_mut().zip(&keyframe.rows) {
+                        row.copy_from_slice(data);
+                    }
+                    active = keyframe.active;
+                    hold = keyframe.hold;
+                    queue = Some(keyframe.queue.clone());
+                }
+            }
+        }
+
+        let filled = matrix
+            .iter_cells()
+            .filter(|&(_, kind)| kind != MinoKind::E)
+            .map(|(loc, kind)| {
+                RecordData::MatrixChange(MatrixUpdate {
+                    loc,
+                    old: MinoKind::E,
+                    new: kind,
+                })
+            });
+        let keyframe = queue.as_ref().map(|queue| {
+            RecordData::Keyframe(Box::new(Keyframe::new(&matrix, active, hold, queue)))
+        });
+        let initial = [RecordData::ActiveChange(active), RecordData::Hold(hold)]
+            .into_iter()
+            .chain(queue.clone().map(RecordData::QueueChange))
+            .chain(filled)
+            .chain(keyframe)
+            .map(|data| RecordItem {
+                time: 0,
+                micros: 0,
+                tick: None,
+                data,
+            });
+
+        let micros = self.frame_rate.frame_to_micros(start);
+        let rest = items[split..]
+            .iter()
+            .take_while(|item| item.time <= end)
+            .map(|item| RecordItem {
+                time: item.time - start,
+                micros: item.micros.saturating_sub(micros),
+                tick: None,
+                data: item.data.clone(),
+            });
+        initial.chain(rest).collect()
     }
 
     pub fn get(&self, range: Range<usize>) -> RecordSlice {
@@ -58,7 +335,14 @@ impl CompleteRecord {
         }
     }
 
+    /// Adds a segment to the end of the chain, branching from the last segment at the time of its
+    /// first item. A segment which begins after the last item of its parent simply continues it.
+    /// Empty segments are ignored unless the record has no segments yet.
     pub fn add_segment(&mut self, segment: RecordSegment) {
+        if segment.is_empty() && !self.segments.is_empty() {
+            return;
+        }
+
         let segment = Arc::new(segment);
         if let Some(parent) = self.segments.last_mut() {
             let first_frame = segment.first().unwrap().time;
@@ -70,18 +354,14 @@ impl CompleteRecord {
                 .position(|(t, _)| *t > first_frame)
                 .unwrap_or(children.len());
 
-            // find the separation location
-            let separation_ix = parent
-                .data
-                .iter()
-                .position(|e| e.time >= first_frame)
-                .unwrap();
+            let separation_ix = parent.separation(first_frame);
+            let base = *self.separations.last().unwrap();
 
             children.insert(location, (first_frame, segment.clone()));
             drop(children);
 
             self.segments.push(segment);
-            self.separations.push(separation_ix);
+            self.separations.push(base + separation_ix);
         } else {
             self.separations = vec![0];
             self.segments = vec![segment];
@@ -89,6 +369,75 @@ impl CompleteRecord {
     }
 }
 
+impl CompleteRecord {
+    /// The record of a chain of segments which are already linked to one another, each a child of
+    /// the one before it
+    fn from_chain(segments: Vec<Arc<RecordSegment>>, parent: &CompleteRecord) -> Self {
+        let mut separations = vec![0];
+        for (parent, child) in segments.iter().tuple_windows() {
+            let first_frame = child.first().map_or(0, |item| item.time);
+            let base = *separations.last().unwrap();
+            separations.push(base + parent.separation(first_frame));
+        }
+        Self {
+            segments,
+            separations,
+            snapshot: parent.snapshot.clone(),
+            frame_rate: parent.frame_rate,
+        }
+    }
+
+    /// Every chain of segments which grows from the same first segment as this record, each
+    /// ending on a segment which nothing carries on from. The chain being viewed is among them.
+    pub fn branches(&self) -> Vec<CompleteRecord> {
+        let Some(root) = self.segments.first() else {
+            return Vec::new();
+        };
+        let mut branches = Vec::new();
+        let mut chains = vec![vec![root.clone()]];
+        while let Some(chain) = chains.pop() {
+            let tip = chain.last().unwrap();
+            if !tip.is_continued() {
+                branches.push(CompleteRecord::from_chain(chain.clone(), self));
+            }
+            // pushed in reverse, so that the earliest branch is visited first
+            for (_, child) in tip.children().into_iter().rev() {
+                let mut longer = chain.clone();
+                longer.push(child);
+                chains.push(longer);
+            }
+        }
+        branches
+    }
+
+    /// Whether this record is made of the very same segments as another
+    pub fn same_chain(&self, other: &CompleteRecord) -> bool {
+        self.segments.len() == other.segments.len()
+            && self
+                .segments
+                .iter()
+                .zip(&other.segments)
+                .all(|(a, b)| Arc::ptr_eq(a, b))
+    }
+
+    /// The frame from which this record and another go their own ways, or nothing if they are the
+    /// same chain
+    pub fn divergence(&self, other: &CompleteRecord) -> Option<u64> {
+        let shared = self
+            .segments
+            .iter()
+            .zip(&other.segments)
+            .take_while(|(a, b)| Arc::ptr_eq(a, b))
+            .count();
+        [self.segments.get(shared), other.segments.get(shared)]
+            .into_iter()
+            .flatten()
+            .filter_map(|segment| segment.first())
+            .map(|item| item.time)
+            .min()
+    }
+}
+
 impl Index<usize> for CompleteRecord {
     type Output = RecordItem;
 
@@ -110,7 +459,7 @@ pub struct RecordSlice<'a> {
 }
 
 impl<'a> RecordSlice<'a> {
-    pub fn iter(&self) -> RecordSliceIter {
+    pub fn iter(&self) -> RecordSliceIter<'a> {
         RecordSliceIter {
             position: self.range.start,
             rposition: self.range.end,
@@ -146,95 +495,181 @@ impl<'a> DoubleEndedIterator for RecordSliceIter<'a> {
     }
 }
 
+/// The engine time at which the record began, from which the times of record items are measured
 #[derive(Resource)]
-pub struct FirstFrame(pub u64);
+pub struct FirstFrame {
+    /// In frames of the record's [`FrameRate`]
+    pub frame: u64,
+    pub micros: u64,
+    /// In ticks of the fixed timestep
+    pub tick: u64,
+}
 
-/// Discretizes time into 60ths of a second
-pub fn discretized_time(time: &Time) -> u64 {
-    (time.elapsed().as_millis() * 60 / 1000) as u64
+impl FirstFrame {
+    pub fn now(time: &Time, tick: &FixedTick, rate: FrameRate) -> Self {
+        Self {
+            frame: rate.discretize(time),
+            micros: precise_time(time),
+            tick: **tick,
+        }
+    }
+}
+
+/// Number of ticks of the fixed timestep since the game started up
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct FixedTick(u64);
+
+pub(crate) fn advance_tick(mut tick: ResMut<FixedTick>) {
+    **tick += 1;
+}
+
+/// Time in microseconds
+pub fn precise_time(time: &Time) -> u64 {
+    time.elapsed().as_micros() as u64
 }
 
 /// A record of what the contents of the matrix were in the previous frame. The frame transition is
 /// managed by [`record`]
-#[derive(Component, Deref, DerefMut, SmartDefault)]
-pub struct PreviousMatrix {
-    #[default(Matrix::default().data)]
-    data: Vec<Vec<MinoKind>>,
-}
+#[derive(Component, Deref, DerefMut, Default)]
+pub struct PreviousMatrix(Matrix);
 
 /// Compares the contents of the new and old matrices, at the same time replacing the contents of
 /// old with new. Since each update contains its own position information, the order in which the
 /// updates are applied is important and should be kept.
-#[allow(clippy::ptr_arg)]
-fn diff_and_copy<'a>(
-    // TODO better lifetime management
-    new: &'a Vec<Vec<MinoKind>>,
-    old: &'a mut Vec<Vec<MinoKind>>,
+pub(crate) fn diff_and_copy<'a>(
+    new: &'a Matrix,
+    old: &'a mut Matrix,
 ) -> impl Iterator<Item = MatrixUpdate> + 'a {
-    let row_size = old[0].len();
-    let old_mut = old.iter_mut().flat_map(|i| i.iter_mut());
-    let new_updates = old_mut
-        .zip(new.iter().flat_map(|i| i.iter().copied()))
-        .zip((0..).map(move |ix| ivec2(ix % row_size as i32, ix / row_size as i32)))
-        .filter(|((old, new), _)| *old != new)
-        .map(|((old, new), loc)| {
+    old.cells_mut()
+        .iter_mut()
+        .zip(new.cells().iter().copied())
+        .enumerate()
+        .filter(|(_, (old_kind, new_kind))| **old_kind != *new_kind)
+        .map(move |(ix, (old_kind, new_kind))| {
             let update = MatrixUpdate {
-                loc,
-                old: *old,
-                new,
+                loc: new.location(ix),
+                old: *old_kind,
+                new: new_kind,
             };
-            *old = new;
+            *old_kind = new_kind;
             update
-        });
-    new_updates
+        })
 }
 
-pub(crate) fn record(
-    mut state: Query<(
-        Ref<Active>,
-        Ref<PieceQueue>,
-        Ref<Hold>,
-        Ref<Matrix>,
-        &mut PreviousMatrix,
-    )>,
-    mut record: ResMut<PartialRecord>,
-    time: Res<Time>,
-    first_frame: Res<FirstFrame>,
+/// The parts of the board which are recorded
+type RecordedState<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Ref<'static, Active>,
+        Ref<'static, PieceQueue>,
+        Ref<'static, Hold>,
+        Ref<'static, Matrix>,
+        &'static mut PreviousMatrix,
+    ),
+>;
+
+/// Records each part of the board which changed since the last time the board was recorded, at
+/// the given time. A keyframe follows the changes once enough time has passed since the last one.
+fn record_changes(
+    state: &mut RecordedState,
+    record: &mut PartialRecord,
+    last_keyframe: &mut Option<u64>,
+    (time, micros, tick): (u64, u64, Option<u64>),
 ) {
-    let current_frame = discretized_time(&time);
-    let dt = current_frame - first_frame.0;
     for (active, queue, hold, matrix, mut previous_matrix) in state.iter_mut() {
         if active.is_changed() {
             record.push(RecordItem {
                 data: RecordData::ActiveChange(active.0),
-                time: dt,
+                time,
+                micros,
+                tick,
             })
         }
 
         if queue.is_changed() {
             record.push(RecordItem {
                 data: RecordData::QueueChange(queue.clone()),
-                time: dt,
+                time,
+                micros,
+                tick,
             })
         }
 
         if hold.is_changed() {
             record.push(RecordItem {
                 data: RecordData::Hold(*hold),
-                time: dt,
+                time,
+                micros,
+                tick,
             })
         }
 
         if matrix.is_changed() {
-            let updates = diff_and_copy(&matrix.data, &mut previous_matrix.data);
+            let updates = diff_and_copy(&matrix, &mut previous_matrix);
             record.extend(updates.map(|up| RecordItem {
                 data: RecordData::MatrixChange(up),
-                time: dt,
+                time,
+                micros,
+                tick,
             }))
         }
+
+        // a new game or segment may start earlier than the last keyframe
+        if last_keyframe.map_or(true, |last| time >= last + KEYFRAME_INTERVAL || time < last) {
+            *last_keyframe = Some(time);
+            record.push(RecordItem {
+                data: RecordData::Keyframe(Box::new(Keyframe::new(
+                    &matrix, active.0, *hold, &queue,
+                ))),
+                time,
+                micros,
+                tick,
+            });
+        }
     }
 }
 
+pub(crate) fn record(
+    mut state: RecordedState,
+    mut record: ResMut<PartialRecord>,
+    complete: Res<CompleteRecord>,
+    time: Res<Time>,
+    first_frame: Res<FirstFrame>,
+    mut last_keyframe: Local<Option<u64>>,
+) {
+    let dt = complete.frame_rate.discretize(&time) - first_frame.frame;
+    let micros = precise_time(&time).saturating_sub(first_frame.micros);
+    record_changes(
+        &mut state,
+        &mut record,
+        &mut last_keyframe,
+        (dt, micros, None),
+    );
+}
+
+/// Records the board after each tick of the fixed timestep. Items are timed by the number of ticks
+/// since the record began rather than by the clock, so that the record comes out the same no
+/// matter the frame rate.
+pub(crate) fn record_tick(
+    mut state: RecordedState,
+    mut record: ResMut<PartialRecord>,
+    complete: Res<CompleteRecord>,
+    tick: Res<FixedTick>,
+    fixed: Res<Time<Fixed>>,
+    first_frame: Res<FirstFrame>,
+    mut last_keyframe: Local<Option<u64>>,
+) {
+    let ticks = tick.saturating_sub(first_frame.tick);
+    let timestep = fixed.timestep();
+    let times = (
+        complete.frame_rate.ticks_to_frames(ticks, timestep),
+        ticks * timestep.as_micros() as u64,
+        Some(ticks),
+    );
+    record_changes(&mut state, &mut record, &mut last_keyframe, times);
+}
+
 pub(crate) fn finalize_record(
     mut complete: ResMut<CompleteRecord>,
     mut finished: ResMut<PartialRecord>,
@@ -242,6 +677,19 @@ pub(crate) fn finalize_record(
     complete.add_segment(std::mem::take(&mut **finished));
 }
 
+/// Closes the segment being recorded and carries on recording into a new one, without leaving
+/// the game. Since the new segment continues from the same first frame, it picks up exactly where
+/// the old one left off.
+pub(crate) fn checkpoint(
+    mut complete: ResMut<CompleteRecord>,
+    mut partial: ResMut<PartialRecord>,
+    keys: Res<ButtonInput<KeyCode>>,
+) {
+    if keys.just_pressed(CHECKPOINT_KEY) && !partial.is_empty() {
+        complete.add_segment(std::mem::take(&mut **partial));
+    }
+}
+
 impl<'world> BoardQueryItem<'world> {
     pub fn apply_record(&mut self, record: &RecordItem) {
         match &record.data {
@@ -249,11 +697,21 @@ impl<'world> BoardQueryItem<'world> {
             RecordData::QueueChange(new_queue) => *(self.queue) = new_queue.clone(),
             RecordData::Hold(replace_with) => *(self.hold) = *replace_with,
             RecordData::MatrixChange(update) => {
-                self.matrix.data[update.loc.y as usize][update.loc.x as usize] = update.new;
+                *self.matrix.get_mut(update.loc).unwrap() = update.new;
             }
+            RecordData::Keyframe(_) => (),
         }
     }
 
+    /// Puts the board into the state held by the given keyframe
+    pub fn restore_keyframe(&mut self, keyframe: &Keyframe) {
+        self.matrix.clear();
+        self.fill_from_bottom(keyframe.rows.clone());
+        self.active.0 = keyframe.active;
+        *self.hold = keyframe.hold;
+        *self.queue = keyframe.queue.clone();
+    }
+
     /// This function undoes a record which has been previously been applied through
     /// [`Self::apply_record`]. This can be used, for example, to rewind through a record.
     pub fn undo_record(&mut self, record: &RecordItem) {
@@ -263,6 +721,8 @@ impl<'world> BoardQueryItem<'world> {
                 self.apply_record(&RecordItem {
                     data: RecordData::MatrixChange(update),
                     time: record.time,
+                    micros: record.micros,
+                    tick: record.tick,
                 }) // TODO this should be cleaner (no need to duplicate time, etc)
             }
             _ => self.apply_record(record),
@@ -271,20 +731,102 @@ impl<'world> BoardQueryItem<'world> {
 }
 
 pub(crate) fn reset_record(mut commands: Commands) {
-    commands.init_resource::<PartialRecord>();
-    commands.init_resource::<CompleteRecord>();
+    commands.insert_resource(PartialRecord::default());
+    commands.insert_resource(CompleteRecord::default());
+}
+
+/// The state of a board before the first piece of the game spawns
+pub(crate) fn initial_state(queue: &PieceQueue, hold: Hold) -> [RecordData; 3] {
+    [
+        RecordData::ActiveChange(None),
+        RecordData::Hold(hold),
+        RecordData::QueueChange(queue.clone()),
+    ]
 }
 
 /// When a new record has been instantiated and a game begins, insert the [`FirstFrame`] resource
-/// referring to the current frame
-pub(crate) fn initialize_time(mut commands: Commands, time: Res<Time>) {
-    commands.insert_resource(FirstFrame(discretized_time(&time)));
+/// referring to the current frame. The record takes the frame rate in the settings, which it keeps
+/// from then on. The state of the board before the game starts is recorded on frame 0, so that
+/// rewinding to the very beginning of the replay has a state to return to.
+pub(crate) fn initialize_time(
+    mut commands: Commands,
+    time: Res<Time>,
+    tick: Res<FixedTick>,
+    settings: Res<GlobalSettings>,
+    mut complete: ResMut<CompleteRecord>,
+    mut record: ResMut<PartialRecord>,
+    boards: Query<(&PieceQueue, &Hold)>,
+) {
+    complete.frame_rate = FrameRate::new(settings.replay_frame_rate);
+    commands.insert_resource(FirstFrame::now(&time, &tick, complete.frame_rate));
+    for (queue, &hold) in boards.iter() {
+        record.extend(initial_state(queue, hold).map(|data| RecordItem {
+            time: 0,
+            micros: 0,
+            tick: Some(0),
+            data,
+        }));
+    }
+}
+
+/// Keeps the settings that the game starts with in the record, so that its replay can show them
+/// and branches from it can be played with them
+pub(crate) fn snapshot_settings(
+    mut record: ResMut<CompleteRecord>,
+    boards: Query<(&Settings, &PieceQueue), Without<Ghost>>,
+) {
+    if let Ok((settings, queue)) = boards.get_single() {
+        record.snapshot = Some(SettingsSnapshot {
+            settings: settings.clone(),
+            seed: queue.seed(),
+        });
+    }
+}
+
+/// Tells the player when a branch is recorded at another frame rate than the one in the settings,
+/// since the branch has to keep the rate of the record it branches from
+pub(crate) fn check_branch_frame_rate(
+    record: Res<CompleteRecord>,
+    settings: Res<GlobalSettings>,
+    mut toasts: ResMut<Toasts>,
+) {
+    if *record.frame_rate != settings.replay_frame_rate {
+        toasts.error(format!(
+            "This record is taken at {} frames per second, so its branches are too. The rate of {} \
+             applies from the next game.",
+            *record.frame_rate, settings.replay_frame_rate
+        ));
+    }
+}
+
+/// Plays a branch with the settings that the game was started with, so that pieces behave in the
+/// branch as they did in the game, unless the player has chosen to branch with their own settings
+pub(crate) fn apply_snapshot(
+    record: Res<CompleteRecord>,
+    global: Res<GlobalSettings>,
+    mut boards: Query<&mut Settings, Without<Ghost>>,
+    mut toasts: ResMut<Toasts>,
+) {
+    let Some(snapshot) = record.snapshot.as_ref() else {
+        return;
+    };
+    if global.branch_with_current_settings {
+        return;
+    }
+    for mut settings in boards.iter_mut() {
+        if *settings != snapshot.settings {
+            *settings = snapshot.settings.clone();
+            toasts.info("Branching with the settings the game was played with");
+        }
+    }
 }
 
 /// Prunes the record and cuts off and sets the first frame according to the current place
 pub(crate) fn begin_new_segment(
     mut commands: Commands,
     time: Res<Time>,
+    tick: Res<FixedTick>,
+    fixed: Res<Time<Fixed>>,
     mut record: ResMut<CompleteRecord>,
     meta: Res<ReplayInfo>,
     mut boards: Query<(&Matrix, &mut PreviousMatrix)>,
@@ -292,12 +834,20 @@ pub(crate) fn begin_new_segment(
     commands.init_resource::<PartialRecord>();
 
     let offset = meta.frame;
-    commands.insert_resource(FirstFrame(discretized_time(&time) - offset));
+    let rate = record.frame_rate;
+    let now = FirstFrame::now(&time, &tick, rate);
+    commands.insert_resource(FirstFrame {
+        frame: now.frame - offset,
+        micros: now.micros.saturating_sub(rate.frame_to_micros(offset)),
+        tick: now
+            .tick
+            .saturating_sub(rate.frames_to_ticks(offset, fixed.timestep())),
+    });
 
     if let Some(p) = record
         .segments
         .iter()
-        .position(|seg| seg.first().unwrap().time > meta.frame)
+        .position(|seg| seg.first().is_some_and(|item| item.time > meta.frame))
     {
         record.segments.drain(p..);
         record.separations.drain(p..);
@@ -308,6 +858,70 @@ pub(crate) fn begin_new_segment(
     // "previous frame"'s matrix (which is in use once recording starts) should actually be the same
     // as this frame's matrix
     for (this_board, mut prev_board) in boards.iter_mut() {
-        prev_board.data = this_board.data.clone()
+        **prev_board = this_board.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hint::black_box;
+    use std::time::Instant;
+
+    use super::*;
+
+    /// Times that each diff is run
+    const DIFFS: u32 = 100_000;
+
+    /// Diffs a matrix kept as a vector of rows, which is how the matrix used to be stored, in the
+    /// same way as [`diff_and_copy`]
+    fn diff_rows(new: &[Vec<MinoKind>], old: &mut [Vec<MinoKind>]) -> usize {
+        let mut changes = 0;
+        for (new_row, old_row) in new.iter().zip(old.iter_mut()) {
+            for (&new_kind, old_kind) in new_row.iter().zip(old_row.iter_mut()) {
+                if *old_kind != new_kind {
+                    *old_kind = new_kind;
+                    changes += 1;
+                }
+            }
+        }
+        changes
+    }
+
+    /// Prints how long diffing the matrix takes against diffing the same rows as a vector of rows,
+    /// from an empty matrix to a full one and back so that most cells change. Run it with
+    /// `cargo test --release --lib diff_timing -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "prints a timing rather than checking anything"]
+    fn diff_timing() {
+        let empty = Matrix::default();
+        let mut full = Matrix::default();
+        let width = full.width();
+        for (y, row) in full.rows_mut().enumerate() {
+            row.fill(MinoKind::G);
+            row[y % width] = MinoKind::E;
+        }
+
+        let start = Instant::now();
+        let mut previous = Matrix::default();
+        for _ in 0..DIFFS {
+            black_box(diff_and_copy(&full, &mut previous).count());
+            black_box(diff_and_copy(&empty, &mut previous).count());
+        }
+        let flat = start.elapsed();
+
+        let rows = |matrix: &Matrix| matrix.rows().map(<[_]>::to_vec).collect::<Vec<_>>();
+        let (full_rows, empty_rows) = (rows(&full), rows(&empty));
+        let start = Instant::now();
+        let mut previous = empty_rows.clone();
+        for _ in 0..DIFFS {
+            black_box(diff_rows(&full_rows, &mut previous));
+            black_box(diff_rows(&empty_rows, &mut previous));
+        }
+        let nested = start.elapsed();
+
+        println!(
+            "Diffing the matrix {} times took {flat:?}, against {nested:?} as a vector of rows",
+            DIFFS * 2
+        );
     }
 }
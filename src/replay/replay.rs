@@ -1,16 +1,31 @@
 //! Replay code currently depends on the board being unique in the world.
 
 use crate::animation::{CameraZoom, DEFAULT_CAMERA_ZOOM, REPLAY_CAMERA_ZOOM};
-use crate::progress_bar::{ProgressBar, ProgressBarBundle, ProgressBarMaterial};
-use crate::replay::record::discretized_time;
-use crate::replay::record::{CompleteRecord, RecordData};
+use crate::progress_bar::{
+    LabelFormat, LabelPlacement, ProgressBar, ProgressBarBundle, ProgressBarLabel,
+    ProgressBarMaterial,
+};
+use crate::replay::ghost::Ghost;
+use crate::replay::record::IDLE_GAP;
+use crate::replay::record::{CompleteRecord, RecordData, RecordItem};
 use bevy::prelude::*;
 use duplicate::duplicate;
 use itertools::Itertools;
+use std::cmp::Ordering;
 
-use crate::board::{Active, BoardQuery};
-use crate::controller::{Controller, ControllerFrozen};
+use crate::assets::palette::Palette;
+use crate::assets::tables::QueryShapeTable;
+use crate::board::events::{lock_events, HoldUsed, LinesCleared, PieceLocked};
+use crate::board::motion::PieceMotion;
+use crate::board::update::has_free_space;
+use crate::board::{Active, BoardQuery, GameMode, Hold, Mino, Settings};
+use crate::confirm::NewGameHold;
+use crate::controller::keybinds::{Action, BindingContext, BoundInput, Hotkey};
+use crate::controller::{BufferedInput, BufferedInputs, Controller, ControllerFrozen};
+use crate::screens::{GlobalSettings, RESTART_KEY};
 use crate::state::MainState;
+use crate::stats::{line_clears, sprint_milestones};
+use crate::toasts::{ToastLevel, Toasts};
 
 /// Stores information about the state of the replay (i.e. paused or played, frames progressed).
 #[derive(Resource, Default, Debug)]
@@ -24,8 +39,98 @@ pub struct ReplayInfo {
     /// The index which `ix` needs to reach in order to be on time.
     next_ix: usize,
     playing: Option<ActiveReplayMeta>,
+    /// Set when the replay jumps to a frame rather than playing up to it, so that the effects of
+    /// playing through each item are skipped
+    seeking: bool,
+    /// Whether the board was last brought up to date by a jump rather than by playing
+    jumped: bool,
+    /// What the active piece has done since it spawned, as far as can be told from the record.
+    /// This is only known once the replay has played forward through a spawn since it last went
+    /// back or jumped from a keyframe.
+    motion: Option<PieceMotion>,
 }
 
+impl ReplayInfo {
+    /// Jumps to the given frame, leaving the replay playing (from the new frame) if it was playing
+    /// before. The board catches up with the new frame the next time the replay is run.
+    pub fn seek(&mut self, frame: u64, record: &CompleteRecord, time: &Time) {
+        let frame = frame.min(record.last_frame());
+        self.frame = frame;
+        self.next_ix = record
+            .get(0..record.len())
+            .iter()
+            .position(|item| item.time > frame)
+            .unwrap_or(record.len());
+        self.seeking = true;
+        if let Some(meta) = &mut self.playing {
+            meta.record_frame = frame;
+            meta.real_frame = record.frame_rate.discretize(time);
+        }
+    }
+
+    /// Plays the replay from the frame it is on, forward or backward
+    pub fn play(&mut self, reverse: bool, record: &CompleteRecord, time: &Time) {
+        self.playing = Some(ActiveReplayMeta {
+            record_frame: self.frame,
+            real_frame: record.frame_rate.discretize(time),
+            reverse,
+        });
+    }
+
+    /// Jumps to the given frame of a record which the board has not been following, such as
+    /// another branch of the same game. Every item up to the frame is played onto the board, so
+    /// the matrix should be cleared beforehand.
+    pub fn restart(&mut self, frame: u64, record: &CompleteRecord, time: &Time) {
+        self.ix = 0;
+        self.seek(frame, record, time);
+    }
+
+    /// Whether the board is being moved around the record by hand, either by jumping to a frame or
+    /// by stepping through it while paused, rather than playing along with it
+    pub fn is_scrubbing(&self) -> bool {
+        self.jumped || self.playing.is_none()
+    }
+
+    /// Whether the replay is paused, and so only moves when stepped through or jumped around
+    pub fn is_paused(&self) -> bool {
+        self.playing.is_none()
+    }
+}
+
+/// Seeking forward by more than this many items starts from a keyframe, if there is one on the way
+const KEYFRAME_JUMP: usize = 200;
+
+/// Plays the replay backward, or pauses it if it is already playing backward
+const REVERSE_REPLAY_KEY: KeyCode = KeyCode::KeyR;
+
+pub(crate) const HOTKEYS: &[Hotkey] = &[
+    Hotkey {
+        keys: &[REVERSE_REPLAY_KEY],
+        name: "Reverse Replay",
+        context: BindingContext::Replay,
+    },
+    Hotkey {
+        keys: &[RESTART_KEY],
+        name: "New Game (Hold)",
+        context: BindingContext::Replay,
+    },
+];
+
+/// Seconds before the next item that skipping idle time stops at, so that the item is not missed
+const IDLE_SKIP_LEAD: f32 = 0.25;
+/// The most seconds of the record that the replay plays through in one update. When more time than
+/// this has passed since the last update, such as after a long hitch or the app being suspended,
+/// the replay jumps to where it should be instead, as a seek would.
+const MAX_REPLAY_STEP: f32 = 0.5;
+/// Seconds that the note of how much idle time was skipped stays up
+const IDLE_SKIP_NOTE_DURATION: f32 = 1.0;
+
+/// Seconds that the marker left on the progress bar by a jump stays visible
+const SEEK_MARKER_DURATION: f32 = 0.6;
+
+/// Color of the marks on the progress bar where each milestone of a sprint was passed
+const MILESTONE_COLOR: Color = Color::rgb(1.0, 0.8, 0.2);
+
 /// If the game is unpaused, this struct holds metadata about how the replay should be reading the record.
 #[derive(Debug, Clone, Copy)]
 pub struct ActiveReplayMeta {
@@ -40,33 +145,79 @@ pub struct ActiveReplayMeta {
 #[derive(Component)]
 pub struct ReplayBar;
 
+/// Puts the progress bar on the side of the window away from the hold box, which is the left side
+/// when the layout is mirrored
+fn place_progress_bar(style: &mut Style, settings: &GlobalSettings) {
+    let (near, far) = if settings.queue_layout.mirrored() {
+        (&mut style.left, &mut style.right)
+    } else {
+        (&mut style.right, &mut style.left)
+    };
+    *near = Val::Percent(5.0);
+    *far = Val::Auto;
+}
+
+/// A line across the progress bar where the replay just jumped to, which fades until it disappears
+#[derive(Component)]
+pub struct SeekMarker {
+    age: f32,
+}
+
+/// A mark on the progress bar where a milestone of a sprint was passed
+#[derive(Component)]
+pub struct MilestoneMarker;
+
 pub(crate) fn setup_progress_bar(
     mut commands: Commands,
     mut materials: ResMut<Assets<ProgressBarMaterial>>,
     record: Res<CompleteRecord>,
+    palette: Res<Palette>,
+    bars: Query<Entity, With<ReplayBar>>,
+    boards: Query<&Settings, Without<Ghost>>,
+    shape_table: QueryShapeTable,
+    settings: Res<GlobalSettings>,
 ) {
-    let style = Style {
+    // a bar left over from entering the replay before replaces nothing
+    for bar in bars.iter() {
+        commands.entity(bar).despawn_recursive();
+    }
+
+    // the bar needs at least one section with some length, even for a game which ended before
+    // anything was recorded
+    let mut sections = record
+        .segments
+        .iter()
+        .enumerate()
+        .filter_map(|(ix, segment)| {
+            let time = segment.last()?.time.max(1);
+            Some((time as u32, palette.segment_color(ix)))
+        })
+        .collect_vec();
+    if sections.is_empty() {
+        sections.push((1, palette.segment_color(0)));
+    }
+
+    let mut style = Style {
         position_type: PositionType::Absolute,
         height: Val::Percent(95.0),
         width: Val::Px(2.0),
-        right: Val::Percent(5.0),
         top: Val::Percent(2.5),
         ..default()
     };
+    place_progress_bar(&mut style, &settings);
 
     commands
         .spawn(ProgressBarBundle {
             progressbar: ProgressBar {
-                sections: record
-                    .segments
-                    .iter()
-                    .enumerate()
-                    .map(|(ix, segment)| {
-                        let time = segment.last().unwrap().time;
-                        let color = Color::hsl(0., 0.5, 0.85f32.powi(ix as i32));
-                        (time as u32, color)
-                    })
-                    .collect_vec(),
+                sections,
+                label: Some(ProgressBarLabel {
+                    format: LabelFormat::Frames(record.last_frame()),
+                    placement: LabelPlacement::End,
+                    style: TextStyle {
+                        font_size: 14.0,
+                        ..default()
+                    },
+                }),
                 ..default()
             },
             material_node_bundle: MaterialNodeBundle {
@@ -75,19 +226,51 @@ pub(crate) fn setup_progress_bar(
                 ..default()
             },
         })
-        .insert(ReplayBar);
+        .insert(ReplayBar)
+        .with_children(|parent| {
+            if !boards.iter().any(|s| s.mode == GameMode::Sprint) {
+                return;
+            }
+            let clears = line_clears(&record, &shape_table);
+            let last_frame = record.last_frame().max(1) as f32;
+            for frame in sprint_milestones(&clears) {
+                parent.spawn((
+                    NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            top: Val::Percent(frame as f32 / last_frame * 100.0),
+                            left: Val::Px(-3.0),
+                            width: Val::Px(8.0),
+                            height: Val::Px(2.0),
+                            ..default()
+                        },
+                        background_color: MILESTONE_COLOR.into(),
+                        ..default()
+                    },
+                    MilestoneMarker,
+                ));
+            }
+        });
 }
 
-pub(crate) fn remove_progress_bar(mut commands: Commands, bar: Query<Entity, With<ReplayBar>>) {
-    commands.entity(bar.single()).despawn_recursive();
+pub(crate) fn remove_progress_bar(mut commands: Commands, bars: Query<Entity, With<ReplayBar>>) {
+    for bar in bars.iter() {
+        commands.entity(bar).despawn_recursive();
+    }
 }
 
 pub(crate) fn update_progress(
-    mut bar: Query<&mut ProgressBar, With<ReplayBar>>,
+    mut bar: Query<(&mut ProgressBar, &mut Style), With<ReplayBar>>,
     info: Res<ReplayInfo>,
     record: Res<CompleteRecord>,
+    settings: Res<GlobalSettings>,
 ) {
-    bar.single_mut().progress = info.frame as f32 / record.last_frame() as f32;
+    if let Ok((mut bar, mut style)) = bar.get_single_mut() {
+        bar.progress = info.frame as f32 / record.last_frame().max(1) as f32;
+        if settings.is_changed() {
+            place_progress_bar(&mut style, &settings);
+        }
+    }
 }
 
 pub fn initialize_replay(
@@ -102,6 +285,9 @@ pub fn initialize_replay(
         ix: record.len(),
         next_ix: record.len(),
         playing: None,
+        seeking: false,
+        jumped: false,
+        motion: None,
     };
 
     tracing::info!("Entering replay with {replay_info:?}");
@@ -115,11 +301,51 @@ pub fn cleanup_replay(mut zoom: ResMut<CameraZoom>) {
 pub fn replay(
     record: Res<CompleteRecord>,
     mut replay_info: ResMut<ReplayInfo>,
-    mut board: Query<BoardQuery>,
+    mut board: Query<BoardQuery, Without<Ghost>>,
+    mut locks: EventWriter<PieceLocked>,
+    mut lines: EventWriter<LinesCleared>,
+    mut holds: EventWriter<HoldUsed>,
+    shape_table: QueryShapeTable,
 ) {
-    let mut board = board.single_mut();
-    if let Some(meta) = replay_info.playing {
-        if meta.reverse {
+    let Ok(mut board) = board.get_single_mut() else {
+        return;
+    };
+    replay_info.jumped = replay_info.seeking;
+
+    // Jumping back, or far ahead, restores the board from the last keyframe before the destination
+    // and plays on from there, rather than working through every item in between. The record is
+    // only searched for a keyframe when jumping, since playing never starts from one.
+    let (ix, next_ix) = (replay_info.ix, replay_info.next_ix);
+    let keyframe = replay_info
+        .seeking
+        .then(|| {
+            record
+                .get(0..next_ix)
+                .iter()
+                .rev()
+                .position(|i| matches!(i.data, RecordData::Keyframe(_)))
+                .map(|from_end| next_ix - 1 - from_end)
+        })
+        .flatten()
+        .filter(|&k| next_ix < ix || (next_ix - ix > KEYFRAME_JUMP && k > ix));
+    if let Some(k) = keyframe {
+        if let RecordData::Keyframe(keyframe) = &record[k].data {
+            board.restore_keyframe(keyframe);
+        }
+        for item in record.get(k + 1..next_ix).iter() {
+            board.apply_record(item);
+        }
+        replay_info.motion = None;
+        replay_info.ix = next_ix;
+        replay_info.seeking = false;
+        return;
+    }
+
+    // the direction is read from the items to be applied rather than from the direction of play,
+    // since seeking can move the replay either way while it is paused
+    match replay_info.next_ix.cmp(&replay_info.ix) {
+        Ordering::Equal => (),
+        Ordering::Less => {
             // Reaching past next_ix to find the current active piece, hold, and queue. This is
             // necessary because these properties can span multiple frames past when they are
             // applied. For example, when dealing with updates to the active piece, the piece may
@@ -136,15 +362,25 @@ pub fn replay(
                     Match; [ActiveChange]; [Hold]; [QueueChange];
                 ]
 
+                // Before the first change to a property, the property is in the state recorded
+                // when the game began, which is the earliest item of its kind in the record
                 if let Some(update) = search
                     .iter()
                     .rev()
                     .find(|i| matches!(i.data, RecordData::Match { .. }))
+                    .or_else(|| {
+                        record
+                            .get(0..record.len())
+                            .iter()
+                            .find(|i| matches!(i.data, RecordData::Match { .. }))
+                    })
                 {
                     board.apply_record(update);
                 }
             }
 
+            replay_info.motion = None;
+
             // matrix changes can be applied immediately
             for item in record
                 .get(replay_info.next_ix..replay_info.ix)
@@ -154,22 +390,108 @@ pub fn replay(
             {
                 board.undo_record(item);
             }
-        } else {
-            for item in record.get(replay_info.ix..replay_info.next_ix).iter() {
-                board.apply_record(item);
+        }
+        Ordering::Greater => {
+            let items = record
+                .get(replay_info.ix..replay_info.next_ix)
+                .iter()
+                .collect::<Vec<_>>();
+            for frame in items.chunk_by(|a, b| a.time == b.time) {
+                // A piece locked on this frame if the matrix changed while the piece was replaced.
+                // The record never holds the piece where it locked if it was hard dropped, so it is
+                // dropped here onto the matrix as it was before the lock.
+                let locked = frame
+                    .iter()
+                    .any(|i| matches!(i.data, RecordData::MatrixChange(_)))
+                    && frame
+                        .iter()
+                        .any(|i| matches!(i.data, RecordData::ActiveChange(_)));
+                if let Some(piece) = board.active.0.filter(|_| locked && !replay_info.seeking) {
+                    let landed = (0..)
+                        .map(|y| Mino {
+                            position: piece.position - IVec2::Y * y,
+                            ..piece
+                        })
+                        .take_while(|m| has_free_space(&board.matrix, *m, &shape_table))
+                        .last()
+                        .unwrap_or(piece);
+                    let spun = replay_info
+                        .motion
+                        .map(|motion| landed == piece && motion.spun());
+                    let (locked, cleared) =
+                        lock_events(board.id, &board.matrix, landed, &shape_table, spun, true);
+                    locks.send(locked);
+                    lines.send_batch(cleared);
+                }
+
+                // a piece spawns on the frame that it is taken from the queue or from hold
+                let spawned = frame
+                    .iter()
+                    .any(|i| matches!(i.data, RecordData::QueueChange(_) | RecordData::Hold(_)));
+                for item in frame {
+                    if let RecordData::ActiveChange(Some(new)) = item.data {
+                        track_motion(&mut replay_info.motion, board.active.0, new, spawned);
+                    }
+                    if let RecordData::Hold(Hold::Inactive(kind)) = item.data {
+                        let fresh = !matches!(*board.hold, Hold::Inactive(_));
+                        if fresh && !replay_info.seeking {
+                            holds.send(HoldUsed {
+                                board: board.id,
+                                kind,
+                                replayed: true,
+                            });
+                        }
+                    }
+                    board.apply_record(item);
+                }
             }
         }
     }
     replay_info.ix = replay_info.next_ix;
+    replay_info.seeking = false;
+}
+
+/// Follows the motion of the active piece as it is replaced by the given piece
+fn track_motion(motion: &mut Option<PieceMotion>, old: Option<Mino>, new: Mino, spawned: bool) {
+    if spawned {
+        *motion = Some(PieceMotion::spawned(new));
+        return;
+    }
+    if let (Some(motion), Some(old)) = (motion.as_mut(), old) {
+        motion.moved(old, new);
+    }
 }
 
 pub fn advance_frame(
     mut replay_info: ResMut<ReplayInfo>,
     record: Res<CompleteRecord>,
     time: Res<Time>,
+    settings: Res<GlobalSettings>,
+    mut toasts: ResMut<Toasts>,
 ) {
+    // when the next item is far off, playing forward jumps to just before it instead of waiting
+    let (frame, ix) = (replay_info.frame, replay_info.ix);
+    let mut skipped = 0;
+    if let Some(meta) = replay_info
+        .playing
+        .as_mut()
+        .filter(|meta| settings.skip_idle && !meta.reverse && ix < record.len())
+    {
+        let next = record[ix].time;
+        let rate = record.frame_rate;
+        if next.saturating_sub(frame) > rate.frames(IDLE_GAP) {
+            skipped = next - rate.frames(IDLE_SKIP_LEAD) - frame;
+            meta.record_frame += skipped;
+            toasts.push(
+                format!("+{:.1}s", rate.seconds(skipped)),
+                ToastLevel::Info,
+                IDLE_SKIP_NOTE_DURATION,
+            );
+        }
+    }
+
     if let Some(initial) = replay_info.playing {
-        let current_time = discretized_time(&time);
+        let current_time = record.frame_rate.discretize(&time);
         let elapsed_time = current_time - initial.real_frame;
 
         let new_record_frame = if initial.reverse {
@@ -178,7 +500,15 @@ pub fn advance_frame(
             initial.record_frame + elapsed_time
         };
 
-        if new_record_frame != replay_info.frame {
+        // skipping idle time is meant to jump ahead, so it does not count as a hitch
+        let step = new_record_frame
+            .abs_diff(replay_info.frame)
+            .saturating_sub(skipped);
+        if step > record.frame_rate.frames(MAX_REPLAY_STEP) {
+            // playing through every item at once would flood out the effects of each lock, while a
+            // seek skips them and starts from a keyframe when there is one on the way
+            replay_info.seek(new_record_frame, &record, &time);
+        } else if new_record_frame != replay_info.frame {
             replay_info.frame = new_record_frame;
 
             replay_info.next_ix = if initial.reverse {
@@ -210,37 +540,115 @@ pub fn advance_frame(
 
 pub(crate) fn adjust_replay(
     mut replay_info: ResMut<ReplayInfo>,
+    record: Res<CompleteRecord>,
     input: Res<ButtonInput<KeyCode>>,
+    bound: BoundInput,
     time: Res<Time>,
 ) {
-    let record_frame = replay_info.frame;
-    let real_frame = discretized_time(&time);
-
-    if input.just_pressed(KeyCode::Space) {
+    if bound.just_pressed(Action::PauseReplay) {
         if replay_info.playing.is_some() {
             replay_info.playing = None;
         } else {
-            replay_info.playing = Some(ActiveReplayMeta {
-                record_frame,
-                real_frame,
-                reverse: false,
-            });
+            replay_info.play(false, &record, &time);
         }
     }
 
-    if input.just_pressed(KeyCode::KeyR) {
+    if input.just_pressed(REVERSE_REPLAY_KEY) {
         if matches!(
             replay_info.playing,
             Some(ActiveReplayMeta { reverse: true, .. })
         ) {
             replay_info.playing = None;
         } else {
-            replay_info.playing = Some(ActiveReplayMeta {
-                record_frame,
-                real_frame,
-                reverse: true,
-            })
+            replay_info.play(true, &record, &time);
+        }
+    }
+}
+
+/// The frames on which a piece spawned among the given items, in the order the items are given. A
+/// piece has spawned when the active piece is replaced on the same frame as the queue or hold
+/// changes.
+fn spawn_frames<'a>(items: &'a [&'a RecordItem]) -> impl Iterator<Item = u64> + 'a {
+    items
+        .chunk_by(|a, b| a.time == b.time)
+        .filter(|frame| {
+            frame
+                .iter()
+                .any(|i| matches!(i.data, RecordData::ActiveChange(Some(_))))
+                && frame
+                    .iter()
+                    .any(|i| matches!(i.data, RecordData::QueueChange(_) | RecordData::Hold(_)))
+        })
+        .map(|frame| frame[0].time)
+}
+
+/// Seeks to the spawn of the piece before or after the current frame, marking the destination on
+/// the progress bar so that the jump can be followed.
+pub(crate) fn jump_to_spawn(
+    mut commands: Commands,
+    mut replay_info: ResMut<ReplayInfo>,
+    record: Res<CompleteRecord>,
+    bound: BoundInput,
+    bar: Query<Entity, With<ReplayBar>>,
+    time: Res<Time>,
+) {
+    let frame = replay_info.frame;
+    let destination = if bound.just_pressed(Action::PreviousSpawn) {
+        let items = record
+            .get(0..replay_info.next_ix)
+            .iter()
+            .rev()
+            .collect::<Vec<_>>();
+        spawn_frames(&items).find(|&spawn| spawn < frame)
+    } else if bound.just_pressed(Action::NextSpawn) {
+        let items = record
+            .get(replay_info.next_ix..record.len())
+            .iter()
+            .collect::<Vec<_>>();
+        spawn_frames(&items).find(|&spawn| spawn > frame)
+    } else {
+        return;
+    };
+    let Some(destination) = destination else {
+        return;
+    };
+
+    replay_info.seek(destination, &record, &time);
+    let progress = destination as f32 / record.last_frame().max(1) as f32;
+    let Ok(bar) = bar.get_single() else {
+        return;
+    };
+    commands.entity(bar).with_children(|parent| {
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Percent(progress * 100.0),
+                    left: Val::Px(-5.0),
+                    width: Val::Px(12.0),
+                    height: Val::Px(2.0),
+                    ..default()
+                },
+                background_color: Color::WHITE.into(),
+                ..default()
+            },
+            SeekMarker { age: 0.0 },
+        ));
+    });
+}
+
+pub(crate) fn fade_seek_marker(
+    mut commands: Commands,
+    mut markers: Query<(Entity, &mut SeekMarker, &mut BackgroundColor)>,
+    time: Res<Time>,
+) {
+    for (e, mut marker, mut color) in markers.iter_mut() {
+        marker.age += time.delta_seconds();
+        if marker.age > SEEK_MARKER_DURATION {
+            commands.entity(e).despawn_recursive();
+            continue;
         }
+        color.0.set_a(1.0 - marker.age / SEEK_MARKER_DURATION);
     }
 }
 
@@ -249,17 +657,23 @@ pub(crate) struct DeferUnfreeze;
 
 // When the controller registers a movement, begins a new segment in the replay and puts the player
 // in control of the game, starting from the current point of the replay. If instead, the grave key
-// is pressed, we return to the ready state.
+// is held down long enough, we return to the ready state. Since that leaves the record behind for
+// good, a tap of the key is not enough.
+//
+// The movement which branched the replay is kept in the frozen controller so that it acts exactly
+// once on the first frame of the new segment. A hard drop pressed on the same frame is dropped, so
+// that the piece is not slammed into place before the player has seen it.
 pub(crate) fn exit_replay(
     mut next_state: ResMut<NextState<MainState>>,
-    controller: Res<Controller>,
+    mut controller: ResMut<Controller>,
+    mut buffered: ResMut<BufferedInputs>,
     keys: Res<ButtonInput<KeyCode>>,
-    active_piece: Query<&Active>,
+    active_piece: Query<&Active, Without<Ghost>>,
     mut controller_freeze: ResMut<ControllerFrozen>,
     mut defer_unfreeze: EventWriter<DeferUnfreeze>,
+    mut new_game: ResMut<NewGameHold>,
+    time: Res<Time>,
 ) {
-    // TODO resolve conflict between space bar for hard drop and pause/play replay
-
     let active_piece_exists = active_piece
         .get_single()
         .is_ok_and(|piece| piece.0.is_some());
@@ -267,9 +681,11 @@ pub(crate) fn exit_replay(
     if controller.any_activation() && !controller.hard_drop && active_piece_exists {
         // we are branching the current record
         next_state.0 = Some(MainState::Playing);
+        controller.hard_drop = false;
+        buffered.retain(|(_, input)| !matches!(input, BufferedInput::HardDrop));
         **controller_freeze = true;
         defer_unfreeze.send(default());
-    } else if keys.just_pressed(KeyCode::Backquote) {
+    } else if new_game.update(keys.pressed(RESTART_KEY), time.delta_seconds()) {
         // we are beginning a new record
         next_state.0 = Some(MainState::Ready);
     }
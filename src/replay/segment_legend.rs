@@ -0,0 +1,111 @@
+//! A legend for the sections of the replay's progress bar, one for each segment of the record that
+//! the game branched into. Clicking a segment seeks the replay to its first frame.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::assets::palette::Palette;
+use crate::replay::move_list::MoveList;
+use crate::replay::record::{CompleteRecord, FrameRate};
+use crate::replay::replay::ReplayInfo;
+
+const SWATCH_SIZE: f32 = 12.0;
+
+/// A segment of the record as shown in the legend
+struct SegmentEntry {
+    /// Index of the segment in the record, which picks its color
+    ix: usize,
+    start: u64,
+    end: u64,
+    pieces: usize,
+}
+
+/// Each segment with anything in it, running from its first frame up to where the next begins
+fn segment_entries(record: &CompleteRecord, moves: &MoveList) -> Vec<SegmentEntry> {
+    let starts = record
+        .segments
+        .iter()
+        .enumerate()
+        .filter_map(|(ix, segment)| Some((ix, segment.first()?.time)))
+        .collect::<Vec<_>>();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(n, &(ix, start))| {
+            let next = starts.get(n + 1).map(|&(_, next)| next);
+            let end = next.unwrap_or_else(|| record.last_frame());
+            let pieces = moves
+                .0
+                .iter()
+                .filter(|outcome| {
+                    let frame = outcome.lock.frame;
+                    frame >= start && next.map_or(frame <= end, |next| frame < next)
+                })
+                .count();
+            SegmentEntry {
+                ix,
+                start,
+                end,
+                pieces,
+            }
+        })
+        .collect()
+}
+
+/// Formats a frame of a record taken at the given rate as minutes and seconds
+fn timestamp(frame: u64, rate: FrameRate) -> String {
+    let seconds = rate.seconds(frame) as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+pub(crate) fn segment_legend(
+    mut contexts: EguiContexts,
+    record: Res<CompleteRecord>,
+    moves: Res<MoveList>,
+    palette: Res<Palette>,
+    mut replay_info: ResMut<ReplayInfo>,
+    time: Res<Time>,
+) {
+    let entries = segment_entries(&record, &moves);
+    // a record which never branched has nothing to explain
+    if entries.len() < 2 {
+        return;
+    }
+
+    let mut seek = None;
+    egui::Window::new("Segments")
+        .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -10.0])
+        .default_open(false)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Grid::new("segment_legend").show(ui, |ui| {
+                for (n, entry) in entries.iter().enumerate() {
+                    let [r, g, b, _] = palette.segment_color(entry.ix).as_rgba_u8();
+                    let (rect, _) = ui.allocate_exact_size(
+                        egui::vec2(SWATCH_SIZE, SWATCH_SIZE),
+                        egui::Sense::hover(),
+                    );
+                    ui.painter()
+                        .rect_filled(rect, 2.0, egui::Color32::from_rgb(r, g, b));
+
+                    let frame = replay_info.frame;
+                    let last = n + 1 == entries.len();
+                    let current = frame >= entry.start && (frame < entry.end || last);
+                    let text = format!(
+                        "Segment {}, started at {}, {} pieces",
+                        n + 1,
+                        timestamp(entry.start, record.frame_rate),
+                        entry.pieces
+                    );
+                    if ui.selectable_label(current, text).clicked() {
+                        seek = Some(entry.start);
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+
+    if let Some(frame) = seek {
+        replay_info.seek(frame, &record, &time);
+    }
+}
@@ -0,0 +1,466 @@
+//! Importing single player TETR.IO replays (`.ttr`), by playing their key presses on a board of
+//! this game and keeping where each piece locked.
+//!
+//! The import keeps the placements but not the timing of the original run: the pieces are played
+//! back as a run code, and the handling, gravity and kicks are only approximated by those of this
+//! game. Anything which would change the placements beyond that, such as incoming garbage, is
+//! refused instead of imported wrongly.
+
+use std::collections::VecDeque;
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy::utils::thiserror;
+use serde_json::Value;
+
+use crate::assets::tables::kick_table::{KickParameters, KickTable};
+use crate::assets::tables::shape_table::ShapeTable;
+use crate::board::queue::{QueueSource, Randomizer};
+use crate::board::update::{default_mino, has_free_space, kick_search, lock_piece};
+use crate::board::{GameMode, Matrix, Mino, MinoKind, RotationState, Settings};
+use crate::replay::code::{RunCode, RunSettings};
+
+/// Soft drop factors at or above this drop the piece to the floor straight away
+const INSTANT_SOFT_DROP: f64 = 41.0;
+/// Times that moving a grounded piece gives it more time before it locks
+const LOCK_RESETS: u32 = 15;
+/// Pieces shown in the queue, which are dealt beyond the last piece played
+const QUEUE_WINDOW: usize = 5;
+
+#[derive(thiserror::Error, Debug)]
+pub enum TetrioError {
+    #[error("Could not read the replay: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Not a TETR.IO replay: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("The replay has no events to play")]
+    NoEvents,
+    #[error("The replay does not say which pieces it dealt")]
+    NoSeed,
+    #[error("Cannot import replays with {}", .0.join(", "))]
+    Unsupported(Vec<String>),
+}
+
+/// The random number generator that TETR.IO deals its pieces with
+struct TetrioRng(u64);
+
+impl TetrioRng {
+    const MODULUS: u64 = 2147483647;
+
+    fn new(seed: u64) -> Self {
+        let t = seed % Self::MODULUS;
+        Self(if t == 0 { Self::MODULUS - 1 } else { t })
+    }
+
+    fn next_float(&mut self) -> f64 {
+        self.0 = 16807 * self.0 % Self::MODULUS;
+        (self.0 - 1) as f64 / (Self::MODULUS - 1) as f64
+    }
+
+    /// The next bag of seven pieces, shuffled in the same way as TETR.IO
+    fn bag(&mut self) -> Vec<MinoKind> {
+        use MinoKind::*;
+        let mut bag = vec![Z, L, O, S, I, J, T];
+        for i in (1..bag.len()).rev() {
+            let r = (self.next_float() * (i + 1) as f64) as usize;
+            bag.swap(i, r);
+        }
+        bag
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Key {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateLeft,
+    RotateRight,
+    Rotate180,
+    Hold,
+}
+
+impl Key {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "moveLeft" => Key::MoveLeft,
+            "moveRight" => Key::MoveRight,
+            "softDrop" => Key::SoftDrop,
+            "hardDrop" => Key::HardDrop,
+            "rotateCCW" => Key::RotateLeft,
+            "rotateCW" => Key::RotateRight,
+            "rotate180" => Key::Rotate180,
+            "hold" => Key::Hold,
+            _ => return None,
+        })
+    }
+}
+
+struct KeyEvent {
+    frame: u64,
+    subframe: f64,
+    key: Key,
+    down: bool,
+}
+
+/// How the original run was played, as far as it matters to the placements
+struct Options {
+    seed: u64,
+    bag_type: String,
+    /// Frames before a held shift starts repeating
+    das: f64,
+    /// Frames between the repeats of a held shift, where zero moves straight to the wall
+    arr: f64,
+    /// Multiplier applied to gravity while soft dropping
+    sdf: f64,
+    /// Rows fallen per frame
+    gravity: f64,
+    /// Frames that a piece can spend on the ground before it locks
+    lock_time: f64,
+}
+
+impl Options {
+    fn read(options: &Value) -> Option<Self> {
+        let number = |value: &Value, default: f64| value.as_f64().unwrap_or(default);
+        let handling = &options["handling"];
+        Some(Self {
+            seed: options["seed"].as_u64()?,
+            bag_type: options["bagtype"].as_str().unwrap_or("7-bag").to_string(),
+            das: number(&handling["das"], 10.0),
+            arr: number(&handling["arr"], 2.0),
+            sdf: number(&handling["sdf"], 6.0),
+            gravity: number(&options["g"], 0.02),
+            lock_time: number(&options["locktime"], 30.0),
+        })
+    }
+}
+
+/// A board of this game which the key presses of the replay are played on
+struct Simulation<'a> {
+    shape_table: &'a ShapeTable,
+    kick_table: &'a KickTable,
+    options: Options,
+    rng: TetrioRng,
+    matrix: Matrix,
+    active: Option<Mino>,
+    hold: Option<MinoKind>,
+    hold_used: bool,
+    upcoming: VecDeque<MinoKind>,
+    /// Every piece taken out of the queue so far, in order
+    dealt: Vec<MinoKind>,
+    placements: Vec<Mino>,
+    /// The direction of the shift being held, along with the frames it has been held for
+    shift: Option<(i32, f64)>,
+    left_held: bool,
+    right_held: bool,
+    soft_drop: bool,
+    fall: f64,
+    grounded_frames: f64,
+    resets: u32,
+}
+
+impl<'a> Simulation<'a> {
+    fn new(options: Options, shape_table: &'a ShapeTable, kick_table: &'a KickTable) -> Self {
+        let mut simulation = Self {
+            shape_table,
+            kick_table,
+            rng: TetrioRng::new(options.seed),
+            options,
+            matrix: Matrix::default(),
+            active: None,
+            hold: None,
+            hold_used: false,
+            upcoming: VecDeque::new(),
+            dealt: Vec::new(),
+            placements: Vec::new(),
+            shift: None,
+            left_held: false,
+            right_held: false,
+            soft_drop: false,
+            fall: 0.0,
+            grounded_frames: 0.0,
+            resets: 0,
+        };
+        let first = simulation.take();
+        simulation.spawn(first);
+        simulation
+    }
+
+    fn take(&mut self) -> MinoKind {
+        if self.upcoming.len() <= QUEUE_WINDOW {
+            let bag = self.rng.bag();
+            self.upcoming.extend(bag);
+        }
+        let kind = self.upcoming.pop_front().unwrap();
+        self.dealt.push(kind);
+        kind
+    }
+
+    /// Puts the given piece at the top of the board, ending the run if it does not fit
+    fn spawn(&mut self, kind: MinoKind) {
+        let mino = default_mino(kind, self.shape_table);
+        self.active = has_free_space(&self.matrix, mino, self.shape_table).then_some(mino);
+        self.fall = 0.0;
+        self.grounded_frames = 0.0;
+        self.resets = 0;
+    }
+
+    fn fits(&self, mino: Mino) -> bool {
+        has_free_space(&self.matrix, mino, self.shape_table)
+    }
+
+    /// Moves the active piece by the given offset, if it fits there
+    fn try_move(&mut self, offset: IVec2) -> bool {
+        let Some(mino) = self.active else {
+            return false;
+        };
+        let moved = Mino {
+            position: mino.position + offset,
+            ..mino
+        };
+        let fits = self.fits(moved);
+        if fits {
+            self.active = Some(moved);
+        }
+        fits
+    }
+
+    /// A grounded piece which moved gets more time before it locks, a limited number of times
+    fn moved(&mut self) {
+        if self.grounded_frames > 0.0 && self.resets < LOCK_RESETS {
+            self.resets += 1;
+            self.grounded_frames = 0.0;
+        }
+    }
+
+    fn shift(&mut self, direction: i32) {
+        if self.try_move(IVec2::X * direction) {
+            self.moved();
+        }
+    }
+
+    fn rotate(&mut self, to: fn(RotationState) -> RotationState) {
+        let Some(mino) = self.active else {
+            return;
+        };
+        let to = to(mino.rotation);
+        let kicks = self
+            .kick_table
+            .0
+            .get(&KickParameters {
+                kind: mino.kind,
+                from: mino.rotation,
+                to,
+            })
+            .map_or(&[][..], Vec::as_slice);
+        if let Some((_, rotated)) = kick_search(&self.matrix, mino, to, kicks, self.shape_table) {
+            self.active = Some(rotated);
+            self.moved();
+        }
+    }
+
+    fn lock(&mut self) {
+        let Some(mino) = self.active.take() else {
+            return;
+        };
+        lock_piece(&mut self.matrix, mino, self.shape_table);
+        self.placements.push(mino);
+        self.hold_used = false;
+        let next = self.take();
+        self.spawn(next);
+    }
+
+    fn press(&mut self, key: Key, down: bool) {
+        match (key, down) {
+            (Key::MoveLeft, _) => self.left_held = down,
+            (Key::MoveRight, _) => self.right_held = down,
+            (Key::SoftDrop, _) => self.soft_drop = down,
+            _ => (),
+        }
+        if !down {
+            // releasing one shift hands the charge over to the other, if it is still held
+            self.shift = match (self.left_held, self.right_held, self.shift) {
+                (true, false, _) => Some((-1, 0.0)),
+                (false, true, _) => Some((1, 0.0)),
+                (true, true, shift) => shift,
+                (false, false, _) => None,
+            };
+            return;
+        }
+
+        match key {
+            Key::MoveLeft | Key::MoveRight => {
+                let direction = if key == Key::MoveLeft { -1 } else { 1 };
+                self.shift = Some((direction, 0.0));
+                self.shift(direction);
+            }
+            Key::SoftDrop => (),
+            Key::HardDrop => {
+                while self.try_move(IVec2::NEG_Y) {}
+                self.lock();
+            }
+            Key::RotateLeft => self.rotate(RotationState::rotate_left),
+            Key::RotateRight => self.rotate(RotationState::rotate_right),
+            Key::Rotate180 => self.rotate(RotationState::rotate_180),
+            Key::Hold => {
+                let Some(mino) = self.active.filter(|_| !self.hold_used) else {
+                    return;
+                };
+                let next = match self.hold.replace(mino.kind) {
+                    Some(held) => held,
+                    None => self.take(),
+                };
+                self.spawn(next);
+                self.hold_used = true;
+            }
+        }
+    }
+
+    /// Runs the held shift, soft drop, gravity and lock delay for one frame
+    fn tick(&mut self) {
+        if let Some((direction, held)) = self.shift {
+            let held = held + 1.0;
+            self.shift = Some((direction, held));
+            if held > self.options.das {
+                if self.options.arr <= 0.0 {
+                    while self.try_move(IVec2::X * direction) {}
+                } else if ((held - self.options.das) % self.options.arr) < 1.0 {
+                    self.shift(direction);
+                }
+            }
+        }
+
+        if self.soft_drop && self.options.sdf >= INSTANT_SOFT_DROP {
+            while self.try_move(IVec2::NEG_Y) {}
+        }
+        let soft_drop = if self.soft_drop {
+            self.options.sdf
+        } else {
+            1.0
+        };
+        self.fall += self.options.gravity * soft_drop;
+        while self.fall >= 1.0 {
+            self.fall -= 1.0;
+            if !self.try_move(IVec2::NEG_Y) {
+                self.fall = 0.0;
+            }
+        }
+
+        let grounded = self.active.is_some_and(|mino| {
+            !self.fits(Mino {
+                position: mino.position - IVec2::Y,
+                ..mino
+            })
+        });
+        if grounded {
+            self.grounded_frames += 1.0;
+            if self.grounded_frames >= self.options.lock_time {
+                self.lock();
+            }
+        } else {
+            self.grounded_frames = 0.0;
+        }
+    }
+}
+
+/// Reads a TETR.IO replay and plays it through, giving a run code of the pieces it placed
+pub fn import(
+    text: &str,
+    shape_table: &ShapeTable,
+    kick_table: &KickTable,
+) -> Result<RunCode, TetrioError> {
+    let root: Value = serde_json::from_str(text)?;
+    let mut unsupported = Vec::new();
+    // multiplayer replays (.ttrm) hold a round for each player
+    if root["ismulti"].as_bool() == Some(true) || root["data"].is_array() {
+        unsupported.push("more than one player".to_string());
+    }
+
+    let events = root["data"]["events"]
+        .as_array()
+        .or_else(|| root["replay"]["events"].as_array())
+        .ok_or(TetrioError::NoEvents)?;
+
+    let mut options = None;
+    let mut keys = Vec::new();
+    for event in events {
+        let frame = event["frame"].as_u64().unwrap_or(0);
+        let data = &event["data"];
+        match event["type"].as_str().unwrap_or_default() {
+            "full" => options = options.or_else(|| Options::read(&data["options"])),
+            "ige" => unsupported.push("garbage".to_string()),
+            kind @ ("keydown" | "keyup") => {
+                let name = data["key"].as_str().unwrap_or_default();
+                let Some(key) = Key::parse(name) else {
+                    unsupported.push(format!("the {name} key"));
+                    continue;
+                };
+                keys.push(KeyEvent {
+                    frame,
+                    subframe: data["subframe"].as_f64().unwrap_or(0.0),
+                    key,
+                    down: kind == "keydown",
+                });
+            }
+            _ => (),
+        }
+    }
+
+    let options = options.ok_or(TetrioError::NoSeed)?;
+    if options.bag_type != "7-bag" {
+        unsupported.push(format!("the {} randomizer", options.bag_type));
+    }
+    unsupported.sort();
+    unsupported.dedup();
+    if !unsupported.is_empty() {
+        return Err(TetrioError::Unsupported(unsupported));
+    }
+
+    keys.sort_by(|a, b| {
+        a.frame
+            .cmp(&b.frame)
+            .then(a.subframe.total_cmp(&b.subframe))
+    });
+    let last_frame = keys.last().map_or(0, |event| event.frame);
+    let mut simulation = Simulation::new(options, shape_table, kick_table);
+    let mut keys = keys.into_iter().peekable();
+    for frame in 0..=last_frame {
+        while let Some(event) = keys.next_if(|event| event.frame == frame) {
+            simulation.press(event.key, event.down);
+        }
+        if simulation.active.is_none() {
+            break;
+        }
+        simulation.tick();
+    }
+
+    let mut sequence = simulation.dealt;
+    sequence.extend(simulation.upcoming.iter().take(QUEUE_WINDOW));
+    let mode = match root["endcontext"]["gametype"].as_str() {
+        Some("40l") => GameMode::Sprint,
+        _ => GameMode::Freestyle,
+    };
+    Ok(RunCode {
+        seed: 0,
+        settings: RunSettings {
+            mode,
+            queue: QueueSource::Scripted {
+                sequence,
+                repeat: false,
+                then: Randomizer::SevenBag,
+            },
+            ..RunSettings::from(&Settings::default())
+        },
+        placements: simulation.placements,
+    })
+}
+
+/// Reads the TETR.IO replay at the given path, as with [`import`]
+pub fn import_file(
+    path: &Path,
+    shape_table: &ShapeTable,
+    kick_table: &KickTable,
+) -> Result<RunCode, TetrioError> {
+    import(&std::fs::read_to_string(path)?, shape_table, kick_table)
+}
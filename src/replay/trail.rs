@@ -0,0 +1,158 @@
+//! A faint trail behind the active piece in the replay, covering each cell that the piece passed
+//! through since it spawned, so that its movement can be studied. There is no control over the
+//! speed of the replay, so the trail is shown while the replay is paused and stepped through, or
+//! all of the time once toggled on.
+
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+use crate::assets::matrix_material::{MatrixMaterial, MatrixMaterialSpawner};
+use crate::assets::tables::QueryShapeTable;
+use crate::board::{Matrix, Mino, MinoKind, MATRIX_DEFAULT_SIZE};
+use crate::controller::keybinds::{BindingContext, Hotkey};
+use crate::replay::ghost::Ghost;
+use crate::replay::record::{CompleteRecord, RecordData};
+use crate::replay::replay::ReplayInfo;
+
+/// Toggles whether the trail is shown while the replay plays, and not only while it is paused
+pub const TRAIL_TOGGLE_KEY: KeyCode = KeyCode::KeyT;
+
+pub(crate) const HOTKEYS: &[Hotkey] = &[Hotkey {
+    keys: &[TRAIL_TOGGLE_KEY],
+    name: "Always Show Piece Trail",
+    context: BindingContext::Replay,
+}];
+
+/// Positions of the active piece that the trail looks back over
+const TRAIL_LENGTH: usize = 24;
+/// Tints of the layers of the trail, from the layer of the most recent positions to the oldest
+const TRAIL_TINTS: [Color; 3] = [
+    Color::rgba(1.0, 1.0, 1.0, 0.35),
+    Color::rgba(1.0, 1.0, 1.0, 0.2),
+    Color::rgba(1.0, 1.0, 1.0, 0.1),
+];
+
+/// Whether the trail is shown while the replay plays
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct AlwaysShowTrail(bool);
+
+/// One layer of the trail, by how far back its positions are
+#[derive(Component)]
+pub struct TrailLayer(usize);
+
+/// The positions that the active piece took since it spawned, up to the given frame of the record
+/// and from the most recent back, not counting where the piece is at that frame. The positions are
+/// found by walking back through the record rather than by following the replay, so that the
+/// trail is right no matter how the replay got to the frame, be it by playing forward, playing in
+/// reverse or seeking.
+pub fn trail(record: &CompleteRecord, frame: u64) -> Vec<Mino> {
+    let mut positions = Vec::new();
+    let items = record.get(0..record.len());
+    for item in items.iter().rev().skip_while(|item| item.time > frame) {
+        match &item.data {
+            // a different piece means the active piece was swapped with hold
+            RecordData::ActiveChange(Some(mino))
+                if positions
+                    .first()
+                    .map_or(true, |m: &Mino| m.kind == mino.kind) =>
+            {
+                positions.push(*mino);
+                if positions.len() > TRAIL_LENGTH {
+                    break;
+                }
+            }
+            // locking (which writes to the matrix) or holding happens just before each spawn
+            RecordData::ActiveChange(_) | RecordData::MatrixChange(_) | RecordData::Hold(_) => {
+                break
+            }
+            RecordData::QueueChange(_) | RecordData::Keyframe(_) => (),
+        }
+    }
+    positions.into_iter().skip(1).collect()
+}
+
+pub(crate) fn toggle_trail(keys: Res<ButtonInput<KeyCode>>, mut always: ResMut<AlwaysShowTrail>) {
+    if keys.just_pressed(TRAIL_TOGGLE_KEY) {
+        **always = !**always;
+    }
+}
+
+pub(crate) fn spawn_trail(
+    mut commands: Commands,
+    boards: Query<Entity, (With<Matrix>, Without<Ghost>)>,
+    mut spawner: MatrixMaterialSpawner,
+) {
+    for board in boards.iter() {
+        // the newest layer is drawn over the older ones, and all of them under the active piece
+        let layers = (0..TRAIL_TINTS.len())
+            .map(|ix| {
+                let z = 0.5 - ix as f32 * 0.1;
+                spawner
+                    .spawn_centered(MATRIX_DEFAULT_SIZE)
+                    .insert((
+                        TrailLayer(ix),
+                        Transform::from_xyz(0.0, 0.0, z),
+                        Visibility::Hidden,
+                    ))
+                    .id()
+            })
+            .collect::<Vec<_>>();
+        commands.entity(board).push_children(&layers);
+    }
+}
+
+/// Redraws the trail whenever the replay moves to another frame. The cells of each position are
+/// drawn into the layer for how recent the position is, leaving out cells which a more recent
+/// position already covers.
+pub(crate) fn update_trail(
+    always: Res<AlwaysShowTrail>,
+    info: Res<ReplayInfo>,
+    record: Res<CompleteRecord>,
+    shape_table: QueryShapeTable,
+    mut layers: Query<(&TrailLayer, &Handle<MatrixMaterial>, &mut Visibility)>,
+    mut materials: ResMut<Assets<MatrixMaterial>>,
+    mut drawn: Local<Option<u64>>,
+) {
+    if !**always && !info.is_paused() {
+        for (_, _, mut vis) in layers.iter_mut() {
+            *vis = Visibility::Hidden;
+        }
+        *drawn = None;
+        return;
+    }
+    if *drawn == Some(info.frame) && !record.is_changed() {
+        return;
+    }
+    *drawn = Some(info.frame);
+
+    let positions = trail(&record, info.frame);
+    let per_layer = TRAIL_LENGTH.div_ceil(TRAIL_TINTS.len());
+    let mut covered = HashSet::new();
+    let mut layers = layers.iter_mut().collect::<Vec<_>>();
+    layers.sort_by_key(|(layer, _, _)| layer.0);
+    for (layer, handle, mut vis) in layers {
+        let Some(material) = materials.get_mut(handle) else {
+            continue;
+        };
+        material.tint = TRAIL_TINTS[layer.0];
+        material.data.fill(MinoKind::E as u32);
+        let chunk = positions.iter().skip(layer.0 * per_layer).take(per_layer);
+        for mino in chunk {
+            for &cell in &shape_table[*mino] {
+                let cell = cell + mino.position;
+                let inside = cell.cmpge(IVec2::ZERO).all() && cell.cmplt(MATRIX_DEFAULT_SIZE).all();
+                if inside && covered.insert(cell) {
+                    let ix = cell.y * MATRIX_DEFAULT_SIZE.x + cell.x;
+                    material.data[ix as usize] = mino.kind as u32;
+                }
+            }
+        }
+        *vis = Visibility::Inherited;
+    }
+}
+
+pub(crate) fn remove_trail(mut commands: Commands, layers: Query<Entity, With<TrailLayer>>) {
+    for layer in layers.iter() {
+        commands.entity(layer).despawn_recursive();
+    }
+}
@@ -0,0 +1,65 @@
+//! Checks that a record plays back consistently, for records which come from outside the game.
+
+use bevy::utils::thiserror;
+
+use crate::assets::tables::shape_table::ShapeTable;
+use crate::board::update::has_free_space;
+use crate::board::{Matrix, Mino};
+use crate::replay::record::{RecordData, RecordItem};
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum VerifyError {
+    #[error("Item {0} of the record is earlier than the item before it")]
+    OutOfOrder(usize),
+    #[error("Item {0} of the record changes a cell which does not hold what it expects")]
+    MatrixMismatch(usize),
+    #[error("The active piece overlaps the stack on frame {0}")]
+    PieceOverlaps(u64),
+    #[error("The active piece on frame {0} is not in the shape table")]
+    UnknownPiece(u64),
+}
+
+/// Plays the record from the start, checking that it is in order, that each change to the matrix
+/// starts from what the matrix holds at that point, and that the active piece never overlaps the
+/// stack once a frame has been played.
+pub fn verify_record<'a>(
+    items: impl IntoIterator<Item = &'a RecordItem>,
+    shape_table: &ShapeTable,
+) -> Result<(), VerifyError> {
+    let items = items.into_iter().collect::<Vec<_>>();
+    if let Some(ix) = items.windows(2).position(|w| w[1].time < w[0].time) {
+        return Err(VerifyError::OutOfOrder(ix + 1));
+    }
+
+    let kinds = shape_table.kinds();
+    let mut matrix = Matrix::default();
+    let mut active: Option<Mino> = None;
+    let mut ix = 0;
+    for frame in items.chunk_by(|a, b| a.time == b.time) {
+        for item in frame {
+            match &item.data {
+                RecordData::ActiveChange(mino) => active = *mino,
+                RecordData::MatrixChange(update) => {
+                    let Some(cell) = matrix.get_mut(update.loc).filter(|c| **c == update.old)
+                    else {
+                        return Err(VerifyError::MatrixMismatch(ix));
+                    };
+                    *cell = update.new;
+                }
+                RecordData::QueueChange(_) | RecordData::Hold(_) | RecordData::Keyframe(_) => (),
+            }
+            ix += 1;
+        }
+
+        let Some(mino) = active else {
+            continue;
+        };
+        if !kinds.contains(&mino.kind) {
+            return Err(VerifyError::UnknownPiece(frame[0].time));
+        }
+        if !has_free_space(&matrix, mino, shape_table) {
+            return Err(VerifyError::PieceOverlaps(frame[0].time));
+        }
+    }
+    Ok(())
+}
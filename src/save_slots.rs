@@ -0,0 +1,124 @@
+//! Quicksaves of the board during live play. Like the replay, this assumes the board is unique.
+
+use bevy::prelude::*;
+
+use crate::board::{motion::PieceMotion, queue::PieceQueue, Active, DropClock, Hold, Matrix};
+use crate::controller::keybinds::{BindingContext, Hotkey};
+use crate::schedule::FrameSet;
+use crate::state::MainState;
+use crate::stats::Stats;
+
+pub const SAVE_SLOT_COUNT: usize = 5;
+
+const SLOT_KEYS: [KeyCode; SAVE_SLOT_COUNT] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+];
+const SAVE_KEY: KeyCode = KeyCode::F5;
+const LOAD_KEY: KeyCode = KeyCode::F9;
+
+pub(crate) const HOTKEYS: &[Hotkey] = &[
+    Hotkey {
+        keys: &SLOT_KEYS,
+        name: "Select Save Slot",
+        context: BindingContext::Playing,
+    },
+    Hotkey {
+        keys: &[SAVE_KEY],
+        name: "Save to Slot",
+        context: BindingContext::Playing,
+    },
+    Hotkey {
+        keys: &[LOAD_KEY],
+        name: "Load from Slot",
+        context: BindingContext::Playing,
+    },
+];
+
+/// Everything needed to put the game back to the moment the snapshot was taken, including the
+/// upcoming pieces.
+#[derive(Clone)]
+pub struct Snapshot {
+    matrix: Matrix,
+    active: Active,
+    hold: Hold,
+    queue: PieceQueue,
+    drop_clock: DropClock,
+    motion: PieceMotion,
+    stats: Stats,
+}
+
+#[derive(Resource, Default)]
+pub struct SaveSlots {
+    pub slots: [Option<Snapshot>; SAVE_SLOT_COUNT],
+    /// The slot which saving and loading act on
+    pub selected: usize,
+}
+
+/// Selects slots with the number keys, and saves to or loads from the selected slot.
+///
+/// Loading changes the board like any other move would, so the record picks up the difference
+/// between the loaded board and the [`PreviousMatrix`](crate::replay::record::PreviousMatrix) on
+/// this frame, and the replay jumps to the loaded board without desyncing.
+fn quick_save_load(
+    mut slots: ResMut<SaveSlots>,
+    mut boards: Query<(
+        &mut Matrix,
+        &mut Active,
+        &mut Hold,
+        &mut PieceQueue,
+        &mut DropClock,
+        &mut PieceMotion,
+    )>,
+    mut stats: ResMut<Stats>,
+    input: Res<ButtonInput<KeyCode>>,
+) {
+    if let Some(ix) = SLOT_KEYS.iter().position(|&k| input.just_pressed(k)) {
+        slots.selected = ix;
+    }
+
+    let Ok((mut matrix, mut active, mut hold, mut queue, mut drop_clock, mut motion)) =
+        boards.get_single_mut()
+    else {
+        return;
+    };
+    let selected = slots.selected;
+
+    if input.just_pressed(SAVE_KEY) {
+        slots.slots[selected] = Some(Snapshot {
+            matrix: matrix.clone(),
+            active: active.clone(),
+            hold: *hold,
+            queue: queue.clone(),
+            drop_clock: drop_clock.clone(),
+            motion: *motion,
+            stats: stats.clone(),
+        });
+    } else if input.just_pressed(LOAD_KEY) {
+        if let Some(snapshot) = &slots.slots[selected] {
+            *matrix = snapshot.matrix.clone();
+            *active = snapshot.active.clone();
+            *hold = snapshot.hold;
+            *queue = snapshot.queue.clone();
+            *drop_clock = snapshot.drop_clock.clone();
+            *motion = snapshot.motion;
+            *stats = snapshot.stats.clone();
+        }
+    }
+}
+
+pub struct SaveSlotsPlugin;
+
+impl Plugin for SaveSlotsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveSlots>().add_systems(
+            Update,
+            quick_save_load
+                .before(FrameSet::Logic)
+                .run_if(in_state(MainState::Playing)),
+        );
+    }
+}
@@ -0,0 +1,115 @@
+//! The order in which each frame is worked through, from the keys pressed to what is drawn.
+//!
+//! Every frame goes through these steps, in this order:
+//!
+//! 1. [`FrameSet::Input`], in `PreUpdate`: the keys pressed this frame are read into the
+//!    [`Controller`](crate::controller::Controller).
+//! 2. [`FrameSet::Logic`], in `FixedUpdate` when the board runs on a fixed timestep and in `Update`
+//!    otherwise: the board takes in the controller, moving, rotating, holding and locking pieces.
+//! 3. [`FrameSet::Record`], in `PostUpdate`: the changes made to the board are written into the
+//!    record of the game.
+//! 4. [`DisplayEntitySet`], in `PostUpdate`: the board is drawn as it now stands.
+//!
+//! Since the input is read before both the fixed timestep and `Update` run, a key pressed on some
+//! frame always acts on the board within that same frame, and is drawn within it. The only
+//! exception is a fixed timestep which does not tick at all in a frame, in which case the press
+//! waits for the next tick. Commands issued by one step are applied before the next step begins,
+//! since each step is in a schedule of its own.
+//!
+//! In debug builds, the number of frames between each press and the first change it makes to the
+//! board is measured, and any press which is drawn late is logged.
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+
+use crate::display::DisplayEntitySet;
+
+#[derive(SystemSet, Hash, Debug, PartialEq, Eq, Clone)]
+pub enum FrameSet {
+    /// Reads the keys pressed this frame into the controller
+    Input,
+    /// Updates the board from the controller
+    Logic,
+    /// Writes the changes to the board into the record
+    Record,
+}
+
+/// Measures how late presses are drawn, in debug builds
+#[cfg(debug_assertions)]
+mod latency {
+    use bevy::core::FrameCount;
+    use bevy::prelude::*;
+
+    use crate::board::{Active, BlockedMoveEvent, Hold, Matrix};
+    use crate::controller::Controller;
+    use crate::screens::GlobalSettings;
+
+    /// Frames to wait for a press to change the board before it is taken to have done nothing
+    const LATENCY_GIVE_UP: u32 = 10;
+
+    /// The frame on which the oldest press which has not yet changed the board was made
+    #[derive(Resource, Default)]
+    pub(super) struct PendingPress(Option<u32>);
+
+    pub(super) fn note_press(
+        keys: Res<ButtonInput<KeyCode>>,
+        controller: Res<Controller>,
+        frame: Res<FrameCount>,
+        mut pending: ResMut<PendingPress>,
+    ) {
+        let pressed = keys.get_just_pressed().next().is_some() && controller.any_activation();
+        if pressed && pending.0.is_none() {
+            pending.0 = Some(frame.0);
+        }
+    }
+
+    /// Logs how many frames passed between the last press and the first change it made to the
+    /// board, which is done drawing by now. A press blocked by the stack counts as a change.
+    pub(super) fn measure_latency(
+        boards: Query<(Ref<Active>, Ref<Hold>, Ref<Matrix>)>,
+        mut blocked: EventReader<BlockedMoveEvent>,
+        frame: Res<FrameCount>,
+        mut pending: ResMut<PendingPress>,
+        settings: Res<GlobalSettings>,
+    ) {
+        let Some(pressed) = pending.0 else {
+            blocked.clear();
+            return;
+        };
+        let changed = blocked.read().count() > 0
+            || boards.iter().any(|(active, hold, matrix)| {
+                active.is_changed() || hold.is_changed() || matrix.is_changed()
+            });
+        let latency = frame.0.wrapping_sub(pressed);
+        if changed {
+            pending.0 = None;
+            // a fixed timestep may not tick on every frame, so its presses can be late by design
+            if latency > 0 && !settings.fixed_timestep {
+                tracing::warn!("A press on frame {pressed} was drawn {latency} frames late");
+            } else {
+                tracing::debug!("A press on frame {pressed} was drawn after {latency} frames");
+            }
+        } else if latency > LATENCY_GIVE_UP {
+            pending.0 = None;
+        }
+    }
+}
+
+pub struct SchedulePlugin;
+
+impl Plugin for SchedulePlugin {
+    fn build(&self, app: &mut App) {
+        app.configure_sets(PreUpdate, FrameSet::Input.after(InputSystem))
+            .configure_sets(Update, FrameSet::Logic)
+            .configure_sets(FixedUpdate, FrameSet::Logic)
+            .configure_sets(PostUpdate, FrameSet::Record.before(DisplayEntitySet::Spawn));
+
+        #[cfg(debug_assertions)]
+        app.init_resource::<latency::PendingPress>()
+            .add_systems(PreUpdate, latency::note_press.after(FrameSet::Input))
+            .add_systems(
+                PostUpdate,
+                latency::measure_latency.after(DisplayEntitySet::Update),
+            );
+    }
+}
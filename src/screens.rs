@@ -1,28 +1,167 @@
 use std::num::{ParseFloatError, ParseIntError};
+use std::path::Path;
 
+use bevy::math::vec2;
 use bevy::prelude::*;
+use bevy::render::view::screenshot::ScreenshotManager;
 use bevy::utils::thiserror;
+use bevy::window::PrimaryWindow;
 use bevy_egui::egui::{Key, TextEdit};
-use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use bevy_egui::{egui, EguiContexts, EguiPlugin, EguiSettings};
 use duplicate::duplicate;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use smart_default::SmartDefault;
+use strum::IntoEnumIterator;
 
-use crate::{board::Settings, state::MainState};
+use crate::animation::tween::{Easing, Timing};
+use crate::animation::CameraFocus;
+use crate::assets::matrix_material::WellBackground;
+use crate::assets::palette::{Palette, PalettePreset};
+use crate::assets::tables::{QueryKickTable, QueryShapeTable};
+use crate::assets::{LoadingErrors, LoadingProgress};
+use crate::board::events::{GameEndReason, GameEnded};
+use crate::board::garbage::GarbagePattern;
+use crate::board::openers::OpenerDrill;
+use crate::board::queue::{PieceQueue, QueueParseError, QueueSource};
+use crate::board::{
+    board_screen_rect, screen_to_cell, Active, BoardQuery, Bounds, GameMode, LockReset, Matrix,
+    MinoKind, RetryQueue, Settings, StackVisibility,
+};
+use crate::config::ConfigWarnings;
+use crate::confirm::hold_button;
+use crate::controller::keybinds::{Action, BindingContext, Hotkey, KeyLayout, Rebinding};
+use crate::controller::profiles::{Handling, Profiles, PROFILE_SWITCH_KEY};
+use crate::controller::SocdPolicy;
+use crate::display::{
+    ActionKind, ActionTextPosition, ActionTextSettings, HideHud, PreviewActionText,
+    PreviewOrientation, QueueLayout,
+};
+use crate::export::{ExportCrop, ExportSettings, WatermarkCorner};
+#[cfg(feature = "overlay")]
+use crate::overlay::OverlaySettings;
+use crate::replay::bookmarks::Bookmarks;
+use crate::replay::chapter::Chapter;
+use crate::replay::code::{Placements, RunCode, RunSettings};
+use crate::replay::file::{
+    list_replays, save_screenshot, ReplayFile, RunTimestamp, IMPORTED_SUFFIX,
+};
+use crate::replay::ghost::{Ghost, GhostReplay};
+use crate::replay::record::{CompleteRecord, FrameRate, SettingsSnapshot, IDLE_GAP};
+use crate::replay::tetrio;
+use crate::replay::verify::verify_record;
+use crate::save_slots::SaveSlots;
+use crate::state::{assets_loaded, MainState};
+use crate::stats::{efficiency_color, Stats};
+use crate::toasts::Toasts;
+use crate::window::{PresentationMode, WindowOptions, BOARD_OFFSET_LIMIT};
+
+use self::settings_profiles::{save_settings_profiles, settings_profile_row, SettingsProfiles};
+
+pub mod settings_profiles;
+
+/// Rows above the legal area of the board included in screenshots, where pieces spawn
+const SCREENSHOT_EXTRA_ROWS: i32 = 4;
+const AUTHORING_TOGGLE_KEY: KeyCode = KeyCode::F3;
+const AUTHORING_COPY_KEY: KeyCode = KeyCode::F4;
+/// Ends the game and shows its results, which is the only way for a game in continuous play to end
+const END_GAME_KEY: KeyCode = KeyCode::F10;
+/// Starts the game, or starts it over
+pub const RESTART_KEY: KeyCode = KeyCode::Backquote;
+/// Starts the game over with the same pieces as before
+const RETRY_KEY: KeyCode = KeyCode::Backspace;
+
+pub(crate) const HOTKEYS: &[Hotkey] = &[
+    Hotkey {
+        keys: &[RESTART_KEY],
+        name: "Start Game",
+        context: BindingContext::Ready,
+    },
+    Hotkey {
+        keys: &[RESTART_KEY],
+        name: "Restart",
+        context: BindingContext::Playing,
+    },
+    Hotkey {
+        keys: &[RETRY_KEY],
+        name: "Retry Same Queue",
+        context: BindingContext::Playing,
+    },
+    Hotkey {
+        keys: &[END_GAME_KEY],
+        name: "End Game",
+        context: BindingContext::Playing,
+    },
+    Hotkey {
+        keys: &[AUTHORING_TOGGLE_KEY],
+        name: "Authoring Overlay",
+        context: BindingContext::Anywhere,
+    },
+    Hotkey {
+        keys: &[AUTHORING_COPY_KEY],
+        name: "Copy Authoring Info",
+        context: BindingContext::Anywhere,
+    },
+];
+/// The UI scales which leave the settings panel usable
+const UI_SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.75..=2.0;
 
 pub struct ScreensPlugin;
 
 impl Plugin for ScreensPlugin {
     fn build(&self, app: &mut App) {
+        let (profiles, warning) = SettingsProfiles::load();
+        app.world
+            .get_resource_or_insert_with(ConfigWarnings::default)
+            .extend(warning);
+
         app.add_plugins(EguiPlugin)
             .init_resource::<GlobalSettings>()
-            .add_systems(Update, (settings_panel, apply_settings).chain())
+            .insert_resource(profiles)
+            .init_resource::<AuthoringOverlay>()
+            .add_systems(
+                Update,
+                (
+                    settings_panel,
+                    run_code_panel.run_if(in_state(MainState::Ready)),
+                    (
+                        apply_settings,
+                        apply_palette_preset,
+                        apply_ui_scale,
+                        fit_camera_to_free_space,
+                    ),
+                )
+                    .chain(),
+            )
+            .add_systems(Update, save_settings_profiles.after(settings_panel))
             .add_systems(
                 Update,
                 start_playing
                     .run_if(in_state(MainState::Ready))
                     .after(apply_settings),
             )
-            .add_systems(OnExit(MainState::Loading), setup_scene);
+            .add_systems(
+                Update,
+                (restart_game, save_slots_panel).run_if(in_state(MainState::Playing)),
+            )
+            .add_systems(
+                Update,
+                (results_panel, replay_browser_panel).run_if(in_state(MainState::PostGame)),
+            )
+            .add_systems(Update, authoring_overlay.run_if(assets_loaded))
+            .add_systems(
+                Update,
+                loading_panel.run_if(
+                    in_state(MainState::Loading).or_else(in_state(MainState::LoadingFailed)),
+                ),
+            )
+            .add_systems(
+                OnTransition {
+                    from: MainState::Loading,
+                    to: MainState::Ready,
+                },
+                setup_scene,
+            );
     }
 }
 
@@ -30,18 +169,220 @@ fn setup_scene(mut commands: Commands) {
     commands.spawn(Camera2dBundle::default());
 }
 
-#[derive(Resource, SmartDefault)]
+/// Focuses the camera on the part of the window not covered by egui's side panels, so that the
+/// board (and the HUD, which is laid out within the camera's viewport) is centered in the space
+/// that is actually visible, then pushed aside by the board offset. The camera moves there
+/// gradually through [`CameraFocus`].
+fn fit_camera_to_free_space(
+    mut contexts: EguiContexts,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut focus: ResMut<CameraFocus>,
+    options: Res<WindowOptions>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let ctx = contexts.ctx_mut();
+    let free = ctx.available_rect();
+    // egui's points include the UI scale on top of the window's own scale factor
+    let scale = ctx.pixels_per_point();
+
+    let free = Rect::from_corners(
+        vec2(free.min.x, free.min.y) * scale,
+        vec2(free.max.x, free.max.y) * scale,
+    );
+    let window_size = vec2(
+        window.physical_width() as f32,
+        window.physical_height() as f32,
+    );
+    let target = options.offset_viewport(free, window_size);
+    if focus.0 != Some(target) {
+        focus.0 = Some(target);
+    }
+}
+
+/// Seconds of loading after which loading is considered stalled, and the player is told so
+const LOADING_STALL_TIMEOUT: f32 = 10.0;
+
+/// Shows how far loading has gotten, and which assets are still on their way, since the window is
+/// otherwise empty until everything is loaded. Lists the assets that failed to load (and why),
+/// offering to try loading them again. The failures are also shown when loading takes suspiciously
+/// long, since a bad asset may simply never finish loading.
+#[allow(clippy::too_many_arguments)]
+fn loading_panel(
+    mut contexts: EguiContexts,
+    mut errors: ResMut<LoadingErrors>,
+    progress: Res<LoadingProgress>,
+    state: Res<State<MainState>>,
+    mut next_state: ResMut<NextState<MainState>>,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+    mut waited: Local<f32>,
+) {
+    let failed = *state.get() == MainState::LoadingFailed;
+    if !failed {
+        *waited += time.delta_seconds();
+    }
+
+    egui::CentralPanel::default().show(contexts.ctx_mut(), |ui| {
+        if !failed {
+            let (fraction, current) = progress.progress(&asset_server);
+            ui.vertical_centered(|ui| {
+                ui.add_space(ui.available_height() / 3.0);
+                ui.heading("Loading");
+                ui.add(
+                    egui::ProgressBar::new(fraction)
+                        .desired_width(300.0)
+                        .show_percentage(),
+                );
+                if let Some(current) = current {
+                    ui.label(current);
+                }
+            });
+            if *waited < LOADING_STALL_TIMEOUT && errors.0.is_empty() {
+                return;
+            }
+            ui.separator();
+        }
+
+        ui.heading(if failed {
+            "Loading failed"
+        } else {
+            "Loading is taking longer than expected"
+        });
+
+        if errors.0.is_empty() {
+            ui.label("No errors have been reported yet.");
+        }
+        egui::Grid::new("loading_errors").show(ui, |ui| {
+            for (path, error) in errors.0.iter() {
+                ui.label(path);
+                ui.label(error);
+                ui.end_row();
+            }
+        });
+
+        if ui.button("Retry").clicked() {
+            for (path, _) in errors.0.drain(..) {
+                asset_server.reload(path);
+            }
+            *waited = 0.0;
+            next_state.0 = Some(MainState::Loading);
+        }
+    });
+}
+
+#[derive(Resource, SmartDefault, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GlobalSettings {
-    #[default = "10"]
-    pub soft_drop_power: String,
-    #[default = "0.02"]
+    #[default = "1.2"]
     pub gravity_power: String,
     #[default = "0.5"]
     pub lock_delay: String,
-    #[default = "1000"]
-    pub initial_delay: String,
-    #[default = "100"]
-    pub repeat_delay: String,
+    pub lock_reset: LockReset,
+    #[default = "15"]
+    pub move_reset_limit: String,
+    pub stack_visibility: StackVisibility,
+    #[default = "3"]
+    pub fade_delay: String,
+    #[default = true]
+    pub particles: bool,
+    /// Briefly light up the cells of each piece as it locks
+    #[default = true]
+    pub lock_flash: bool,
+    pub hold_preview: bool,
+    /// Pulse the border of the hold box when hold can be used again
+    #[default = true]
+    pub hold_pulse: bool,
+    pub das_indicator: bool,
+    pub bag_tracker: bool,
+    /// Label the columns and rows of the matrix
+    pub rulers: bool,
+    /// Run the board at a fixed number of ticks per second instead of once per rendered frame
+    pub fixed_timestep: bool,
+    /// Ticks per second of the fixed timestep
+    #[default = "60"]
+    pub tick_rate: String,
+    /// How far the replay camera zooms in when following the active piece
+    #[default = 2.0]
+    pub focus_zoom: f32,
+    /// Seconds that the camera takes to move to a new zoom, target or viewport
+    #[default = 0.3]
+    pub camera_duration: f32,
+    pub camera_easing: Easing,
+    /// Size of the panels and text, relative to their usual size
+    #[default = 1.0]
+    pub ui_scale: f32,
+    /// Place pieces with the mouse instead of letting them fall
+    pub mouse_mode: bool,
+    /// Jump over long stretches of the replay in which nothing happens
+    pub skip_idle: bool,
+    /// Branch from a replay with these settings, rather than those the game was played with
+    pub branch_with_current_settings: bool,
+    /// Frames counted each second by the records of new games. Records keep the rate they were
+    /// taken at, so this only applies from the next game.
+    #[default = 60]
+    pub replay_frame_rate: u64,
+    /// Show the gravity and lock delay timers above the active piece
+    pub timer_overlay: bool,
+    /// Play a soft sound when a rotation or shift is blocked
+    #[default = true]
+    pub blocked_sound: bool,
+    /// Shake the active piece when a rotation or shift is blocked
+    #[default = true]
+    pub blocked_shake: bool,
+    /// Pause the game when the window loses focus in the middle of play
+    #[default = true]
+    pub pause_on_focus_loss: bool,
+    /// Slide the active piece between cells instead of jumping, purely for looks
+    pub smooth_movement: bool,
+    /// Milliseconds that the active piece takes to slide between cells, with smooth movement on
+    #[default = 20.0]
+    pub smoothing_time: f32,
+    pub palette: PalettePreset,
+    /// Drawn behind the empty cells of the matrix, as a cue for the height of the stack
+    pub well_background: WellBackground,
+    #[default([0.4, 0.5, 0.7])]
+    pub well_color: [f32; 3],
+    /// How bright the well background is drawn, from 0 to 1
+    #[default = 0.15]
+    pub well_intensity: f32,
+    pub queue_layout: QueueLayout,
+    /// Which way the pieces in the hold box and the queue are turned
+    pub preview_orientation: PreviewOrientation,
+    pub action_text: ActionTextSettings,
+    pub export: ExportSettings,
+    #[cfg(feature = "overlay")]
+    pub overlay: OverlaySettings,
+    pub mode: GameMode,
+    /// Wipe the board when topping out in freestyle, instead of ending the game
+    pub continuous: bool,
+    #[default = true]
+    pub wipe_hold: bool,
+    /// Deal a new bag for each repetition of an openers drill
+    pub reseed_bags: bool,
+    /// Rows cleared off the top of the stack when a piece cannot spawn, or none to end the game
+    #[default = "0"]
+    pub lifesaver_rows: String,
+    #[default = "9"]
+    pub cheese_height: String,
+    pub garbage_pattern: GarbagePattern,
+    #[default = 1.0]
+    pub messiness: f32,
+    /// Push a row of garbage in under the stack after each clear which makes no new holes
+    pub adaptive_cheese: bool,
+    /// File that custom garbage patterns are loaded from
+    pub custom_pattern_path: String,
+    #[default = "4"]
+    pub target_height: String,
+    /// Pieces served before the randomizer, in text notation
+    pub queue: String,
+    /// Pieces left out by the randomizer, for drilling the others
+    pub excluded_pieces: Vec<MinoKind>,
+    /// The piece in hold when the game starts
+    pub initial_hold: Option<MinoKind>,
+    /// Pieces dealt first, ahead of the queue
+    pub preset_queue: Vec<MinoKind>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -50,24 +391,349 @@ pub enum ParseNumError {
     Float(#[from] ParseFloatError),
     #[error("Invalid int in settings: {0}")]
     Int(#[from] ParseIntError),
+    #[error("Invalid queue in settings: {0}")]
+    Queue(#[from] QueueParseError),
+    #[error("The target height of a downstack must be below the cheese height")]
+    TargetHeight,
 }
 
-impl TryFrom<&GlobalSettings> for Settings {
+/// The board's settings come from the global settings, along with the handling of the active
+/// profile.
+impl TryFrom<(&GlobalSettings, &Handling)> for Settings {
     type Error = ParseNumError;
 
-    fn try_from(value: &GlobalSettings) -> Result<Self, Self::Error> {
+    fn try_from((value, handling): (&GlobalSettings, &Handling)) -> Result<Self, Self::Error> {
+        let cheese_height = value.cheese_height.parse()?;
+        let target_height = value.target_height.parse()?;
+        // otherwise no cell starts above the target, and the downstack is won before it begins
+        if value.mode == GameMode::Downstack && target_height >= cheese_height {
+            return Err(ParseNumError::TargetHeight);
+        }
         Ok(Self {
-            soft_drop_power: value.soft_drop_power.parse()?,
+            soft_drop_power: handling.soft_drop_power.parse()?,
             gravity_power: value.gravity_power.parse()?,
             lock_delay: value.lock_delay.parse()?,
-            initial_delay: value.initial_delay.parse()?,
-            repeat_delay: value.repeat_delay.parse()?,
+            lock_reset: value.lock_reset,
+            move_reset_limit: value.move_reset_limit.parse()?,
+            initial_delay: handling.initial_delay.parse()?,
+            repeat_delay: handling.repeat_delay.parse()?,
+            input_buffer: handling.input_buffer.parse()?,
+            socd: handling.socd,
+            stack_visibility: value.stack_visibility,
+            fade_delay: value.fade_delay.parse()?,
+            mode: value.mode,
+            continuous: value.continuous,
+            wipe_hold: value.wipe_hold,
+            reseed_bags: value.reseed_bags,
+            lifesaver_rows: value.lifesaver_rows.parse()?,
+            cheese_height,
+            garbage_pattern: value.garbage_pattern.clone(),
+            messiness: value.messiness,
+            adaptive_cheese: value.adaptive_cheese,
+            target_height,
+            queue: value.queue.parse()?,
+            excluded_pieces: value.excluded_pieces.clone(),
+            initial_hold: value.initial_hold,
+            preset_queue: value.preset_queue.clone(),
+            mouse_mode: value.mouse_mode,
+            tick_rate: value.tick_rate.parse()?,
         })
     }
 }
 
-fn settings_panel(mut contexts: EguiContexts, mut settings: ResMut<GlobalSettings>) {
+impl GlobalSettings {
+    /// How the camera moves between zooms, targets and viewports
+    pub fn camera_timing(&self) -> Timing {
+        Timing {
+            duration: self.camera_duration,
+            easing: self.camera_easing,
+        }
+    }
+
+    /// How the active piece slides between cells, with smooth movement on
+    pub fn movement_timing(&self) -> Timing {
+        Timing {
+            duration: self.smoothing_time / 1000.0,
+            easing: Easing::EaseOutCubic,
+        }
+    }
+
+    /// Takes on the settings which decide how a loaded run starts, so that branching from its
+    /// replay plays under the same settings as the run itself
+    pub fn load_run_settings(&mut self, run: &RunSettings) {
+        self.mode = run.mode;
+        self.cheese_height = run.cheese_height.to_string();
+        self.garbage_pattern = run.garbage_pattern.clone();
+        self.messiness = run.messiness;
+        self.adaptive_cheese = run.adaptive_cheese;
+        self.continuous = run.continuous;
+        self.wipe_hold = run.wipe_hold;
+        self.lifesaver_rows = run.lifesaver_rows.to_string();
+        self.target_height = run.target_height.to_string();
+        self.queue = run.queue.to_string();
+        self.excluded_pieces.clone_from(&run.excluded_pieces);
+        self.initial_hold = run.initial_hold;
+        self.preset_queue.clone_from(&run.preset_queue);
+    }
+}
+
+/// Number of pieces at the front of the queue which can be set before the game starts
+const PRESET_SLOTS: usize = 5;
+
+/// Lets pieces be dragged from a row of every piece into the first few slots of the queue. A piece
+/// dropped past the filled slots goes into the first empty one, so that the set pieces always come
+/// first. Right-clicking a slot empties it.
+fn preset_queue_editor(ui: &mut egui::Ui, preset: &mut Vec<MinoKind>) {
+    let dragged_id = egui::Id::new("preset_queue_dragged");
+    let mut dragged = ui.data(|d| d.get_temp::<MinoKind>(dragged_id));
+    let released = ui.input(|i| i.pointer.any_released());
+
+    ui.vertical(|ui| {
+        ui.horizontal(|ui| {
+            for kind in MinoKind::STANDARD {
+                let piece = egui::Label::new(egui::RichText::new(format!("{kind:?}")).monospace())
+                    .sense(egui::Sense::drag());
+                let response = ui.add(piece);
+                if response.drag_started() {
+                    dragged = Some(kind);
+                }
+                if response.hovered() || response.dragged() {
+                    ui.ctx().set_cursor_icon(egui::CursorIcon::Grab);
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            let mut emptied = None;
+            for slot in 0..PRESET_SLOTS {
+                let text = preset.get(slot).map_or("_".into(), |k| format!("{k:?}"));
+                let response = ui.add(egui::SelectableLabel::new(false, text));
+                let over = dragged.is_some() && ui.rect_contains_pointer(response.rect);
+                if over {
+                    ui.painter()
+                        .rect_stroke(response.rect, 2.0, ui.visuals().selection.stroke);
+                }
+                if let (true, true, Some(kind)) = (over, released, dragged) {
+                    match preset.get_mut(slot) {
+                        Some(piece) => *piece = kind,
+                        None => preset.push(kind),
+                    }
+                }
+                if response.secondary_clicked() && slot < preset.len() {
+                    emptied = Some(slot);
+                }
+            }
+            if let Some(slot) = emptied {
+                preset.remove(slot);
+            }
+        });
+    });
+
+    if let Some(kind) = dragged {
+        egui::show_tooltip_at_pointer(ui.ctx(), dragged_id, |ui| ui.label(format!("{kind:?}")));
+    }
+    if released {
+        dragged = None;
+    }
+    ui.data_mut(|d| match dragged {
+        Some(kind) => d.insert_temp(dragged_id, kind),
+        None => d.remove::<MinoKind>(dragged_id),
+    });
+}
+
+/// Shows a small button which puts a setting back to its default, as long as the setting differs
+/// from it. Returns the default once the button is clicked.
+fn revert_button<T: PartialEq + Clone>(ui: &mut egui::Ui, current: &T, default: &T) -> Option<T> {
+    if current == default {
+        ui.label("");
+        return None;
+    }
+    ui.small_button("↺")
+        .on_hover_text("Revert to default")
+        .clicked()
+        .then(|| default.clone())
+}
+
+/// The options for the window, which are kept between sessions rather than reset with the rest
+fn window_options_grid(ui: &mut egui::Ui, options: &mut ResMut<WindowOptions>) {
+    let defaults = WindowOptions::default();
+    egui::Grid::new("window_options").show(ui, |ui| {
+        duplicate! {
+            [
+                field           display_name;
+                [borderless]    ["Borderless"];
+                [always_on_top] ["Always on Top"];
+                [fixed_size]    ["Fixed Size"]
+            ]
+            let mut copy = options.field;
+            ui.label(display_name);
+            ui.checkbox(&mut copy, "");
+            if options.field != copy {
+                options.field = copy;
+            }
+            if let Some(default) = revert_button(ui, &options.field, &defaults.field) {
+                options.field = default;
+            }
+            ui.end_row();
+        }
+
+        duplicate! {
+            [
+                axis    display_name;
+                [x]     ["Board Offset X"];
+                [y]     ["Board Offset Y"]
+            ]
+            let mut offset = options.board_offset.axis;
+            ui.label(display_name);
+            ui.add(
+                egui::Slider::new(&mut offset, -BOARD_OFFSET_LIMIT..=BOARD_OFFSET_LIMIT)
+                    .suffix("%"),
+            )
+            .on_hover_text("Push the board aside, such as to leave room for an overlay");
+            if options.board_offset.axis != offset {
+                options.board_offset.axis = offset;
+            }
+            if let Some(default) =
+                revert_button(ui, &options.board_offset.axis, &defaults.board_offset.axis)
+            {
+                options.board_offset.axis = default;
+            }
+            ui.end_row();
+        }
+    });
+}
+
+/// How the text announcing clears and spins is shown, with buttons to show examples of it
+fn action_text_grid(
+    ui: &mut egui::Ui,
+    settings: &mut ResMut<GlobalSettings>,
+    defaults: &ActionTextSettings,
+    previews: &mut EventWriter<PreviewActionText>,
+) {
+    egui::Grid::new("action_text").show(ui, |ui| {
+        let mut position = settings.action_text.position;
+        ui.label("Position");
+        egui::ComboBox::from_id_source("action_text_position")
+            .selected_text(position.to_string())
+            .show_ui(ui, |ui| {
+                for p in ActionTextPosition::iter() {
+                    ui.selectable_value(&mut position, p, p.to_string());
+                }
+            });
+        if settings.action_text.position != position {
+            settings.action_text.position = position;
+        }
+        if let Some(default) = revert_button(ui, &settings.action_text.position, &defaults.position)
+        {
+            settings.action_text.position = default;
+        }
+        ui.end_row();
+
+        duplicate! {
+            [
+                field       display_name    range       suffix;
+                [scale]     ["Scale"]       [0.5..=2.0] [""];
+                [duration]  ["Duration"]    [0.3..=3.0] ["s"]
+            ]
+            let mut value = settings.action_text.field;
+            ui.label(display_name);
+            ui.add(egui::Slider::new(&mut value, range).suffix(suffix));
+            if settings.action_text.field != value {
+                settings.action_text.field = value;
+            }
+            if let Some(default) = revert_button(ui, &settings.action_text.field, &defaults.field)
+            {
+                settings.action_text.field = default;
+            }
+            ui.end_row();
+        }
+
+        duplicate! {
+            [
+                field               display_name;
+                [clears]            ["Show Clears"];
+                [spins]             ["Show Spins"];
+                [perfect_clears]    ["Show Perfect Clears"]
+            ]
+            let mut copy = settings.action_text.field;
+            ui.label(display_name);
+            ui.checkbox(&mut copy, "");
+            if settings.action_text.field != copy {
+                settings.action_text.field = copy;
+            }
+            if let Some(default) = revert_button(ui, &settings.action_text.field, &defaults.field)
+            {
+                settings.action_text.field = default;
+            }
+            ui.end_row();
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Preview");
+        if ui.button("Quad").clicked() {
+            previews.send(PreviewActionText(vec![ActionKind::Clear(4)]));
+        }
+        if ui.button("T-Spin Double").clicked() {
+            previews.send(PreviewActionText(vec![ActionKind::Spin(MinoKind::T, 2)]));
+        }
+        if ui.button("Perfect Clear").clicked() {
+            previews.send(PreviewActionText(vec![
+                ActionKind::Clear(4),
+                ActionKind::PerfectClear,
+            ]));
+        }
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn settings_panel(
+    mut contexts: EguiContexts,
+    mut settings: ResMut<GlobalSettings>,
+    mut profiles: ResMut<Profiles>,
+    mut window_options: ResMut<WindowOptions>,
+    mut settings_profiles: ResMut<SettingsProfiles>,
+    mut rebinding: ResMut<Rebinding>,
+    layout: Res<KeyLayout>,
+    mut action_text_previews: EventWriter<PreviewActionText>,
+    mut pattern_error: Local<Option<String>>,
+    mut collapsed: Local<bool>,
+    mut new_profile_name: Local<String>,
+    mut presentation: ResMut<PresentationMode>,
+    // the same defaults that the game starts with
+    defaults: Local<GlobalSettings>,
+) {
+    if **presentation {
+        return;
+    }
+    if *collapsed {
+        egui::Area::new("settings_panel_collapsed")
+            .anchor(egui::Align2::LEFT_TOP, [10.0, 10.0])
+            .show(contexts.ctx_mut(), |ui| {
+                if ui.button("Settings ▸").clicked() {
+                    *collapsed = false;
+                }
+            });
+        return;
+    }
+
     egui::SidePanel::left("settings_panel").show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            if ui.button("◂ Hide").clicked() {
+                *collapsed = true;
+            }
+            if hold_button(ui, "Reset to Defaults") {
+                // goes through the same path as any other change to the settings
+                *settings = GlobalSettings::default();
+                *pattern_error = None;
+            }
+        });
+        settings_profile_row(
+            ui,
+            &mut settings,
+            &mut settings_profiles,
+            &mut new_profile_name,
+        );
+
         let had_focus = ui.memory(|e| e.focus().is_some());
         let tab_pressed = ui.input(|i| i.key_pressed(Key::Tab));
         let must_surrender = !had_focus && tab_pressed;
@@ -76,11 +742,14 @@ fn settings_panel(mut contexts: EguiContexts, mut settings: ResMut<GlobalSetting
             duplicate! {
                 [
                     field               display_name;
-                    [soft_drop_power]   ["Soft Drop Power"];
                     [gravity_power]     ["Gravity power"];
                     [lock_delay]        ["Lock Delay"];
-                    [initial_delay]     ["Initial Delay"];
-                    [repeat_delay]      ["Repeat Delay"]
+                    [move_reset_limit]  ["Move Reset Limit"];
+                    [fade_delay]        ["Fade Delay"];
+                    [cheese_height]     ["Cheese Height"];
+                    [target_height]     ["Target Height"];
+                    [lifesaver_rows]    ["Lifesaver Rows"];
+                    [tick_rate]         ["Tick Rate"]
                 ]
                 let mut copy = settings.field.clone();
                 ui.label(display_name);
@@ -93,19 +762,707 @@ fn settings_panel(mut contexts: EguiContexts, mut settings: ResMut<GlobalSetting
                 if settings.field != copy {
                     settings.field = copy;
                 }
+                if let Some(default) = revert_button(ui, &settings.field, &defaults.field) {
+                    settings.field = default;
+                }
                 ui.end_row();
             }
-        })
+
+            let mut queue = settings.queue.clone();
+            ui.label("Queue");
+            let text_edit = ui.add(TextEdit::singleline(&mut queue).hint_text("e.g. ILJO TSZ*"));
+            if must_surrender {
+                text_edit.surrender_focus();
+            }
+            if settings.queue != queue {
+                settings.queue = queue;
+            }
+            if let Some(default) = revert_button(ui, &settings.queue, &defaults.queue) {
+                settings.queue = default;
+            }
+            ui.end_row();
+
+            if let Err(e) = settings.queue.parse::<QueueSource>() {
+                ui.label("");
+                ui.colored_label(egui::Color32::RED, e.to_string());
+                ui.end_row();
+            }
+
+            // the last piece left in the bag cannot be taken out as well
+            ui.label("Pieces")
+                .on_hover_text("The pieces dealt by the randomizer, for drilling a few of them");
+            ui.horizontal(|ui| {
+                let included = MinoKind::STANDARD
+                    .iter()
+                    .filter(|kind| !settings.excluded_pieces.contains(kind))
+                    .count();
+                for kind in MinoKind::STANDARD {
+                    let selected = !settings.excluded_pieces.contains(&kind);
+                    let enabled = !selected || included > 1;
+                    let toggle = ui.add_enabled(
+                        enabled,
+                        egui::SelectableLabel::new(selected, format!("{kind:?}")),
+                    );
+                    if toggle.clicked() {
+                        if selected {
+                            settings.excluded_pieces.push(kind);
+                        } else {
+                            settings.excluded_pieces.retain(|&k| k != kind);
+                        }
+                    }
+                }
+            });
+            if let Some(default) =
+                revert_button(ui, &settings.excluded_pieces, &defaults.excluded_pieces)
+            {
+                settings.excluded_pieces = default;
+            }
+            ui.end_row();
+
+            let mut hold = settings.initial_hold;
+            let hold_text =
+                |hold: Option<MinoKind>| hold.map_or("None".into(), |k| format!("{k:?}"));
+            ui.label("Hold")
+                .on_hover_text("The piece in hold when the game starts");
+            egui::ComboBox::from_id_source("initial_hold")
+                .selected_text(hold_text(hold))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut hold, None, hold_text(None));
+                    for kind in MinoKind::STANDARD {
+                        ui.selectable_value(&mut hold, Some(kind), hold_text(Some(kind)));
+                    }
+                });
+            if settings.initial_hold != hold {
+                settings.initial_hold = hold;
+            }
+            if let Some(default) = revert_button(ui, &settings.initial_hold, &defaults.initial_hold)
+            {
+                settings.initial_hold = default;
+            }
+            ui.end_row();
+
+            let mut preset = settings.preset_queue.clone();
+            ui.label("Next Queue").on_hover_text(
+                "Pieces dealt first, ahead of the queue. Drag pieces into the slots, and \
+                 right-click a slot to empty it.",
+            );
+            preset_queue_editor(ui, &mut preset);
+            if settings.preset_queue != preset {
+                settings.preset_queue = preset;
+            }
+            if let Some(default) = revert_button(ui, &settings.preset_queue, &defaults.preset_queue)
+            {
+                settings.preset_queue = default;
+            }
+            ui.end_row();
+
+            duplicate! {
+                [
+                    field           display_name;
+                    [particles]     ["Line Clear Particles"];
+                    [lock_flash]    ["Lock Flash"];
+                    [hold_preview]  ["Hold Preview"];
+                    [hold_pulse]    ["Hold Ready Pulse"];
+                    [das_indicator] ["DAS Indicator"];
+                    [bag_tracker]   ["Bag Tracker"];
+                    [rulers]        ["Rulers"];
+                    [fixed_timestep]["Fixed Timestep"];
+                    [mouse_mode]    ["Mouse Placement"];
+                    [skip_idle]     ["Skip Idle Replay"];
+                    [branch_with_current_settings] ["Branch With Current Settings"];
+                    [timer_overlay] ["Timer Overlay"];
+                    [blocked_sound] ["Blocked Move Sound"];
+                    [blocked_shake] ["Blocked Move Shake"];
+                    [pause_on_focus_loss] ["Pause When Unfocused"];
+                    [smooth_movement] ["Smooth Movement"]
+                ]
+                let mut copy = settings.field;
+                ui.label(display_name);
+                ui.checkbox(&mut copy, "");
+                if settings.field != copy {
+                    settings.field = copy;
+                }
+                if let Some(default) = revert_button(ui, &settings.field, &defaults.field) {
+                    settings.field = default;
+                }
+                ui.end_row();
+            }
+
+            // the scale is only applied once the slider is let go, so that the slider does not move
+            // out from under the pointer while it is dragged
+            let mut ui_scale = settings.ui_scale;
+            ui.label("UI Scale");
+            let slider = ui.add(egui::Slider::new(&mut ui_scale, UI_SCALE_RANGE).step_by(0.05));
+            if slider.drag_released() || (slider.changed() && !slider.dragged()) {
+                settings.ui_scale = ui_scale;
+            }
+            if let Some(default) = revert_button(ui, &settings.ui_scale, &defaults.ui_scale) {
+                settings.ui_scale = default;
+            }
+            ui.end_row();
+
+            let mut frame_rate = settings.replay_frame_rate;
+            ui.label("Replay Frame Rate");
+            egui::ComboBox::from_id_source("replay_frame_rate")
+                .selected_text(format!("{frame_rate}/s"))
+                .show_ui(ui, |ui| {
+                    for rate in FrameRate::CHOICES {
+                        ui.selectable_value(&mut frame_rate, rate, format!("{rate}/s"));
+                    }
+                })
+                .response
+                .on_hover_text("How finely the replays of new games can be scrubbed");
+            if settings.replay_frame_rate != frame_rate {
+                settings.replay_frame_rate = frame_rate;
+            }
+            if let Some(default) =
+                revert_button(ui, &settings.replay_frame_rate, &defaults.replay_frame_rate)
+            {
+                settings.replay_frame_rate = default;
+            }
+            ui.end_row();
+
+            let mut focus_zoom = settings.focus_zoom;
+            ui.label("Focus Zoom");
+            ui.add(egui::Slider::new(&mut focus_zoom, 1.0..=4.0))
+                .on_hover_text("How far the replay zooms in on the active piece (toggled with F)");
+            if settings.focus_zoom != focus_zoom {
+                settings.focus_zoom = focus_zoom;
+            }
+            if let Some(default) = revert_button(ui, &settings.focus_zoom, &defaults.focus_zoom) {
+                settings.focus_zoom = default;
+            }
+            ui.end_row();
+
+            let mut smoothing = settings.smoothing_time;
+            ui.label("Smoothing Time");
+            ui.add_enabled(
+                settings.smooth_movement,
+                egui::Slider::new(&mut smoothing, 10.0..=30.0).suffix("ms"),
+            );
+            if settings.smoothing_time != smoothing {
+                settings.smoothing_time = smoothing;
+            }
+            if let Some(default) =
+                revert_button(ui, &settings.smoothing_time, &defaults.smoothing_time)
+            {
+                settings.smoothing_time = default;
+            }
+            ui.end_row();
+
+            let mut duration = settings.camera_duration;
+            ui.label("Camera Duration");
+            ui.add(egui::Slider::new(&mut duration, 0.0..=1.0).suffix("s"));
+            if settings.camera_duration != duration {
+                settings.camera_duration = duration;
+            }
+            if let Some(default) =
+                revert_button(ui, &settings.camera_duration, &defaults.camera_duration)
+            {
+                settings.camera_duration = default;
+            }
+            ui.end_row();
+
+            let mut easing = settings.camera_easing;
+            ui.label("Camera Easing");
+            egui::ComboBox::from_id_source("camera_easing")
+                .selected_text(easing.to_string())
+                .show_ui(ui, |ui| {
+                    for e in Easing::iter() {
+                        ui.selectable_value(&mut easing, e, e.to_string());
+                    }
+                });
+            if settings.camera_easing != easing {
+                settings.camera_easing = easing;
+            }
+            if let Some(default) =
+                revert_button(ui, &settings.camera_easing, &defaults.camera_easing)
+            {
+                settings.camera_easing = default;
+            }
+            ui.end_row();
+
+            let mut preset = settings.palette;
+            ui.label("Palette");
+            egui::ComboBox::from_id_source("palette")
+                .selected_text(preset.to_string())
+                .show_ui(ui, |ui| {
+                    for p in PalettePreset::iter() {
+                        ui.selectable_value(&mut preset, p, p.to_string());
+                    }
+                });
+            if settings.palette != preset {
+                settings.palette = preset;
+            }
+            if let Some(default) = revert_button(ui, &settings.palette, &defaults.palette) {
+                settings.palette = default;
+            }
+            ui.end_row();
+
+            let mut background = settings.well_background;
+            ui.label("Well Background");
+            egui::ComboBox::from_id_source("well_background")
+                .selected_text(background.to_string())
+                .show_ui(ui, |ui| {
+                    for b in WellBackground::iter() {
+                        ui.selectable_value(&mut background, b, b.to_string());
+                    }
+                });
+            if settings.well_background != background {
+                settings.well_background = background;
+            }
+            if let Some(default) =
+                revert_button(ui, &settings.well_background, &defaults.well_background)
+            {
+                settings.well_background = default;
+            }
+            ui.end_row();
+
+            if settings.well_background != WellBackground::Plain {
+                let mut color = settings.well_color;
+                ui.label("Well Color");
+                ui.color_edit_button_rgb(&mut color);
+                if settings.well_color != color {
+                    settings.well_color = color;
+                }
+                if let Some(default) = revert_button(ui, &settings.well_color, &defaults.well_color)
+                {
+                    settings.well_color = default;
+                }
+                ui.end_row();
+
+                let mut intensity = settings.well_intensity;
+                ui.label("Well Intensity");
+                ui.add(egui::Slider::new(&mut intensity, 0.0..=0.5));
+                if settings.well_intensity != intensity {
+                    settings.well_intensity = intensity;
+                }
+                if let Some(default) =
+                    revert_button(ui, &settings.well_intensity, &defaults.well_intensity)
+                {
+                    settings.well_intensity = default;
+                }
+                ui.end_row();
+            }
+
+            let mut layout = settings.queue_layout;
+            ui.label("Queue Layout");
+            egui::ComboBox::from_id_source("queue_layout")
+                .selected_text(layout.to_string())
+                .show_ui(ui, |ui| {
+                    for l in QueueLayout::iter() {
+                        ui.selectable_value(&mut layout, l, l.to_string());
+                    }
+                });
+            if settings.queue_layout != layout {
+                settings.queue_layout = layout;
+            }
+            if let Some(default) = revert_button(ui, &settings.queue_layout, &defaults.queue_layout)
+            {
+                settings.queue_layout = default;
+            }
+            ui.end_row();
+
+            let mut orientation = settings.preview_orientation;
+            ui.label("Preview Orientation");
+            egui::ComboBox::from_id_source("preview_orientation")
+                .selected_text(orientation.to_string())
+                .show_ui(ui, |ui| {
+                    for o in PreviewOrientation::iter() {
+                        ui.selectable_value(&mut orientation, o, o.to_string());
+                    }
+                });
+            if settings.preview_orientation != orientation {
+                settings.preview_orientation = orientation;
+            }
+            if let Some(default) = revert_button(
+                ui,
+                &settings.preview_orientation,
+                &defaults.preview_orientation,
+            ) {
+                settings.preview_orientation = default;
+            }
+            ui.end_row();
+
+            let mut mode = settings.mode;
+            ui.label("Mode");
+            egui::ComboBox::from_id_source("game_mode")
+                .selected_text(mode.to_string())
+                .show_ui(ui, |ui| {
+                    for m in GameMode::iter() {
+                        ui.selectable_value(&mut mode, m, m.to_string());
+                    }
+                });
+            if settings.mode != mode {
+                settings.mode = mode;
+            }
+            if let Some(default) = revert_button(ui, &settings.mode, &defaults.mode) {
+                settings.mode = default;
+            }
+            ui.end_row();
+
+            if let Err(e @ ParseNumError::TargetHeight) =
+                Settings::try_from((&*settings, &profiles.active().handling))
+            {
+                ui.label("");
+                ui.colored_label(egui::Color32::RED, e.to_string());
+                ui.end_row();
+            }
+
+            if settings.mode == GameMode::Freestyle {
+                let mut continuous = settings.continuous;
+                ui.label("Continuous Play");
+                ui.checkbox(&mut continuous, "")
+                    .on_hover_text("Wipe the board on topping out instead of ending the game");
+                if settings.continuous != continuous {
+                    settings.continuous = continuous;
+                }
+                if let Some(default) = revert_button(ui, &settings.continuous, &defaults.continuous)
+                {
+                    settings.continuous = default;
+                }
+                ui.end_row();
+
+                let mut wipe_hold = settings.wipe_hold;
+                ui.label("Wipe Hold");
+                ui.add_enabled(continuous, egui::Checkbox::new(&mut wipe_hold, ""))
+                    .on_hover_text("Empty the hold along with the board on each wipe");
+                if settings.wipe_hold != wipe_hold {
+                    settings.wipe_hold = wipe_hold;
+                }
+                if let Some(default) = revert_button(ui, &settings.wipe_hold, &defaults.wipe_hold) {
+                    settings.wipe_hold = default;
+                }
+                ui.end_row();
+            }
+
+            if settings.mode == GameMode::Openers {
+                let mut reseed_bags = settings.reseed_bags;
+                ui.label("Re-seed Each Bag");
+                ui.checkbox(&mut reseed_bags, "").on_hover_text(
+                    "Deal a new bag for each repetition, instead of carrying on with the queue",
+                );
+                if settings.reseed_bags != reseed_bags {
+                    settings.reseed_bags = reseed_bags;
+                }
+                if let Some(default) =
+                    revert_button(ui, &settings.reseed_bags, &defaults.reseed_bags)
+                {
+                    settings.reseed_bags = default;
+                }
+                ui.end_row();
+            }
+
+            let mut pattern = settings.garbage_pattern.clone();
+            ui.label("Garbage Pattern");
+            egui::ComboBox::from_id_source("garbage_pattern")
+                .selected_text(pattern.to_string())
+                .show_ui(ui, |ui| {
+                    for preset in GarbagePattern::PRESETS {
+                        let text = preset.to_string();
+                        ui.selectable_value(&mut pattern, preset, text);
+                    }
+                });
+            if settings.garbage_pattern != pattern {
+                settings.garbage_pattern = pattern;
+            }
+            if let Some(default) =
+                revert_button(ui, &settings.garbage_pattern, &defaults.garbage_pattern)
+            {
+                settings.garbage_pattern = default;
+            }
+            ui.end_row();
+
+            let mut messiness = settings.messiness;
+            ui.label("Messiness");
+            ui.add(egui::Slider::new(&mut messiness, 0.0..=1.0));
+            if settings.messiness != messiness {
+                settings.messiness = messiness;
+            }
+            if let Some(default) = revert_button(ui, &settings.messiness, &defaults.messiness) {
+                settings.messiness = default;
+            }
+            ui.end_row();
+
+            let mut adaptive = settings.adaptive_cheese;
+            ui.label("Adaptive Cheese");
+            ui.checkbox(&mut adaptive, "")
+                .on_hover_text("Add a row of garbage after each clear which makes no new holes");
+            if settings.adaptive_cheese != adaptive {
+                settings.adaptive_cheese = adaptive;
+            }
+            if let Some(default) =
+                revert_button(ui, &settings.adaptive_cheese, &defaults.adaptive_cheese)
+            {
+                settings.adaptive_cheese = default;
+            }
+            ui.end_row();
+
+            let mut path = settings.custom_pattern_path.clone();
+            ui.label("Custom Pattern");
+            ui.horizontal(|ui| {
+                let text_edit = ui.add(TextEdit::singleline(&mut path).hint_text("pattern.ron"));
+                if must_surrender {
+                    text_edit.surrender_focus();
+                }
+                if ui.button("Load").clicked() {
+                    match GarbagePattern::load(Path::new(&path)) {
+                        Ok(loaded) => {
+                            settings.garbage_pattern = loaded;
+                            *pattern_error = None;
+                        }
+                        Err(e) => *pattern_error = Some(e.to_string()),
+                    }
+                }
+            });
+            if settings.custom_pattern_path != path {
+                settings.custom_pattern_path = path;
+            }
+            if let Some(default) = revert_button(
+                ui,
+                &settings.custom_pattern_path,
+                &defaults.custom_pattern_path,
+            ) {
+                settings.custom_pattern_path = default;
+            }
+            ui.end_row();
+
+            if let Some(error) = &*pattern_error {
+                ui.label("");
+                ui.colored_label(egui::Color32::RED, error);
+                ui.end_row();
+            }
+
+            let mut lock_reset = settings.lock_reset;
+            ui.label("Lock Reset");
+            egui::ComboBox::from_id_source("lock_reset")
+                .selected_text(lock_reset.to_string())
+                .show_ui(ui, |ui| {
+                    for reset in LockReset::iter() {
+                        ui.selectable_value(&mut lock_reset, reset, reset.to_string());
+                    }
+                });
+            if settings.lock_reset != lock_reset {
+                settings.lock_reset = lock_reset;
+            }
+            if let Some(default) = revert_button(ui, &settings.lock_reset, &defaults.lock_reset) {
+                settings.lock_reset = default;
+            }
+            ui.end_row();
+
+            let mut visibility = settings.stack_visibility;
+            ui.label("Stack Visibility");
+            egui::ComboBox::from_id_source("stack_visibility")
+                .selected_text(visibility.to_string())
+                .show_ui(ui, |ui| {
+                    for mode in StackVisibility::iter() {
+                        ui.selectable_value(&mut visibility, mode, mode.to_string());
+                    }
+                });
+            if settings.stack_visibility != visibility {
+                settings.stack_visibility = visibility;
+            }
+            if let Some(default) =
+                revert_button(ui, &settings.stack_visibility, &defaults.stack_visibility)
+            {
+                settings.stack_visibility = default;
+            }
+            ui.end_row();
+        });
+
+        ui.separator();
+        ui.heading("Window");
+        window_options_grid(ui, &mut window_options);
+        if ui
+            .button("Presentation Mode")
+            .on_hover_text("Hide this panel and show the board as large as the window allows")
+            .clicked()
+        {
+            **presentation = true;
+        }
+
+        ui.separator();
+        ui.heading("Action Text");
+        action_text_grid(
+            ui,
+            &mut settings,
+            &defaults.action_text,
+            &mut action_text_previews,
+        );
+
+        #[cfg(feature = "overlay")]
+        {
+            ui.separator();
+            ui.heading("Stream Overlay");
+            overlay_grid(ui, &mut settings);
+        }
+
+        ui.separator();
+        ui.heading("Controls");
+
+        let mut active = profiles.active;
+        ui.horizontal(|ui| {
+            ui.label("Profile");
+            egui::ComboBox::from_id_source("profile")
+                .selected_text(profiles.active().name.clone())
+                .show_ui(ui, |ui| {
+                    for (ix, profile) in profiles.profiles.iter().enumerate() {
+                        ui.selectable_value(&mut active, ix, profile.name.clone());
+                    }
+                })
+                .response
+                .on_hover_text(format!(
+                    "Press {PROFILE_SWITCH_KEY:?} to switch while ready"
+                ));
+        });
+        if profiles.active != active {
+            profiles.active = active;
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("New").clicked() {
+                profiles.create();
+            }
+            if ui.button("Duplicate").clicked() {
+                profiles.duplicate_active();
+            }
+            let can_delete = profiles.profiles.len() > 1;
+            if ui
+                .add_enabled(can_delete, egui::Button::new("Delete"))
+                .clicked()
+            {
+                profiles.delete_active();
+            }
+        });
+
+        egui::Grid::new("profile_panel_inner").show(ui, |ui| {
+            let mut name = profiles.active().name.clone();
+            ui.label("Name");
+            let text_edit = ui.add(TextEdit::singleline(&mut name));
+            if must_surrender {
+                text_edit.surrender_focus();
+            }
+            if profiles.active().name != name {
+                profiles.active_mut().name = name;
+            }
+            ui.end_row();
+
+            duplicate! {
+                [
+                    field               display_name;
+                    [soft_drop_power]   ["Soft Drop Power"];
+                    [initial_delay]     ["Initial Delay"];
+                    [repeat_delay]      ["Repeat Delay"];
+                    [input_buffer]      ["Input Buffer"]
+                ]
+                let mut copy = profiles.active().handling.field.clone();
+                ui.label(display_name);
+
+                let text_edit = ui.add(TextEdit::singleline(&mut copy));
+                if must_surrender {
+                    text_edit.surrender_focus();
+                }
+
+                if profiles.active().handling.field != copy {
+                    profiles.active_mut().handling.field = copy;
+                }
+                ui.end_row();
+            }
+
+            let mut socd = profiles.active().handling.socd;
+            ui.label("Opposite Shifts");
+            egui::ComboBox::from_id_source("socd_policy")
+                .selected_text(socd.to_string())
+                .show_ui(ui, |ui| {
+                    for policy in SocdPolicy::iter() {
+                        ui.selectable_value(&mut socd, policy, policy.to_string());
+                    }
+                })
+                .response
+                .on_hover_text("Which way the piece shifts while both shift keys are held");
+            if profiles.active().handling.socd != socd {
+                profiles.active_mut().handling.socd = socd;
+            }
+            ui.end_row();
+        });
+
+        let mut physical = profiles.active().keybinds.physical;
+        ui.checkbox(&mut physical, "Bind by physical key")
+            .on_hover_text("Keep bindings on the same keys regardless of keyboard layout");
+        if profiles.active().keybinds.physical != physical {
+            profiles.active_mut().keybinds.physical = physical;
+        }
+
+        egui::Grid::new("keybinds_panel_inner").show(ui, |ui| {
+            for action in Action::iter() {
+                ui.label(action.to_string());
+                let text = if **rebinding == Some(action) {
+                    "Press a key...".to_string()
+                } else {
+                    profiles.active().keybinds.describe(action, &layout)
+                };
+                if ui.button(text).clicked() {
+                    **rebinding = Some(action);
+                }
+                ui.end_row();
+            }
+        });
+
+        let bindings = &profiles.active().keybinds.bindings;
+        if_chain::if_chain! {
+            if let Some(hard) = bindings.get(&Action::HardDrop);
+            if bindings.get(&Action::FirmDrop) == Some(hard);
+            then {
+                ui.colored_label(
+                    egui::Color32::RED,
+                    "Hard drop and firm drop are bound to the same key",
+                );
+            }
+        }
     });
 }
 
+/// Sizes the egui panels and the UI nodes (such as the progress bar and the HUD text) by the UI
+/// scale in the settings, kept within [`UI_SCALE_RANGE`] so that the panel stays usable. The scale
+/// is raised further in presentation mode, while the settings panel is hidden.
+fn apply_ui_scale(
+    mut settings: ResMut<GlobalSettings>,
+    mut egui_settings: ResMut<EguiSettings>,
+    mut ui_scale: ResMut<UiScale>,
+    presentation: Res<PresentationMode>,
+) {
+    if !settings.is_changed() && !presentation.is_changed() {
+        return;
+    }
+    let scale = settings
+        .ui_scale
+        .clamp(*UI_SCALE_RANGE.start(), *UI_SCALE_RANGE.end());
+    if settings.ui_scale != scale {
+        settings.ui_scale = scale;
+    }
+    let scale = scale * presentation.scale();
+    if egui_settings.scale_factor != scale {
+        egui_settings.scale_factor = scale;
+    }
+    if ui_scale.0 != scale {
+        ui_scale.0 = scale;
+    }
+}
+
+fn apply_palette_preset(settings: Res<GlobalSettings>, mut palette: ResMut<Palette>) {
+    if settings.is_changed() && palette.preset() != settings.palette {
+        *palette = Palette::new(settings.palette);
+    }
+}
+
 pub fn apply_settings(
     global_settings: Res<GlobalSettings>,
+    profiles: Res<Profiles>,
     mut all_settings: Query<&mut Settings>,
 ) {
     if_chain::if_chain! {
-        if global_settings.is_changed();
-        if let Ok(global) = Settings::try_from(&*global_settings);
+        if global_settings.is_changed() || profiles.is_changed();
+        if let Ok(global) = Settings::try_from((&*global_settings, &profiles.active().handling));
         then {
             for mut s in all_settings.iter_mut() {
                 *s = global.clone()
@@ -114,12 +1471,795 @@ pub fn apply_settings(
     }
 }
 
+/// Marks that a new game should begin as soon as the board has been reset
+#[derive(Resource)]
+pub struct Restarting;
+
 pub fn start_playing(
+    mut commands: Commands,
     input: Res<ButtonInput<KeyCode>>,
     mut state: ResMut<NextState<MainState>>,
     settings: Res<GlobalSettings>,
+    profiles: Res<Profiles>,
+    restarting: Option<Res<Restarting>>,
 ) {
-    if input.just_pressed(KeyCode::Backquote) && Settings::try_from(&*settings).is_ok() {
+    let requested = input.just_pressed(RESTART_KEY) || restarting.is_some();
+    if requested && Settings::try_from((&*settings, &profiles.active().handling)).is_ok() {
+        commands.remove_resource::<Restarting>();
         state.0 = Some(MainState::Playing);
     }
 }
+
+/// Abandons the current game and immediately begins a fresh one with the same settings, either
+/// with a new queue or with the same queue again. Since the same pieces coming up again can be
+/// confusing, the player is told whenever the queue is kept, and when it stops being kept.
+pub fn restart_game(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    mut state: ResMut<NextState<MainState>>,
+    boards: Query<Entity, (With<Matrix>, Without<Ghost>)>,
+    mut ended: EventWriter<GameEnded>,
+    mut toasts: ResMut<Toasts>,
+    mut retrying: Local<bool>,
+) {
+    let reason = if input.just_pressed(RESTART_KEY) {
+        if std::mem::take(&mut *retrying) {
+            toasts.info("Restarting with a new queue");
+        }
+        commands.insert_resource(Restarting);
+        state.0 = Some(MainState::Ready);
+        GameEndReason::Restarted
+    } else if input.just_pressed(RETRY_KEY) {
+        *retrying = true;
+        toasts.info("Retrying the same queue");
+        commands.insert_resource(Restarting);
+        commands.insert_resource(RetryQueue);
+        state.0 = Some(MainState::Ready);
+        GameEndReason::Restarted
+    } else if input.just_pressed(END_GAME_KEY) {
+        state.0 = Some(MainState::PostGame);
+        GameEndReason::Ended
+    } else {
+        return;
+    };
+    ended.send_batch(boards.iter().map(|board| GameEnded { board, reason }));
+}
+
+/// Shows which save slots are occupied, and which one is selected
+fn save_slots_panel(mut contexts: EguiContexts, mut slots: ResMut<SaveSlots>) {
+    egui::Window::new("Save Slots")
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+        .resizable(false)
+        .title_bar(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                let mut selected = slots.selected;
+                for (ix, slot) in slots.slots.iter().enumerate() {
+                    let text = egui::RichText::new((ix + 1).to_string());
+                    let text = if slot.is_some() {
+                        text.strong()
+                    } else {
+                        text.weak()
+                    };
+                    ui.selectable_value(&mut selected, ix, text)
+                        .on_hover_text(if slot.is_some() { "Saved" } else { "Empty" });
+                }
+                if slots.selected != selected {
+                    slots.selected = selected;
+                }
+            });
+        });
+}
+
+/// A bar for each kind of piece in the piece set, showing how many of that piece were placed.
+fn piece_distribution(ui: &mut egui::Ui, stats: &Stats, kinds: &[MinoKind], palette: &Palette) {
+    let placed = &stats.session.placed;
+    let most = placed.values().copied().max().unwrap_or(0).max(1);
+
+    egui::Grid::new("piece_distribution_inner").show(ui, |ui| {
+        for &kind in kinds {
+            let count = placed.get(&kind).copied().unwrap_or(0);
+            let [r, g, b, _] = palette.color(kind).as_rgba_u8();
+
+            ui.label(format!("{kind:?}"));
+            ui.add(
+                egui::ProgressBar::new(count as f32 / most as f32)
+                    .desired_width(120.0)
+                    .fill(egui::Color32::from_rgb(r, g, b))
+                    .text(count.to_string()),
+            );
+            ui.end_row();
+        }
+    });
+}
+
+/// The time and finesse faults of each bag played in an openers drill
+fn bag_runs(ui: &mut egui::Ui, drill: &OpenerDrill) {
+    egui::Grid::new("bag_runs_inner")
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Bag");
+            ui.label("Time");
+            ui.label("Faults");
+            ui.end_row();
+            for (i, bag) in drill.bags.iter().enumerate() {
+                ui.label((i + 1).to_string());
+                ui.label(format!("{:.2}s", bag.time));
+                ui.label(bag.finesse_faults.to_string());
+                ui.end_row();
+            }
+        });
+}
+
+/// Lists the settings that a game was played with, marking those which differ from the settings
+/// that a new game would be played with
+fn played_with(ui: &mut egui::Ui, snapshot: &SettingsSnapshot, current: Option<&Settings>) {
+    let played = &snapshot.settings;
+    let rows = [
+        (
+            "Mode",
+            played.mode.to_string(),
+            current.map(|c| c.mode != played.mode),
+        ),
+        (
+            "Gravity",
+            played.gravity_power.to_string(),
+            current.map(|c| c.gravity_power != played.gravity_power),
+        ),
+        (
+            "Soft Drop",
+            played.soft_drop_power.to_string(),
+            current.map(|c| c.soft_drop_power != played.soft_drop_power),
+        ),
+        (
+            "Lock Delay",
+            played.lock_delay.to_string(),
+            current.map(|c| c.lock_delay != played.lock_delay),
+        ),
+        (
+            "Lock Reset",
+            played.lock_reset.to_string(),
+            current.map(|c| c.lock_reset != played.lock_reset),
+        ),
+        (
+            "Initial Delay",
+            played.initial_delay.to_string(),
+            current.map(|c| c.initial_delay != played.initial_delay),
+        ),
+        (
+            "Repeat Delay",
+            played.repeat_delay.to_string(),
+            current.map(|c| c.repeat_delay != played.repeat_delay),
+        ),
+        (
+            "Input Buffer",
+            played.input_buffer.to_string(),
+            current.map(|c| c.input_buffer != played.input_buffer),
+        ),
+        (
+            "Opposite Shifts",
+            played.socd.to_string(),
+            current.map(|c| c.socd != played.socd),
+        ),
+        (
+            "Queue",
+            format!("{:?}", played.queue),
+            current.map(|c| c.queue != played.queue),
+        ),
+        ("Seed", snapshot.seed.to_string(), None),
+    ];
+    egui::Grid::new("played_with").show(ui, |ui| {
+        for (name, value, differs) in rows {
+            ui.label(name);
+            if differs == Some(true) {
+                ui.colored_label(egui::Color32::YELLOW, value)
+                    .on_hover_text("Differs from the current settings");
+            } else {
+                ui.label(value);
+            }
+            ui.end_row();
+        }
+    });
+}
+
+#[cfg(feature = "overlay")]
+fn overlay_grid(ui: &mut egui::Ui, settings: &mut ResMut<GlobalSettings>) {
+    let defaults = OverlaySettings::default();
+    egui::Grid::new("overlay").show(ui, |ui| {
+        let mut enabled = settings.overlay.enabled;
+        ui.label("Enabled");
+        ui.checkbox(&mut enabled, "").on_hover_text(format!(
+            "Send the events of the game to overlays at ws://localhost:{}",
+            settings.overlay.port
+        ));
+        if settings.overlay.enabled != enabled {
+            settings.overlay.enabled = enabled;
+        }
+        if let Some(default) = revert_button(ui, &settings.overlay.enabled, &defaults.enabled) {
+            settings.overlay.enabled = default;
+        }
+        ui.end_row();
+
+        let mut port = settings.overlay.port;
+        ui.label("Port");
+        ui.add(egui::DragValue::new(&mut port).clamp_range(1024..=u16::MAX));
+        if settings.overlay.port != port {
+            settings.overlay.port = port;
+        }
+        if let Some(default) = revert_button(ui, &settings.overlay.port, &defaults.port) {
+            settings.overlay.port = default;
+        }
+        ui.end_row();
+    });
+}
+
+fn export_options_grid(ui: &mut egui::Ui, settings: &mut ResMut<GlobalSettings>) {
+    let defaults = ExportSettings::default();
+    egui::Grid::new("export_options").show(ui, |ui| {
+        duplicate! {
+            [
+                field               enum_type           display_name;
+                [crop]              [ExportCrop]        ["Crop"];
+                [watermark_corner]  [WatermarkCorner]   ["Watermark Corner"]
+            ]
+            let mut copy = settings.export.field;
+            ui.label(display_name);
+            egui::ComboBox::from_id_source(stringify!(field))
+                .selected_text(copy.to_string())
+                .show_ui(ui, |ui| {
+                    for variant in enum_type::iter() {
+                        ui.selectable_value(&mut copy, variant, variant.to_string());
+                    }
+                });
+            if settings.export.field != copy {
+                settings.export.field = copy;
+            }
+            if let Some(default) = revert_button(ui, &settings.export.field, &defaults.field) {
+                settings.export.field = default;
+            }
+            ui.end_row();
+        }
+
+        duplicate! {
+            [
+                field               display_name;
+                [include_hud]       ["Include HUD"];
+                [watermark]         ["Watermark"];
+                [watermark_date]    ["Watermark Date"]
+            ]
+            let mut copy = settings.export.field;
+            ui.label(display_name);
+            ui.checkbox(&mut copy, "");
+            if settings.export.field != copy {
+                settings.export.field = copy;
+            }
+            if let Some(default) = revert_button(ui, &settings.export.field, &defaults.field) {
+                settings.export.field = default;
+            }
+            ui.end_row();
+        }
+
+        let mut name = settings.export.watermark_name.clone();
+        ui.label("Watermark Name");
+        ui.text_edit_singleline(&mut name);
+        if settings.export.watermark_name != name {
+            settings.export.watermark_name = name;
+        }
+        if let Some(default) = revert_button(
+            ui,
+            &settings.export.watermark_name,
+            &defaults.watermark_name,
+        ) {
+            settings.export.watermark_name = default;
+        }
+        ui.end_row();
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn results_panel(
+    mut contexts: EguiContexts,
+    stats: Res<Stats>,
+    placements: Res<Placements>,
+    record: Res<CompleteRecord>,
+    mut global: ResMut<GlobalSettings>,
+    profiles: Res<Profiles>,
+    run: Res<RunTimestamp>,
+    boards: Query<(&Settings, &PieceQueue, &GlobalTransform, &Bounds), Without<Ghost>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    windows: Query<(Entity, &Window), With<PrimaryWindow>>,
+    mut screenshots: ResMut<ScreenshotManager>,
+    mut hide_hud: ResMut<HideHud>,
+    mut copy_error: Local<Option<String>>,
+    mut toasts: ResMut<Toasts>,
+    palette: Res<Palette>,
+    drill: Res<OpenerDrill>,
+) {
+    let Ok((settings, queue, board_transform, bounds)) = boards.get_single() else {
+        return;
+    };
+
+    egui::Window::new("Results")
+        .anchor(egui::Align2::RIGHT_TOP, [-10.0, 10.0])
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            if settings.mode == GameMode::CheeseRace {
+                let headline = match stats.efficiency() {
+                    Some(efficiency) => {
+                        let [r, g, b, _] = efficiency_color(efficiency).as_rgba_u8();
+                        egui::RichText::new(format!("{efficiency:.2} pieces per garbage line"))
+                            .color(egui::Color32::from_rgb(r, g, b))
+                    }
+                    None => egui::RichText::new("No garbage cleared"),
+                };
+                ui.heading(headline);
+                ui.separator();
+            }
+            if settings.mode == GameMode::Openers {
+                ui.heading(match drill.average_time() {
+                    Some(average) => format!("{average:.2}s per bag"),
+                    None => "No bags finished".into(),
+                });
+                ui.separator();
+            }
+
+            egui::Grid::new("results_inner").show(ui, |ui| {
+                ui.label("Mode");
+                ui.label(settings.mode.to_string());
+                ui.end_row();
+
+                ui.label("Time");
+                ui.label(format!("{:.2}s", stats.time));
+                ui.end_row();
+
+                ui.label("Pieces");
+                ui.label(stats.pieces.to_string());
+                ui.end_row();
+
+                ui.label("Lines");
+                ui.label(stats.lines.to_string());
+                ui.end_row();
+
+                if settings.mode.has_cheese() {
+                    ui.label("Garbage Lines");
+                    ui.label(stats.garbage_lines.to_string());
+                    ui.end_row();
+                }
+
+                if settings.mode != GameMode::Freestyle {
+                    ui.label("Goal");
+                    ui.label(if stats.goal_reached {
+                        "Reached"
+                    } else {
+                        "Not reached"
+                    });
+                    ui.end_row();
+                }
+
+                ui.label("Holds");
+                ui.label(stats.session.holds.to_string());
+                ui.end_row();
+
+                ui.label("Max Combo");
+                // counted as usual, from the second clear in a row
+                ui.label(stats.max_combo.saturating_sub(1).to_string());
+                ui.end_row();
+
+                ui.label("Max B2B");
+                ui.label(stats.max_b2b.saturating_sub(1).to_string());
+                ui.end_row();
+
+                ui.label("Finesse Faults");
+                ui.label(stats.finesse_faults.to_string());
+                ui.end_row();
+
+                if settings.mode == GameMode::Openers {
+                    ui.label("Repetitions");
+                    ui.label(drill.bags.len().to_string());
+                    ui.end_row();
+                }
+
+                if settings.is_continuous() {
+                    ui.label("Deaths");
+                    ui.label(stats.deaths.to_string());
+                    ui.end_row();
+
+                    ui.label("Longest Survival");
+                    ui.label(format!("{} pieces", stats.longest_survival()));
+                    ui.end_row();
+                }
+
+                if settings.lifesaver_rows > 0 {
+                    ui.label("Lifesavers Used");
+                    ui.label(stats.lifesavers.to_string());
+                    ui.end_row();
+                }
+
+                ui.label("Avg. Piece Time");
+                ui.label(match stats.session.average_active_time() {
+                    Some(t) => format!("{t:.2}s"),
+                    None => "-".into(),
+                });
+                ui.end_row();
+            });
+
+            ui.collapsing("Piece Distribution", |ui| {
+                piece_distribution(ui, &stats, queue.pieces(), &palette);
+            });
+
+            if settings.mode == GameMode::Openers && !drill.bags.is_empty() {
+                ui.collapsing("Bags", |ui| bag_runs(ui, &drill));
+            }
+
+            ui.collapsing("Played With", |ui| match &record.snapshot {
+                Some(snapshot) => {
+                    let current = Settings::try_from((&*global, &profiles.active().handling));
+                    played_with(ui, snapshot, current.as_ref().ok());
+                }
+                None => {
+                    ui.label("The settings of this game were not recorded");
+                }
+            });
+
+            ui.separator();
+            let copy = ui
+                .add_enabled(!placements.branched, egui::Button::new("Copy Run Code"))
+                .on_disabled_hover_text(
+                    "Run codes cannot describe games branched from a replay, played out of order, started from an imported board, or dealt a new bag for each repetition",
+                );
+            if copy.clicked() {
+                let code = RunCode {
+                    seed: queue.seed(),
+                    settings: settings.into(),
+                    placements: placements.minos.clone(),
+                };
+                match code.encode() {
+                    Ok(text) => {
+                        ui.output_mut(|o| o.copied_text = text);
+                        *copy_error = None;
+                    }
+                    Err(e) => *copy_error = Some(e.to_string()),
+                }
+            }
+            if let Some(error) = &*copy_error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+
+            egui::CollapsingHeader::new("Export Options")
+                .show(ui, |ui| export_options_grid(ui, &mut global));
+
+            if ui.button("Save Screenshot").clicked() {
+                let rows = bounds.legal_bounds.y + SCREENSHOT_EXTRA_ROWS;
+                if_chain::if_chain! {
+                    if let Ok((window_entity, window)) = windows.get_single();
+                    if let Some(region) = match global.export.crop {
+                        ExportCrop::Board => cameras
+                            .get_single()
+                            .ok()
+                            .and_then(|camera| {
+                                board_screen_rect(rows, camera, (board_transform, bounds))
+                            }),
+                        ExportCrop::Window => {
+                            Some(Rect::new(0.0, 0.0, window.width(), window.height()))
+                        }
+                    };
+                    then {
+                        if !global.export.include_hud {
+                            hide_hud.request();
+                        }
+                        let window = (window_entity, window.scale_factor());
+                        toasts.report(
+                            save_screenshot(&mut screenshots, window, region, &global.export, *run),
+                            |path| format!("Saved to {}", path.display()),
+                        );
+                    } else {
+                        toasts.error("The board is not on screen");
+                    }
+                }
+            }
+        });
+}
+
+/// Takes a run code pasted in by the player, and plays it back as a replay
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::too_many_arguments)]
+fn run_code_panel(
+    mut contexts: EguiContexts,
+    mut boards: Query<BoardQuery, Without<Ghost>>,
+    mut settings: ResMut<GlobalSettings>,
+    mut record: ResMut<CompleteRecord>,
+    mut stats: ResMut<Stats>,
+    mut placements: ResMut<Placements>,
+    mut next_state: ResMut<NextState<MainState>>,
+    mut toasts: ResMut<Toasts>,
+    shape_table: QueryShapeTable,
+    kick_table: QueryKickTable,
+    mut text: Local<String>,
+    mut import_path: Local<String>,
+    mut error: Local<Option<String>>,
+) {
+    egui::Window::new("Run Code")
+        .anchor(egui::Align2::RIGHT_TOP, [-10.0, 10.0])
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.add(TextEdit::singleline(&mut *text).hint_text("Paste a run code"));
+            let load = ui.button("Load").clicked();
+            ui.separator();
+            ui.add(TextEdit::singleline(&mut *import_path).hint_text("Path to a .ttr file"));
+            let import = ui.button("Import TETR.IO Replay").clicked();
+            if let Some(error) = &*error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+
+            let code = if load {
+                RunCode::decode(&text).map_err(|e| e.to_string())
+            } else if import {
+                let path = std::path::Path::new(import_path.trim());
+                match tetrio::import_file(path, &shape_table, &kick_table) {
+                    Ok(code) => Ok(code),
+                    Err(e) => {
+                        // the reasons an import was refused are shown where they cannot be missed
+                        toasts.error(e.to_string());
+                        return;
+                    }
+                }
+            } else {
+                return;
+            };
+            let loaded = code.and_then(|code| {
+                let (new_record, new_stats) =
+                    code.resimulate(&shape_table).map_err(|e| e.to_string())?;
+                Ok((code, new_record, new_stats))
+            });
+            let (code, new_record, new_stats) = match loaded {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    *error = Some(e);
+                    return;
+                }
+            };
+            if import {
+                let stem = std::path::Path::new(import_path.trim())
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned();
+                toasts.report(
+                    ReplayFile::from_record(&new_record).save_imported(&stem),
+                    |path| {
+                        format!(
+                            "Imported {} pieces to {}, without their timing",
+                            code.placements.len(),
+                            path.display()
+                        )
+                    },
+                );
+                import_path.clear();
+            }
+            let Ok(mut board) = boards.get_single_mut() else {
+                return;
+            };
+
+            // the replay begins at the end of the record, so the board is brought there
+            for item in new_record.get(0..new_record.len()).iter() {
+                board.apply_record(item);
+            }
+
+            settings.load_run_settings(&code.settings);
+            *record = new_record;
+            *stats = new_stats;
+            *placements = Placements {
+                minos: code.placements,
+                branched: false,
+            };
+            *error = None;
+            text.clear();
+            next_state.0 = Some(MainState::PostGame);
+        });
+}
+
+/// Saves the record being viewed, and picks a saved record to play beside it for comparison
+#[allow(clippy::too_many_arguments)]
+fn replay_browser_panel(
+    mut contexts: EguiContexts,
+    mut commands: Commands,
+    record: Res<CompleteRecord>,
+    bookmarks: Res<Bookmarks>,
+    chapter: Res<Chapter>,
+    run: Res<RunTimestamp>,
+    ghost: Option<Res<GhostReplay>>,
+    ghost_boards: Query<Entity, With<Ghost>>,
+    mut replays: Local<Option<Vec<std::path::PathBuf>>>,
+    mut toasts: ResMut<Toasts>,
+    shape_table: QueryShapeTable,
+) {
+    let replays = replays.get_or_insert_with(list_replays);
+
+    egui::Window::new("Replays")
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Save Replay").clicked() {
+                    let file = ReplayFile::from_record(&record).with_bookmarks(&bookmarks, &record);
+                    toasts.report(file.save(*run), |path| {
+                        format!("Saved to {}", path.display())
+                    });
+                    *replays = list_replays();
+                }
+                if ui.button("Save Trimmed").clicked() {
+                    let trimmed = ReplayFile::from_record(
+                        &record.trim_idle(record.frame_rate.frames(IDLE_GAP)),
+                    );
+                    toasts.report(trimmed.save_trimmed(*run), |path| {
+                        format!("Saved to {}", path.display())
+                    });
+                    *replays = list_replays();
+                }
+                let frames = chapter.frames();
+                let save_chapter = ui
+                    .add_enabled(frames.is_some(), egui::Button::new("Save Chapter"))
+                    .on_disabled_hover_text("Mark the start and end of a chapter with I and O");
+                if let Some(frames) = frames.filter(|_| save_chapter.clicked()) {
+                    let file = ReplayFile::from_chapter(&record, &bookmarks, frames.clone());
+                    toasts.report(file.save_chapter(*run, frames), |path| {
+                        format!("Saved to {}", path.display())
+                    });
+                    *replays = list_replays();
+                }
+                if ui.button("Refresh").clicked() {
+                    *replays = list_replays();
+                }
+                if ghost.is_some() && ui.button("Stop Comparing").clicked() {
+                    for e in ghost_boards.iter() {
+                        commands.entity(e).despawn_recursive();
+                    }
+                    commands.remove_resource::<GhostReplay>();
+                }
+            });
+
+            ui.separator();
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("replay_browser_inner").show(ui, |ui| {
+                        for path in replays.iter() {
+                            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                            match stem.strip_suffix(IMPORTED_SUFFIX) {
+                                Some(name) => {
+                                    ui.label(name).on_hover_text(
+                                        "Imported from TETR.IO: the placements are kept, but the \
+                                         time spent on each piece is not",
+                                    );
+                                    ui.weak("TETR.IO");
+                                }
+                                None => {
+                                    ui.label(stem.to_string());
+                                    ui.label("");
+                                }
+                            }
+                            if ui.button("Compare").clicked() {
+                                // saved replays may have been edited or come from elsewhere
+                                let loaded = ReplayFile::load(path).map_err(|e| e.to_string());
+                                let verified = loaded.and_then(|file| {
+                                    verify_record(&file.items, &shape_table)
+                                        .map(|_| file)
+                                        .map_err(|e| e.to_string())
+                                });
+                                match verified {
+                                    Ok(file) => {
+                                        if let Some(kinds) = file.drill_pieces(&shape_table.kinds())
+                                        {
+                                            let kinds =
+                                                kinds.iter().map(|k| format!("{k:?}")).join("/");
+                                            toasts.info(format!("This replay was a {kinds} drill"));
+                                        }
+                                        commands.insert_resource(GhostReplay::new(
+                                            file.items_at(record.frame_rate),
+                                        ))
+                                    }
+                                    Err(e) => toasts.error(e),
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+        });
+}
+
+/// Whether the overlay showing exact piece coordinates is open
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct AuthoringOverlay(bool);
+
+/// Shows where the active piece is and which cells it covers, along with the cell under the cursor,
+/// for writing down setups by hand. The current placement can be copied as RON.
+fn authoring_overlay(
+    mut contexts: EguiContexts,
+    mut shown: ResMut<AuthoringOverlay>,
+    keys: Res<ButtonInput<KeyCode>>,
+    boards: Query<(&Active, &GlobalTransform, &Bounds), Without<Ghost>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    shape_table: QueryShapeTable,
+) {
+    if keys.just_pressed(AUTHORING_TOGGLE_KEY) {
+        **shown = !**shown;
+    }
+    if !**shown {
+        return;
+    }
+
+    let Ok((active, board_transform, bounds)) = boards.get_single() else {
+        return;
+    };
+    let hovered = if_chain::if_chain! {
+        if let Ok(window) = windows.get_single();
+        if let Some(cursor) = window.cursor_position();
+        if let Ok(camera) = cameras.get_single();
+        then {
+            screen_to_cell(cursor, camera, (board_transform, bounds))
+        } else {
+            None
+        }
+    };
+
+    let ctx = contexts.ctx_mut();
+    let snippet = active
+        .0
+        .map(|mino| ron::to_string(&mino).expect("placements should always be serializable"));
+    if let Some(snippet) = snippet
+        .as_ref()
+        .filter(|_| keys.just_pressed(AUTHORING_COPY_KEY))
+    {
+        ctx.output_mut(|o| o.copied_text = snippet.clone());
+    }
+
+    egui::Window::new("Authoring")
+        .anchor(egui::Align2::LEFT_BOTTOM, [10.0, -10.0])
+        .resizable(false)
+        .show(ctx, |ui| {
+            egui::Grid::new("authoring_inner").show(ui, |ui| {
+                if let Some(mino) = active.0 {
+                    let cells = shape_table[mino]
+                        .iter()
+                        .map(|&p| p + mino.position)
+                        .map(|p| format!("({}, {})", p.x, p.y))
+                        .collect::<Vec<_>>();
+
+                    ui.label("Piece");
+                    ui.label(format!("{:?}", mino.kind));
+                    ui.end_row();
+
+                    ui.label("Position");
+                    ui.label(format!("({}, {})", mino.position.x, mino.position.y));
+                    ui.end_row();
+
+                    ui.label("Rotation");
+                    ui.label(format!("{:?}", mino.rotation));
+                    ui.end_row();
+
+                    ui.label("Cells");
+                    ui.label(cells.join(" "));
+                    ui.end_row();
+                } else {
+                    ui.label("Piece");
+                    ui.label("None");
+                    ui.end_row();
+                }
+
+                ui.label("Cursor");
+                ui.label(hovered.map_or_else(|| "-".into(), |c| format!("({}, {})", c.x, c.y)));
+                ui.end_row();
+            });
+
+            if let Some(snippet) = snippet {
+                if ui
+                    .button(format!("Copy ({AUTHORING_COPY_KEY:?})"))
+                    .clicked()
+                {
+                    ui.output_mut(|o| o.copied_text = snippet);
+                }
+            }
+        });
+}
@@ -0,0 +1,165 @@
+//! Named sets of gameplay settings, kept between sessions. These are separate from the keybind and
+//! handling profiles of the controller, so that either can be switched without the other.
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{self, ConfigError, Versioned};
+use crate::toasts::Toasts;
+
+use super::GlobalSettings;
+
+pub const SETTINGS_PROFILES_PATH: &str = "settings_profiles.ron";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SettingsProfile {
+    pub name: String,
+    pub settings: GlobalSettings,
+}
+
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct SettingsProfiles {
+    pub profiles: Vec<SettingsProfile>,
+    /// The profile last selected or saved, which the current settings are compared against
+    pub selected: Option<usize>,
+}
+
+impl Versioned for SettingsProfiles {
+    const VERSION: u32 = 1;
+
+    fn migrate(version: u32, _: &str) -> Result<Self, ConfigError> {
+        Err(ConfigError::UnknownVersion(version))
+    }
+}
+
+impl SettingsProfiles {
+    /// Loads the profiles saved by a previous session, along with anything the player should be
+    /// warned about
+    pub fn load() -> (Self, Option<String>) {
+        let (mut profiles, warning) = match config::load::<Self>(SETTINGS_PROFILES_PATH) {
+            Ok(loaded) => loaded,
+            Err(ConfigError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => default(),
+            Err(e) => (
+                default(),
+                Some(format!("Could not read saved settings profiles: {e}")),
+            ),
+        };
+        if let Some(warning) = &warning {
+            tracing::warn!("{warning}");
+        }
+        // the settings start at their defaults, so no profile is in use until one is selected
+        profiles.selected = None;
+        (profiles, warning)
+    }
+
+    pub fn selected(&self) -> Option<&SettingsProfile> {
+        self.profiles.get(self.selected?)
+    }
+
+    /// Whether the settings have been changed since the selected profile was selected or saved
+    pub fn is_dirty(&self, settings: &GlobalSettings) -> bool {
+        self.selected()
+            .is_some_and(|profile| profile.settings != *settings)
+    }
+
+    /// Overwrites the selected profile with the given settings
+    pub fn save_selected(&mut self, settings: &GlobalSettings) {
+        if let Some(ix) = self.selected {
+            self.profiles[ix].settings = settings.clone();
+        }
+    }
+
+    /// Saves the given settings under the given name and selects them, replacing any profile which
+    /// already has that name
+    pub fn save_as(&mut self, name: String, settings: &GlobalSettings) {
+        let profile = SettingsProfile {
+            name,
+            settings: settings.clone(),
+        };
+        match self.profiles.iter().position(|p| p.name == profile.name) {
+            Some(ix) => {
+                self.profiles[ix] = profile;
+                self.selected = Some(ix);
+            }
+            None => {
+                self.profiles.push(profile);
+                self.selected = Some(self.profiles.len() - 1);
+            }
+        }
+    }
+
+    /// Deletes the selected profile. The settings are left as they are.
+    pub fn delete_selected(&mut self) {
+        if let Some(ix) = self.selected.take() {
+            self.profiles.remove(ix);
+        }
+    }
+}
+
+/// The row at the top of the settings panel for choosing a settings profile. Choosing a profile
+/// replaces the settings wholesale, which applies them as any other change to the settings would.
+/// A profile whose settings have been changed since is marked with an asterisk, and can be saved
+/// over or saved under a new name.
+pub(super) fn settings_profile_row(
+    ui: &mut egui::Ui,
+    settings: &mut ResMut<GlobalSettings>,
+    profiles: &mut ResMut<SettingsProfiles>,
+    new_name: &mut String,
+) {
+    let dirty = profiles.is_dirty(&**settings);
+    let shown = profiles.selected().map_or("(none)".to_string(), |profile| {
+        format!("{}{}", profile.name, if dirty { "*" } else { "" })
+    });
+
+    let mut selected = profiles.selected;
+    ui.horizontal(|ui| {
+        ui.label("Settings Profile");
+        egui::ComboBox::from_id_source("settings_profile")
+            .selected_text(shown)
+            .show_ui(ui, |ui| {
+                for (ix, profile) in profiles.profiles.iter().enumerate() {
+                    ui.selectable_value(&mut selected, Some(ix), profile.name.clone());
+                }
+            });
+    });
+    if profiles.selected != selected {
+        profiles.selected = selected;
+        if let Some(profile) = profiles.selected() {
+            **settings = profile.settings.clone();
+        }
+    }
+
+    ui.horizontal(|ui| {
+        if ui.add_enabled(dirty, egui::Button::new("Save")).clicked() {
+            profiles.save_selected(&**settings);
+        }
+        ui.add(
+            egui::TextEdit::singleline(new_name)
+                .hint_text("profile name")
+                .desired_width(100.0),
+        );
+        let name = new_name.trim();
+        if ui
+            .add_enabled(!name.is_empty(), egui::Button::new("Save As"))
+            .clicked()
+        {
+            profiles.save_as(name.to_string(), &**settings);
+            new_name.clear();
+        }
+        if ui
+            .add_enabled(profiles.selected.is_some(), egui::Button::new("Delete"))
+            .clicked()
+        {
+            profiles.delete_selected();
+        }
+    });
+}
+
+pub(super) fn save_settings_profiles(profiles: Res<SettingsProfiles>, mut toasts: ResMut<Toasts>) {
+    if profiles.is_changed() && !profiles.is_added() {
+        if let Err(e) = config::save(SETTINGS_PROFILES_PATH, &*profiles) {
+            toasts.error(format!("Could not save settings profiles: {e}"));
+        }
+    }
+}
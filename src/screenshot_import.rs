@@ -0,0 +1,397 @@
+//! Recreating a board from a screenshot of it. The screenshot is shown in a window where the
+//! playfield is picked out by dragging a rectangle over it, and each cell under the rectangle is
+//! read as the piece of the palette nearest to its average color. Cells whose color is not close to
+//! any one piece are read as garbage and outlined, so that they can be corrected by hand before the
+//! board is put in place for the next game.
+
+use bevy::math::{ivec2, URect};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use image::RgbaImage;
+
+use crate::assets::palette::Palette;
+use crate::board::{Matrix, MinoKind};
+use crate::controller::keybinds::{BindingContext, Hotkey};
+use crate::replay::code::{self, Placements};
+use crate::replay::ghost::Ghost;
+use crate::state::MainState;
+
+const IMPORT_TOGGLE_KEY: KeyCode = KeyCode::F11;
+
+pub(crate) const HOTKEYS: &[Hotkey] = &[Hotkey {
+    keys: &[IMPORT_TOGGLE_KEY],
+    name: "Import Screenshot",
+    context: BindingContext::Ready,
+}];
+
+/// Cells with no channel brighter than this are read as empty
+const EMPTY_BRIGHTNESS: f32 = 60.0;
+/// Cells further than this from every color of the palette are ambiguous
+const MAX_DISTANCE: f32 = 90.0;
+/// Cells are ambiguous unless the nearest color of the palette is at most this fraction of the
+/// distance to the next nearest
+const AMBIGUITY_RATIO: f32 = 0.6;
+/// The widest that the screenshot is shown, in points
+const PREVIEW_WIDTH: f32 = 420.0;
+/// Size of each cell of the board read from the screenshot, in points
+const CELL_SIZE: f32 = 14.0;
+/// The kinds that clicking a cell of the board read from the screenshot steps through
+const CYCLE: [MinoKind; 9] = {
+    use MinoKind::*;
+    [E, T, O, L, J, S, Z, I, G]
+};
+
+/// Reads the average color of a cell of a screenshot as a kind of cell, under the given palette.
+/// Returns nothing if the color lies too far from every piece, or too close to more than one.
+pub fn classify(color: [f32; 3], palette: &Palette) -> Option<MinoKind> {
+    if color.iter().all(|&c| c < EMPTY_BRIGHTNESS) {
+        return Some(MinoKind::E);
+    }
+    let distance = |kind: MinoKind| {
+        let [r, g, b, _] = palette.color(kind).as_rgba_u8();
+        [r, g, b]
+            .iter()
+            .zip(color)
+            .map(|(&a, b)| (a as f32 - b).powi(2))
+            .sum::<f32>()
+            .sqrt()
+    };
+    let mut distances = CYCLE[1..]
+        .iter()
+        .map(|&kind| (distance(kind), kind))
+        .collect::<Vec<_>>();
+    distances.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let (nearest, kind) = distances[0];
+    let next = distances[1].0;
+    (nearest <= MAX_DISTANCE && nearest <= next * AMBIGUITY_RATIO).then_some(kind)
+}
+
+/// A board read from a screenshot
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedBoard {
+    pub matrix: Matrix,
+    /// The cells which could not be read, which are left as garbage
+    pub flagged: Vec<IVec2>,
+}
+
+/// Number of rows of cells of the given width that fit in the region, counting the cells as square
+pub fn region_rows(region: URect, width: usize) -> usize {
+    if region.width() == 0 {
+        return 0;
+    }
+    let cell = region.width() as f32 / width as f32;
+    (region.height() as f32 / cell).round() as usize
+}
+
+/// Reads the cells of the region of a screenshot, which covers the width of the matrix and as many
+/// rows from the bottom as fit in it. Each cell is read from the average color of its middle, so
+/// that grid lines and the edges of textures are left out.
+pub fn read_board(image: &RgbaImage, region: URect, palette: &Palette) -> ImportedBoard {
+    let mut matrix = Matrix::default();
+    let width = matrix.width();
+    let rows = region_rows(region, width).min(matrix.height());
+    let cell = region.size().as_vec2() / Vec2::new(width as f32, rows.max(1) as f32);
+
+    let mut flagged = Vec::new();
+    for y in 0..rows {
+        for x in 0..width {
+            // rows are counted up from the bottom of the region
+            let min = region.min.as_vec2() + cell * Vec2::new(x as f32, (rows - 1 - y) as f32);
+            let from = (min + cell * 0.25).as_uvec2();
+            let to = (min + cell * 0.75).as_uvec2().max(from + UVec2::ONE);
+
+            let mut sum = [0.0; 3];
+            let mut count = 0.0;
+            for py in from.y..to.y.min(image.height()) {
+                for px in from.x..to.x.min(image.width()) {
+                    let pixel = image.get_pixel(px, py);
+                    for (s, &c) in sum.iter_mut().zip(&pixel.0[..3]) {
+                        *s += c as f32;
+                    }
+                    count += 1.0;
+                }
+            }
+            if count == 0.0 {
+                continue;
+            }
+
+            let position = ivec2(x as i32, y as i32);
+            let kind = classify(sum.map(|s| s / count), palette).unwrap_or_else(|| {
+                flagged.push(position);
+                MinoKind::G
+            });
+            if let Some(c) = matrix.get_mut(position) {
+                *c = kind;
+            }
+        }
+    }
+    ImportedBoard { matrix, flagged }
+}
+
+#[derive(Resource, Default)]
+pub struct ScreenshotImport {
+    open: bool,
+    path: String,
+    /// The loaded screenshot, along with the handle of the texture that it is shown through
+    screenshot: Option<(RgbaImage, Handle<Image>)>,
+    /// Where the rectangle over the playfield was started, in pixels of the screenshot
+    drag_start: Option<UVec2>,
+    /// The playfield, in pixels of the screenshot
+    region: Option<URect>,
+    board: Option<ImportedBoard>,
+    status: Option<String>,
+}
+
+fn toggle_import(
+    mut import: ResMut<ScreenshotImport>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut contexts: EguiContexts,
+) {
+    if keys.just_pressed(IMPORT_TOGGLE_KEY) && !contexts.ctx_mut().wants_keyboard_input() {
+        import.open = !import.open;
+    }
+}
+
+/// Loads the screenshot at the given path, replacing the one loaded before it
+fn load_screenshot(
+    import: &mut ScreenshotImport,
+    images: &mut Assets<Image>,
+    contexts: &mut EguiContexts,
+) {
+    let path = import.path.trim().to_string();
+    match image::open(&path) {
+        Ok(loaded) => {
+            if let Some((_, old)) = import.screenshot.take() {
+                contexts.remove_image(&old);
+                images.remove(&old);
+            }
+            let rgba = loaded.to_rgba8();
+            let handle = images.add(Image::from_dynamic(loaded, true, default()));
+            import.screenshot = Some((rgba, handle));
+            import.region = None;
+            import.board = None;
+            import.status = None;
+        }
+        Err(e) => import.status = Some(format!("Could not load {path}: {e}")),
+    }
+}
+
+fn import_window(
+    mut contexts: EguiContexts,
+    mut import: ResMut<ScreenshotImport>,
+    mut images: ResMut<Assets<Image>>,
+    mut boards: Query<&mut Matrix, Without<Ghost>>,
+    palette: Res<Palette>,
+) {
+    if !import.open {
+        return;
+    }
+    let texture = import
+        .screenshot
+        .as_ref()
+        .map(|(_, handle)| contexts.add_image(handle.clone_weak()));
+
+    let mut open = true;
+    let mut load = false;
+    let mut apply = false;
+    egui::Window::new("Import Screenshot")
+        .open(&mut open)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let import = &mut *import;
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut import.path).hint_text("Path to an image"));
+                load = ui.button("Load").clicked();
+            });
+
+            if let (Some(texture), Some((screenshot, _))) = (texture, &import.screenshot) {
+                ui.label("Drag a rectangle over the playfield");
+                let pixels = Vec2::new(screenshot.width() as f32, screenshot.height() as f32);
+                let scale = (PREVIEW_WIDTH / pixels.x).min(1.0);
+                let size = egui::vec2(pixels.x * scale, pixels.y * scale);
+                let response = ui.add(
+                    egui::Image::new(egui::load::SizedTexture::new(texture, size))
+                        .sense(egui::Sense::drag()),
+                );
+                let rect = response.rect;
+                let to_pixel = |pos: egui::Pos2| {
+                    let offset = (pos - rect.min) / scale;
+                    Vec2::new(offset.x, offset.y)
+                        .clamp(Vec2::ZERO, pixels)
+                        .as_uvec2()
+                };
+                let to_screen =
+                    |pixel: UVec2| rect.min + egui::vec2(pixel.x as f32, pixel.y as f32) * scale;
+
+                if let Some(pointer) = response.interact_pointer_pos() {
+                    if response.drag_started() {
+                        import.drag_start = Some(to_pixel(pointer));
+                    }
+                    if let (true, Some(start)) = (response.dragged(), import.drag_start) {
+                        let end = to_pixel(pointer);
+                        import.region = Some(URect::from_corners(start, end));
+                        import.board = None;
+                    }
+                }
+                if response.drag_released() {
+                    import.drag_start = None;
+                }
+
+                if let Some(region) = import.region {
+                    let width = Matrix::default().width();
+                    let rows = region_rows(region, width);
+                    let painter = ui.painter();
+                    let stroke = egui::Stroke::new(1.0, egui::Color32::YELLOW);
+                    let screen =
+                        egui::Rect::from_min_max(to_screen(region.min), to_screen(region.max));
+                    painter.rect_stroke(screen, 0.0, (2.0, egui::Color32::YELLOW));
+                    // the cells that the region will be read as
+                    for x in 1..width {
+                        let sx = screen.min.x + screen.width() * x as f32 / width as f32;
+                        painter.line_segment(
+                            [egui::pos2(sx, screen.min.y), egui::pos2(sx, screen.max.y)],
+                            stroke,
+                        );
+                    }
+                    for y in 1..rows {
+                        let sy = screen.min.y + screen.height() * y as f32 / rows as f32;
+                        painter.line_segment(
+                            [egui::pos2(screen.min.x, sy), egui::pos2(screen.max.x, sy)],
+                            stroke,
+                        );
+                    }
+                    ui.label(format!("{width} columns by {rows} rows"));
+                    if ui
+                        .add_enabled(rows > 0, egui::Button::new("Read Board"))
+                        .clicked()
+                    {
+                        import.board = Some(read_board(screenshot, region, &palette));
+                    }
+                }
+            }
+
+            if let Some(board) = &mut import.board {
+                ui.separator();
+                if board.flagged.is_empty() {
+                    ui.label("Every cell was read");
+                } else {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!(
+                            "{} cells could not be read and were set to garbage",
+                            board.flagged.len()
+                        ),
+                    );
+                }
+                ui.label("Click a cell to change it");
+                board_grid(ui, board, &palette);
+                apply = ui.button("Use Board").clicked();
+            }
+
+            if let Some(status) = &import.status {
+                ui.label(status.as_str());
+            }
+        });
+
+    if load {
+        load_screenshot(&mut import, &mut images, &mut contexts);
+    }
+    if apply {
+        if let Some(board) = &import.board {
+            for mut matrix in boards.iter_mut() {
+                *matrix = board.matrix.clone();
+            }
+            import.status = Some("The next game starts from this board".into());
+        }
+    }
+    import.open &= open;
+}
+
+/// Draws the rows of the board read from the screenshot, outlining the cells which could not be
+/// read. Clicking a cell steps it through the kinds of cell, which also clears its outline.
+fn board_grid(ui: &mut egui::Ui, board: &mut ImportedBoard, palette: &Palette) {
+    let width = board.matrix.width();
+    let rows = (0..board.matrix.height())
+        .rev()
+        .find(|&y| board.matrix.row(y).iter().any(|&kind| kind != MinoKind::E))
+        .map_or(1, |top| top + 1);
+    let size = egui::vec2(width as f32, rows as f32) * CELL_SIZE;
+    let (response, painter) = ui.allocate_painter(size, egui::Sense::click());
+    let rect = response.rect;
+    let cell_rect = |cell: IVec2| {
+        egui::Rect::from_min_size(
+            egui::pos2(
+                rect.min.x + cell.x as f32 * CELL_SIZE,
+                rect.max.y - (cell.y + 1) as f32 * CELL_SIZE,
+            ),
+            egui::Vec2::splat(CELL_SIZE),
+        )
+    };
+
+    painter.rect_filled(rect, 0.0, egui::Color32::from_gray(20));
+    for (cell, kind) in board.matrix.iter_cells() {
+        if cell.y as usize >= rows {
+            continue;
+        }
+        let r = cell_rect(cell).shrink(1.0);
+        if kind == MinoKind::E {
+            painter.rect_stroke(r, 0.0, (1.0, egui::Color32::from_gray(40)));
+        } else {
+            let [red, g, b, _] = palette.color(kind).as_rgba_u8();
+            painter.rect_filled(r, 0.0, egui::Color32::from_rgb(red, g, b));
+        }
+        if board.flagged.contains(&cell) {
+            painter.rect_stroke(r, 0.0, (2.0, egui::Color32::RED));
+        }
+    }
+
+    let Some(pointer) = response
+        .interact_pointer_pos()
+        .filter(|_| response.clicked())
+    else {
+        return;
+    };
+    let cell = ivec2(
+        ((pointer.x - rect.min.x) / CELL_SIZE) as i32,
+        ((rect.max.y - pointer.y) / CELL_SIZE) as i32,
+    );
+    if let Some(kind) = board.matrix.get_mut(cell) {
+        let ix = CYCLE.iter().position(|k| k == kind).unwrap_or(0);
+        *kind = CYCLE[(ix + 1) % CYCLE.len()];
+        board.flagged.retain(|&c| c != cell);
+    }
+}
+
+/// Run codes only describe games played from an empty board, so a game starting from an imported
+/// board cannot be written as one
+fn mark_imported(mut placements: ResMut<Placements>, boards: Query<&Matrix, Without<Ghost>>) {
+    let imported = boards
+        .iter()
+        .any(|matrix| matrix.cells().iter().any(|&kind| kind != MinoKind::E));
+    if imported {
+        placements.branched = true;
+    }
+}
+
+pub struct ScreenshotImportPlugin;
+
+impl Plugin for ScreenshotImportPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScreenshotImport>()
+            .add_systems(
+                Update,
+                (toggle_import, import_window)
+                    .chain()
+                    .run_if(in_state(MainState::Ready)),
+            )
+            .add_systems(
+                OnTransition {
+                    from: MainState::Ready,
+                    to: MainState::Playing,
+                },
+                mark_imported
+                    .after(code::reset_placements)
+                    .before(crate::board::start_game),
+            );
+    }
+}
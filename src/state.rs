@@ -4,11 +4,18 @@ use bevy::prelude::*;
 pub enum MainState {
     #[default]
     Loading,
+    /// Some assets could not be loaded, so the game cannot start
+    LoadingFailed,
     Ready,
     Playing,
     PostGame,
 }
 
+/// Whether the game's assets have finished loading, so systems depending on them can run
+pub fn assets_loaded(state: Res<State<MainState>>) -> bool {
+    !matches!(state.get(), MainState::Loading | MainState::LoadingFailed)
+}
+
 pub struct StatePlugin;
 
 impl Plugin for StatePlugin {
@@ -0,0 +1,700 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use itertools::Itertools;
+
+use crate::assets::palette::Palette;
+use crate::assets::tables::shape_table::{ShapeParameters, ShapeTable};
+use crate::board::events::{HoldUsed, PieceLocked};
+use crate::board::{
+    Active, BoardWipeEvent, GameMode, Hold, LifesaverUsed, LineClearEvent, Matrix, Mino, MinoKind,
+    RotationState, Settings, SPRINT_LINES,
+};
+use crate::progress_bar::{
+    LabelFormat, LabelPlacement, Orientation, ProgressBar, ProgressBarBundle, ProgressBarLabel,
+    ProgressBarMaterial,
+};
+use crate::replay::ghost::Ghost;
+use crate::replay::record::{CompleteRecord, RecordData};
+use crate::screens::GlobalSettings;
+use crate::state::MainState;
+
+/// Pieces per garbage line under which a cheese race counts as efficient
+pub const GOOD_EFFICIENCY: f32 = 1.25;
+/// Pieces per garbage line under which a cheese race counts as passable
+pub const FAIR_EFFICIENCY: f32 = 1.75;
+/// Lines between the milestones of a sprint
+pub const SPRINT_MILESTONE: u32 = 10;
+/// Seconds of play that each point of a pace graph looks back over
+pub const PACE_WINDOW: f32 = 5.0;
+/// Clears in a row from which a combo is marked on a pace graph
+pub const BIG_COMBO: u32 = 4;
+/// Lines sent by clearing each number of lines at once, from none up to four
+const CLEAR_ATTACK: [u32; 5] = [0, 0, 1, 2, 4];
+/// Lines added to a clear for each clear in a row before it
+const COMBO_ATTACK: [u32; 12] = [0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+const PERFECT_CLEAR_ATTACK: u32 = 10;
+/// Bounds on the seconds that the combo bar takes to drain a notch, which follows the pace of play
+const COMBO_WINDOW: std::ops::RangeInclusive<f32> = 0.5..=5.0;
+
+/// Running totals for the game currently being played.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct Stats {
+    pub pieces: u32,
+    pub lines: u32,
+    /// Cleared lines which contained garbage before they were cleared
+    pub garbage_lines: u32,
+    /// Seconds spent in play
+    pub time: f32,
+    /// Whether the game ended by reaching its goal (rather than by topping out)
+    pub goal_reached: bool,
+    /// Times the board was wiped after topping out in continuous play
+    pub deaths: u32,
+    /// The most pieces placed between two deaths, not counting the pieces since the last death
+    pub longest_streak: u32,
+    /// Pieces placed before the last death
+    pub streak_start: u32,
+    /// The combo and back to back chain going on after the last piece
+    pub chain: ClearChain,
+    /// The most clears in a row
+    pub max_combo: u32,
+    /// The longest chain of back to back clears
+    pub max_b2b: u32,
+    /// Pieces placed with more presses than they needed
+    pub finesse_faults: u32,
+    /// Times the top of the stack was cleared away to make room for a piece to spawn
+    pub lifesavers: u32,
+    pub session: SessionStats,
+}
+
+/// Clears in a row and difficult clears in a row, as they stand after each lock. Both the stats of
+/// a game being played and the analysis of a record go through this, so that they agree.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClearChain {
+    /// Clears in a row up to and including the last piece, or zero if it cleared nothing
+    pub combo: u32,
+    /// Difficult clears in a row, which pieces clearing nothing do not break. The chain is back to
+    /// back from its second clear on.
+    pub b2b: u32,
+}
+
+impl ClearChain {
+    /// Counts a piece locking and clearing the given number of lines. Returns whether the clear was
+    /// back to back. Spins cannot be seen in a record, so only fours count as difficult.
+    pub fn lock(&mut self, lines: u32) -> bool {
+        if lines == 0 {
+            self.combo = 0;
+            return false;
+        }
+        self.combo += 1;
+        if lines >= 4 {
+            self.b2b += 1;
+        } else {
+            self.b2b = 0;
+        }
+        self.b2b > 1
+    }
+
+    /// A wipe leaves nothing for the next clear to follow on from
+    pub fn wipe(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Finer stats about how the pieces were played, which can be worked out again from the record of
+/// a game through [`compute_stats`].
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct SessionStats {
+    /// Number of pieces of each kind locked into the matrix
+    pub placed: HashMap<MinoKind, u32>,
+    /// Number of times a piece was swapped into hold
+    pub holds: u32,
+    /// Seconds during which there was an active piece
+    pub active_time: f32,
+    /// Every piece locked, in order. Only known when the stats are worked out from a record.
+    pub locks: Vec<Lock>,
+}
+
+/// A piece locking into the matrix, as found in a record
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lock {
+    pub frame: u64,
+    /// The piece as last seen before it locked. Its kind, column and rotation are those it locked
+    /// with, but a piece dropped on the frame it locked is seen higher than where it landed.
+    pub mino: Mino,
+    /// Whether the piece could have been swapped into hold instead of being placed
+    pub hold_available: bool,
+}
+
+impl SessionStats {
+    pub fn pieces(&self) -> u32 {
+        self.placed.values().sum()
+    }
+
+    /// Average seconds that each piece was active before it locked
+    pub fn average_active_time(&self) -> Option<f32> {
+        let pieces = self.pieces();
+        (pieces > 0).then(|| self.active_time / pieces as f32)
+    }
+
+    /// Number of pieces which were placed while they could have been swapped into hold
+    pub fn holds_ignored(&self) -> usize {
+        self.locks.iter().filter(|lock| lock.hold_available).count()
+    }
+}
+
+/// Works out the session stats of a game from its record.
+///
+/// The record does not say outright when a piece locks, so locks are found frame by frame: a piece
+/// locked on a frame where it was active beforehand, and either the queue moved or the board was
+/// left without an active piece, without the piece going into hold. Hold was available to the piece
+/// if, going into that frame, the hold was not already used up.
+pub fn compute_stats(record: &CompleteRecord) -> SessionStats {
+    let mut stats = SessionStats::default();
+    let items = record.get(0..record.len()).iter().collect::<Vec<_>>();
+
+    let mut active: Option<Mino> = None;
+    let mut hold = Hold::Empty;
+    let mut last_micros = 0;
+
+    for frame in items.chunk_by(|a, b| a.time == b.time) {
+        let micros = frame[0].micros;
+        let previous = active;
+        let previous_hold = hold;
+        if previous.is_some() {
+            stats.active_time += micros.saturating_sub(last_micros) as f32 / 1_000_000.0;
+        }
+        last_micros = micros;
+
+        let mut queue_changed = false;
+        let mut held = false;
+        for item in frame {
+            match &item.data {
+                RecordData::ActiveChange(mino) => active = *mino,
+                RecordData::QueueChange(_) => queue_changed = true,
+                RecordData::Hold(new_hold) => {
+                    held |=
+                        matches!(new_hold, Hold::Inactive(_)) && !matches!(hold, Hold::Inactive(_));
+                    hold = *new_hold;
+                }
+                RecordData::MatrixChange(_) | RecordData::Keyframe(_) => (),
+            }
+        }
+
+        if held {
+            stats.holds += 1;
+        } else if let Some(piece) = previous.filter(|_| queue_changed || active.is_none()) {
+            *stats.placed.entry(piece.kind).or_default() += 1;
+            stats.locks.push(Lock {
+                frame: frame[0].time,
+                mino: piece,
+                hold_available: !matches!(previous_hold, Hold::Inactive(_)),
+            });
+        }
+    }
+
+    stats
+}
+
+/// What became of the matrix when a piece locked
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LockOutcome {
+    pub lock: Lock,
+    pub lines: u32,
+    /// Whether the lines cleared by the piece left the matrix empty
+    pub perfect_clear: bool,
+    /// Whether the board was wiped after the piece, having topped out in continuous play
+    pub wiped: bool,
+    /// Clears in a row up to and including this piece, or zero if it cleared nothing
+    pub combo: u32,
+    /// Difficult clears in a row up to this piece, as counted by [`ClearChain`]
+    pub b2b: u32,
+    /// Lines sent by the piece, as worked out by [`attack`]
+    pub attack: u32,
+}
+
+/// Finds what each piece of a record did to the matrix as it locked.
+///
+/// Clears are not written in the record any more than locks are, so they are found by counting the
+/// filled cells: a piece locking adds its cells to the matrix, and whatever is missing from the
+/// matrix after the frame was cleared, a row at a time. A wipe empties the matrix on the frame of
+/// the lock which caused it, which can be told apart from a perfect clear by the cells missing not
+/// making up whole rows (or making up more rows than a piece can clear). Garbage rising on the same
+/// frame hides the clears under it, so the count is only exact for modes without garbage.
+pub fn lock_outcomes(record: &CompleteRecord, shape_table: &ShapeTable) -> Vec<LockOutcome> {
+    let locks = compute_stats(record).locks;
+    let mut locks = locks.iter().peekable();
+    let width = Matrix::default().width();
+    let items = record.get(0..record.len()).iter().collect::<Vec<_>>();
+
+    let mut filled = 0usize;
+    let mut chain = ClearChain::default();
+    let mut outcomes: Vec<LockOutcome> = Vec::new();
+    for frame in items.chunk_by(|a, b| a.time == b.time) {
+        let before = filled;
+        for item in frame {
+            if let RecordData::MatrixChange(update) = &item.data {
+                filled = (filled + (update.new != MinoKind::E) as usize)
+                    .saturating_sub((update.old != MinoKind::E) as usize);
+            }
+        }
+
+        let Some(&lock) = locks.next_if(|lock| lock.frame == frame[0].time) else {
+            continue;
+        };
+        let placed = shape_table[ShapeParameters {
+            kind: lock.mino.kind,
+            rotation: RotationState::Up,
+        }]
+        .len();
+        let removed = (before + placed).saturating_sub(filled);
+        let wiped = filled == 0 && (removed % width != 0 || removed > placed * width);
+        let lines = if wiped { 0 } else { (removed / width) as u32 };
+        let perfect_clear = filled == 0 && lines > 0;
+
+        let streak = chain.combo;
+        let back_to_back = chain.lock(lines);
+        outcomes.push(LockOutcome {
+            lock,
+            lines,
+            perfect_clear,
+            wiped,
+            combo: chain.combo,
+            b2b: chain.b2b,
+            attack: attack(lines, streak, back_to_back, perfect_clear),
+        });
+        if wiped {
+            chain.wipe();
+        }
+    }
+    outcomes
+}
+
+/// The matrix as it is at the end of a record
+pub fn final_matrix(record: &CompleteRecord) -> Matrix {
+    let mut matrix = Matrix::default();
+    for item in record.get(0..record.len()).iter() {
+        if let RecordData::MatrixChange(update) = &item.data {
+            if let Some(cell) = matrix.get_mut(update.loc) {
+                *cell = update.new;
+            }
+        }
+    }
+    matrix
+}
+
+/// The frames of a record on which lines were cleared, along with the number of lines cleared on
+/// each. See [`lock_outcomes`] for how the clears are found.
+pub fn line_clears(record: &CompleteRecord, shape_table: &ShapeTable) -> Vec<(u64, u32)> {
+    lock_outcomes(record, shape_table)
+        .into_iter()
+        .filter(|outcome| outcome.lines > 0)
+        .map(|outcome| (outcome.lock.frame, outcome.lines))
+        .collect()
+}
+
+/// Lines sent by a clear, following the usual guideline table, given the number of clears in a row
+/// before it. Spins cannot be seen in a record, so they count as plain clears.
+pub fn attack(lines: u32, combo: u32, back_to_back: bool, perfect_clear: bool) -> u32 {
+    if lines == 0 {
+        return 0;
+    }
+    CLEAR_ATTACK[lines.min(4) as usize]
+        + COMBO_ATTACK[(combo as usize).min(COMBO_ATTACK.len() - 1)]
+        + back_to_back as u32
+        + if perfect_clear {
+            PERFECT_CLEAR_ATTACK
+        } else {
+            0
+        }
+}
+
+/// The pace of play as of a piece locking
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PacePoint {
+    pub frame: u64,
+    /// Pieces per second over the last [`PACE_WINDOW`] seconds
+    pub pps: f32,
+    /// Attack per minute over the last [`PACE_WINDOW`] seconds
+    pub apm: f32,
+}
+
+/// Something worth pointing out on a pace graph
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaceMarker {
+    TopOut,
+    PerfectClear,
+    /// The end of a combo of at least [`BIG_COMBO`] clears, with the number of clears in it
+    Combo(u32),
+}
+
+/// The pace of a record over time, with a point for each piece locked
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Pace {
+    pub points: Vec<PacePoint>,
+    /// Frames on which something worth pointing out happened, in order of time
+    pub markers: Vec<(u64, PaceMarker)>,
+}
+
+/// Works out the rolling pieces per second and attack per minute of a record, at each piece locked.
+/// Early in the record, the rates are taken over the time played so far rather than the whole
+/// window.
+pub fn pace(record: &CompleteRecord, shape_table: &ShapeTable) -> Pace {
+    let outcomes = lock_outcomes(record, shape_table);
+    let mut markers = Vec::new();
+    for (ix, outcome) in outcomes.iter().enumerate() {
+        let frame = outcome.lock.frame;
+        if outcome.wiped {
+            markers.push((frame, PaceMarker::TopOut));
+        }
+        if outcome.perfect_clear {
+            markers.push((frame, PaceMarker::PerfectClear));
+        }
+        let combo_ends = outcomes.get(ix + 1).map_or(true, |next| next.combo == 0);
+        if outcome.combo >= BIG_COMBO && combo_ends {
+            markers.push((frame, PaceMarker::Combo(outcome.combo)));
+        }
+    }
+
+    let rate = record.frame_rate;
+    let window = rate.frames(PACE_WINDOW);
+    let points = outcomes
+        .iter()
+        .enumerate()
+        .map(|(ix, outcome)| {
+            let frame = outcome.lock.frame;
+            let recent = outcomes[..=ix]
+                .iter()
+                .rev()
+                .take_while(|o| o.lock.frame + window > frame);
+            let (pieces, attack) = recent.fold((0, 0), |(p, a), o| (p + 1, a + o.attack));
+            let seconds = rate.seconds(frame.clamp(1, window));
+            PacePoint {
+                frame,
+                pps: pieces as f32 / seconds,
+                apm: attack as f32 / seconds * 60.0,
+            }
+        })
+        .collect();
+
+    Pace { points, markers }
+}
+
+/// The frames on which each milestone of a sprint was passed, given the line clears of its record
+pub fn sprint_milestones(clears: &[(u64, u32)]) -> Vec<u64> {
+    let mut lines = 0;
+    let mut milestones = Vec::new();
+    for &(frame, cleared) in clears {
+        let passed = (lines + cleared) / SPRINT_MILESTONE - lines / SPRINT_MILESTONE;
+        milestones.extend(std::iter::repeat(frame).take(passed as usize));
+        lines += cleared;
+    }
+    milestones
+}
+
+impl Stats {
+    /// Pieces used for each garbage line cleared, or nothing if no garbage has been cleared yet.
+    /// Lower is better.
+    pub fn efficiency(&self) -> Option<f32> {
+        (self.garbage_lines > 0).then(|| self.pieces as f32 / self.garbage_lines as f32)
+    }
+
+    /// Counts a piece locking and clearing the given number of lines, following its combo and back
+    /// to back chain
+    pub fn record_clear(&mut self, lines: u32) {
+        self.chain.lock(lines);
+        self.max_combo = self.max_combo.max(self.chain.combo);
+        self.max_b2b = self.max_b2b.max(self.chain.b2b);
+    }
+
+    /// Counts a death in continuous play, ending the current streak
+    pub fn record_death(&mut self) {
+        self.chain.wipe();
+        self.deaths += 1;
+        self.longest_streak = self.longest_streak.max(self.pieces - self.streak_start);
+        self.streak_start = self.pieces;
+    }
+
+    /// Counts the top of the stack being cleared away to keep the game going. As a penalty, this
+    /// breaks the combo and back to back chain going on, as if the board were wiped.
+    pub fn record_lifesaver(&mut self) {
+        self.chain.wipe();
+        self.lifesavers += 1;
+    }
+
+    /// The most pieces placed without dying, including the streak still going
+    pub fn longest_survival(&self) -> u32 {
+        self.longest_streak.max(self.pieces - self.streak_start)
+    }
+}
+
+/// The color that an efficiency figure (in pieces per garbage line) is shown in
+pub fn efficiency_color(efficiency: f32) -> Color {
+    if efficiency < GOOD_EFFICIENCY {
+        Color::LIME_GREEN
+    } else if efficiency < FAIR_EFFICIENCY {
+        Color::GOLD
+    } else {
+        Color::TOMATO
+    }
+}
+
+/// Whether any cell of a row held garbage
+pub fn is_garbage_row(row: &[MinoKind]) -> bool {
+    row.contains(&MinoKind::G)
+}
+
+fn reset_stats(mut stats: ResMut<Stats>) {
+    *stats = default();
+}
+
+/// Counts the lines cleared as soon as the board clears them, rather than with the rest of the
+/// stats, so that the goal of a sprint is checked against every line cleared so far
+pub(crate) fn count_lines(mut stats: ResMut<Stats>, mut clears: EventReader<LineClearEvent>) {
+    for clear in clears.read() {
+        stats.lines += clear.rows.len() as u32;
+        stats.garbage_lines += clear
+            .contents
+            .iter()
+            .filter(|row| is_garbage_row(row))
+            .count() as u32;
+    }
+}
+
+fn count_stats(
+    mut stats: ResMut<Stats>,
+    mut locks: EventReader<PieceLocked>,
+    mut holds: EventReader<HoldUsed>,
+    mut wipes: EventReader<BoardWipeEvent>,
+    mut lifesavers: EventReader<LifesaverUsed>,
+    boards: Query<&Active, Without<Ghost>>,
+    time: Res<Time>,
+) {
+    stats.time += time.delta_seconds();
+    if boards.iter().any(|active| active.0.is_some()) {
+        stats.session.active_time += time.delta_seconds();
+    }
+    // the replay sends locks and holds as well, which may still be waiting when play resumes
+    for lock in locks.read().filter(|lock| !lock.replayed) {
+        stats.pieces += 1;
+        *stats.session.placed.entry(lock.kind).or_default() += 1;
+        stats.record_clear(lock.clear as u32);
+    }
+    stats.session.holds += holds.read().filter(|hold| !hold.replayed).count() as u32;
+    // deaths always come after the lock which caused them
+    for _ in wipes.read() {
+        stats.record_death();
+    }
+    for _ in lifesavers.read() {
+        stats.record_lifesaver();
+    }
+}
+
+/// The bar under the board counting down the lines left in a sprint
+#[derive(Component)]
+pub struct SprintBar;
+
+fn setup_sprint_bar(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ProgressBarMaterial>>,
+    boards: Query<&Settings, Without<Ghost>>,
+    palette: Res<Palette>,
+    bars: Query<Entity, With<SprintBar>>,
+) {
+    for bar in bars.iter() {
+        commands.entity(bar).despawn_recursive();
+    }
+    if !boards
+        .iter()
+        .any(|settings| settings.mode == GameMode::Sprint)
+    {
+        return;
+    }
+
+    // each block of lines up to a milestone is colored as its own section
+    let sections = (0..SPRINT_LINES.div_ceil(SPRINT_MILESTONE))
+        .map(|ix| {
+            let lines = SPRINT_MILESTONE.min(SPRINT_LINES - ix * SPRINT_MILESTONE);
+            (lines, palette.segment_color(ix as usize))
+        })
+        .collect();
+
+    let style = Style {
+        position_type: PositionType::Absolute,
+        width: Val::Percent(30.0),
+        height: Val::Px(6.0),
+        left: Val::Percent(35.0),
+        bottom: Val::Percent(3.0),
+        ..default()
+    };
+
+    commands.spawn((
+        ProgressBarBundle {
+            progressbar: ProgressBar {
+                sections,
+                orientation: Orientation::Right,
+                empty_color: Color::rgba(1.0, 1.0, 1.0, 0.1),
+                label: Some(ProgressBarLabel {
+                    format: LabelFormat::Custom(Box::new(|progress| {
+                        let left = ((1.0 - progress) * SPRINT_LINES as f32).round();
+                        format!("{left} lines left")
+                    })),
+                    placement: LabelPlacement::End,
+                    style: TextStyle {
+                        font_size: 14.0,
+                        ..default()
+                    },
+                }),
+                smoothing: Some(0.1),
+                ..default()
+            },
+            material_node_bundle: MaterialNodeBundle {
+                material: materials.add(ProgressBarMaterial::default()),
+                style,
+                ..default()
+            },
+        },
+        SprintBar,
+    ));
+}
+
+fn update_sprint_bar(stats: Res<Stats>, mut bars: Query<&mut ProgressBar, With<SprintBar>>) {
+    for mut bar in bars.iter_mut() {
+        bar.progress = (stats.lines as f32 / SPRINT_LINES as f32).min(1.0);
+    }
+}
+
+fn remove_sprint_bar(mut commands: Commands, bars: Query<Entity, With<SprintBar>>) {
+    for bar in bars.iter() {
+        commands.entity(bar).despawn_recursive();
+    }
+}
+
+/// The bar beside the board which fills a notch for each clear of the combo going on, and drains
+/// its top notch over roughly the time that the next piece has to clear in to keep the combo going
+#[derive(Component, Default)]
+pub struct ComboBar {
+    /// The combo as of the last update
+    combo: u32,
+    /// Seconds of play at which the combo last went up
+    cleared_at: f32,
+}
+
+fn setup_combo_bar(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ProgressBarMaterial>>,
+    palette: Res<Palette>,
+    settings: Res<GlobalSettings>,
+    bars: Query<Entity, With<ComboBar>>,
+) {
+    for bar in bars.iter() {
+        commands.entity(bar).despawn_recursive();
+    }
+
+    // a notch for each clear that adds to the attack, colored by how much it adds
+    let sections = COMBO_ATTACK
+        .iter()
+        .group_by(|&&attack| attack)
+        .into_iter()
+        .enumerate()
+        .map(|(ix, (_, notches))| (notches.count() as u32, palette.segment_color(ix)))
+        .collect();
+
+    // on the side of the hold box, under it
+    let mut style = Style {
+        position_type: PositionType::Absolute,
+        width: Val::Px(6.0),
+        height: Val::Percent(40.0),
+        bottom: Val::Percent(10.0),
+        ..default()
+    };
+    let (near, far) = if settings.queue_layout.mirrored() {
+        (&mut style.right, &mut style.left)
+    } else {
+        (&mut style.left, &mut style.right)
+    };
+    *near = Val::Percent(36.0);
+    *far = Val::Auto;
+
+    commands.spawn((
+        ProgressBarBundle {
+            progressbar: ProgressBar {
+                sections,
+                orientation: Orientation::Up,
+                empty_color: Color::rgba(1.0, 1.0, 1.0, 0.1),
+                ..default()
+            },
+            material_node_bundle: MaterialNodeBundle {
+                material: materials.add(ProgressBarMaterial::default()),
+                style,
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        },
+        ComboBar::default(),
+    ));
+}
+
+/// Fills the combo bar up to the combo going on, less however much of the time to keep it going has
+/// passed. The bar is emptied and hidden as soon as the combo breaks.
+fn update_combo_bar(
+    stats: Res<Stats>,
+    mut bars: Query<(&mut ComboBar, &mut ProgressBar, &mut Visibility)>,
+) {
+    let combo = stats.chain.combo;
+    for (mut combo_bar, mut bar, mut vis) in bars.iter_mut() {
+        if combo > combo_bar.combo {
+            combo_bar.cleared_at = stats.time;
+        }
+        combo_bar.combo = combo;
+
+        if combo == 0 {
+            bar.progress = 0.0;
+            *vis = Visibility::Hidden;
+            continue;
+        }
+
+        // about as long as a piece has been taking, so the bar drains at the pace of play
+        let window = (stats.time / stats.pieces.max(1) as f32)
+            .clamp(*COMBO_WINDOW.start(), *COMBO_WINDOW.end());
+        let left = 1.0 - ((stats.time - combo_bar.cleared_at) / window).clamp(0.0, 1.0);
+        let notches = COMBO_ATTACK.len() as f32;
+        bar.progress = (((combo - 1) as f32 + left) / notches).min(1.0);
+        *vis = Visibility::Inherited;
+    }
+}
+
+fn remove_combo_bar(mut commands: Commands, bars: Query<Entity, With<ComboBar>>) {
+    for bar in bars.iter() {
+        commands.entity(bar).despawn_recursive();
+    }
+}
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Stats>()
+            .add_systems(
+                OnTransition {
+                    from: MainState::Ready,
+                    to: MainState::Playing,
+                },
+                reset_stats,
+            )
+            .add_systems(PostUpdate, count_stats.run_if(in_state(MainState::Playing)))
+            .add_systems(
+                OnEnter(MainState::Playing),
+                (setup_sprint_bar, setup_combo_bar),
+            )
+            .add_systems(
+                PostUpdate,
+                (update_sprint_bar, update_combo_bar)
+                    .after(count_stats)
+                    .run_if(in_state(MainState::Playing)),
+            )
+            .add_systems(
+                OnExit(MainState::Playing),
+                (remove_sprint_bar, remove_combo_bar),
+            );
+    }
+}
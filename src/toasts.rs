@@ -0,0 +1,137 @@
+//! Short messages which appear over the game and fade away on their own, for telling the player
+//! something without interrupting them.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::config::{report_config_warnings, ConfigWarnings};
+
+/// Seconds that a toast stays up when pushed through one of the shorthands on [`Toasts`]
+pub const DEFAULT_TOAST_DURATION: f32 = 4.0;
+/// Seconds over which a toast fades out at the end of its duration
+const FADE_TIME: f32 = 0.5;
+/// Toasts beyond this many are only counted, until the ones before them expire
+const MAX_VISIBLE_TOASTS: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToastLevel {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl ToastLevel {
+    fn color(self) -> egui::Color32 {
+        match self {
+            ToastLevel::Info => egui::Color32::from_gray(220),
+            ToastLevel::Success => egui::Color32::from_rgb(120, 220, 120),
+            ToastLevel::Warning => egui::Color32::from_rgb(240, 200, 80),
+            ToastLevel::Error => egui::Color32::from_rgb(240, 100, 100),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Toast {
+    pub message: String,
+    pub level: ToastLevel,
+    /// Seconds left before the toast disappears
+    remaining: f32,
+}
+
+/// The toasts waiting to be shown, oldest first. Only the oldest few are shown at once, and the
+/// others wait their turn without running down their time.
+#[derive(Resource, Default)]
+pub struct Toasts(VecDeque<Toast>);
+
+impl Toasts {
+    pub fn push(&mut self, message: impl Into<String>, level: ToastLevel, duration: f32) {
+        self.0.push_back(Toast {
+            message: message.into(),
+            level,
+            remaining: duration,
+        });
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(message, ToastLevel::Info, DEFAULT_TOAST_DURATION);
+    }
+
+    pub fn success(&mut self, message: impl Into<String>) {
+        self.push(message, ToastLevel::Success, DEFAULT_TOAST_DURATION);
+    }
+
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.push(message, ToastLevel::Warning, DEFAULT_TOAST_DURATION);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(message, ToastLevel::Error, DEFAULT_TOAST_DURATION);
+    }
+
+    /// Shows the outcome of an operation, as a success if it went through and an error otherwise
+    pub fn report<T, E: std::fmt::Display>(
+        &mut self,
+        result: Result<T, E>,
+        success: impl FnOnce(T) -> String,
+    ) {
+        match result {
+            Ok(value) => self.success(success(value)),
+            Err(e) => self.error(e.to_string()),
+        }
+    }
+}
+
+fn expire_toasts(mut toasts: ResMut<Toasts>, time: Res<Time>) {
+    if toasts.0.is_empty() {
+        return;
+    }
+
+    for toast in toasts.0.iter_mut().take(MAX_VISIBLE_TOASTS) {
+        toast.remaining -= time.delta_seconds();
+    }
+    toasts.0.retain(|toast| toast.remaining > 0.0);
+}
+
+fn show_toasts(mut contexts: EguiContexts, toasts: Res<Toasts>) {
+    if toasts.0.is_empty() {
+        return;
+    }
+
+    let ctx = contexts.ctx_mut();
+    egui::Area::new("toasts")
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 10.0])
+        .interactable(false)
+        .show(ctx, |ui| {
+            for toast in toasts.0.iter().take(MAX_VISIBLE_TOASTS) {
+                let opacity = (toast.remaining / FADE_TIME).min(1.0);
+                let frame = egui::Frame::popup(ui.style());
+                frame
+                    .fill(frame.fill.gamma_multiply(opacity))
+                    .stroke(egui::Stroke::NONE)
+                    .show(ui, |ui| {
+                        let color = toast.level.color().gamma_multiply(opacity);
+                        ui.colored_label(color, &toast.message);
+                    });
+            }
+
+            let hidden = toasts.0.len().saturating_sub(MAX_VISIBLE_TOASTS);
+            if hidden > 0 {
+                ui.label(format!("+{hidden} more"));
+            }
+        });
+}
+
+pub struct ToastsPlugin;
+
+impl Plugin for ToastsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Toasts>()
+            .init_resource::<ConfigWarnings>()
+            .add_systems(Startup, report_config_warnings)
+            .add_systems(Update, (expire_toasts, show_toasts).chain());
+    }
+}
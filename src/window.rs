@@ -0,0 +1,198 @@
+//! Options for the window itself and for where the board sits within it, kept between sessions.
+
+use bevy::math::vec2;
+use bevy::prelude::*;
+use bevy::window::{PrimaryWindow, WindowLevel, WindowMode};
+use serde::{Deserialize, Serialize};
+
+use crate::board::{Bounds, CELL_SIZE};
+use crate::config::{self, ConfigError, ConfigWarnings, Versioned};
+use crate::controller::keybinds::{BindingContext, Hotkey};
+use crate::toasts::Toasts;
+
+pub const WINDOW_OPTIONS_PATH: &str = "window.ron";
+/// The furthest that the board can be pushed from the center, in percent of the window
+pub const BOARD_OFFSET_LIMIT: f32 = 40.0;
+
+/// Turns presentation mode on or off
+pub const PRESENTATION_KEY: KeyCode = KeyCode::F12;
+
+pub(crate) const HOTKEYS: &[Hotkey] = &[Hotkey {
+    keys: &[PRESENTATION_KEY],
+    name: "Presentation Mode",
+    context: BindingContext::Anywhere,
+}];
+
+/// How much larger the UI and the action text are drawn in presentation mode
+const PRESENTATION_SCALE: f32 = 1.5;
+/// The share of the window that the board (along with its hold and queue) is zoomed to fill in
+/// presentation mode
+const PRESENTATION_FILL: f32 = 0.9;
+/// Cells beside the matrix taken up by the hold and the queue, and above it by the spawn rows
+const PRESENTATION_MARGIN: IVec2 = IVec2::new(14, 4);
+
+#[derive(Resource, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowOptions {
+    /// How far the board is pushed from the center of the space beside the panels, in percent of
+    /// the width and height of the window. Positive values push right and up.
+    pub board_offset: Vec2,
+    /// Cover the whole monitor that the window is on, without a border
+    pub borderless: bool,
+    /// Keep the window above every other window
+    pub always_on_top: bool,
+    /// Keep the window from being resized
+    pub fixed_size: bool,
+}
+
+impl Versioned for WindowOptions {
+    const VERSION: u32 = 1;
+
+    fn migrate(version: u32, _: &str) -> Result<Self, ConfigError> {
+        Err(ConfigError::UnknownVersion(version))
+    }
+}
+
+impl WindowOptions {
+    /// Loads the options saved by a previous session, along with anything the player should be
+    /// warned about
+    pub fn load() -> (Self, Option<String>) {
+        let (options, warning) = match config::load::<Self>(WINDOW_OPTIONS_PATH) {
+            Ok(loaded) => loaded,
+            Err(ConfigError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => default(),
+            Err(e) => (
+                default(),
+                Some(format!("Could not read saved window options: {e}")),
+            ),
+        };
+        if let Some(warning) = &warning {
+            tracing::warn!("{warning}");
+        }
+        (options, warning)
+    }
+
+    /// Shrinks the given part of the window (in physical pixels) from one side, so that its center
+    /// is moved by the board offset while it stays within the part it was taken from. The board
+    /// and the HUD are laid out within the camera's viewport, so both follow the offset.
+    pub fn offset_viewport(&self, free: Rect, window_size: Vec2) -> Rect {
+        // the window's y axis points down, while the offset's points up
+        let offset = self.board_offset / 100.0 * window_size * vec2(1.0, -1.0);
+        // shrinking one side by twice the offset moves the center by the offset
+        let shrink = (offset * 2.0)
+            .abs()
+            .min((free.size() - Vec2::ONE).max(Vec2::ZERO));
+        let mut rect = free;
+        if offset.x > 0.0 {
+            rect.min.x += shrink.x;
+        } else {
+            rect.max.x -= shrink.x;
+        }
+        if offset.y > 0.0 {
+            rect.min.y += shrink.y;
+        } else {
+            rect.max.y -= shrink.y;
+        }
+        rect
+    }
+}
+
+/// Shows the board as large as the window allows, for showing the game to an audience such as on a
+/// projector. The settings panel is hidden, and the UI and the action text are scaled up. Nothing
+/// in the settings is changed, so that the player's own sizes are back as soon as this is turned
+/// off; instead, the systems applying each of them take this into account. Not kept between
+/// sessions.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct PresentationMode(bool);
+
+impl PresentationMode {
+    /// The factor that the UI scale and the size of the action text are multiplied by
+    pub fn scale(&self) -> f32 {
+        if self.0 {
+            PRESENTATION_SCALE
+        } else {
+            1.0
+        }
+    }
+
+    /// The camera zoom which fits the board into most of the window, if it should
+    pub fn zoom(&self, bounds: &Bounds, window: &Window) -> Option<f32> {
+        if !self.0 {
+            return None;
+        }
+        let size = (bounds.legal_bounds + PRESENTATION_MARGIN).as_vec2() * CELL_SIZE as f32;
+        let available = vec2(window.width(), window.height()) * PRESENTATION_FILL;
+        Some((size / available).max_element())
+    }
+}
+
+fn toggle_presentation(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut presentation: ResMut<PresentationMode>,
+    mut toasts: ResMut<Toasts>,
+) {
+    if keys.just_pressed(PRESENTATION_KEY) {
+        **presentation = !**presentation;
+    }
+    if presentation.is_changed() && **presentation {
+        toasts.info("Press F12 to leave presentation mode");
+    }
+}
+
+/// Brings the primary window in line with the options. Only the properties which differ are
+/// written, since bevy remakes parts of the window (and changing the mode remakes the rendering
+/// surface) whenever they are written.
+fn apply_window_options(
+    options: Res<WindowOptions>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    if !options.is_changed() && !window.is_added() {
+        return;
+    }
+
+    let mode = if options.borderless {
+        WindowMode::BorderlessFullscreen
+    } else {
+        WindowMode::Windowed
+    };
+    let level = if options.always_on_top {
+        WindowLevel::AlwaysOnTop
+    } else {
+        WindowLevel::Normal
+    };
+    if window.mode != mode {
+        window.mode = mode;
+    }
+    if window.window_level != level {
+        window.window_level = level;
+    }
+    if window.resizable == options.fixed_size {
+        window.resizable = !options.fixed_size;
+    }
+}
+
+fn save_window_options(options: Res<WindowOptions>, mut toasts: ResMut<Toasts>) {
+    if options.is_changed() && !options.is_added() {
+        if let Err(e) = config::save(WINDOW_OPTIONS_PATH, &*options) {
+            toasts.error(format!("Could not save window options: {e}"));
+        }
+    }
+}
+
+pub struct WindowOptionsPlugin;
+
+impl Plugin for WindowOptionsPlugin {
+    fn build(&self, app: &mut App) {
+        let (options, warning) = WindowOptions::load();
+        app.world
+            .get_resource_or_insert_with(ConfigWarnings::default)
+            .extend(warning);
+
+        app.insert_resource(options)
+            .init_resource::<PresentationMode>()
+            .add_systems(Update, (apply_window_options, save_window_options))
+            .add_systems(Update, toggle_presentation);
+    }
+}